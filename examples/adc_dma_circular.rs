@@ -0,0 +1,91 @@
+//! Continuously samples PA0 and PA1 at 100 kS/s each into a circular DMA buffer.
+//!
+//! With the ADC clocked from the 52 MHz sysclk, one conversion takes 247.5 + 12.5 = 260
+//! cycles, so the two channel sequence is converted at 200 kS/s.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32l4xx_hal::{
+    adc::{Adc, AdcCommon, SampleTime, Sequence},
+    delay::DelayCM,
+    dma::{dma1, CircBuffer, CircReadDma, Event, RxDma},
+    pac::ADC1,
+    prelude::*,
+};
+
+use rtic::app;
+
+/// Two halves of 64 sequences of two samples
+const BUFFER_LEN: usize = 256;
+
+#[app(device = stm32l4xx_hal::stm32, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        samples: CircBuffer<[u16; BUFFER_LEN], RxDma<Adc<ADC1>, dma1::C1>>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        let buffer = {
+            static mut BUFFER: [u16; BUFFER_LEN] = [0; BUFFER_LEN];
+            unsafe { &mut BUFFER }
+        };
+
+        rtt_init_print!();
+
+        let pac = cx.device;
+
+        let mut rcc = pac.RCC.constrain();
+        let mut flash = pac.FLASH.constrain();
+        let mut pwr = pac.PWR.constrain(&mut rcc.apb1r1);
+        let dma_channels = pac.DMA1.split(&mut rcc.ahb1);
+
+        let clocks = rcc.cfgr.sysclk(52.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+        let mut delay = DelayCM::new(clocks);
+
+        let mut gpioa = pac.GPIOA.split(&mut rcc.ahb2);
+        let mut pa0 = gpioa.pa0.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+        let mut pa1 = gpioa.pa1.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+        let adc_common = AdcCommon::new(pac.ADC_COMMON, &mut rcc.ahb2);
+        let mut adc = Adc::adc1(pac.ADC1, adc_common, &mut rcc.ccipr, &mut delay);
+
+        adc.configure_sequence(&mut pa0, Sequence::One, SampleTime::Cycles247_5);
+        adc.configure_sequence(&mut pa1, Sequence::Two, SampleTime::Cycles247_5);
+
+        let mut rx_dma = adc.with_dma(dma_channels.1);
+        rx_dma.channel.listen(Event::HalfTransfer);
+        rx_dma.channel.listen(Event::TransferComplete);
+
+        init::LateResources {
+            samples: rx_dma.circ_read(buffer),
+        }
+    }
+
+    #[idle]
+    fn idle(_cx: idle::Context) -> ! {
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    #[task(binds = DMA1_CH1, resources = [samples])]
+    fn dma1_ch1(cx: dma1_ch1::Context) {
+        let mut half = [0u16; BUFFER_LEN / 2];
+
+        // Each interrupt signals that another half of the buffer has been filled
+        match cx.resources.samples.read(&mut half) {
+            Ok(len) => {
+                let (pa0, pa1) = half[..len]
+                    .chunks_exact(2)
+                    .fold((0u32, 0u32), |(a, b), s| (a + s[0] as u32, b + s[1] as u32));
+                let sequences = (len / 2).max(1) as u32;
+                rprintln!("PA0: {}, PA1: {}", pa0 / sequences, pa1 / sequences);
+            }
+            Err(_) => rprintln!("samples were lost"),
+        }
+    }
+};
@@ -0,0 +1,75 @@
+//! Samples PA0 at exactly 1 kHz, triggered by the TRGO output of TIM6, into a DMA buffer.
+//!
+//! The time the transfer takes is measured with the cycle counter to verify the sample
+//! spacing.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    adc::{Adc, AdcCommon, DmaMode, ExternalTrigger, SampleTime, Sequence, TriggerEdge},
+    delay::Delay,
+    dma::Transfer,
+    pac,
+    prelude::*,
+    timer::{MasterMode, Timer},
+};
+
+const SAMPLES: usize = 100;
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let buffer = {
+        static mut BUFFER: [u16; SAMPLES] = [0; SAMPLES];
+        unsafe { &mut BUFFER }
+    };
+
+    let mut cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    cp.DCB.enable_trace();
+    cp.DWT.enable_cycle_counter();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let dma_channels = dp.DMA1.split(&mut rcc.ahb1);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let mut pa0 = gpioa.pa0.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    let adc_common = AdcCommon::new(dp.ADC_COMMON, &mut rcc.ahb2);
+    let mut adc = Adc::adc1(dp.ADC1, adc_common, &mut rcc.ccipr, &mut delay);
+
+    adc.configure_sequence(&mut pa0, Sequence::One, SampleTime::Cycles47_5);
+    adc.set_external_trigger(ExternalTrigger::Tim6Trgo, TriggerEdge::Rising);
+
+    // TIM6 in master mode, TRGO pulses on every update event
+    let mut timer = Timer::tim6(dp.TIM6, 1.kHz(), clocks, &mut rcc.apb1r1);
+    timer.set_master_mode(MasterMode::Update);
+
+    let start = cortex_m::peripheral::DWT::cycle_count();
+
+    // Arms the ADC, each TIM6 update then converts PA0 once
+    let transfer = Transfer::from_adc(adc, dma_channels.1, buffer, DmaMode::Oneshot, false);
+    let (buffer, _rx_dma) = transfer.wait();
+
+    let cycles = cortex_m::peripheral::DWT::cycle_count().wrapping_sub(start);
+    let spacing_us = cycles / (clocks.sysclk().raw() / 1_000_000) / (SAMPLES as u32 - 1);
+
+    rprintln!("Sample spacing: {} us", spacing_us);
+    rprintln!("Samples: {:?}", buffer);
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
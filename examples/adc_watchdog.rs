@@ -0,0 +1,67 @@
+//! Sleeps until the VBAT voltage drops below 2.4 V, detected by the ADC analog watchdog.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    adc::{Adc, AdcCommon, Event, SampleTime, Sequence, Watchdog},
+    delay::Delay,
+    pac::{self, Interrupt},
+    prelude::*,
+};
+
+/// VBAT is measured through an internal divider by 3
+const VBAT_LOW_MV: u32 = 2400 / 3;
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+    let adc_common = AdcCommon::new(dp.ADC_COMMON, &mut rcc.ahb2);
+    let mut adc = Adc::adc1(dp.ADC1, adc_common, &mut rcc.ccipr, &mut delay);
+    let vdda = u32::from(adc.calibrate(&mut delay));
+
+    let mut vbat = adc.enable_vbat().unwrap();
+    let low = (VBAT_LOW_MV * u32::from(adc.get_max_value()) / vdda) as u16;
+
+    adc.configure_sequence(&mut vbat, Sequence::One, SampleTime::Cycles640_5);
+    adc.configure_watchdog(Watchdog::Awd1, &vbat, low, adc.get_max_value());
+    adc.clear_event(Event::AnalogWatchdog1);
+    adc.listen(Event::AnalogWatchdog1);
+    adc.set_continuous(true);
+    adc.start_conversion();
+
+    // With interrupts masked, the pending ADC interrupt only wakes the core from WFI
+    // without running a handler.
+    cortex_m::interrupt::disable();
+    unsafe { NVIC::unmask(Interrupt::ADC1_2) };
+
+    rprintln!("Waiting for VBAT to drop below 2.4 V");
+
+    loop {
+        cortex_m::asm::wfi();
+
+        if adc.is_pending(Event::AnalogWatchdog1) {
+            rprintln!(
+                "VBAT low: {} mV",
+                adc.to_millivolts(adc.current_sample()) * 3
+            );
+            adc.clear_event(Event::AnalogWatchdog1);
+        }
+        NVIC::unpend(Interrupt::ADC1_2);
+    }
+}
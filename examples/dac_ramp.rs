@@ -0,0 +1,43 @@
+//! Generates a slow sawtooth ramp on PA4, from 0 V to VREF+ in about 4 seconds.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{delay::Delay, pac, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let pa4 = gpioa.pa4.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    let mut dac = dp.DAC1.constrain(pa4, &mut rcc.apb1r1);
+    dac.enable();
+
+    let mut value = 0;
+
+    loop {
+        dac.set_value(value);
+        delay.delay_ms(1u32);
+
+        if value % 1024 == 0 {
+            rprintln!("Output: {}", dac.get_value());
+        }
+        value = (value + 1) % 4096;
+    }
+}
@@ -0,0 +1,55 @@
+//! Plays a 1 kHz sine tone on PA4, 32 samples per period fed by DMA at every TIM6 update.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    dac::{Transfer, Trigger},
+    pac,
+    prelude::*,
+    timer::{MasterMode, Timer},
+};
+
+/// One period of a full scale sine
+static SINE: [u16; 32] = [
+    2048, 2447, 2831, 3185, 3495, 3750, 3939, 4056, 4095, 4056, 3939, 3750, 3495, 3185, 2831, 2447,
+    2048, 1649, 1265, 911, 601, 346, 157, 40, 1, 40, 157, 346, 601, 911, 1265, 1649,
+];
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let dma_channels = dp.DMA1.split(&mut rcc.ahb1);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let pa4 = gpioa.pa4.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+
+    let dac = dp.DAC1.constrain(pa4, &mut rcc.apb1r1);
+
+    // TIM6 in master mode, TRGO pulses on every update event
+    let mut timer = Timer::tim6(dp.TIM6, 32.kHz(), clocks, &mut rcc.apb1r1);
+    timer.set_master_mode(MasterMode::Update);
+
+    let mut transfer = Transfer::from_c1(dac, dma_channels.3, &SINE, Trigger::Tim6Trgo, true);
+
+    loop {
+        cortex_m::asm::delay(clocks.sysclk().raw());
+
+        if transfer.check_underrun().is_err() {
+            rprintln!("DMA underrun, restarting");
+            let (dac, dma, buffer) = transfer.stop();
+            transfer = Transfer::from_c1(dac, dma, buffer, Trigger::Tim6Trgo, true);
+        }
+    }
+}
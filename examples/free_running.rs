@@ -0,0 +1,38 @@
+//! Measures the time of a busy loop and polls a countdown on TIM2 counting µs
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+
+use cortex_m_rt::entry;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32l4xx_hal::{pac, prelude::*, timer::FreeRunningTimer};
+
+#[entry]
+fn main() -> ! {
+    rtt_init_print!();
+
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut rcc = dp.RCC.constrain();
+    let mut flash = dp.FLASH.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let timer = FreeRunningTimer::tim2(dp.TIM2, clocks, &mut rcc.apb1r1);
+
+    let start = timer.now();
+    for _ in 0..100_000 {
+        cortex_m::asm::nop();
+    }
+    rprintln!("100 000 nops in {} µs", timer.elapsed(start).ticks());
+
+    let mut countdown = timer.countdown(500_000.micros());
+    loop {
+        if countdown.is_expired() {
+            rprintln!("tick at {} µs", timer.now().ticks());
+            countdown.restart();
+        }
+    }
+}
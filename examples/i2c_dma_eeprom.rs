@@ -0,0 +1,72 @@
+//! Read a 4 KiB EEPROM, e.g. a 24LC32, with I2C DMA
+//!
+//! The memory address is written first, the bytes are read after a repeated start. The
+//! read is longer than 255 bytes, so it is programmed in chunks by `poll`.
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{i2c, i2c::I2c, prelude::*};
+
+const EEPROM_ADDR: u8 = 0x50;
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    #[init]
+    fn init(cx: init::Context) {
+        static mut MEMORY_ADDRESS: [u8; 2] = [0; 2];
+        static mut CONTENTS: [u8; 4096] = [0; 4096];
+
+        rtt_target::rtt_init_print!();
+        rprintln!("Initializing... ");
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+        let dma1_channels = dp.DMA1.split(&mut rcc.ahb1);
+
+        let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+        let scl = gpioa.pa9.into_alternate_open_drain(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrh,
+        );
+        let sda = gpioa.pa10.into_alternate_open_drain(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrh,
+        );
+
+        let i2c = I2c::i2c1(
+            dp.I2C1,
+            (scl, sda),
+            i2c::Config::new(400.kHz(), clocks),
+            &mut rcc.apb1r1,
+        );
+        let dma_i2c = i2c.with_rxtx_dma(dma1_channels.7, dma1_channels.6);
+
+        // Read the whole memory, starting at address 0
+        let transfer = dma_i2c.write_read(EEPROM_ADDR, MEMORY_ADDRESS, CONTENTS);
+        let (result, (_, contents), dma_i2c) = transfer.complete();
+        match result {
+            Ok(()) => rprintln!("First bytes: {:x?}", &contents[..16]),
+            Err(err) => rprintln!("Read failed: {:?}", err),
+        }
+
+        let (_i2c, _rx_channel, _tx_channel) = dma_i2c.split();
+    }
+
+    // Idle function so RTT keeps working
+    #[idle]
+    fn idle(_cx: idle::Context) -> ! {
+        loop {
+            continue;
+        }
+    }
+};
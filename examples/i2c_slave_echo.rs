@@ -0,0 +1,108 @@
+//! I2C slave at address 0x42 which echoes the bytes written by the master
+//!
+//! The master writes up to 32 bytes, the slave does not acknowledge further bytes. A
+//! following read, e.g. after a repeated start, returns the bytes written before. With
+//! a Raspberry Pi as the master:
+//!
+//! ```text
+//! $ i2ctransfer -y 1 w3@0x42 1 2 3 r3
+//! 0x01 0x02 0x03
+//! ```
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    gpio::{Alternate, OpenDrain, PA10, PA9},
+    i2c::{self, Address, Direction, I2c, I2cSlave, SlaveEvent},
+    pac::I2C1,
+    prelude::*,
+};
+
+const BUFFER_SIZE: usize = 32;
+
+type Slave = I2cSlave<I2C1, (PA9<Alternate<OpenDrain, 4>>, PA10<Alternate<OpenDrain, 4>>)>;
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        slave: Slave,
+        #[init([0; BUFFER_SIZE])]
+        buffer: [u8; BUFFER_SIZE],
+        // Bytes written by the master
+        #[init(0)]
+        len: usize,
+        // Bytes read by the master in the current transfer
+        #[init(0)]
+        position: usize,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        rtt_target::rtt_init_print!();
+        rprintln!("Initializing... ");
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+
+        let scl = gpioa.pa9.into_alternate_open_drain(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrh,
+        );
+        let sda = gpioa.pa10.into_alternate_open_drain(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrh,
+        );
+
+        let mut slave = I2c::new_slave(
+            dp.I2C1,
+            (scl, sda),
+            Address::Seven(0x42),
+            i2c::Config::new(100.kHz(), clocks),
+            &mut rcc.apb1r1,
+        );
+        slave.listen(SlaveEvent::AddressMatch);
+        slave.listen(SlaveEvent::RxNotEmpty);
+        slave.listen(SlaveEvent::TxEmpty);
+        slave.listen(SlaveEvent::Stop);
+
+        rprintln!("Listening on 0x42");
+
+        init::LateResources { slave }
+    }
+
+    #[task(binds = I2C1_EV, resources = [slave, buffer, len, position])]
+    fn i2c1_ev(cx: i2c1_ev::Context) {
+        let slave = cx.resources.slave;
+        let buffer = cx.resources.buffer;
+        let len = cx.resources.len;
+        let position = cx.resources.position;
+
+        if let Some(address_match) = slave.address_match() {
+            match address_match.direction {
+                // A write replaces the previous bytes
+                Direction::Write => *len = 0,
+                Direction::Read => *position = 0,
+            }
+        }
+        if slave.is_event_triggered(SlaveEvent::RxNotEmpty) {
+            *len += slave.slave_read(&mut buffer[*len..]);
+        }
+        if slave.is_event_triggered(SlaveEvent::TxEmpty) {
+            *position += slave.slave_write(&buffer[*position..*len]);
+        }
+        if slave.is_event_triggered(SlaveEvent::Stop) {
+            slave.clear_stop();
+            rprintln!("Echoing {:?}", &buffer[..*len]);
+        }
+    }
+};
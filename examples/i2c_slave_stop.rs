@@ -0,0 +1,126 @@
+//! I2C slave at address 0x42 which sleeps in Stop 2 between the transfers
+//!
+//! The I2C3 runs on the HSI16, which the peripheral wakes up by itself when the master
+//! starts a transfer. An address match then wakes the core up into the `I2C3_EV` task.
+//! The master writes one byte and reads it back, e.g. from a Raspberry Pi:
+//!
+//! ```text
+//! $ i2ctransfer -y 1 w1@0x42 0x5a r1
+//! 0x5a
+//! ```
+//!
+//! Between the transfers, the supply current measured at the IDD jumper of a Nucleo
+//! board is a few µA, well below 10 µA. The debugger has to be disconnected and the
+//! board power cycled for the measurement, a debug connection keeps the clocks running.
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use cortex_m::peripheral::SCB;
+use panic_halt as _;
+use stm32l4xx_hal::{
+    gpio::{Alternate, OpenDrain, PA7, PB4},
+    i2c::{self, Address, I2c, I2cSlave, SlaveEvent},
+    pac::I2C3,
+    prelude::*,
+    pwr::Pwr,
+    rcc::I2cClockSource,
+};
+
+type Slave = I2cSlave<I2C3, (PA7<Alternate<OpenDrain, 4>>, PB4<Alternate<OpenDrain, 4>>)>;
+
+/// Set from the address match to the STOP of a transfer, Stop 2 is only entered outside
+static IN_TRANSFER: AtomicBool = AtomicBool::new(false);
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        slave: Slave,
+        pwr: Pwr,
+        scb: SCB,
+        #[init(0)]
+        byte: u8,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+        let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+
+        // The system resumes from Stop 2 on the MSI, so keep running on it
+        let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+        rcc.ccipr
+            .set_kernel_clock::<I2C3>(I2cClockSource::Hsi16, &clocks);
+
+        let scl = gpioa.pa7.into_alternate_open_drain(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            &mut gpioa.afrl,
+        );
+        let sda = gpiob.pb4.into_alternate_open_drain(
+            &mut gpiob.moder,
+            &mut gpiob.otyper,
+            &mut gpiob.afrl,
+        );
+
+        let config = i2c::Config::with_kernel_clock(100.kHz(), clocks.kernel_clock::<I2C3>())
+            .wakeup_from_stop(true);
+        let mut slave = I2c::new_slave(
+            dp.I2C3,
+            (scl, sda),
+            Address::Seven(0x42),
+            config,
+            &mut rcc.apb1r1,
+        );
+        slave.listen(SlaveEvent::AddressMatch);
+        slave.listen(SlaveEvent::RxNotEmpty);
+        slave.listen(SlaveEvent::TxEmpty);
+        slave.listen(SlaveEvent::Stop);
+
+        init::LateResources {
+            slave,
+            pwr,
+            scb: cx.core.SCB,
+        }
+    }
+
+    #[idle(resources = [pwr, scb])]
+    fn idle(cx: idle::Context) -> ! {
+        loop {
+            // With interrupts disabled, an address match between the check and the entry
+            // leaves the interrupt pending, which prevents the entry
+            cortex_m::interrupt::free(|_| {
+                if !IN_TRANSFER.load(Ordering::Relaxed) {
+                    cx.resources.pwr.stop2(cx.resources.scb);
+                }
+            });
+            // The pending I2C3_EV task runs here
+        }
+    }
+
+    #[task(binds = I2C3_EV, resources = [slave, byte])]
+    fn i2c3_ev(cx: i2c3_ev::Context) {
+        let slave = cx.resources.slave;
+        let byte = cx.resources.byte;
+
+        // The address match which woke the core up
+        if slave.address_match().is_some() {
+            IN_TRANSFER.store(true, Ordering::Relaxed);
+        }
+        if slave.is_event_triggered(SlaveEvent::RxNotEmpty) {
+            slave.slave_read(core::slice::from_mut(byte));
+        }
+        if slave.is_event_triggered(SlaveEvent::TxEmpty) {
+            slave.slave_write(&[*byte]);
+        }
+        if slave.is_event_triggered(SlaveEvent::Stop) {
+            slave.clear_stop();
+            IN_TRANSFER.store(false, Ordering::Relaxed);
+        }
+    }
+};
@@ -0,0 +1,59 @@
+//! Blink an LED from Stop 2, woken up every 500 ms by LPTIM1
+//!
+//! LPTIM1 runs on the LSI at 32 kHz, which keeps running in Stop 2, and its auto reload
+//! match wakes the system up. The LED on PB3 (Nucleo-L432KC) toggles at every wakeup,
+//! in between the system draws a few µA.
+#![no_main]
+#![no_std]
+
+use cortex_m::peripheral::NVIC;
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{
+    lptimer::{ClockSource, Event, LowPowerTimer, LowPowerTimerConfig, PreScaler},
+    pac::{self, Interrupt},
+    prelude::*,
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let mut cp = pac::CorePeripherals::take().unwrap();
+    let dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+
+    // The system resumes from Stop 2 on the MSI, so keep running on it
+    let clocks = rcc.cfgr.lsi(true).freeze(&mut flash.acr, &mut pwr);
+
+    let mut led = gpiob
+        .pb3
+        .into_push_pull_output(&mut gpiob.moder, &mut gpiob.otyper);
+
+    let config = LowPowerTimerConfig::default()
+        .clock_source(ClockSource::LSI)
+        .prescaler(PreScaler::U1)
+        .arr_value(16_000 - 1);
+    let mut lptim =
+        LowPowerTimer::lptim1(dp.LPTIM1, config, &mut rcc.apb1r1, &mut rcc.ccipr, clocks);
+    lptim.listen(Event::AutoReloadMatch);
+
+    // The pending interrupt wakes the core up, it is handled with interrupts disabled
+    unsafe { NVIC::unmask(Interrupt::LPTIM1) };
+
+    loop {
+        cortex_m::interrupt::free(|_| {
+            pwr.stop2(&mut cp.SCB);
+
+            if lptim.is_event_triggered(Event::AutoReloadMatch) {
+                lptim.clear_event_flag(Event::AutoReloadMatch);
+                led.toggle();
+            }
+            NVIC::unpend(Interrupt::LPTIM1);
+        });
+    }
+}
@@ -0,0 +1,91 @@
+//! Debug console on the LPUART1 which keeps receiving in Stop 2
+//!
+//! The LPUART1 runs on the LSE at 9600 baud, so it does not need any other clock in
+//! Stop 2. Every received character wakes the core up into the `LPUART1` task, which
+//! echoes it and goes back to Stop 2.
+//!
+//! On a Nucleo-64 board, the ST-LINK virtual COM port is connected to PA2 and PA3,
+//! which are the TX and RX of the LPUART1 as well. The USART2 of the board support
+//! package uses the same pins.
+#![no_main]
+#![no_std]
+
+use cortex_m::peripheral::SCB;
+use panic_halt as _;
+use stm32l4xx_hal::{
+    gpio::{Alternate, PushPull, PA2, PA3},
+    pac::LPUART1,
+    prelude::*,
+    pwr::Pwr,
+    rcc::{ClockSecuritySystem, CrystalBypass, UsartClockSource},
+    serial::{Config, Event, Serial, WakeSource},
+};
+
+type Console = Serial<LPUART1, (PA2<Alternate<PushPull, 8>>, PA3<Alternate<PushPull, 8>>)>;
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        console: Console,
+        pwr: Pwr,
+        scb: SCB,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        // The system resumes from Stop 2 on the MSI, so keep running on it
+        let clocks = rcc
+            .cfgr
+            .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+            .freeze(&mut flash.acr, &mut pwr);
+        rcc.ccipr
+            .set_kernel_clock::<LPUART1>(UsartClockSource::Lse, &clocks);
+
+        let tx = gpioa
+            .pa2
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let rx = gpioa
+            .pa3
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+        let config = Config::default().baudrate(9_600.bps());
+        let mut console = Serial::lpuart1(dp.LPUART1, (tx, rx), config, clocks, &mut rcc.apb1r2);
+        console.enable_stop_wakeup(WakeSource::RxNotEmpty).unwrap();
+        console.listen(Event::Rxne);
+
+        init::LateResources {
+            console,
+            pwr,
+            scb: cx.core.SCB,
+        }
+    }
+
+    #[idle(resources = [pwr, scb])]
+    fn idle(cx: idle::Context) -> ! {
+        loop {
+            // A character received meanwhile raises RXNE, which wakes the core up again
+            cx.resources.pwr.stop2(cx.resources.scb);
+        }
+    }
+
+    #[task(binds = LPUART1, resources = [console])]
+    fn lpuart1(cx: lpuart1::Context) {
+        let console = cx.resources.console;
+
+        // An error is cleared by the read, a pending character raises RXNE again
+        if let Ok(byte) = console.read() {
+            // The LPUART keeps transmitting in Stop 2 as well
+            nb::block!(console.write(byte)).unwrap();
+            if byte == b'\r' {
+                nb::block!(console.write(b'\n')).unwrap();
+            }
+        }
+    }
+};
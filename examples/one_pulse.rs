@@ -0,0 +1,51 @@
+//! Fire a camera strobe 2 µs after an external edge, for 10 µs
+//!
+//! TIM15 generates the pulse on PA2 (CH1), started in hardware by the rising edge on PA3
+//! (CH2), so the delay does not depend on the interrupt latency. To try it without an
+//! external trigger, connect PA3 to PA4, which toggles every 100 ms.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{
+    delay::Delay,
+    one_pulse::{Filter, Polarity},
+    prelude::*,
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let strobe = gpioa
+        .pa2
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let trigger = gpioa
+        .pa3
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let mut source = gpioa
+        .pa4
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
+
+    let mut pulse =
+        dp.TIM15
+            .one_pulse(strobe, 2_000.nanos(), 10_000.nanos(), clocks, &mut rcc.apb2);
+    pulse.enable_trigger(trigger, Polarity::Rising, Filter::None);
+
+    loop {
+        delay.delay_ms(100_u32);
+        source.toggle();
+    }
+}
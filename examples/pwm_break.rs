@@ -0,0 +1,95 @@
+//! Shut a half bridge down in hardware on the FAULT output of its gate driver
+//!
+//! TIM1 drives the high side on PA8 (CH1) and the low side on PA7 (CH1N) at 20 kHz.
+//! The open-drain, active-low FAULT output of the gate driver is connected to the break
+//! input BKIN on PA6. A fault disables both outputs at once, without the core, and
+//! `TIM1_BRK_TIM15` reports it. The outputs are enabled again one second after the
+//! fault is gone.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    pac::TIM1,
+    prelude::*,
+    pwm::{BreakConfig, Event, Filter, LockLevel, Polarity, PwmTimer},
+};
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        timer: PwmTimer<TIM1>,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        rtt_target::rtt_init_print!();
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+        let high = gpioa
+            .pa8
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+        let low = gpioa
+            .pa7
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let mut fault =
+            gpioa
+                .pa6
+                .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        fault.internal_pull_up(&mut gpioa.pupdr, true);
+
+        let (mut timer, c1) = dp.TIM1.pwm_timer(high, 20.kHz(), clocks, &mut rcc.apb2);
+        timer.set_dead_time(500);
+        timer.set_off_state(true, true);
+        // Ignore glitches shorter than 100 ns on the FAULT line
+        let config = BreakConfig::default()
+            .polarity(Polarity::ActiveLow)
+            .filter(Filter::TimerClockN8);
+        timer.enable_break(fault, config);
+        // No software error can change the protection anymore
+        timer.set_lock_level(LockLevel::Level1);
+        timer.listen(Event::Break);
+
+        let mut c1 = c1.with_complementary(low);
+        c1.set_idle_state(false, false);
+        c1.set_duty(c1.get_max_duty() / 2);
+        c1.enable();
+        c1.enable_complementary();
+
+        init::LateResources { timer }
+    }
+
+    #[idle(resources = [timer])]
+    fn idle(mut cx: idle::Context) -> ! {
+        loop {
+            let enabled = cx.resources.timer.lock(|timer| timer.outputs_enabled());
+            if !enabled {
+                cortex_m::asm::delay(80_000_000);
+                cx.resources.timer.lock(|timer| {
+                    // The flag stays set while the fault is active
+                    if !timer.is_break(true) {
+                        timer.enable_outputs();
+                        timer.listen(Event::Break);
+                        rprintln!("Outputs enabled again");
+                    }
+                });
+            }
+        }
+    }
+
+    #[task(binds = TIM1_BRK_TIM15, resources = [timer])]
+    fn fault(cx: fault::Context) {
+        // The flag stays set while the fault is active, so wait for it in `idle`
+        cx.resources.timer.unlisten(Event::Break);
+        rprintln!("Fault, outputs disabled");
+    }
+};
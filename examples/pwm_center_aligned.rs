@@ -0,0 +1,79 @@
+//! Drive a half bridge with center-aligned PWM, and shift a second pulse within the period
+//!
+//! TIM1 counts up and down at 20 kHz. The high side on PA8 (CH1) and the low side on PA7
+//! (CH1N) switch in the middle of the period, with a dead time of 500 ns. PA10 (CH3) runs
+//! in the asymmetric mode, its pulse of a quarter period moves from before to after the
+//! middle of the period and back within two seconds.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    delay::Delay,
+    pac::TIM1,
+    prelude::*,
+    pwm::{Alignment, Pwm, PwmMode, C3},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let high = gpioa
+        .pa8
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let low = gpioa
+        .pa7
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let shifted = gpioa
+        .pa10
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    let (mut timer, (c1, mut c3)) =
+        dp.TIM1
+            .pwm_timer((high, shifted), 20.kHz(), clocks, &mut rcc.apb2);
+    timer.set_alignment(Alignment::Center1);
+    timer.set_dead_time(500);
+
+    let mut c1 = c1.with_complementary(low);
+    c1.set_duty(c1.get_max_duty() / 2);
+    c1.enable();
+    c1.enable_complementary();
+
+    // The counter counts up to `top` and down again
+    let top = c3.get_max_duty() - 1;
+    rprintln!("{} ticks per half period", top);
+    c3.enable();
+
+    loop {
+        for step in (0..=20).chain((1..20).rev()) {
+            shift(&mut c3, top, step);
+            delay.delay_ms(50_u32);
+        }
+    }
+}
+
+/// Places the pulse of a quarter period from ending at the top of the count (`step` 0)
+/// to starting at the top (`step` 20)
+fn shift(c3: &mut Pwm<TIM1, C3>, top: u16, step: u16) {
+    // The period has `2 * top` ticks, the pulse runs from the count `up` counting up,
+    // over the top, to the count `down` counting down
+    let width = top / 2;
+    let start = top - width + (u32::from(width) * u32::from(step) / 20) as u16;
+    let up = start;
+    let down = top - (start + width - top);
+    c3.set_asymmetric_duty(PwmMode::Mode2, up, down);
+}
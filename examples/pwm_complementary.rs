@@ -0,0 +1,63 @@
+//! Drive a half bridge with a complementary PWM pair and dead time
+//!
+//! TIM1 drives the high side on PA8 (CH1) and the low side on PA7 (CH1N) at 20 kHz.
+//! Between the switching of the two outputs, both are low for 500 ns, so the two
+//! switches of the bridge never conduct at the same time. The duty cycle ramps from
+//! 10 % to 90 % and back.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{delay::Delay, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let high = gpioa
+        .pa8
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let low = gpioa
+        .pa7
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    let (mut timer, c1) = dp.TIM1.pwm_timer(high, 20.kHz(), clocks, &mut rcc.apb2);
+    timer.set_dead_time(500);
+    // Both switches off while the outputs are disabled, e.g. by a break
+    timer.set_off_state(true, true);
+
+    let mut c1 = c1.with_complementary(low);
+    c1.set_idle_state(false, false);
+    let max = c1.get_max_duty();
+    c1.set_duty(max / 10);
+    c1.enable();
+    c1.enable_complementary();
+
+    let (min, max) = (max / 10, max * 9 / 10);
+    let step = max / 100;
+    let mut duty = min;
+    let mut rising = true;
+    loop {
+        delay.delay_ms(20_u32);
+        if rising {
+            duty += step;
+            rising = duty + step <= max;
+        } else {
+            duty -= step;
+            rising = duty < min + step;
+        }
+        c1.set_duty(duty);
+    }
+}
@@ -0,0 +1,54 @@
+//! Stream the duties of two PWM channels from a table with the timer DMA burst
+//!
+//! TIM1 drives PA8 (CH1) and PA9 (CH2) at 40 kHz. At every update event, the DMA writes
+//! the next pair of duties from the table, without any interrupt: CH1 ramps up and CH2
+//! ramps down over 64 periods, over and over again.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{hal::Pwm as _, prelude::*, pwm::Channel};
+
+const STEPS: usize = 64;
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let dma_channels = dp.DMA1.split(&mut rcc.ahb1);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let c1 = gpioa
+        .pa8
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let c2 = gpioa
+        .pa9
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    let (mut timer, _) = dp.TIM1.pwm_timer((c1, c2), 40.kHz(), clocks, &mut rcc.apb2);
+    timer.enable(Channel::C1);
+    timer.enable(Channel::C2);
+
+    // Blocks of the duties of CH1 and CH2
+    let max = u32::from(timer.get_max_duty());
+    let table = cortex_m::singleton!(: [u16; 2 * STEPS] = [0; 2 * STEPS]).unwrap();
+    for (step, block) in table.chunks_exact_mut(2).enumerate() {
+        let duty = (max * step as u32 / (STEPS as u32 - 1)) as u16;
+        block[0] = duty;
+        block[1] = max as u16 - duty;
+    }
+
+    let _transfer = timer.dma_burst(dma_channels.6, table, 2, true);
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
@@ -0,0 +1,60 @@
+//! Measure the frequency and the duty cycle of an external PWM signal
+//!
+//! TIM2 measures the signal on PA0 (CH1). To try it without an external source, connect
+//! PA0 to PA6, which TIM16 drives with 1 kHz at a duty cycle of 30 %.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{capture::Filter, delay::Delay, prelude::*};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let output = gpioa
+        .pa6
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let mut source = dp.TIM16.pwm(output, 1.kHz(), clocks, &mut rcc.apb2);
+    source.set_duty(source.get_max_duty() * 3 / 10);
+    source.enable();
+
+    let input = gpioa
+        .pa0
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let mut input = dp
+        .TIM2
+        .pwm_input(input, Filter::TimerClockN8, clocks, &mut rcc.apb1r1);
+
+    loop {
+        delay.delay_ms(500_u32);
+
+        // Without rising edges, the counter runs up to its maximum and overflows
+        if input.is_overflow(true) {
+            rprintln!("No signal");
+            continue;
+        }
+        let period = input.period();
+        let pulse_width = input.pulse_width();
+        let duty = 1000 * u64::from(pulse_width) / u64::from(period);
+        rprintln!(
+            "{} Hz, duty cycle {}.{} %",
+            input.frequency().raw(),
+            duty / 10,
+            duty % 10
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Send the 560 µs marks and spaces of an IR remote control on a 38 kHz carrier
+//!
+//! TIM16 drives the IR LED on PA6 (CH1) with a duty of one third. The repetition counter
+//! raises the update event every 21 periods of the carrier, 553 µs, and the interrupt
+//! switches between a mark and a space. The duty is preloaded, so it changes exactly at
+//! the next update event, without any jitter of the interrupt.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use stm32l4xx_hal::{
+    pac::TIM16,
+    prelude::*,
+    pwm::{Event, Pwm, PwmTimer, C1},
+};
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        timer: PwmTimer<TIM16>,
+        led: Pwm<TIM16, C1>,
+        #[init(false)]
+        mark: bool,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        rtt_target::rtt_init_print!();
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+        let pin = gpioa
+            .pa6
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+        let (mut timer, mut led) = dp.TIM16.pwm_timer(pin, 38.kHz(), clocks, &mut rcc.apb2);
+        timer.set_repetition(20);
+        timer.listen(Event::Update);
+
+        led.set_duty(0);
+        led.enable();
+
+        init::LateResources { timer, led }
+    }
+
+    #[task(binds = TIM1_UP_TIM16, resources = [timer, led, mark])]
+    fn update(cx: update::Context) {
+        cx.resources.timer.is_update(true);
+
+        // Takes effect at the next update event, after the current mark or space
+        *cx.resources.mark = !*cx.resources.mark;
+        let duty = if *cx.resources.mark {
+            cx.resources.led.get_max_duty() / 3
+        } else {
+            0
+        };
+        cx.resources.led.set_duty(duty);
+    }
+};
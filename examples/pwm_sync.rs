@@ -0,0 +1,55 @@
+//! Phase-lock two PWM timers with the master and slave modes
+//!
+//! TIM1 drives PA8 (CH1) and TIM2 drives PA0 (CH1), both at 10 kHz. Every update event
+//! of TIM1 resets the counter of TIM2 through TRGO, so the rising edges of both outputs
+//! stay aligned, while their duty cycles of 25 % and 50 % differ.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{
+    hal::Pwm as _,
+    pac::{TIM1, TIM2},
+    prelude::*,
+    pwm::{Channel, MasterMode, SlaveMode},
+    timer::internal_trigger,
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let pa8 = gpioa
+        .pa8
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let pa0 = gpioa
+        .pa0
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    let (mut master, _) = dp.TIM1.pwm_timer(pa8, 10.kHz(), clocks, &mut rcc.apb2);
+    let (mut slave, _) = dp.TIM2.pwm_timer(pa0, 10.kHz(), clocks, &mut rcc.apb1r1);
+
+    master.set_master_mode(MasterMode::Update);
+    slave.set_slave_mode(SlaveMode::Reset, internal_trigger::<TIM2, TIM1>());
+
+    let max = master.get_max_duty();
+    master.set_duty(Channel::C1, max / 4);
+    let max = slave.get_max_duty();
+    slave.set_duty(Channel::C1, max / 2);
+    master.enable(Channel::C1);
+    slave.enable(Channel::C1);
+
+    loop {
+        cortex_m::asm::wfi();
+    }
+}
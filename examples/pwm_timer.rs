@@ -0,0 +1,65 @@
+//! Sweep the frequency of two PWM outputs, one of them inverted
+//!
+//! TIM2 drives PA0 (CH1) and PA1 (CH2) with a duty cycle of 25 %, PA1 active low, so
+//! the outputs are high 25 % and 75 % of the period. The frequency steps from 1 kHz up
+//! to 64 kHz, each step for one second, and the duty cycles are kept.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    delay::Delay,
+    hal::Pwm as _,
+    prelude::*,
+    pwm::{Channel, Polarity},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let c1 = gpioa
+        .pa0
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let c2 = gpioa
+        .pa1
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    let (mut timer, (_, mut c2)) = dp
+        .TIM2
+        .pwm_timer((c1, c2), 1.kHz(), clocks, &mut rcc.apb1r1);
+    c2.set_polarity(Polarity::ActiveLow);
+    let max = timer.get_max_duty();
+    timer.set_duty(Channel::C1, max / 4);
+    timer.set_duty(Channel::C2, max / 4);
+    timer.enable(Channel::C1);
+    timer.enable(Channel::C2);
+
+    let mut frequency = 1_000;
+    loop {
+        // Scales the duties of the enabled channels along
+        timer.set_frequency(frequency.Hz());
+        let max = timer.get_max_duty();
+        rprintln!("{} Hz, {} duty steps", timer.get_period().raw(), max + 1);
+
+        delay.delay_ms(1_000_u32);
+        frequency = if frequency < 64_000 {
+            frequency * 2
+        } else {
+            1_000
+        };
+    }
+}
@@ -0,0 +1,68 @@
+//! Read the position of a rotary encoder with the encoder mode of TIM2
+//!
+//! Connect the A and B outputs of the encoder to PA0 and PA1, and its index output, if
+//! any, to PA2. The example prints the count, the direction and the position relative to
+//! the last index pulse whenever the count changes.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    hal::Direction,
+    prelude::*,
+    qei::{Config, Filter, Qei},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    // Mechanical encoders have open-drain outputs
+    let mut a = gpioa
+        .pa0
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    a.internal_pull_up(&mut gpioa.pupdr, true);
+    let mut b = gpioa
+        .pa1
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    b.internal_pull_up(&mut gpioa.pupdr, true);
+    let index = gpioa
+        .pa2
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    // Filter the contact bounce of the signals
+    let config = Config::default().filter(Filter::DeadTimeClock32N8);
+    let mut qei = Qei::new(dp.TIM2, (a, b), config, &mut rcc.apb1r1);
+    qei.enable_index(index, Filter::DeadTimeClock32N8);
+
+    let mut last = qei.count();
+    let mut origin = 0;
+    loop {
+        if let Some(count) = qei.index_count() {
+            origin = count;
+        }
+
+        let count = qei.count();
+        if count != last {
+            let direction = match qei.direction() {
+                Direction::Upcounting => "up",
+                Direction::Downcounting => "down",
+            };
+            // The count wraps around, so does the difference
+            let position = count.wrapping_sub(origin) as i32;
+            rprintln!("count {} {}, position {}", count, direction, position);
+            last = count;
+        }
+    }
+}
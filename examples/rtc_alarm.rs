@@ -12,7 +12,7 @@ extern crate stm32l4xx_hal as hal;
 
 use crate::hal::prelude::*;
 use crate::hal::rcc::{ClockSecuritySystem, CrystalBypass};
-use crate::hal::rtc::{Event, Rtc, RtcClockSource, RtcConfig};
+use crate::hal::rtc::{Alarm, AlarmConfig, Event, Rtc, RtcClockSource, RtcConfig};
 use crate::rt::ExceptionFrame;
 use cortex_m::interrupt::{free, Mutex};
 use time::{Date, Time};
@@ -56,16 +56,16 @@ fn main() -> ! {
 
     rtc.set_datetime(&date.with_time(time));
 
-    // Set alarm A for 1 minute
-    // let alarm_time = Time::new(21.hours(), 57.minutes(), 37.secs(), 0.micros(), false);
-    // let alarm_date = date;
-    // rtc.set_alarm(Alarm::AlarmA, alarm_date, alarm_time);
-    let mut wkp = rtc.wakeup_timer();
-    wkp.start(15_u32);
-    rtc.listen(&mut dp.EXTI, Event::WakeupTimer);
+    // Set alarm A for every minute at 37 seconds
+    let alarm_time = Time::from_hms(21, 57, 37).unwrap();
+    rtc.set_alarm(
+        Alarm::AlarmA,
+        AlarmConfig::new(alarm_time).mask_hours().mask_minutes(),
+    );
+    rtc.listen(&mut dp.EXTI, Event::AlarmA);
 
     unsafe {
-        NVIC::unmask(pac::Interrupt::RTC_WKUP);
+        NVIC::unmask(pac::Interrupt::RTC_ALARM);
     }
 
     free(|cs| {
@@ -78,13 +78,13 @@ fn main() -> ! {
 }
 
 #[interrupt]
-fn RTC_WKUP() {
+fn RTC_ALARM() {
     let mut hstdout = hio::hstdout().unwrap();
     free(|cs| {
         let mut rtc_ref = RTC.borrow(cs).borrow_mut();
         if let Some(ref mut rtc) = rtc_ref.deref_mut() {
-            if rtc.check_interrupt(Event::WakeupTimer, true) {
-                writeln!(hstdout, "RTC Wakeup!").unwrap();
+            if rtc.check_interrupt(Event::AlarmA, true) {
+                writeln!(hstdout, "RTC Alarm!").unwrap();
             }
         }
     });
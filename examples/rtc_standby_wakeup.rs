@@ -0,0 +1,61 @@
+//! Wake up from Standby every 30 s with the RTC wakeup timer
+//!
+//! The RTC runs on the LSE in the backup domain, which keeps the wakeup timer and the backup
+//! registers running in Standby. Every wakeup resets the MCU, which increments a counter in
+//! backup register 0 and enters Standby again. A power-on reset starts the timer and the
+//! counter over.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    pac,
+    prelude::*,
+    pwr::WakeUpSource,
+    rcc::{ClockSecuritySystem, CrystalBypass},
+    rtc::{Event, Rtc, RtcClockSource, RtcConfig},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let mut cp = pac::CorePeripherals::take().unwrap();
+    let mut dp = pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+
+    rcc.cfgr
+        .lse(CrystalBypass::Disable, ClockSecuritySystem::Disable)
+        .freeze(&mut flash.acr, &mut pwr);
+
+    // The RTC is only reset if its clock source changes, it keeps running otherwise
+    let mut rtc = Rtc::rtc(
+        dp.RTC,
+        &mut rcc.apb1r1,
+        &mut rcc.bdcr,
+        &mut pwr.cr1,
+        RtcConfig::default().clock_config(RtcClockSource::LSE),
+    );
+
+    let count = if pwr.read_wakeup_reason().standby() {
+        // Clearing the flag of the wakeup timer also clears the internal wakeup flag
+        rtc.check_interrupt(Event::WakeupTimer, true);
+        rtc.read_backup_register(0).unwrap().wrapping_add(1)
+    } else {
+        rtc.set_wakeup_timer(30.secs()).unwrap();
+        rtc.listen(&mut dp.EXTI, Event::WakeupTimer);
+        rtc.enable_wakeup_timer();
+        0
+    };
+    rtc.write_backup_register(0, count);
+    rprintln!("Wakeup {}", count);
+
+    let mut wkup = WakeUpSource(0);
+    wkup.set_internal_wkup(true);
+    pwr.standby(&wkup, true, &mut cp.SCB)
+}
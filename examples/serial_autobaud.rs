@@ -0,0 +1,63 @@
+//! Echo console which detects the baud rate of the host
+//!
+//! Open a terminal at any baud rate from 9600 to 230 400 on the USART2, e.g. the ST-LINK
+//! virtual COM port of a Nucleo board, and type `U`. The detected baud rate is printed,
+//! then the USART2 echoes everything typed.
+#![no_main]
+#![no_std]
+
+use core::fmt::Write;
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    prelude::*,
+    serial::{AutobaudMode, Config, Serial},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let tx = gpioa
+        .pa2
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let rx = gpioa
+        .pa3
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    let mut serial = Serial::usart2(
+        dp.USART2,
+        (tx, rx),
+        Config::default().baudrate(9_600.bps()),
+        clocks,
+        &mut rcc.apb1r1,
+    );
+
+    serial.request_autobaud(AutobaudMode::Frame0x55);
+    while let Err(error) = nb::block!(serial.check_autobaud()) {
+        rprintln!("{:?}, type U again", error);
+        serial.request_autobaud(AutobaudMode::Frame0x55);
+    }
+    // The `U` of the detection
+    let _ = nb::block!(serial.read());
+
+    let baud_rate = serial.baud_rate(&clocks);
+    rprintln!("{} baud", baud_rate.0);
+    writeln!(serial, "Detected {} baud\r", baud_rate.0).unwrap();
+
+    loop {
+        if let Ok(byte) = nb::block!(serial.read()) {
+            nb::block!(serial.write(byte)).unwrap();
+        }
+    }
+}
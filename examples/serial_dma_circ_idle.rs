@@ -0,0 +1,106 @@
+//! Receive the NMEA sentences of a GPS module into a circular DMA buffer
+//!
+//! The GPS module sends a burst of sentences once per second. The DMA writes every byte
+//! into the ring, the core only wakes up when the line goes idle after a burst, and
+//! prints the complete sentences straight out of the ring.
+//!
+//! Connect the TX pin of the GPS module to PA3, the RX of USART2.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    dma::{self, CircBuffer, CircReadDma, RxDma},
+    pac::USART2,
+    prelude::*,
+    serial::{self, Config, Rx, Serial},
+};
+
+const RING_SIZE: usize = 512;
+
+type Ring = CircBuffer<[u8; RING_SIZE], RxDma<Rx<USART2>, dma::dma1::C6>>;
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        ring: Ring,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        static mut RING: [u8; RING_SIZE] = [0; RING_SIZE];
+
+        rtt_target::rtt_init_print!();
+        rprintln!("Initializing... ");
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+        let channels = dp.DMA1.split(&mut rcc.ahb1);
+
+        let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+        let tx = gpioa
+            .pa2
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let rx = gpioa
+            .pa3
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+        let mut serial = Serial::usart2(
+            dp.USART2,
+            (tx, rx),
+            Config::default().baudrate(9_600.bps()),
+            clocks,
+            &mut rcc.apb1r1,
+        );
+        serial.listen(serial::Event::Idle);
+        let (_tx, rx) = serial.split();
+
+        init::LateResources {
+            ring: rx.with_dma(channels.6).circ_read(RING),
+        }
+    }
+
+    /// The line went idle after a burst of sentences
+    #[task(binds = USART2, resources = [ring])]
+    fn usart2(cx: usart2::Context) {
+        let ring = cx.resources.ring;
+
+        if !ring.is_idle(true) {
+            return;
+        }
+        if let Err(error) = ring.check_for_error() {
+            rprintln!("Serial error {:?}", error);
+        }
+
+        // Print the complete sentences, up to the last line end
+        let len = {
+            let (first, second) = ring.peek::<u8>();
+            let len = match second.iter().rposition(|&byte| byte == b'\n') {
+                Some(end) => first.len() + end + 1,
+                None => first
+                    .iter()
+                    .rposition(|&byte| byte == b'\n')
+                    .map_or(0, |end| end + 1),
+            };
+            // Either part may end in the middle of a UTF-8 character
+            let first = &first[..len.min(first.len())];
+            let second = &second[..len - first.len()];
+            rprintln!(
+                "{}{}",
+                core::str::from_utf8(first).unwrap_or("?"),
+                core::str::from_utf8(second).unwrap_or("?")
+            );
+            len
+        };
+        // The printed text is garbage if the DMA overwrote it meanwhile
+        if ring.consume::<u8>(len).is_err() {
+            rprintln!("Ring overrun, sentences lost");
+        }
+    }
+};
@@ -0,0 +1,100 @@
+//! Receive text lines into a circular DMA buffer, with an interrupt per line
+//!
+//! The DMA writes every byte into the ring, the character match interrupt fires on each
+//! line end, and the handler prints the lines straight out of the ring. Type into a
+//! terminal connected to the USART2, e.g. the ST-LINK virtual COM port of a Nucleo board.
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    dma::{self, CircBuffer, CircReadDma, RxDma},
+    pac::USART2,
+    prelude::*,
+    serial::{self, Config, Rx, Serial},
+};
+
+const RING_SIZE: usize = 256;
+
+type Ring = CircBuffer<[u8; RING_SIZE], RxDma<Rx<USART2>, dma::dma1::C6>>;
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        ring: Ring,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        static mut RING: [u8; RING_SIZE] = [0; RING_SIZE];
+
+        rtt_target::rtt_init_print!();
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+        let channels = dp.DMA1.split(&mut rcc.ahb1);
+
+        let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+        let tx = gpioa
+            .pa2
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let rx = gpioa
+            .pa3
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+        let mut serial = Serial::usart2(
+            dp.USART2,
+            (tx, rx),
+            Config::default().baudrate(115_200.bps()),
+            clocks,
+            &mut rcc.apb1r1,
+        );
+        // Terminals send a carriage return for the enter key
+        serial.set_character_match(b'\r');
+        serial.listen(serial::Event::CharacterMatch);
+        let (_tx, rx) = serial.split();
+
+        init::LateResources {
+            ring: rx.with_dma(channels.6).circ_read(RING),
+        }
+    }
+
+    /// A line end has been received
+    #[task(binds = USART2, resources = [ring])]
+    fn usart2(cx: usart2::Context) {
+        let ring = cx.resources.ring;
+
+        if !ring.check_character_match(true) {
+            return;
+        }
+
+        // Up to the last line end, the next line may have started meanwhile
+        let mut line = [0; RING_SIZE];
+        let len = {
+            let (first, second) = ring.peek::<u8>();
+            line[..first.len()].copy_from_slice(first);
+            line[first.len()..first.len() + second.len()].copy_from_slice(second);
+            line[..first.len() + second.len()]
+                .iter()
+                .rposition(|&byte| byte == b'\r')
+                .map_or(0, |end| end + 1)
+        };
+        // The copied lines are garbage if the DMA overwrote them meanwhile
+        if ring.consume::<u8>(len).is_err() {
+            rprintln!("Ring overrun, lines lost");
+            return;
+        }
+
+        for text in line[..len].split(|&byte| byte == b'\r') {
+            if !text.is_empty() {
+                rprintln!("{}", core::str::from_utf8(text).unwrap_or("?"));
+            }
+        }
+    }
+};
@@ -0,0 +1,79 @@
+//! Send frames with the serial TX DMA and receive them back with the RX DMA
+//!
+//! This example requires you to short (connect) the TX (PA9) and RX (PA10) pins. Two
+//! frames are sent back to back, the second one starts right after the first one is
+//! complete, and both have to arrive in full.
+#![no_main]
+#![no_std]
+
+use cortex_m::singleton;
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    dma::{CircReadDma, WriteDma},
+    prelude::*,
+    serial::{Config, Serial},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+    let channels = dp.DMA1.split(&mut rcc.ahb1);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let tx = gpioa
+        .pa9
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let rx = gpioa
+        .pa10
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    let serial = Serial::usart1(
+        dp.USART1,
+        (tx, rx),
+        Config::default().baudrate(115_200.bps()),
+        clocks,
+        &mut rcc.apb2,
+    );
+    // Each half carries its own DMA channel
+    let (tx, rx) = serial.split();
+    let mut tx = tx.with_dma(channels.4);
+    let mut ring = rx
+        .with_dma(channels.5)
+        .circ_read(singleton!(: [u8; 512] = [0; 512]).unwrap());
+
+    let mut frame = singleton!(: [u8; 200] = [0; 200]).unwrap();
+    for (i, byte) in frame.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    // Back to back, the first byte of the second frame must not get lost
+    for _ in 0..2 {
+        let (buffer, tx_dma) = tx.write(frame).complete();
+        frame = buffer;
+        tx = tx_dma;
+    }
+
+    let mut received = [0; 400];
+    let mut len = 0;
+    while len < received.len() {
+        len += ring.read(&mut received[len..]).unwrap();
+    }
+    assert_eq!(&received[..200], &frame[..]);
+    assert_eq!(&received[200..], &frame[..]);
+
+    rprintln!("Both frames received");
+
+    loop {
+        continue;
+    }
+}
@@ -0,0 +1,71 @@
+//! DMX512 controller which fades all 512 channels up and down
+//!
+//! The USART1 TX (PA9) drives an RS-485 transceiver, e.g. a MAX485 with DE and /RE
+//! tied high. Every packet starts with a break of at least 92 µs, which is longer than
+//! the break the USART sends at 250 kbaud, and the null start code.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{
+    delay::Delay,
+    prelude::*,
+    serial::{Config, Serial, StopBits},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let tx = gpioa
+        .pa9
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let rx = gpioa
+        .pa10
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    let serial = Serial::usart1(
+        dp.USART1,
+        (tx, rx),
+        Config::default()
+            .baudrate(250_000.bps())
+            .stopbits(StopBits::STOP2),
+        clocks,
+        &mut rcc.apb2,
+    );
+    let (mut tx, _rx) = serial.split();
+
+    let mut level = 0u8;
+    let mut up = true;
+    loop {
+        // The mark after the break lasts at least one bit time at the break baud rate
+        tx.send_long_break(100.micros(), &clocks);
+        tx.bwrite_all(&[0]).unwrap();
+        for _ in 0..512 {
+            tx.bwrite_all(&[level]).unwrap();
+        }
+        tx.bflush().unwrap();
+
+        if up {
+            level = level.saturating_add(5);
+        } else {
+            level = level.saturating_sub(5);
+        }
+        if level == 0 || level == 255 {
+            up = !up;
+        }
+        delay.delay_ms(25_u32);
+    }
+}
@@ -1,88 +1,94 @@
-//! Test the serial interface in Half-Duplex mode.
+//! Ping a servo on a single-wire half duplex bus, with a second USART as the servo
 //!
-//! This example requires you to hook-up a pullup resistor on the TX pin. RX pin is not used.
-//! Resistor value depends on the baurate and line caracteristics, 1KOhms works well in most cases.
-//! Half-Duplex mode internally connect TX to RX, meaning that bytes sent will also be received.
-#![deny(warnings)]
+//! This example requires you to connect the TX pins of the USART1 (PA9) and the USART2
+//! (PA2), the internal pull-ups keep the line high. The USART1 sends a Dynamixel
+//! protocol 1.0 ping, the USART2 answers with a status packet, neither receives the
+//! echo of its own packet.
 #![no_main]
 #![no_std]
 
-extern crate cortex_m;
-#[macro_use(entry, exception)]
-extern crate cortex_m_rt as rt;
-#[macro_use(block)]
-extern crate nb;
-extern crate panic_semihosting;
-
-extern crate stm32l4xx_hal as hal;
-// #[macro_use(block)]
-// extern crate nb;
-
-use crate::hal::prelude::*;
-use crate::hal::serial::{Config, Serial};
-use crate::rt::ExceptionFrame;
-use cortex_m::asm;
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    delay::Delay,
+    prelude::*,
+    serial::{Config, Serial},
+};
+
+const SERVO_ID: u8 = 1;
+/// Length of the parameters plus 2, 0 parameters
+const LENGTH: u8 = 2;
+const PING: u8 = 1;
+
+fn checksum(packet: &[u8]) -> u8 {
+    !packet[2..]
+        .iter()
+        .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+}
 
 #[entry]
 fn main() -> ! {
-    let p = hal::stm32::Peripherals::take().unwrap();
+    rtt_target::rtt_init_print!();
 
-    let mut flash = p.FLASH.constrain();
-    let mut rcc = p.RCC.constrain();
-    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
 
-    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
-    // let mut gpiob = p.GPIOB.split(&mut rcc.ahb2);
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
 
-    // clock configuration using the default settings (all clocks run at 8 MHz)
-    // let clocks = rcc.cfgr.freeze(&mut flash.acr);
-    // TRY this alternate clock configuration (clocks run at nearly the maximum frequency)
-    let clocks = rcc
-        .cfgr
-        .sysclk(80.MHz())
-        .pclk1(80.MHz())
-        .pclk2(80.MHz())
-        .freeze(&mut flash.acr, &mut pwr);
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
 
-    // The Serial API is highly generic
-    // TRY the commented out, different pin configurations
-    // let tx = gpioa.pa9.into_af7(&mut gpioa.moder, &mut gpioa.afrh).set_open_drain();
-    let tx =
+    let mut master_pin =
+        gpioa
+            .pa9
+            .into_alternate_open_drain(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    master_pin.internal_pull_up(&mut gpioa.pupdr, true);
+    let mut servo_pin =
         gpioa
             .pa2
             .into_alternate_open_drain(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
-    // let tx = gpiob.pb6.into_alternate_open_drain(&mut gpiob.moder, &mut gpioa.otyper, &mut gpiob.afrl);
-
-    // TRY using a different USART peripheral here
-    let serial = Serial::usart2(
-        p.USART2,
-        (tx,),
-        Config::default().baudrate(9_600.bps()),
-        clocks,
-        &mut rcc.apb1r1,
-    );
-    let (mut tx, mut rx) = serial.split();
-
-    let sent = b'X';
+    servo_pin.internal_pull_up(&mut gpioa.pupdr, true);
 
-    // The `block!` macro makes an operation block until it finishes
-    // NOTE the error type is `!`
+    let config = || Config::default().baudrate(1_000_000.bps());
+    let mut master =
+        Serial::new_half_duplex(dp.USART1, master_pin, config(), clocks, &mut rcc.apb2);
+    let mut servo =
+        Serial::new_half_duplex(dp.USART2, servo_pin, config(), clocks, &mut rcc.apb1r1);
 
-    block!(tx.write(sent)).ok();
-
-    let received = block!(rx.read()).unwrap();
-
-    assert_eq!(received, sent);
-
-    // if all goes well you should reach this breakpoint
-    asm::bkpt();
+    let mut request = [0xff, 0xff, SERVO_ID, LENGTH, PING, 0];
+    request[5] = checksum(&request);
+    // No error
+    let mut status = [0xff, 0xff, SERVO_ID, LENGTH, 0, 0];
+    status[5] = checksum(&status);
 
+    let mut pings = 0u32;
     loop {
-        continue;
+        // The servo takes each character before the next one arrives
+        let mut received = [0; 6];
+        for (byte, received) in request.iter().zip(received.iter_mut()) {
+            nb::block!(master.write(*byte)).unwrap();
+            *received = nb::block!(servo.read()).unwrap();
+        }
+        nb::block!(master.flush()).unwrap();
+        assert_eq!(received, request);
+
+        for (byte, received) in status.iter().zip(received.iter_mut()) {
+            nb::block!(servo.write(*byte)).unwrap();
+            *received = nb::block!(master.read()).unwrap();
+        }
+        nb::block!(servo.flush()).unwrap();
+        assert_eq!(received, status);
+
+        // Without the turnaround, the echo of the last character would be pending
+        assert!(matches!(servo.read(), Err(nb::Error::WouldBlock)));
+        assert!(matches!(master.read(), Err(nb::Error::WouldBlock)));
+
+        pings += 1;
+        rprintln!("{} pings answered", pings);
+        delay.delay_ms(100_u32);
     }
 }
-
-#[exception]
-unsafe fn HardFault(ef: &ExceptionFrame) -> ! {
-    panic!("{:#?}", ef);
-}
@@ -0,0 +1,72 @@
+//! Provoke a serial overrun and check that the receiver recovers from it
+//!
+//! This example requires you to short (connect) the TX (PA9) and RX (PA10) pins. Three
+//! characters are sent back to back without reading them, so the second one overruns
+//! the first one, which has to be received still, and the third one gets lost as well.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    prelude::*,
+    serial::{Config, Error, Serial},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let tx = gpioa
+        .pa9
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let rx = gpioa
+        .pa10
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+
+    let serial = Serial::usart1(
+        dp.USART1,
+        (tx, rx),
+        Config::default().baudrate(115_200.bps()),
+        clocks,
+        &mut rcc.apb2,
+    );
+    let (mut tx, mut rx) = serial.split();
+
+    for round in 0u8.. {
+        let first = round.wrapping_mul(3);
+        tx.bwrite_all(&[first, first.wrapping_add(1), first.wrapping_add(2)])
+            .unwrap();
+        tx.bflush().unwrap();
+        // The receiver samples the stop bit of the last character after TC, wait for
+        // a character time
+        cortex_m::asm::delay(clocks.sysclk().raw() / 115_200 * 10);
+
+        // Polling does not consume anything
+        assert_eq!(rx.check_errors(), Err(Error::Overrun));
+        assert_eq!(nb::block!(rx.read()), Ok(first));
+        assert_eq!(nb::block!(rx.read()), Err(Error::Overrun));
+        assert_eq!(rx.read(), Err(nb::Error::WouldBlock));
+
+        // Not wedged, the next character is received again
+        let next = first.wrapping_add(3);
+        tx.bwrite_all(&[next]).unwrap();
+        assert_eq!(nb::block!(rx.read()), Ok(next));
+
+        rprintln!("Round {} recovered from the overrun", round);
+    }
+
+    loop {
+        continue;
+    }
+}
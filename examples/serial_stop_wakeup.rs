@@ -0,0 +1,144 @@
+//! Data logger which sleeps in Stop 1 until the host starts sending
+//!
+//! The USART2 runs on the HSI16, which the start bit of the first character starts up
+//! in Stop 1. The HSI16 takes a few µs to start, a fraction of the 8.7 µs bit time at
+//! 115 200 baud, so the first character may be received with an error, but none of
+//! the following characters gets lost. The line going idle ends a frame.
+//!
+//! Send frames of counting bytes from the host, e.g. with pyserial:
+//!
+//! ```text
+//! $ python3 -c "import serial, time; s = serial.Serial('/dev/ttyACM0', 115200);
+//!   [(s.write(bytes(range(256))), time.sleep(1)) for _ in range(10)]"
+//! ```
+//!
+//! Every frame prints `256 bytes, 0 out of sequence, 0 errors`, or with an error in the
+//! first character, `255 bytes, 0 out of sequence, 1 errors`.
+#![no_main]
+#![no_std]
+
+use core::sync::atomic::{AtomicBool, Ordering};
+use cortex_m::peripheral::SCB;
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    pac::USART2,
+    prelude::*,
+    pwr::Pwr,
+    rcc::UsartClockSource,
+    serial::{Config, Event, Rx, Serial, WakeSource},
+};
+
+/// Set from the wakeup to the end of a frame, Stop 1 is only entered outside
+static IN_FRAME: AtomicBool = AtomicBool::new(false);
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    struct Resources {
+        rx: Rx<USART2>,
+        pwr: Pwr,
+        scb: SCB,
+        #[init(None)]
+        last: Option<u8>,
+        #[init(0)]
+        bytes: usize,
+        #[init(0)]
+        out_of_sequence: usize,
+        #[init(0)]
+        errors: usize,
+    }
+
+    #[init]
+    fn init(cx: init::Context) -> init::LateResources {
+        rtt_target::rtt_init_print!();
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+        // The system resumes from Stop 1 on the MSI, so keep running on it
+        let clocks = rcc.cfgr.freeze(&mut flash.acr, &mut pwr);
+        rcc.ccipr
+            .set_kernel_clock::<USART2>(UsartClockSource::Hsi16, &clocks);
+
+        let tx = gpioa
+            .pa2
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+        let rx = gpioa
+            .pa3
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+        let mut serial = Serial::usart2(
+            dp.USART2,
+            (tx, rx),
+            Config::default().baudrate(115_200.bps()),
+            clocks,
+            &mut rcc.apb1r1,
+        );
+        serial.enable_stop_wakeup(WakeSource::StartBit).unwrap();
+        serial.listen(Event::WakeupFromStop);
+        serial.listen(Event::Rxne);
+        serial.listen(Event::Idle);
+        let (_tx, rx) = serial.split();
+
+        init::LateResources {
+            rx,
+            pwr,
+            scb: cx.core.SCB,
+        }
+    }
+
+    #[idle(resources = [pwr, scb])]
+    fn idle(cx: idle::Context) -> ! {
+        loop {
+            // With interrupts disabled, a start bit between the check and the entry
+            // leaves the interrupt pending, which prevents the entry
+            cortex_m::interrupt::free(|_| {
+                if !IN_FRAME.load(Ordering::Relaxed) {
+                    cx.resources.pwr.stop1(cx.resources.scb);
+                }
+            });
+            // The pending USART2 task runs here
+        }
+    }
+
+    #[task(binds = USART2, resources = [rx, last, bytes, out_of_sequence, errors])]
+    fn usart2(cx: usart2::Context) {
+        let rx = cx.resources.rx;
+        let last = cx.resources.last;
+
+        // The start bit which woke the core up
+        if rx.is_wakeup(true) {
+            IN_FRAME.store(true, Ordering::Relaxed);
+        }
+
+        match rx.read() {
+            Ok(byte) => {
+                if matches!(*last, Some(last) if byte != last.wrapping_add(1)) {
+                    *cx.resources.out_of_sequence += 1;
+                }
+                *last = Some(byte);
+                *cx.resources.bytes += 1;
+            }
+            Err(nb::Error::Other(_)) => *cx.resources.errors += 1,
+            Err(nb::Error::WouldBlock) => (),
+        }
+
+        if rx.is_idle(true) {
+            rprintln!(
+                "{} bytes, {} out of sequence, {} errors",
+                cx.resources.bytes,
+                cx.resources.out_of_sequence,
+                cx.resources.errors
+            );
+            *last = None;
+            *cx.resources.bytes = 0;
+            *cx.resources.out_of_sequence = 0;
+            *cx.resources.errors = 0;
+            IN_FRAME.store(false, Ordering::Relaxed);
+        }
+    }
+};
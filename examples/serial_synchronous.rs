@@ -0,0 +1,68 @@
+//! Use the USART1 as an additional SPI master, in synchronous mode
+//!
+//! The USART drives a 74HC595 shift register, which shows a counter on eight LEDs:
+//! CK (PA8) to SRCLK, TX (PA9) to SER and PA4 to RCLK. RX (PA10) is not connected.
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use stm32l4xx_hal::{
+    delay::Delay,
+    hal::spi::MODE_0,
+    prelude::*,
+    serial::{Config, Serial, SynchronousConfig},
+};
+
+#[entry]
+fn main() -> ! {
+    rtt_target::rtt_init_print!();
+
+    let cp = cortex_m::Peripherals::take().unwrap();
+    let dp = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = dp.FLASH.constrain();
+    let mut rcc = dp.RCC.constrain();
+    let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+    let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+
+    let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    let mut delay = Delay::new(cp.SYST, clocks);
+
+    let ck = gpioa
+        .pa8
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let tx = gpioa
+        .pa9
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let rx = gpioa
+        .pa10
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+    let mut latch = gpioa
+        .pa4
+        .into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
+
+    // 8 clocks per byte, MSB first, as the 74HC595 expects it
+    let serial = Serial::new_synchronous(
+        dp.USART1,
+        (tx, rx),
+        ck,
+        Config::default().baudrate(1_000_000.bps()),
+        SynchronousConfig::new(MODE_0).msb_first(true),
+        clocks,
+        &mut rcc.apb2,
+    );
+    let (mut tx, _rx) = serial.split();
+
+    let mut counter = 0u8;
+    loop {
+        tx.bwrite_all(&[counter]).unwrap();
+        tx.bflush().unwrap();
+        // The outputs take over the shifted byte on the rising edge
+        latch.set_high();
+        latch.set_low();
+
+        counter = counter.wrapping_add(1);
+        delay.delay_ms(100_u32);
+    }
+}
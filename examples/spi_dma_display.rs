@@ -0,0 +1,167 @@
+//! Flush a frame to an ILI9341 display with SPI TX DMA
+//!
+//! The commands are written with the blocking API in 8-bit frames, the RGB565 pixels in
+//! 16-bit frames with DMA, 40 lines at a time.
+#![deny(unsafe_code)]
+#![no_main]
+#![no_std]
+
+use panic_rtt_target as _;
+use rtt_target::rprintln;
+use stm32l4xx_hal::{
+    delay::Delay,
+    dma::WriteDma,
+    gpio::{PinState, Speed},
+    hal::{
+        blocking::{delay::DelayMs, spi::Write},
+        digital::v2::OutputPin,
+        spi::{Mode, Phase, Polarity},
+    },
+    prelude::*,
+    rcc::MsiFreq,
+    spi::Spi,
+};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 240;
+const STRIP_LINES: usize = 40;
+
+/// Writes the command `cmd` followed by its parameters `data`
+fn command<SPI, DC>(spi: &mut SPI, dc: &mut DC, cmd: u8, data: &[u8])
+where
+    SPI: Write<u8>,
+    DC: OutputPin,
+{
+    dc.set_low().ok();
+    spi.write(&[cmd]).ok();
+    dc.set_high().ok();
+    spi.write(data).ok();
+}
+
+#[rtic::app(device = stm32l4xx_hal::pac, peripherals = true)]
+const APP: () = {
+    #[init]
+    fn init(cx: init::Context) {
+        static mut STRIP: [u16; WIDTH * STRIP_LINES] = [0; WIDTH * STRIP_LINES];
+
+        rtt_target::rtt_init_print!();
+        rprintln!("Initializing... ");
+
+        let dp = cx.device;
+
+        let mut flash = dp.FLASH.constrain();
+        let mut rcc = dp.RCC.constrain();
+        let mut pwr = dp.PWR.constrain(&mut rcc.apb1r1);
+        let mut gpioa = dp.GPIOA.split(&mut rcc.ahb2);
+        let mut gpiob = dp.GPIOB.split(&mut rcc.ahb2);
+        let dma1_channels = dp.DMA1.split(&mut rcc.ahb1);
+
+        let clocks = rcc
+            .cfgr
+            .msi(MsiFreq::RANGE4M)
+            .sysclk(80.MHz())
+            .freeze(&mut flash.acr, &mut pwr);
+        let mut delay = Delay::new(cx.core.SYST, clocks);
+
+        //
+        // Initialize the SPI and the control pins of the display
+        //
+        let sck = gpioa
+            .pa5
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl)
+            .speed(Speed::VeryHigh);
+        let miso = gpioa
+            .pa6
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl)
+            .speed(Speed::VeryHigh);
+        let mosi = gpioa
+            .pa7
+            .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl)
+            .speed(Speed::VeryHigh);
+        let mut cs = gpioa.pa4.into_push_pull_output_in_state(
+            &mut gpioa.moder,
+            &mut gpioa.otyper,
+            PinState::High,
+        );
+        let mut dc = gpiob.pb0.into_push_pull_output_in_state(
+            &mut gpiob.moder,
+            &mut gpiob.otyper,
+            PinState::High,
+        );
+        let mut rst = gpiob.pb1.into_push_pull_output_in_state(
+            &mut gpiob.moder,
+            &mut gpiob.otyper,
+            PinState::High,
+        );
+
+        let mut spi = Spi::spi1(
+            dp.SPI1,
+            (sck, miso, mosi),
+            Mode {
+                phase: Phase::CaptureOnFirstTransition,
+                polarity: Polarity::IdleLow,
+            },
+            40.MHz(),
+            clocks,
+            &mut rcc.apb2,
+        );
+
+        //
+        // Wake up the display
+        //
+        rst.set_low();
+        delay.delay_ms(10_u32);
+        rst.set_high();
+        delay.delay_ms(120_u32);
+
+        cs.set_low();
+        // Sleep out
+        command(&mut spi, &mut dc, 0x11, &[]);
+        delay.delay_ms(120_u32);
+        // Pixel format: 16 bits per pixel
+        command(&mut spi, &mut dc, 0x3a, &[0x55]);
+        // Memory access control: landscape
+        command(&mut spi, &mut dc, 0x36, &[0x28]);
+        // Display on
+        command(&mut spi, &mut dc, 0x29, &[]);
+
+        //
+        // Flush the frame
+        //
+        let [x_hi, x_lo] = (WIDTH as u16 - 1).to_be_bytes();
+        let [y_hi, y_lo] = (HEIGHT as u16 - 1).to_be_bytes();
+        // Column and page address set: the whole display
+        command(&mut spi, &mut dc, 0x2a, &[0, 0, x_hi, x_lo]);
+        command(&mut spi, &mut dc, 0x2b, &[0, 0, y_hi, y_lo]);
+        // Memory write, the pixels follow as data
+        command(&mut spi, &mut dc, 0x2c, &[]);
+
+        let mut strip: &'static mut [u16] = STRIP;
+        // Each pixel is sent as one 16-bit frame, most significant byte first
+        let mut dma_spi = spi.frame_size::<u16>().with_tx_dma(dma1_channels.3);
+        for first_line in (0..HEIGHT).step_by(STRIP_LINES) {
+            // A gradient from black to red, top to bottom
+            let red = (first_line * 32 / HEIGHT) as u16;
+            strip.iter_mut().for_each(|pixel| *pixel = red << 11);
+
+            let (buf, tx) = dma_spi.write(strip).wait();
+            strip = buf;
+            dma_spi = tx;
+        }
+        cs.set_high();
+
+        // Back to 8-bit frames for further commands
+        let (spi, _channel) = dma_spi.split();
+        let _spi = spi.frame_size::<u8>();
+
+        rprintln!("Frame flushed");
+    }
+
+    // Idle function so RTT keeps working
+    #[idle]
+    fn idle(_cx: idle::Context) -> ! {
+        loop {
+            continue;
+        }
+    }
+};
@@ -0,0 +1,71 @@
+//! Receive the frames of an SPI master into a circular DMA buffer
+#![no_main]
+#![no_std]
+
+use cortex_m_rt::entry;
+use panic_rtt_target as _;
+use rtt_target::{rprintln, rtt_init_print};
+use stm32l4xx_hal::{
+    dma::CircReadDma,
+    hal::spi::{Mode, Phase, Polarity},
+    prelude::*,
+    spi::Spi,
+};
+
+#[entry]
+fn main() -> ! {
+    static mut BUF: [u8; 64] = [0; 64];
+
+    rtt_init_print!();
+
+    let p = stm32l4xx_hal::pac::Peripherals::take().unwrap();
+
+    let mut flash = p.FLASH.constrain();
+    let mut rcc = p.RCC.constrain();
+    let mut pwr = p.PWR.constrain(&mut rcc.apb1r1);
+    let _clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+
+    let mut gpioa = p.GPIOA.split(&mut rcc.ahb2);
+    let channels = p.DMA1.split(&mut rcc.ahb1);
+
+    let nss = gpioa
+        .pa4
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let sck = gpioa
+        .pa5
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let miso = gpioa
+        .pa6
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+    let mosi = gpioa
+        .pa7
+        .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+
+    // The clock is provided by the master, which selects the slave by pulling NSS low
+    let spi = Spi::new_slave(
+        p.SPI1,
+        (sck, miso, mosi, nss),
+        Mode {
+            phase: Phase::CaptureOnFirstTransition,
+            polarity: Polarity::IdleLow,
+        },
+        &mut rcc.apb2,
+    );
+
+    let mut rx = spi.with_rx_dma(channels.2).circ_read(BUF);
+
+    let mut frames = [0; 64];
+    loop {
+        match rx.read(&mut frames) {
+            Ok(0) => {}
+            Ok(len) => rprintln!("received {:x?}", &frames[..len]),
+            Err(_) => {
+                // The master sent faster than we read, start over with a resynchronized slave
+                let (buf, rx_dma) = rx.stop();
+                let (mut spi, channel) = rx_dma.split();
+                spi.reset_frame(&mut rcc.apb2);
+                rx = spi.with_rx_dma(channel).circ_read(buf);
+            }
+        }
+    }
+}
@@ -40,7 +40,7 @@ fn main() -> ! {
 
     unsafe { NVIC::unmask(hal::stm32::Interrupt::TIM7) };
     let mut timer = Timer::tim6(dp.TIM6, 1.Hz(), clocks, &mut rcc.apb1r1);
-    timer.listen(Event::TimeOut);
+    timer.listen(Event::Update);
 
     loop {
         continue;
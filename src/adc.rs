@@ -7,10 +7,37 @@ use core::{
     sync::atomic::{self, Ordering},
 };
 
+use embedded_dma::StaticWriteBuffer;
 use stable_deref_trait::StableDeref;
 
+#[cfg(any(
+    feature = "stm32l412",
+    feature = "stm32l422",
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+mod dual;
+#[cfg(any(
+    feature = "stm32l412",
+    feature = "stm32l422",
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+pub use dual::DualAdc;
+
 use crate::{
-    dma::{dma1, Event as DMAEvent, RxDma, Transfer, TransferPayload, W},
+    dma::{
+        dma1, CircBuffer, CircReadDma, Event as DMAEvent, Receive, RxDma, Transfer,
+        TransferPayload, W,
+    },
     dmamux::{DmaInput, DmaMux},
     gpio::{self, Analog},
     hal::{
@@ -18,7 +45,7 @@ use crate::{
         blocking::delay::DelayUs,
     },
     pac::{self, ADC1},
-    rcc::{Enable, Reset, AHB2, CCIPR},
+    rcc::{AdcClockSource, Enable, Reset, AHB2, CCIPR},
     signature::{VrefCal, VtempCalHigh, VtempCalLow, VDDA_CALIB_MV},
 };
 
@@ -28,6 +55,11 @@ pub struct Vref {
 }
 
 /// Internal battery monitoring channel.
+///
+/// The channel is sampled for at least 640.5 ADC clock cycles, the longest sample time.
+/// The datasheet asks for at least 12 us, which this only gives with an ADC clock of up to
+/// 53 MHz. With a faster system clock, select a slower ADC kernel clock with
+/// [`CCIPR::set_adc_clock_source`] before reading VBAT.
 pub struct Vbat {
     _0: (),
 }
@@ -37,6 +69,37 @@ pub struct Temperature {
     _0: (),
 }
 
+impl Vref {
+    /// Calculates VDDA in millivolts from a 12 bit conversion of the internal voltage
+    /// reference, using the factory calibration value `VREFINT_CAL`.
+    ///
+    /// [`Adc::calibrate`] does this with a conversion at the current ADC settings.
+    pub fn calculate_vdda(raw: u16) -> u32 {
+        (VDDA_CALIB_MV * u32::from(VrefCal::get().read())) / u32::from(raw.max(1))
+    }
+}
+
+impl Temperature {
+    /// Converts a 12 bit conversion of the temperature sensor to degrees Celsius, using the
+    /// factory calibration values `TS_CAL1` and `TS_CAL2`.
+    ///
+    /// `vdda` is the VDDA voltage in millivolts during the conversion, as returned by
+    /// [`Vref::calculate_vdda`].
+    pub fn convert_to_celsius(raw: u16, vdda: u32) -> f32 {
+        // The calibration values were measured at VDDA = 3.0 V
+        let sample = (u32::from(raw) * vdda) / VDDA_CALIB_MV;
+        (VtempCalHigh::TEMP_DEGREES - VtempCalLow::TEMP_DEGREES) as f32
+          // as signed because RM0351 doesn't specify against this being an
+          // inverse relation (which would result in a negative differential)
+          / (VtempCalHigh::get().read() as i32 - VtempCalLow::get().read() as i32) as f32
+          // this can definitely be negative so must be done as a signed value
+          * (sample as i32 - VtempCalLow::get().read() as i32) as f32
+          // while it would make sense for this to be `VtempCalLow::TEMP_DEGREES` (which is 30*C),
+          // the RM specifically uses 30*C so this will too
+          + 30.0
+    }
+}
+
 /// Wrapper for safely sharing [`ADC_COMMON`](pac::ADC_COMMON) between `Adc`s.
 #[derive(Clone, Copy)]
 pub struct AdcCommon {
@@ -85,6 +148,22 @@ impl AdcCommonCcr {
     }
 }
 
+impl AdcCommonCdr {
+    /// Address of the register, for DMA transfers
+    #[allow(unused)]
+    #[inline]
+    fn address(&self) -> u32 {
+        unsafe { &(*pac::ADC_COMMON::ptr()).cdr as *const _ as u32 }
+    }
+
+    #[allow(unused)]
+    #[inline]
+    fn read(&self) -> u32 {
+        let adc_common = unsafe { &*pac::ADC_COMMON::ptr() };
+        adc_common.cdr.read().bits()
+    }
+}
+
 impl AdcCommon {
     /// Enable and reset [`ADC_COMMON`](pac::ADC_COMMON) peripheral.
     pub fn new(adc_common: pac::ADC_COMMON, ahb: &mut AHB2) -> Self {
@@ -108,18 +187,28 @@ pub struct Adc<ADC> {
     adc_common: AdcCommon,
     resolution: Resolution,
     sample_time: SampleTime,
+    align: Align,
+    oversampling: Option<(OversamplingRatio, OversamplingShift)>,
     calibrated_vdda: u32,
+    /// Calibration factors saved by `Adc::power_down`, both the single-ended and the
+    /// differential one
+    calfact: Option<u32>,
 }
 
 #[derive(Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DmaMode {
     Disabled = 0,
+    /// Converts the sequence until the buffer is full, then stops
     Oneshot = 1,
-    // FIXME: Figure out how to get circular DMA to function properly (requires circbuffer?)
-    // Circular = 2,
+    /// Converts continuously, wrapping around at the end of the buffer
+    ///
+    /// Use [`CircReadDma::circ_read`] to read the samples as they arrive.
+    Circular = 2,
 }
 
 #[derive(PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Sequence {
     One = 0,
     Two = 1,
@@ -186,16 +275,76 @@ impl Into<u8> for Sequence {
     }
 }
 
+/// ADC error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A conversion result was overwritten before it was read
+    Overrun,
+    /// The oversampling ratio and shift give results wider than 16 bits
+    OversamplingOverflow,
+    /// The configuration can not be changed while a conversion is ongoing
+    ConversionOngoing,
+    /// More samples were requested than a single sequence converts
+    SequenceLength,
+}
+
 #[derive(PartialEq, PartialOrd, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
     EndOfRegularSequence,
     EndOfRegularConversion,
+    /// The watched channel of [`Watchdog::Awd1`] left its threshold window
+    AnalogWatchdog1,
+    /// A watched channel of [`Watchdog::Awd2`] left its threshold window
+    AnalogWatchdog2,
+    /// A watched channel of [`Watchdog::Awd3`] left its threshold window
+    AnalogWatchdog3,
+    EndOfInjectedConversion,
+    EndOfInjectedSequence,
+}
+
+impl Event {
+    /// Position of the flag in ADC_ISR and of the enable bit in ADC_IER
+    fn bit(self) -> u32 {
+        match self {
+            Event::EndOfRegularConversion => 1 << 2,
+            Event::EndOfRegularSequence => 1 << 3,
+            Event::AnalogWatchdog1 => 1 << 7,
+            Event::AnalogWatchdog2 => 1 << 8,
+            Event::AnalogWatchdog3 => 1 << 9,
+            Event::EndOfInjectedConversion => 1 << 5,
+            Event::EndOfInjectedSequence => 1 << 6,
+        }
+    }
+}
+
+/// ADC analog watchdog
+///
+/// `Awd1` watches a single channel with 12 bit thresholds, `Awd2` and `Awd3` watch any
+/// set of channels with 8 bit thresholds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Watchdog {
+    Awd1,
+    Awd2,
+    Awd3,
 }
 
 impl<ADC> Adc<ADC> {
-    /// Set the ADC resolution
-    pub fn set_resolution(&mut self, resolution: Resolution) {
-        self.resolution = resolution;
+    /// Convert a differential measurement to millivolts
+    pub fn to_differential_millivolts(&self, sample: u16) -> i32 {
+        let mid = (self.max_count() + 1) >> 1;
+        (i64::from(i32::from(sample) - mid as i32) * i64::from(self.calibrated_vdda)
+            / i64::from(mid)) as i32
+    }
+
+    /// Set the VDDA voltage in millivolts used to convert the results
+    ///
+    /// Only ADC1 can measure VDDA with [`Adc::calibrate`], so this passes its result
+    /// to the other ADCs.
+    pub fn set_calibrated_vdda(&mut self, vdda: u16) {
+        self.calibrated_vdda = u32::from(vdda);
     }
 
     /// Set the sample time
@@ -203,41 +352,56 @@ impl<ADC> Adc<ADC> {
         self.sample_time = sample_time;
     }
 
-    /// Get the max value for the current resolution
+    /// Get the max value for the current resolution, alignment and oversampling
+    ///
+    /// The oversampling setters reject results wider than 16 bits, the value saturates at
+    /// `u16::MAX` regardless.
     pub fn get_max_value(&self) -> u16 {
-        match self.resolution {
-            Resolution::Bits12 => 4095,
-            Resolution::Bits10 => 1023,
-            Resolution::Bits8 => 255,
-            Resolution::Bits6 => 63,
+        self.max_count().min(u32::from(u16::MAX)) as u16
+    }
+
+    /// Max value of a regular conversion result
+    fn max_count(&self) -> u32 {
+        let max = self.resolution.to_max_count();
+        match (self.oversampling, self.align) {
+            (Some((ratio, shift)), _) => (max << (ratio as u8 + 1)) >> shift as u8,
+            (None, Align::Right) => max,
+            (None, Align::Left) if self.resolution == Resolution::Bits6 => max << 2,
+            (None, Align::Left) => max << (16 - self.resolution.bits()),
         }
     }
 
-    /// Release the ADC peripheral
-    ///
-    /// Drops `Adc` and returns the `(pac::ADC, pad::ADC_COMMON)` that it was wrapping,
-    /// giving the user full access to the peripheral.
-    pub fn release(self) -> ADC {
-        self.adc
+    /// Scales a threshold in the unit of the conversion results to the 12 bit value the
+    /// analog watchdogs compare against
+    fn to_watchdog_threshold(&self, value: u16) -> u32 {
+        let bits = match self.oversampling {
+            Some((ratio, shift)) => {
+                i32::from(self.resolution.bits()) + ratio as i32 + 1 - shift as i32
+            }
+            None => i32::from(self.resolution.bits()),
+        };
+        let value = u32::from(value);
+        if bits <= 12 {
+            value << (12 - bits)
+        } else {
+            value >> (bits - 12)
+        }
+    }
+
+    /// Scales a regular conversion result to the 12 bit range of the factory calibration
+    /// values
+    fn to_12_bits(&self, sample: u16) -> u32 {
+        u32::from(sample) * Resolution::Bits12.to_max_count() / self.max_count()
     }
 
     /// Convert a measurement to millivolts
     pub fn to_millivolts(&self, sample: u16) -> u16 {
-        ((u32::from(sample) * self.calibrated_vdda) / self.resolution.to_max_count()) as u16
+        ((u32::from(sample) * self.calibrated_vdda) / self.max_count()) as u16
     }
 
     /// Convert a raw sample from the `Temperature` to deg C
     pub fn to_degrees_centigrade(&self, sample: u16) -> f32 {
-        let sample = (u32::from(sample) * self.calibrated_vdda) / VDDA_CALIB_MV;
-        (VtempCalHigh::TEMP_DEGREES - VtempCalLow::TEMP_DEGREES) as f32
-          // as signed because RM0351 doesn't specify against this being an
-          // inverse relation (which would result in a negative differential)
-          / (VtempCalHigh::get().read() as i32 - VtempCalLow::get().read() as i32) as f32
-          // this can definitely be negative so must be done as a signed value
-          * (sample as i32 - VtempCalLow::get().read() as i32) as f32
-          // while it would make sense for this to be `VtempCalLow::TEMP_DEGREES` (which is 30*C),
-          // the RM specifically uses 30*C so this will too
-          + 30.0
+        Temperature::convert_to_celsius(self.to_12_bits(sample) as u16, self.calibrated_vdda)
     }
 }
 
@@ -247,35 +411,48 @@ impl Adc<ADC1> {
     //  ADC2: DMA2_4 with C2S 0000
     //  ADC1: DMA1_1 with C1S 0000 (implemented)
     //  ADC2: DMA1_2 with C1S 0000
-}
-
-impl<C> OneShot<ADC1, u16, C> for Adc<ADC1>
-where
-    C: Channel<ADC1>,
-{
-    type Error = Infallible;
-
-    fn read(&mut self, channel: &mut C) -> nb::Result<u16, Self::Error> {
-        self.configure_sequence(channel, Sequence::One, self.sample_time);
 
-        self.start_conversion();
-        while !self.has_completed_sequence() {}
-
-        // Read ADC value first time and discard it, as per errata sheet.
-        // The errata state that if we do conversions slower than 1 kHz, the
-        // first read ADC value can be corrupted, so we discard it and measure again.
-        let _ = self.current_sample();
+    /// Pairs the ADC with DMA1 channel 1 for DMA transfers of the regular sequence
+    ///
+    /// The sequence has to be configured with [`Adc::configure_sequence`] before
+    /// starting a transfer with [`Transfer::from_adc_dma`] or
+    /// [`CircReadDma::circ_read`].
+    pub fn with_dma(self, mut channel: dma1::C1) -> RxDma<Self, dma1::C1> {
+        channel.set_peripheral_address(&self.adc.dr as *const _ as u32, false);
+        channel.set_request_line(DmaInput::Adc1).unwrap();
 
-        self.start_conversion();
-        while !self.has_completed_sequence() {}
+        RxDma {
+            payload: self,
+            channel,
+        }
+    }
 
-        // Read ADC value.
-        let val = self.current_sample();
+    /// Configures the DMA requests, continuous conversions and overrun behaviour of
+    /// the ADC for `dma_mode`
+    fn set_dma_mode(&mut self, dma_mode: DmaMode) {
+        let (enable, circular) = match dma_mode {
+            DmaMode::Disabled => (false, false),
+            DmaMode::Oneshot => (true, false),
+            DmaMode::Circular => (true, true),
+        };
 
-        // Disable ADC.
-        self.disable();
+        // The configuration can only be changed while no conversion is ongoing
+        self.stop_conversion();
 
-        Ok(val)
+        // In circular mode the ADC free-runs and late samples are overwritten, the
+        // `CircBuffer` detects when the consumer falls behind. In one-shot mode an
+        // overrun blocks further DMA requests, which `has_overrun` reports.
+        self.adc.cfgr.modify(|_, w| {
+            w.dmaen()
+                .bit(enable)
+                .dmacfg()
+                .bit(circular)
+                .cont()
+                .bit(circular)
+                .ovrmod()
+                .bit(circular)
+        });
+        self.clear_overrun();
     }
 }
 
@@ -290,8 +467,11 @@ impl TransferPayload for RxDma<Adc<ADC1>, dma1::C1> {
 }
 
 impl RxDma<Adc<ADC1>, dma1::C1> {
+    /// Stops the DMA and the conversions and returns the ADC with DMA requests
+    /// disabled, and the channel
     pub fn split(mut self) -> (Adc<ADC1>, dma1::C1) {
         self.stop();
+        self.payload.set_dma_mode(DmaMode::Disabled);
         (self.payload, self.channel)
     }
 }
@@ -325,14 +505,8 @@ where
     ) -> Self {
         assert!(dma_mode != DmaMode::Disabled);
 
-        let (enable, circular) = match dma_mode {
-            DmaMode::Disabled => (false, false),
-            DmaMode::Oneshot => (true, false),
-        };
-
-        adc.adc
-            .cfgr
-            .modify(|_, w| w.dmaen().bit(enable).dmacfg().bit(circular));
+        let circular = dma_mode == DmaMode::Circular;
+        adc.set_dma_mode(dma_mode);
 
         channel.set_peripheral_address(&adc.adc.dr as *const _ as u32, false);
 
@@ -381,24 +555,101 @@ where
     }
 }
 
+impl Receive for RxDma<Adc<ADC1>, dma1::C1> {
+    type RxChannel = dma1::C1;
+    type TransmittedWord = u16;
+}
+
+impl<B> CircReadDma<B, u16> for RxDma<Adc<ADC1>, dma1::C1>
+where
+    &'static mut B: StaticWriteBuffer<Word = u16>,
+    B: 'static,
+{
+    /// Starts continuous conversions of the regular sequence into `buffer`
+    ///
+    /// The samples of the sequence are stored in order, so the buffer length should be
+    /// a multiple of the sequence length. Listen to [`DMAEvent::HalfTransfer`] and
+    /// [`DMAEvent::TransferComplete`] on the channel before calling this to be
+    /// notified each time one half of the buffer has been filled.
+    fn circ_read(mut self, mut buffer: &'static mut B) -> CircBuffer<B, Self> {
+        let (ptr, len) = unsafe { buffer.static_write_buffer() };
+
+        self.payload.set_dma_mode(DmaMode::Circular);
+
+        self.channel.set_memory_address(ptr as u32, true);
+        self.channel.set_transfer_length(len as u16);
+
+        self.channel.ccr().modify(|_, w| unsafe {
+            w.mem2mem()
+                .clear_bit()
+                // 00: Low, 01: Medium, 10: High, 11: Very high
+                .pl()
+                .bits(0b01)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .msize()
+                .bits(0b01)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .psize()
+                .bits(0b01)
+                // Peripheral -> Mem
+                .dir()
+                .clear_bit()
+                .circ()
+                .set_bit()
+        });
+
+        // NOTE(compiler_fence) operations on `buffer` should not be reordered after
+        // the next statement, which starts the DMA transfer
+        atomic::compiler_fence(Ordering::Release);
+
+        self.channel.start();
+        self.payload.start_conversion();
+
+        CircBuffer::new(buffer, self)
+    }
+}
+
 /// ADC resolution setting
 ///
-/// The default setting is 12 bits.
+/// The default setting is 12 bits. A conversion takes the sample time plus the successive
+/// approximation time given for each resolution.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Resolution {
-    /// 12-bit resolution
+    /// 12-bit resolution, 12.5 ADC clock cycles
     Bits12 = 0b00,
 
-    /// 10-bit resolution
+    /// 10-bit resolution, 10.5 ADC clock cycles
     Bits10 = 0b01,
 
-    /// 8-bit resolution
+    /// 8-bit resolution, 8.5 ADC clock cycles
     Bits8 = 0b10,
 
-    /// 6-bit resolution
+    /// 6-bit resolution, 6.5 ADC clock cycles
     Bits6 = 0b11,
 }
 
+/// Alignment of the conversion results in the 16 bit data register
+///
+/// The default setting is right aligned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Align {
+    /// The result is in the least significant bits
+    Right,
+    /// The result is in the most significant bits, for 6-bit resolution in the most
+    /// significant bits of the lower byte
+    ///
+    /// Left alignment is ignored while oversampling.
+    Left,
+}
+
+impl Default for Align {
+    fn default() -> Self {
+        Self::Right
+    }
+}
+
 impl Default for Resolution {
     fn default() -> Self {
         Self::Bits12
@@ -406,6 +657,15 @@ impl Default for Resolution {
 }
 
 impl Resolution {
+    fn bits(&self) -> u8 {
+        match self {
+            Resolution::Bits12 => 12,
+            Resolution::Bits10 => 10,
+            Resolution::Bits8 => 8,
+            Resolution::Bits6 => 6,
+        }
+    }
+
     fn to_max_count(&self) -> u32 {
         match self {
             Resolution::Bits12 => (1 << 12) - 1,
@@ -420,6 +680,7 @@ impl Resolution {
 ///
 /// The default setting is 2.5 ADC clock cycles.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum SampleTime {
     /// 2.5 ADC clock cycles
@@ -453,11 +714,198 @@ impl Default for SampleTime {
     }
 }
 
+/// External trigger of the regular conversions (EXTSEL)
+///
+/// Not every timer exists on every device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ExternalTrigger {
+    Tim1Cc1 = 0,
+    Tim1Cc2 = 1,
+    Tim1Cc3 = 2,
+    Tim2Cc2 = 3,
+    Tim3Trgo = 4,
+    Tim4Cc4 = 5,
+    Exti11 = 6,
+    Tim8Trgo = 7,
+    Tim8Trgo2 = 8,
+    Tim1Trgo = 9,
+    Tim1Trgo2 = 10,
+    Tim2Trgo = 11,
+    Tim4Trgo = 12,
+    Tim6Trgo = 13,
+    Tim15Trgo = 14,
+    Tim3Cc4 = 15,
+}
+
+/// Edge of the external trigger that starts a conversion (EXTEN)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerEdge {
+    Rising = 0b01,
+    Falling = 0b10,
+    Both = 0b11,
+}
+
+/// External trigger of the injected conversions (JEXTSEL)
+///
+/// Not every timer exists on every device.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InjectedTrigger {
+    Tim1Trgo = 0,
+    Tim1Cc4 = 1,
+    Tim2Trgo = 2,
+    Tim2Cc1 = 3,
+    Tim3Cc4 = 4,
+    Tim4Trgo = 5,
+    Exti15 = 6,
+    Tim8Cc4 = 7,
+    Tim1Trgo2 = 8,
+    Tim8Trgo = 9,
+    Tim8Trgo2 = 10,
+    Tim3Cc3 = 11,
+    Tim3Trgo = 12,
+    Tim3Cc1 = 13,
+    Tim6Trgo = 14,
+    Tim15Trgo = 15,
+}
+
+/// Rank in the injected sequence
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InjectedRank {
+    One = 0,
+    Two = 1,
+    Three = 2,
+    Four = 3,
+}
+
+/// Number of conversions accumulated by the oversampler for each result
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OversamplingRatio {
+    X2 = 0b000,
+    X4 = 0b001,
+    X8 = 0b010,
+    X16 = 0b011,
+    X32 = 0b100,
+    X64 = 0b101,
+    X128 = 0b110,
+    X256 = 0b111,
+}
+
+/// Right shift applied to the accumulated oversampling result
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OversamplingShift {
+    None = 0,
+    Bits1 = 1,
+    Bits2 = 2,
+    Bits3 = 3,
+    Bits4 = 4,
+    Bits5 = 5,
+    Bits6 = 6,
+    Bits7 = 7,
+    Bits8 = 8,
+}
+
+/// Returns `true` if the shifted sum of `ratio` conversions at `resolution` fits in the
+/// 16 bit data register
+fn oversampling_fits(
+    resolution: Resolution,
+    ratio: OversamplingRatio,
+    shift: OversamplingShift,
+) -> bool {
+    let max = resolution.to_max_count() << (ratio as u8 + 1);
+    max >> shift as u8 <= u32::from(u16::MAX)
+}
+
 /// Implemented for all types that represent ADC channels
 pub trait Channel<T>: EmbeddedHalChannel<T, ID = u8> {
     fn set_sample_time(&mut self, adc: &mut T, sample_time: SampleTime);
 }
 
+/// Object safe form of [`Channel`], which allows passing channels of different types
+/// to [`Adc::set_sequence`]
+///
+/// This is implemented for all [`Channel`]s.
+pub trait SequenceChannel<T> {
+    /// Channel number
+    fn id(&self) -> u8;
+
+    /// Programs the sample time of the channel, see [`Channel::set_sample_time`]
+    fn program_sample_time(&mut self, adc: &mut T, sample_time: SampleTime);
+}
+
+impl<T, C> SequenceChannel<T> for C
+where
+    C: Channel<T>,
+{
+    #[inline]
+    fn id(&self) -> u8 {
+        C::channel()
+    }
+
+    #[inline]
+    fn program_sample_time(&mut self, adc: &mut T, sample_time: SampleTime) {
+        self.set_sample_time(adc, sample_time)
+    }
+}
+
+/// Pair of pins `P` and `N` that can be converted as a differential channel on `ADC`
+///
+/// Implemented for the pins of adjacent channels, where `N` is the channel after `P`.
+pub trait DifferentialPair<ADC, N>: Channel<ADC> {}
+
+/// Differential channel, that converts the voltage of the `P` pin relative to the `N` pin
+///
+/// Created by `Adc::into_differential`. The result is `2^(n-1)` when both pins are at the
+/// same voltage, `0` at `-VREF+` and the maximum value at `+VREF+`, use
+/// [`Adc::to_differential_millivolts`] to convert it.
+pub struct DifferentialChannel<P, N> {
+    positive: P,
+    negative: N,
+}
+
+impl<P, N> DifferentialChannel<P, N> {
+    /// Returns the positive and negative pin without changing the ADC configuration
+    ///
+    /// Use `Adc::release_differential` to also turn the channel back to single-ended mode.
+    pub fn free(self) -> (P, N) {
+        (self.positive, self.negative)
+    }
+}
+
+impl<ADC, P, N> EmbeddedHalChannel<ADC> for DifferentialChannel<P, N>
+where
+    P: DifferentialPair<ADC, N>,
+{
+    type ID = u8;
+
+    fn channel() -> Self::ID {
+        <P as EmbeddedHalChannel<ADC>>::channel()
+    }
+}
+
+impl<ADC, P, N> Channel<ADC> for DifferentialChannel<P, N>
+where
+    P: DifferentialPair<ADC, N>,
+{
+    #[inline]
+    fn set_sample_time(&mut self, adc: &mut ADC, sample_time: SampleTime) {
+        self.positive.set_sample_time(adc, sample_time)
+    }
+}
+
+macro_rules! adc_differential_pairs {
+    ($adc_type:ident: $($positive:ty => $negative:ty,)+) => {
+        $(
+            impl DifferentialPair<pac::$adc_type, $negative> for $positive {}
+        )+
+    };
+}
+
 macro_rules! impl_embedded_hal_channel {
     ($pin:ty => ($adc_type:ident, $chan:expr)) => {
         impl EmbeddedHalChannel<pac::$adc_type> for $pin {
@@ -513,13 +961,10 @@ macro_rules! adc {
         pub fn calibrate(&mut self, delay: &mut impl DelayUs<u32>) -> u16 {
             let vref = self.enable_vref(delay);
 
-            let vref_cal = VrefCal::get().read();
-
             // This can't actually fail, it's just in a result to satisfy hal trait
             let vref_samp = self.read(&mut Vref { _0: () }).unwrap();
 
-            // Safety: DIV by 0 is possible if vref_samp is 0
-            self.calibrated_vdda = (VDDA_CALIB_MV * u32::from(vref_cal)) / u32::from(vref_samp);
+            self.calibrated_vdda = Vref::calculate_vdda(self.to_12_bits(vref_samp) as u16);
 
             // Disable VREF again if it was disabled before.
             if let Some(vref) = vref {
@@ -674,16 +1119,11 @@ macro_rules! adc {
                     ccipr: &mut CCIPR,
                     delay: &mut impl DelayUs<u32>,
                 ) -> Self {
-                    // Select system clock as ADC clock source
-                    ccipr.ccipr().modify(|_, w| {
-                        // This is sound, as `0b11` is a valid value for this field.
-                        unsafe {
-                            // TODO: Switch to enum once https://github.com/stm32-rs/stm32-rs/pull/720 is released.
-                            w.adcsel().bits(0b11);
-                        }
-
-                        w
-                    });
+                    // Select system clock as ADC clock source, unless another source
+                    // has been selected with `CCIPR::set_adc_clock_source`
+                    if ccipr.ccipr().read().adcsel().bits() == 0b00 {
+                        ccipr.set_adc_clock_source(AdcClockSource::Sysclk);
+                    }
 
                     // Initialize the ADC, according to the STM32L4xx Reference Manual,
                     // section 16.4.6.
@@ -714,7 +1154,10 @@ macro_rules! adc {
                         adc_common,
                         resolution: Resolution::default(),
                         sample_time: SampleTime::default(),
+                        align: Align::default(),
+                        oversampling: None,
                         calibrated_vdda: VDDA_CALIB_MV,
+                        calfact: None,
                     };
 
                     s.calibrate(delay);
@@ -724,6 +1167,124 @@ macro_rules! adc {
 
                 adc!(@additionals: $adc_type => ($common_type));
 
+                /// Combines two pins into a differential channel
+                ///
+                /// The ADC is disabled to select the differential mode for the channel of
+                /// `positive` and, as it has a separate calibration factor, to calibrate
+                /// the differential mode. The single-ended calibration is kept.
+                pub fn into_differential<P, N>(
+                    &mut self,
+                    positive: P,
+                    negative: N,
+                    delay: &mut impl DelayUs<u32>,
+                ) -> DifferentialChannel<P, N>
+                where
+                    P: DifferentialPair<pac::$adc_type, N>,
+                {
+                    let channel = <P as EmbeddedHalChannel<pac::$adc_type>>::channel();
+
+                    // DIFSEL and the calibration can only be changed while the ADC is disabled
+                    // (See reference manual section 16.4.12)
+                    self.disable_and_wait();
+                    self.adc
+                        .difsel
+                        .modify(|r, w| unsafe { w.bits(r.bits() | 1 << channel) });
+
+                    // This only updates CALFACT_D
+                    self.adc.cr.modify(|_, w| w.adcaldif().set_bit().adcal().set_bit());
+                    while self.adc.cr.read().adcal().bit_is_set() {}
+                    self.adc.cr.modify(|_, w| w.adcaldif().clear_bit());
+
+                    // We need to wait 4 ADC clock after ADCAL goes low, 1 us is more than enough
+                    delay.delay_us(1);
+
+                    DifferentialChannel { positive, negative }
+                }
+
+                /// Turns a differential channel back to single-ended mode and returns its pins
+                pub fn release_differential<P, N>(
+                    &mut self,
+                    channel: DifferentialChannel<P, N>,
+                ) -> (P, N)
+                where
+                    P: DifferentialPair<pac::$adc_type, N>,
+                {
+                    let id = <P as EmbeddedHalChannel<pac::$adc_type>>::channel();
+
+                    self.disable_and_wait();
+                    self.adc
+                        .difsel
+                        .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << id)) });
+
+                    channel.free()
+                }
+
+                /// Disables the ADC and waits until it is off, which is required to change
+                /// its configuration
+                fn disable_and_wait(&mut self) {
+                    if self.is_enabled() {
+                        self.stop_conversion();
+                        self.adc.cr.modify(|_, w| w.addis().set_bit());
+                    }
+                    while self.is_enabled() {}
+                }
+
+                /// Release the ADC peripheral
+                ///
+                /// Powers the ADC down like [`Adc::power_down`], then drops `Adc` and returns
+                /// the `pac::ADC` that it was wrapping, giving the user full access to the
+                /// peripheral.
+                pub fn release(mut self) -> pac::$adc_type {
+                    self.power_down();
+                    self.adc
+                }
+
+                /// Stops the conversions, disables the ADC and its voltage regulator and enters
+                /// deep-power-down mode, in which the ADC draws no current
+                ///
+                /// The calibration factors are lost in deep-power-down mode, so they are saved
+                /// and restored by [`Adc::power_up`].
+                pub fn power_down(&mut self) {
+                    if self.is_powered_down() {
+                        return;
+                    }
+
+                    self.stop_injected();
+                    self.disable_and_wait();
+                    self.calfact = Some(self.adc.calfact.read().bits());
+
+                    // The regulator has to be disabled before entering deep-power-down mode
+                    // (See reference manual section 16.4.6)
+                    self.adc.cr.modify(|_, w| w.advregen().clear_bit());
+                    self.adc.cr.modify(|_, w| w.deeppwd().set_bit());
+                }
+
+                /// Leaves deep-power-down mode and enables the ADC with the calibration
+                /// factors saved by [`Adc::power_down`], without running a new calibration
+                ///
+                /// If the ADC was not powered down by [`Adc::power_down`], the current
+                /// calibration factors are kept.
+                pub fn power_up(&mut self, delay: &mut impl DelayUs<u32>) {
+                    self.adc.cr.modify(|_, w| w.deeppwd().clear_bit());
+                    self.adc.cr.modify(|_, w| w.advregen().set_bit());
+
+                    // Wait for T_ADCVREG_STUP (20 us) after enabling the internal voltage
+                    // regulator, as in the constructor.
+                    delay.delay_us(25);
+
+                    // CALFACT can only be written while the ADC is enabled
+                    self.enable();
+                    if let Some(calfact) = self.calfact.take() {
+                        self.adc.calfact.write(|w| unsafe { w.bits(calfact) });
+                    }
+                }
+
+                /// Check if the ADC is in deep-power-down mode.
+                #[inline]
+                pub fn is_powered_down(&self) -> bool {
+                    self.adc.cr.read().deeppwd().bit_is_set()
+                }
+
                 /// Check if the ADC is enabled.
                 #[inline]
                 pub fn is_enabled(&self) -> bool {
@@ -780,7 +1341,49 @@ macro_rules! adc {
                 {
                     let channel_bits = C::channel();
                     channel.set_sample_time(&mut self.adc, sample_time);
+                    self.set_rank(sequence, channel_bits);
 
+                    // This will only ever extend the sequence, not shrink it.
+                    let current_seql = self.get_sequence_length();
+                    let next_seql: u8 = sequence.into();
+                    if next_seql >= current_seql {
+                        // Note: sequence length of 0 = 1 conversion
+                        self.set_sequence_length(sequence.into());
+                    }
+                }
+
+                /// Configure the whole regular sequence at once
+                ///
+                /// `channels[i]` is converted at rank `i`, with the given sample time, and
+                /// the sequence length is set to the number of channels:
+                ///
+                /// ```ignore
+                /// adc.set_sequence(&mut [&mut pa0, &mut pa1, &mut pc0], SampleTime::Cycles47_5);
+                /// adc.start_sequence();
+                /// let mut samples = [0; 3];
+                /// adc.read_sequence(&mut samples)?;
+                /// ```
+                ///
+                /// # Panics
+                ///
+                /// Panics if `channels` is empty or has more than 16 entries.
+                pub fn set_sequence(
+                    &mut self,
+                    channels: &mut [&mut dyn SequenceChannel<pac::$adc_type>],
+                    sample_time: SampleTime,
+                ) {
+                    assert!(!channels.is_empty() && channels.len() <= 16);
+
+                    for (rank, channel) in channels.iter_mut().enumerate() {
+                        channel.program_sample_time(&mut self.adc, sample_time);
+                        self.set_rank(Sequence::from(rank as u8), channel.id());
+                    }
+
+                    self.set_sequence_length(channels.len() as u8 - 1);
+                }
+
+                /// Programs the channel converted at the `sequence` rank in SQR1-SQR4
+                fn set_rank(&mut self, sequence: Sequence, channel_bits: u8) {
                     unsafe {
                         // This is sound as channel() always returns a valid channel number
                         match sequence {
@@ -802,14 +1405,54 @@ macro_rules! adc {
                             Sequence::Sixteen => self.adc.sqr4.modify(|_, w| w.sq16().bits(channel_bits)),
                         }
                     }
+                }
 
-                    // This will only ever extend the sequence, not shrink it.
-                    let current_seql = self.get_sequence_length();
-                    let next_seql: u8 = sequence.into();
-                    if next_seql >= current_seql {
-                        // Note: sequence length of 0 = 1 conversion
-                        self.set_sequence_length(sequence.into());
+                /// Starts a single conversion of the configured regular sequence
+                ///
+                /// The results have to be read with [`Adc::read_sequence`].
+                #[inline]
+                pub fn start_sequence(&mut self) {
+                    self.clear_overrun();
+                    self.start_conversion();
+                }
+
+                /// Reads the results of a sequence started with [`Adc::start_sequence`]
+                ///
+                /// Waits for the conversion of each rank and stores it in `samples`, which
+                /// should be as long as the sequence. Returns `Err(Error::Overrun)` if a
+                /// result was overwritten because the previous rank was not read in time, in
+                /// which case the sequence has to be restarted.
+                ///
+                /// Returns `Err(Error::SequenceLength)` without waiting if `samples` is longer
+                /// than the sequence, unless the sequence is repeated in continuous mode or by
+                /// an external trigger.
+                pub fn read_sequence(&mut self, samples: &mut [u16]) -> Result<(), Error> {
+                    // A software started sequence stops after the last rank, so any further
+                    // sample would be waited for forever
+                    let cfgr = self.adc.cfgr.read();
+                    let repeated = cfgr.cont().bit_is_set() || cfgr.bits() & (0b11 << 10) != 0;
+                    if !repeated && samples.len() > usize::from(self.get_sequence_length()) + 1 {
+                        return Err(Error::SequenceLength);
+                    }
+
+                    for sample in samples.iter_mut() {
+                        while !self.has_completed_conversion() {
+                            if self.has_overrun() {
+                                self.clear_overrun();
+                                return Err(Error::Overrun);
+                            }
+                        }
+
+                        // Reading the data register clears EOC
+                        *sample = self.current_sample();
+
+                        if self.has_overrun() {
+                            self.clear_overrun();
+                            return Err(Error::Overrun);
+                        }
                     }
+
+                    Ok(())
                 }
 
                 /// Get the configured sequence length (= `actual sequence length - 1`)
@@ -834,6 +1477,103 @@ macro_rules! adc {
                     self.adc.sqr1.modify(|_, w| unsafe { w.l().bits(0b0000) })
                 }
 
+                /// Set the ADC resolution
+                ///
+                /// Returns `Err(Error::ConversionOngoing)` if a conversion is ongoing, and
+                /// `Err(Error::OversamplingOverflow)` if the oversampled results would not fit
+                /// in 16 bits at the new resolution. The thresholds of the analog watchdogs are
+                /// scaled for the resolution, so they have to be configured again after
+                /// changing it.
+                pub fn set_resolution(&mut self, resolution: Resolution) -> Result<(), Error> {
+                    if self.is_converting() || self.adc.cr.read().jadstart().bit_is_set() {
+                        return Err(Error::ConversionOngoing);
+                    }
+                    if let Some((ratio, shift)) = self.oversampling {
+                        if !oversampling_fits(resolution, ratio, shift) {
+                            return Err(Error::OversamplingOverflow);
+                        }
+                    }
+
+                    self.resolution = resolution;
+                    self.adc
+                        .cfgr
+                        .modify(|_, w| unsafe { w.res().bits(resolution as u8) });
+
+                    Ok(())
+                }
+
+                /// Set the alignment of the conversion results
+                ///
+                /// Returns `Err(Error::ConversionOngoing)` if a conversion is ongoing.
+                /// [`Adc::get_max_value`] and [`Adc::to_millivolts`] take the alignment into
+                /// account, the analog watchdog thresholds are always right aligned.
+                pub fn set_align(&mut self, align: Align) -> Result<(), Error> {
+                    if self.is_converting() || self.adc.cr.read().jadstart().bit_is_set() {
+                        return Err(Error::ConversionOngoing);
+                    }
+
+                    self.align = align;
+                    self.adc
+                        .cfgr
+                        .modify(|_, w| w.align().bit(align == Align::Left));
+
+                    Ok(())
+                }
+
+                /// Enables the hardware oversampler for regular conversions
+                ///
+                /// Each result is the sum of `ratio` conversions shifted right by `shift`, so
+                /// e.g. `X16` with `Bits4` averages 16 conversions and `X16` with `Bits2`
+                /// gives a 14 bit result. [`Adc::get_max_value`] and
+                /// [`Adc::to_millivolts`] take the oversampling into account.
+                ///
+                /// Returns `Err(Error::OversamplingOverflow)` if the shifted result would
+                /// not fit in 16 bits at the current resolution.
+                pub fn set_oversampling(
+                    &mut self,
+                    ratio: OversamplingRatio,
+                    shift: OversamplingShift,
+                ) -> Result<(), Error> {
+                    if !oversampling_fits(self.resolution, ratio, shift) {
+                        return Err(Error::OversamplingOverflow);
+                    }
+
+                    self.stop_conversion();
+                    self.adc.cfgr2.modify(|r, w| unsafe {
+                        // ROVSE (bit 0), OVSR (bits 2-4), OVSS (bits 5-8)
+                        w.bits(
+                            (r.bits() & !0x1fd)
+                                | 1
+                                | (ratio as u32) << 2
+                                | (shift as u32) << 5,
+                        )
+                    });
+                    self.oversampling = Some((ratio, shift));
+
+                    Ok(())
+                }
+
+                /// Selects triggered oversampling, where each trigger converts only one
+                /// of the `ratio` samples of an oversampled result
+                ///
+                /// By default all the samples are converted from a single trigger.
+                pub fn set_triggered_oversampling(&mut self, triggered: bool) {
+                    self.stop_conversion();
+                    // TROVS (bit 9), the field is named differently across the PACs
+                    self.adc.cfgr2.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(1 << 9)) | (triggered as u32) << 9)
+                    });
+                }
+
+                /// Disables the oversampler for regular conversions
+                pub fn disable_oversampling(&mut self) {
+                    self.stop_conversion();
+                    self.adc
+                        .cfgr2
+                        .modify(|r, w| unsafe { w.bits(r.bits() & !1) });
+                    self.oversampling = None;
+                }
+
                 #[inline]
                 pub fn has_completed_conversion(&self) -> bool {
                     self.adc.isr.read().eoc().bit_is_set()
@@ -864,21 +1604,305 @@ macro_rules! adc {
                     self.adc.cr.read().adstart().bit_is_set()
                 }
 
+                /// Stops ongoing regular conversions and waits until the ADC is idle
+                #[inline]
+                pub fn stop_conversion(&mut self) {
+                    if self.is_converting() {
+                        self.adc.cr.modify(|_, w| w.adstp().set_bit());
+                        while self.adc.cr.read().adstp().bit_is_set() {}
+                    }
+                }
+
+                /// Check if a conversion result was lost because the previous one was not
+                /// read in time
+                #[inline]
+                pub fn has_overrun(&self) -> bool {
+                    self.adc.isr.read().ovr().bit_is_set()
+                }
+
+                /// Clears the overrun flag, which also unblocks the DMA requests
+                #[inline]
+                pub fn clear_overrun(&mut self) {
+                    // OVR is reset by setting it (See reference manual section 16.6.1)
+                    self.adc.isr.write(|w| w.ovr().set_bit());
+                }
+
                 #[inline]
                 pub fn listen(&mut self, event: Event) {
-                    self.adc.ier.modify(|_, w| match event {
-                        Event::EndOfRegularSequence => w.eosie().set_bit(),
-                        Event::EndOfRegularConversion => w.eocie().set_bit(),
-                    });
+                    self.adc
+                        .ier
+                        .modify(|r, w| unsafe { w.bits(r.bits() | event.bit()) });
                 }
 
                 #[inline]
                 pub fn unlisten(&mut self, event: Event) {
-                    self.adc.ier.modify(|_, w| match event {
-                        Event::EndOfRegularSequence => w.eosie().clear_bit(),
-                        Event::EndOfRegularConversion => w.eocie().clear_bit(),
+                    self.adc
+                        .ier
+                        .modify(|r, w| unsafe { w.bits(r.bits() & !event.bit()) });
+                }
+
+                /// Check if the flag of `event` is set
+                #[inline]
+                pub fn is_pending(&self, event: Event) -> bool {
+                    self.adc.isr.read().bits() & event.bit() != 0
+                }
+
+                /// Clears the flag of `event`, which has to be done in the interrupt handler
+                #[inline]
+                pub fn clear_event(&mut self, event: Event) {
+                    // The flags are reset by setting them (See reference manual section 16.6.1)
+                    self.adc.isr.write(|w| unsafe { w.bits(event.bit()) });
+                }
+
+                /// Starts the regular sequence on `edge` of `trigger` instead of by software
+                ///
+                /// The ADC has to be armed with [`Adc::start_conversion_external`], then each
+                /// trigger edge converts the sequence once. Combined with a DMA transfer this
+                /// samples at the exact rate of the trigger.
+                pub fn set_external_trigger(&mut self, trigger: ExternalTrigger, edge: TriggerEdge) {
+                    self.stop_conversion();
+                    // EXTSEL (bits 6-9), EXTEN (bits 10-11)
+                    self.adc.cfgr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !(0x3f << 6))
+                                | (trigger as u32) << 6
+                                | (edge as u32) << 10,
+                        )
                     });
                 }
+
+                /// Starts the regular sequence by software again, the default
+                pub fn disable_external_trigger(&mut self) {
+                    self.stop_conversion();
+                    self.adc
+                        .cfgr
+                        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << 10)) });
+                }
+
+                /// Arms the ADC, after which the regular sequence is converted on each edge of
+                /// the trigger selected with [`Adc::set_external_trigger`]
+                ///
+                /// This does not start a conversion itself. Use [`Adc::stop_conversion`] to
+                /// disarm the ADC.
+                #[inline]
+                pub fn start_conversion_external(&mut self) {
+                    debug_assert!(self.adc.cfgr.read().bits() & (0b11 << 10) != 0);
+                    self.start_conversion();
+                }
+
+                /// Configures the injected sequence, which interrupts the regular sequence
+                ///
+                /// `channels[i]` is converted at rank `i` with the given sample time. With a
+                /// `trigger`, the sequence is converted on each trigger edge once it was armed
+                /// with [`Adc::start_injected`], otherwise on each call to `start_injected`.
+                ///
+                /// # Panics
+                ///
+                /// Panics if `channels` is empty or has more than 4 entries.
+                pub fn configure_injected(
+                    &mut self,
+                    channels: &mut [&mut dyn SequenceChannel<pac::$adc_type>],
+                    trigger: Option<(InjectedTrigger, TriggerEdge)>,
+                    sample_time: SampleTime,
+                ) {
+                    assert!(!channels.is_empty() && channels.len() <= 4);
+
+                    // JL (bits 0-1), JEXTSEL (bits 2-5), JEXTEN (bits 6-7), JSQx (bits 8 + 6x)
+                    let mut jsqr = channels.len() as u32 - 1;
+                    if let Some((trigger, edge)) = trigger {
+                        jsqr |= (trigger as u32) << 2 | (edge as u32) << 6;
+                    }
+                    for (rank, channel) in channels.iter_mut().enumerate() {
+                        channel.program_sample_time(&mut self.adc, sample_time);
+                        jsqr |= u32::from(channel.id()) << (8 + 6 * rank);
+                    }
+
+                    self.stop_injected();
+                    self.adc.jsqr.write(|w| unsafe { w.bits(jsqr) });
+                }
+
+                /// Starts the injected sequence, or arms the ADC for the injected trigger
+                #[inline]
+                pub fn start_injected(&mut self) {
+                    self.enable();
+                    self.clear_event(Event::EndOfInjectedConversion);
+                    self.clear_event(Event::EndOfInjectedSequence);
+                    self.adc.cr.modify(|_, w| w.jadstart().set_bit());
+                }
+
+                /// Stops ongoing injected conversions and waits until they are stopped
+                #[inline]
+                pub fn stop_injected(&mut self) {
+                    if self.adc.cr.read().jadstart().bit_is_set() {
+                        self.adc.cr.modify(|_, w| w.jadstp().set_bit());
+                        while self.adc.cr.read().jadstp().bit_is_set() {}
+                    }
+                }
+
+                #[inline]
+                pub fn has_completed_injected_sequence(&self) -> bool {
+                    self.is_pending(Event::EndOfInjectedSequence)
+                }
+
+                /// Returns the last result of the injected `rank`
+                ///
+                /// The result is signed if an offset is set for the rank with
+                /// [`Adc::set_injected_offset`].
+                #[inline]
+                pub fn read_injected(&self, rank: InjectedRank) -> i16 {
+                    let bits = match rank {
+                        InjectedRank::One => self.adc.jdr1.read().bits(),
+                        InjectedRank::Two => self.adc.jdr2.read().bits(),
+                        InjectedRank::Three => self.adc.jdr3.read().bits(),
+                        InjectedRank::Four => self.adc.jdr4.read().bits(),
+                    };
+                    // The data is sign extended to 16 bits when an offset is applied
+                    bits as u16 as i16
+                }
+
+                /// Subtracts `offset` from the results of the channel converted at the injected
+                /// `rank`
+                ///
+                /// The offset is in 12 bit units, and applies to regular conversions of the
+                /// channel as well. It has to be set after [`Adc::configure_injected`], `None`
+                /// removes it.
+                pub fn set_injected_offset(&mut self, rank: InjectedRank, offset: Option<u16>) {
+                    let rank = rank as usize;
+                    let channel = (self.adc.jsqr.read().bits() >> (8 + 6 * rank)) & 0x1f;
+                    // OFFSET (bits 0-11), OFFSET_CH (bits 26-30), OFFSET_EN (bit 31)
+                    let ofr = match offset {
+                        Some(offset) => 1 << 31 | channel << 26 | u32::from(offset & 0xfff),
+                        None => 0,
+                    };
+
+                    self.stop_conversion();
+                    self.stop_injected();
+                    unsafe {
+                        match rank {
+                            0 => self.adc.ofr1.write(|w| w.bits(ofr)),
+                            1 => self.adc.ofr2.write(|w| w.bits(ofr)),
+                            2 => self.adc.ofr3.write(|w| w.bits(ofr)),
+                            _ => self.adc.ofr4.write(|w| w.bits(ofr)),
+                        }
+                    }
+                }
+
+                /// Selects auto-injection, where the injected sequence is converted after each
+                /// regular sequence without a trigger
+                ///
+                /// The injected sequence must be configured without a trigger for this.
+                pub fn set_auto_injection(&mut self, auto: bool) {
+                    self.stop_conversion();
+                    self.stop_injected();
+                    // JAUTO (bit 25)
+                    self.adc.cfgr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(1 << 25)) | (auto as u32) << 25)
+                    });
+                }
+
+                /// Selects continuous conversions, where the regular sequence is restarted
+                /// as soon as it is complete, until [`Adc::stop_conversion`] is called
+                pub fn set_continuous(&mut self, continuous: bool) {
+                    self.stop_conversion();
+                    self.adc.cfgr.modify(|_, w| w.cont().bit(continuous));
+                }
+
+                /// Watches `channel` with an analog watchdog
+                ///
+                /// The watchdog flags [`Event::AnalogWatchdog1`] (or 2, 3) when a conversion
+                /// of the channel is below `low` or above `high`. The thresholds are in the
+                /// unit of the conversion results and are scaled for the current resolution and
+                /// oversampling, so they have to be configured after these. `Awd2` and `Awd3`
+                /// only compare the 8 most significant bits and can watch several channels,
+                /// which are added by calling this again.
+                pub fn configure_watchdog<C>(
+                    &mut self,
+                    watchdog: Watchdog,
+                    _channel: &C,
+                    low: u16,
+                    high: u16,
+                ) where
+                    C: Channel<pac::$adc_type>,
+                {
+                    let channel = u32::from(C::channel());
+                    let low = self.to_watchdog_threshold(low).min(0xfff);
+                    let high = self.to_watchdog_threshold(high).min(0xfff);
+
+                    self.stop_conversion();
+                    unsafe {
+                        match watchdog {
+                            Watchdog::Awd1 => {
+                                self.adc.tr1.write(|w| w.bits(low | high << 16));
+                                // AWD1CH (bits 26-30), AWD1EN (bit 23), AWD1SGL (bit 22)
+                                self.adc.cfgr.modify(|r, w| {
+                                    w.bits(
+                                        (r.bits() & !(0x1f << 26))
+                                            | channel << 26
+                                            | 1 << 23
+                                            | 1 << 22,
+                                    )
+                                });
+                            }
+                            Watchdog::Awd2 => {
+                                self.adc.tr2.write(|w| w.bits(low >> 4 | (high >> 4) << 16));
+                                self.adc
+                                    .awd2cr
+                                    .modify(|r, w| w.bits(r.bits() | 1 << channel));
+                            }
+                            Watchdog::Awd3 => {
+                                self.adc.tr3.write(|w| w.bits(low >> 4 | (high >> 4) << 16));
+                                self.adc
+                                    .awd3cr
+                                    .modify(|r, w| w.bits(r.bits() | 1 << channel));
+                            }
+                        }
+                    }
+                }
+
+                /// Stops watching all channels with `watchdog`
+                pub fn disable_watchdog(&mut self, watchdog: Watchdog) {
+                    self.stop_conversion();
+                    unsafe {
+                        match watchdog {
+                            Watchdog::Awd1 => self
+                                .adc
+                                .cfgr
+                                .modify(|r, w| w.bits(r.bits() & !(1 << 23))),
+                            Watchdog::Awd2 => self.adc.awd2cr.write(|w| w.bits(0)),
+                            Watchdog::Awd3 => self.adc.awd3cr.write(|w| w.bits(0)),
+                        }
+                    }
+                }
+            }
+
+            impl<C> OneShot<pac::$adc_type, u16, C> for Adc<pac::$adc_type>
+            where
+                C: Channel<pac::$adc_type>,
+            {
+                type Error = Infallible;
+
+                fn read(&mut self, channel: &mut C) -> nb::Result<u16, Self::Error> {
+                    self.configure_sequence(channel, Sequence::One, self.sample_time);
+
+                    self.start_conversion();
+                    while !self.has_completed_sequence() {}
+
+                    // Read ADC value first time and discard it, as per errata sheet.
+                    // The errata state that if we do conversions slower than 1 kHz, the
+                    // first read ADC value can be corrupted, so we discard it and measure again.
+                    let _ = self.current_sample();
+
+                    self.start_conversion();
+                    while !self.has_completed_sequence() {}
+
+                    // Read ADC value.
+                    let val = self.current_sample();
+
+                    // Disable ADC.
+                    self.disable();
+
+                    Ok(val)
+                }
             }
         )*
     };
@@ -907,36 +1931,53 @@ adc_pins!(
     gpio::PC5<Analog> => (ADC1, 14, smpr2, smp14),
     gpio::PB0<Analog> => (ADC1, 15, smpr2, smp15),
     gpio::PB1<Analog> => (ADC1, 16, smpr2, smp16),
-    Temperature       => (ADC1, 17, smpr2, smp17),
-    Vbat              => (ADC1, 18, smpr2, smp18),
+    // The temperature sensor and VBAT channel need a sample time of at least 5 us and 12 us,
+    // use the longest sample time for them as well. This is 8 us at 80 MHz, so VBAT needs an
+    // ADC clock of at most 53 MHz, see `Vbat`.
+    Temperature       => (ADC1, 17, smpr2, smp17, SampleTime::Cycles640_5),
+    Vbat              => (ADC1, 18, smpr2, smp18, SampleTime::Cycles640_5),
+);
+
+adc_differential_pairs!(ADC1:
+    gpio::PC0<Analog> => gpio::PC1<Analog>,
+    gpio::PC1<Analog> => gpio::PC2<Analog>,
+    gpio::PC2<Analog> => gpio::PC3<Analog>,
+    gpio::PC3<Analog> => gpio::PA0<Analog>,
+    gpio::PA0<Analog> => gpio::PA1<Analog>,
+    gpio::PA1<Analog> => gpio::PA2<Analog>,
+    gpio::PA2<Analog> => gpio::PA3<Analog>,
+    gpio::PA3<Analog> => gpio::PA4<Analog>,
+    gpio::PA4<Analog> => gpio::PA5<Analog>,
+    gpio::PA5<Analog> => gpio::PA6<Analog>,
+    gpio::PA6<Analog> => gpio::PA7<Analog>,
+    gpio::PA7<Analog> => gpio::PC4<Analog>,
+    gpio::PC4<Analog> => gpio::PC5<Analog>,
+    gpio::PC5<Analog> => gpio::PB0<Analog>,
+    gpio::PB0<Analog> => gpio::PB1<Analog>,
 );
 
-#[cfg(not(any(
-    feature = "stm32l433",
-    feature = "stm32l443",
-    // feature = "stm32l4p5",
-    // feature = "stm32l4q5",
-    // feature = "stm32l4r5",
-    // feature = "stm32l4s5",
-    // feature = "stm32l4r7",
-    // feature = "stm32l4s7",
-    feature = "stm32l4r9",
-    feature = "stm32l4s9",
-)))]
+#[cfg(any(
+    feature = "stm32l412",
+    feature = "stm32l422",
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
 adc!(ADC2 => (adc2, ADC_COMMON));
 
-#[cfg(not(any(
-  feature = "stm32l433",
-  feature = "stm32l443",
-  // feature = "stm32l4p5",
-  // feature = "stm32l4q5",
-  // feature = "stm32l4r5",
-  // feature = "stm32l4s5",
-  // feature = "stm32l4r7",
-  // feature = "stm32l4s7",
-  feature = "stm32l4r9",
-  feature = "stm32l4s9",
-)))]
+#[cfg(any(
+    feature = "stm32l412",
+    feature = "stm32l422",
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
 adc_pins!(
     gpio::PC0<Analog> => (ADC2, 1,  smpr1, smp1),
     gpio::PC1<Analog> => (ADC2, 2,  smpr1, smp2),
@@ -959,6 +2000,35 @@ adc_pins!(
 );
 
 #[cfg(any(
+    feature = "stm32l412",
+    feature = "stm32l422",
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+adc_differential_pairs!(ADC2:
+    gpio::PC0<Analog> => gpio::PC1<Analog>,
+    gpio::PC1<Analog> => gpio::PC2<Analog>,
+    gpio::PC2<Analog> => gpio::PC3<Analog>,
+    gpio::PC3<Analog> => gpio::PA0<Analog>,
+    gpio::PA0<Analog> => gpio::PA1<Analog>,
+    gpio::PA1<Analog> => gpio::PA2<Analog>,
+    gpio::PA2<Analog> => gpio::PA3<Analog>,
+    gpio::PA3<Analog> => gpio::PA4<Analog>,
+    gpio::PA4<Analog> => gpio::PA5<Analog>,
+    gpio::PA5<Analog> => gpio::PA6<Analog>,
+    gpio::PA6<Analog> => gpio::PA7<Analog>,
+    gpio::PA7<Analog> => gpio::PC4<Analog>,
+    gpio::PC4<Analog> => gpio::PC5<Analog>,
+    gpio::PC5<Analog> => gpio::PB0<Analog>,
+    gpio::PB0<Analog> => gpio::PB1<Analog>,
+);
+
+#[cfg(any(
+    feature = "stm32l475",
     feature = "stm32l476",
     feature = "stm32l486",
     feature = "stm32l496",
@@ -967,6 +2037,7 @@ adc_pins!(
 adc!(ADC3 => (adc3, ADC_COMMON));
 
 #[cfg(any(
+    feature = "stm32l475",
     feature = "stm32l476",
     feature = "stm32l486",
     feature = "stm32l496",
@@ -987,6 +2058,44 @@ adc_pins!(
     gpio::PF10<Analog> => (ADC3, 13, smpr2, smp13),
     // DAC1            => (ADC2, 14, smpr2, smp14),
     // DAC2            => (ADC2, 15, smpr2, smp15),
-    Temperature        => (ADC3, 17, smpr2, smp17),
-    Vbat               => (ADC3, 18, smpr2, smp18),
+    Temperature        => (ADC3, 17, smpr2, smp17, SampleTime::Cycles640_5),
+    Vbat               => (ADC3, 18, smpr2, smp18, SampleTime::Cycles640_5),
+);
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+adc_differential_pairs!(ADC3:
+    gpio::PC0<Analog> => gpio::PC1<Analog>,
+    gpio::PC1<Analog> => gpio::PC2<Analog>,
+    gpio::PC2<Analog> => gpio::PC3<Analog>,
+    gpio::PF3<Analog> => gpio::PF4<Analog>,
+    gpio::PF4<Analog> => gpio::PF5<Analog>,
+    gpio::PF5<Analog> => gpio::PF6<Analog>,
+    gpio::PF6<Analog> => gpio::PF7<Analog>,
+    gpio::PF7<Analog> => gpio::PF8<Analog>,
+    gpio::PF8<Analog> => gpio::PF9<Analog>,
+    gpio::PF9<Analog> => gpio::PF10<Analog>,
 );
+
+#[cfg(test)]
+mod tests {
+    use super::{oversampling_fits, OversamplingRatio, OversamplingShift, Resolution};
+
+    #[test]
+    fn oversampling_overflow() {
+        use OversamplingRatio::*;
+        use OversamplingShift as Shift;
+
+        assert!(oversampling_fits(Resolution::Bits12, X16, Shift::None));
+        assert!(!oversampling_fits(Resolution::Bits12, X32, Shift::None));
+        assert!(oversampling_fits(Resolution::Bits12, X256, Shift::Bits4));
+        assert!(!oversampling_fits(Resolution::Bits12, X256, Shift::Bits3));
+        assert!(oversampling_fits(Resolution::Bits8, X256, Shift::None));
+        assert!(oversampling_fits(Resolution::Bits6, X256, Shift::None));
+    }
+}
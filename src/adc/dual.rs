@@ -0,0 +1,215 @@
+//! Dual ADC mode, where ADC1 and ADC2 convert at the same time
+
+use super::*;
+
+use crate::pac::ADC2;
+
+/// ADC1 and ADC2 in regular simultaneous mode
+///
+/// Each start converts the regular sequence of ADC1 (the master) and ADC2 (the slave)
+/// at the same time, e.g. to measure the voltage and current of a load at the exact
+/// same instant:
+///
+/// ```ignore
+/// adc1.configure_sequence(&mut voltage_pin, Sequence::One, SampleTime::Cycles47_5);
+/// adc2.configure_sequence(&mut current_pin, Sequence::One, SampleTime::Cycles47_5);
+/// let mut dual = DualAdc::new(adc1, adc2);
+/// let (voltage, current) = dual.read_simultaneous();
+/// ```
+///
+/// Both sequences must have the same length, and the channels at the same rank the same
+/// sample time. The same channel can not be converted by both ADCs at the same time.
+pub struct DualAdc {
+    master: Adc<ADC1>,
+    slave: Adc<ADC2>,
+}
+
+impl DualAdc {
+    /// Switches ADC1 and ADC2 to regular simultaneous mode
+    pub fn new(mut master: Adc<ADC1>, mut slave: Adc<ADC2>) -> Self {
+        master.stop_conversion();
+        slave.stop_conversion();
+
+        // DUAL (bits 0-4): regular simultaneous mode only
+        master
+            .adc_common
+            .ccr
+            .modify(|r, w| unsafe { w.bits((r.bits() & !0x1f) | 0b00110) });
+
+        Self { master, slave }
+    }
+
+    /// Access to ADC1, e.g. to change its sequence
+    pub fn master(&mut self) -> &mut Adc<ADC1> {
+        &mut self.master
+    }
+
+    /// Access to ADC2, e.g. to change its sequence
+    pub fn slave(&mut self) -> &mut Adc<ADC2> {
+        &mut self.slave
+    }
+
+    /// Converts a sequence of length one on both ADCs and returns the results of ADC1 and
+    /// ADC2
+    pub fn read_simultaneous(&mut self) -> (u16, u16) {
+        // The slave has to be enabled, its conversions are started by the master
+        self.slave.enable();
+        self.slave.clear_end_flags();
+        self.master.start_conversion();
+
+        while !(self.master.has_completed_sequence() && self.slave.has_completed_sequence()) {}
+
+        let data = self.master.adc_common.cdr.read();
+        (data as u16, (data >> 16) as u16)
+    }
+
+    /// Switches the ADCs back to independent mode and returns them
+    pub fn release(mut self) -> (Adc<ADC1>, Adc<ADC2>) {
+        self.set_dma_mode(DmaMode::Disabled);
+
+        // DUAL (bits 0-4): independent mode
+        self.master
+            .adc_common
+            .ccr
+            .modify(|r, w| unsafe { w.bits(r.bits() & !0x1f) });
+
+        (self.master, self.slave)
+    }
+
+    /// Configures the DMA requests of the common data register and continuous conversions
+    /// for `dma_mode`
+    fn set_dma_mode(&mut self, dma_mode: DmaMode) {
+        let (mdma, circular) = match dma_mode {
+            DmaMode::Disabled => (0b00, false),
+            DmaMode::Oneshot => (0b10, false),
+            DmaMode::Circular => (0b10, true),
+        };
+
+        self.master.stop_conversion();
+        self.slave.stop_conversion();
+
+        // The requests of the individual ADCs are replaced by the ones of the common data
+        // register
+        for cfgr in [&self.master.adc.cfgr, &self.slave.adc.cfgr].iter() {
+            cfgr.modify(|_, w| {
+                w.dmaen()
+                    .clear_bit()
+                    .cont()
+                    .bit(circular)
+                    .ovrmod()
+                    .bit(circular)
+            });
+        }
+        self.master.clear_overrun();
+        self.slave.clear_overrun();
+
+        // DMACFG (bit 13), MDMA (bits 14-15). MDMA = 0b10 transfers both 16 bit results
+        // as one word, at every resolution.
+        self.master.adc_common.ccr.modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b111 << 13)) | (circular as u32) << 13 | mdma << 14)
+        });
+    }
+}
+
+impl TransferPayload for RxDma<DualAdc, dma1::C1> {
+    fn start(&mut self) {
+        self.channel.start();
+    }
+
+    fn stop(&mut self) {
+        self.channel.stop();
+    }
+}
+
+impl RxDma<DualAdc, dma1::C1> {
+    /// Stops the DMA and the conversions and returns the ADCs with DMA requests disabled,
+    /// and the channel
+    pub fn split(mut self) -> (DualAdc, dma1::C1) {
+        self.stop();
+        self.payload.set_dma_mode(DmaMode::Disabled);
+        (self.payload, self.channel)
+    }
+}
+
+impl<BUFFER, const N: usize> Transfer<W, BUFFER, RxDma<DualAdc, dma1::C1>>
+where
+    BUFFER: Sized + StableDeref<Target = [u32; N]> + DerefMut + 'static,
+{
+    pub fn from_dual_adc_dma(
+        dma: RxDma<DualAdc, dma1::C1>,
+        buffer: BUFFER,
+        dma_mode: DmaMode,
+        transfer_complete_interrupt: bool,
+    ) -> Self {
+        let (adc, channel) = dma.split();
+        Transfer::from_dual_adc(adc, channel, buffer, dma_mode, transfer_complete_interrupt)
+    }
+
+    /// Initiate a new DMA transfer of simultaneous conversions.
+    ///
+    /// Each word of the buffer holds the result of ADC1 in the lower and the result of
+    /// ADC2 in the upper half word.
+    ///
+    /// `dma_mode` indicates the desired mode for DMA.
+    ///
+    /// If `transfer_complete_interrupt` is true, the transfer
+    /// complete interrupt (= `DMA1_CH1`) will be enabled
+    pub fn from_dual_adc(
+        mut adc: DualAdc,
+        mut channel: dma1::C1,
+        buffer: BUFFER,
+        dma_mode: DmaMode,
+        transfer_complete_interrupt: bool,
+    ) -> Self {
+        assert!(dma_mode != DmaMode::Disabled);
+
+        let circular = dma_mode == DmaMode::Circular;
+        adc.set_dma_mode(dma_mode);
+
+        channel.set_peripheral_address(adc.master.adc_common.cdr.address(), false);
+
+        // SAFETY: since the length of BUFFER is known to be `N`, we are allowed
+        // to perform N transfers into said buffer
+        channel.set_memory_address(buffer.as_ptr() as u32, true);
+        channel.set_transfer_length(N as u16);
+
+        channel.set_request_line(DmaInput::Adc1).unwrap();
+
+        channel.ccr().modify(|_, w| unsafe {
+            w.mem2mem()
+                .clear_bit()
+                // 00: Low, 01: Medium, 10: High, 11: Very high
+                .pl()
+                .bits(0b01)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .msize()
+                .bits(0b10)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .psize()
+                .bits(0b10)
+                // Peripheral -> Mem
+                .dir()
+                .clear_bit()
+                .circ()
+                .bit(circular)
+        });
+
+        if transfer_complete_interrupt {
+            channel.listen(DMAEvent::TransferComplete);
+        }
+
+        atomic::compiler_fence(Ordering::Release);
+
+        channel.start();
+        adc.slave.enable();
+        adc.master.start_conversion();
+
+        Transfer::w(
+            buffer,
+            RxDma {
+                channel,
+                payload: adc,
+            },
+        )
+    }
+}
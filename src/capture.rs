@@ -0,0 +1,597 @@
+//! Input capture
+//!
+//! `input_capture` runs the counter of a timer freely and captures its value on the
+//! edges of a pin. `pwm_input` measures the period and the pulse width of a PWM signal,
+//! with the counter reset on every rising edge.
+//!
+//! Usage example:
+//! ```ignore
+//! let tach = gpioa
+//!     .pa0
+//!     .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+//! let mut capture = dp.TIM2.input_capture(
+//!     tach,
+//!     Polarity::Rising,
+//!     Prescaler::Div1,
+//!     Filter::TimerClockN8,
+//!     clocks,
+//!     &mut rcc.apb1r1,
+//! );
+//!
+//! // Two pulses per revolution
+//! let first = nb::block!(capture.capture()).unwrap();
+//! let second = nb::block!(capture.capture()).unwrap();
+//! let rpm = 60 * capture.clock().raw() / capture.elapsed(first, second) / 2;
+//! ```
+
+use core::marker::PhantomData;
+
+pub use crate::pwm::{Channel, Filter};
+
+use crate::gpio::gpioa::{PA0, PA1, PA10, PA11, PA15, PA2, PA3, PA5, PA6, PA8, PA9};
+use crate::gpio::gpiob::{PB10, PB11, PB14, PB3, PB8};
+use crate::gpio::Alternate;
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::gpio::{gpioa::PA7, gpiob::PB9};
+use crate::rcc::{Clocks, Enable, RccBus, Reset};
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::stm32::TIM17;
+use crate::stm32::{TIM1, TIM15, TIM16, TIM2};
+use crate::time::Hertz;
+
+/// Edges of the input which are captured
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Polarity {
+    Rising,
+    Falling,
+    Both,
+}
+
+/// Number of edges per capture
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Prescaler {
+    Div1 = 0,
+    Div2 = 1,
+    Div4 = 2,
+    Div8 = 3,
+}
+
+/// Interrupt events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// Channel 1 captured a value
+    Capture1,
+    /// Channel 2 captured a value
+    Capture2,
+    /// Channel 3 captured a value
+    Capture3,
+    /// Channel 4 captured a value
+    Capture4,
+    /// The counter overflowed
+    Overflow,
+}
+
+impl Event {
+    /// Bit of the event in DIER and SR
+    fn bit(self) -> u32 {
+        match self {
+            Event::Capture1 => 1 << 1,
+            Event::Capture2 => 1 << 2,
+            Event::Capture3 => 1 << 3,
+            Event::Capture4 => 1 << 4,
+            Event::Overflow => 1 << 0,
+        }
+    }
+}
+
+/// Input capture error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// A captured value was overwritten before it was read
+    Overcapture,
+}
+
+/// Marks pins as the input of a capture channel
+pub trait CapturePin<TIM> {
+    #[doc(hidden)]
+    const CHANNEL: Channel;
+}
+
+/// Marks pins as the input of [`PwmInputExt::pwm_input`], channel 1 or 2
+pub trait PwmInputPin<TIM>: CapturePin<TIM> {}
+
+macro_rules! capture_pins {
+    ( $( $TIMX:ident: $CX:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> CapturePin<$TIMX> for $PINX<Alternate<OTYPE, $AF>> {
+                    const CHANNEL: Channel = Channel::$CX;
+                }
+            )+
+        )+
+    };
+}
+
+macro_rules! pwm_input_pins {
+    ( $( $TIMX:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> PwmInputPin<$TIMX> for $PINX<Alternate<OTYPE, $AF>> {}
+            )+
+        )+
+    };
+}
+
+capture_pins! {
+    TIM1: C1: (PA8, 1);
+    TIM1: C2: (PA9, 1);
+    TIM1: C3: (PA10, 1);
+    TIM1: C4: (PA11, 1);
+
+    TIM2: C1: (PA0, 1), (PA5, 1), (PA15, 1);
+    TIM2: C2: (PA1, 1), (PB3, 1);
+    TIM2: C3: (PA2, 1), (PB10, 1);
+    TIM2: C4: (PA3, 1), (PB11, 1);
+
+    TIM15: C1: (PA2, 14), (PB14, 14);
+
+    TIM16: C1: (PA6, 14), (PB8, 14);
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+capture_pins! {
+    TIM17: C1: (PA7, 14), (PB9, 14);
+}
+
+pwm_input_pins! {
+    TIM1: (PA8, 1), (PA9, 1);
+    TIM2: (PA0, 1), (PA5, 1), (PA15, 1), (PA1, 1), (PB3, 1);
+}
+
+/// Extension trait to run a timer for input capture
+pub trait CaptureExt: RccBus + Sized {
+    /// Captures the counter, running at the timer clock, on the `polarity` edges of
+    /// `pin`
+    ///
+    /// The counter counts up to the maximum of the timer, 16 or 32 bits, and wraps
+    /// around.
+    fn input_capture<PIN>(
+        self,
+        pin: PIN,
+        polarity: Polarity,
+        prescaler: Prescaler,
+        filter: Filter,
+        clocks: Clocks,
+        apb: &mut Self::Bus,
+    ) -> Capture<Self>
+    where
+        PIN: CapturePin<Self>;
+}
+
+/// Extension trait to measure a PWM signal with a timer
+pub trait PwmInputExt: RccBus + Sized {
+    /// Measures the period and the pulse width of the PWM signal on `pin`
+    ///
+    /// The channel of `pin` captures the period from rising edge to rising edge, which
+    /// resets the counter, the other one of channel 1 and 2 captures the high time.
+    fn pwm_input<PIN>(
+        self,
+        pin: PIN,
+        filter: Filter,
+        clocks: Clocks,
+        apb: &mut Self::Bus,
+    ) -> PwmInput<Self>
+    where
+        PIN: PwmInputPin<Self>;
+}
+
+/// Input capture channel
+pub struct Capture<TIM> {
+    channel: Channel,
+    clock: Hertz,
+    _tim: PhantomData<TIM>,
+}
+
+/// PWM input, see [`PwmInputExt::pwm_input`]
+pub struct PwmInput<TIM> {
+    channel: Channel,
+    clock: Hertz,
+    _tim: PhantomData<TIM>,
+}
+
+/// Value of CCxS for a channel connected to its own input
+const DIRECT: u32 = 0b01;
+/// Value of CCxS for a channel connected to the input of its neighbour
+const INDIRECT: u32 = 0b10;
+
+macro_rules! capture {
+    ($($TIMX:ident: ($timclk:ident, $width:ident, [$(($CX:ident, $ccrX:ident, $ccmrX:ident, $offset:literal),)+]),)+) => {
+        $(
+            impl Capture<$TIMX> {
+                /// Returns the last captured counter value
+                ///
+                /// Reading the value clears the capture flag.
+                pub fn read(&self) -> u32 {
+                    read_ccr(self.channel)
+                }
+
+                /// Waits for the next captured counter value
+                ///
+                /// An overwritten value, which was captured before the previous one was
+                /// read, returns an [`Error::Overcapture`] once, then the latest value.
+                pub fn capture(&mut self) -> nb::Result<u32, Error> {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let index = self.channel as u32;
+                    let sr = tim.sr.read().bits();
+                    if sr & 1 << (index + 1) == 0 {
+                        Err(nb::Error::WouldBlock)
+                    } else if sr & 1 << (index + 9) != 0 {
+                        tim.sr.write(|w| unsafe { w.bits(!(1 << (index + 9))) });
+                        Err(nb::Error::Other(Error::Overcapture))
+                    } else {
+                        Ok(self.read())
+                    }
+                }
+
+                /// Checks whether a captured value was overwritten before it was read, and
+                /// clears the flag if `clear` is set
+                pub fn is_overcapture(&mut self, clear: bool) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let bit = 1 << (self.channel as u32 + 9);
+                    let event = tim.sr.read().bits() & bit != 0;
+                    if event && clear {
+                        tim.sr.write(|w| unsafe { w.bits(!bit) });
+                    }
+                    event
+                }
+
+                /// Returns the counter ticks from the captured value `earlier` to the
+                /// captured value `later`, across one wrap around of the counter
+                pub fn elapsed(&self, earlier: u32, later: u32) -> u32 {
+                    later.wrapping_sub(earlier) & <$width>::MAX as u32
+                }
+
+                common!($TIMX: $timclk);
+            }
+
+            impl PwmInput<$TIMX> {
+                /// Returns the last captured period in counter ticks
+                pub fn period(&self) -> u32 {
+                    // The counter is reset after the last tick of the period
+                    read_ccr(self.channel) + 1
+                }
+
+                /// Returns the last captured high time in counter ticks
+                pub fn pulse_width(&self) -> u32 {
+                    let other = match self.channel {
+                        Channel::C1 => Channel::C2,
+                        _ => Channel::C1,
+                    };
+                    read_ccr(other) + 1
+                }
+
+                /// Returns the frequency of the last captured period
+                pub fn frequency(&self) -> Hertz {
+                    self.clock / self.period()
+                }
+
+                /// Checks whether a captured period was overwritten before it was read, and
+                /// clears the flag if `clear` is set
+                pub fn is_overcapture(&mut self, clear: bool) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let bit = 1 << (self.channel as u32 + 9);
+                    let event = tim.sr.read().bits() & bit != 0;
+                    if event && clear {
+                        tim.sr.write(|w| unsafe { w.bits(!bit) });
+                    }
+                    event
+                }
+
+                common!($TIMX: $timclk);
+            }
+
+            impl CaptureExt for $TIMX {
+                fn input_capture<PIN>(
+                    self,
+                    _pin: PIN,
+                    polarity: Polarity,
+                    prescaler: Prescaler,
+                    filter: Filter,
+                    clocks: Clocks,
+                    apb: &mut Self::Bus,
+                ) -> Capture<Self>
+                where
+                    PIN: CapturePin<Self>,
+                {
+                    <$TIMX>::enable(apb);
+                    <$TIMX>::reset(apb);
+
+                    set_input(PIN::CHANNEL, DIRECT, prescaler, filter, polarity);
+                    start(&self);
+
+                    Capture {
+                        channel: PIN::CHANNEL,
+                        clock: clocks.$timclk(),
+                        _tim: PhantomData,
+                    }
+                }
+            }
+
+            /// Returns the captured value of `channel`
+            #[allow(unreachable_patterns)]
+            fn read_ccr(channel: Channel) -> u32 {
+                let tim = unsafe { &*$TIMX::ptr() };
+                match channel {
+                    $(Channel::$CX => tim.$ccrX.read().bits(),)+
+                    _ => unreachable!(),
+                }
+            }
+
+            /// Connects `channel` to its input or the input of its neighbour, and enables
+            /// the capture
+            #[allow(unreachable_patterns)]
+            fn set_input(
+                channel: Channel,
+                selection: u32,
+                prescaler: Prescaler,
+                filter: Filter,
+                polarity: Polarity,
+            ) {
+                let tim = unsafe { &*$TIMX::ptr() };
+
+                // CCxS (bits 0-1), ICxPSC (bits 2-3) and ICxF (bits 4-7)
+                let bits = selection | (prescaler as u32) << 2 | (filter as u32) << 4;
+                match channel {
+                    $(
+                        Channel::$CX => tim.$ccmrX().modify(|r, w| unsafe {
+                            w.bits((r.bits() & !(0xff << $offset)) | bits << $offset)
+                        }),
+                    )+
+                    _ => unreachable!(),
+                }
+
+                // CCxE, CCxP and CCxNP (bits 0, 1 and 3 of the channel)
+                let bits = match polarity {
+                    Polarity::Rising => 0b0001,
+                    Polarity::Falling => 0b0011,
+                    Polarity::Both => 0b1011,
+                };
+                let shift = 4 * channel as u32;
+                tim.ccer.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b1111 << shift)) | bits << shift)
+                });
+            }
+
+            /// Starts the counter at the timer clock, up to its maximum
+            fn start(tim: &$TIMX) {
+                tim.psc.write(|w| unsafe { w.bits(0) });
+                tim.arr.write(|w| unsafe { w.bits(<$width>::MAX as u32) });
+                // Only an overflow raises the update flag, not the update generation or a
+                // reset from the slave mode controller
+                tim.cr1.write(|w| w.urs().set_bit());
+                tim.egr.write(|w| w.ug().set_bit());
+                tim.cr1.modify(|_, w| w.cen().set_bit());
+            }
+        )+
+    }
+}
+
+/// Methods of both [`Capture`] and [`PwmInput`]
+macro_rules! common {
+    ($TIMX:ident: $timclk:ident) => {
+        /// Returns the frequency of the counter
+        pub fn clock(&self) -> Hertz {
+            self.clock
+        }
+
+        /// Sets the frequency of the counter to the timer clock divided by an integer,
+        /// rounded up to the next possible frequency, and restarts the counter
+        ///
+        /// A lower frequency measures longer periods on the 16-bit timers.
+        pub fn set_clock(&mut self, frequency: Hertz, clocks: Clocks) {
+            let tim = unsafe { &*$TIMX::ptr() };
+            let clock = clocks.$timclk();
+            let prescaler = clock / frequency;
+            assert!(
+                prescaler > 0 && prescaler <= 0x1_0000,
+                "capture frequency out of range"
+            );
+            tim.psc.write(|w| unsafe { w.bits(prescaler - 1) });
+            tim.egr.write(|w| w.ug().set_bit());
+            self.clock = clock / prescaler;
+        }
+
+        /// Checks whether the counter overflowed, and clears the flag if `clear` is set
+        pub fn is_overflow(&mut self, clear: bool) -> bool {
+            let tim = unsafe { &*$TIMX::ptr() };
+            let event = tim.sr.read().uif().bit_is_set();
+            if event && clear {
+                tim.sr.write(|w| unsafe { w.bits(!Event::Overflow.bit()) });
+            }
+            event
+        }
+
+        /// Starts listening for an interrupt event
+        pub fn listen(&mut self, event: Event) {
+            let tim = unsafe { &*$TIMX::ptr() };
+            tim.dier
+                .modify(|r, w| unsafe { w.bits(r.bits() | event.bit()) });
+        }
+
+        /// Stops listening for an interrupt event
+        pub fn unlisten(&mut self, event: Event) {
+            let tim = unsafe { &*$TIMX::ptr() };
+            tim.dier
+                .modify(|r, w| unsafe { w.bits(r.bits() & !event.bit()) });
+        }
+    };
+}
+
+macro_rules! pwm_input {
+    ($($TIMX:ident: $timclk:ident,)+) => {
+        $(
+            impl PwmInputExt for $TIMX {
+                fn pwm_input<PIN>(
+                    self,
+                    _pin: PIN,
+                    filter: Filter,
+                    clocks: Clocks,
+                    apb: &mut Self::Bus,
+                ) -> PwmInput<Self>
+                where
+                    PIN: PwmInputPin<Self>,
+                {
+                    <$TIMX>::enable(apb);
+                    <$TIMX>::reset(apb);
+
+                    // TS is TI1FP1 or TI2FP2, the input of the channel of the pin
+                    let (other, trigger) = match PIN::CHANNEL {
+                        Channel::C1 => (Channel::C2, 0b101),
+                        _ => (Channel::C1, 0b110),
+                    };
+                    set_input(PIN::CHANNEL, DIRECT, Prescaler::Div1, filter, Polarity::Rising);
+                    set_input(other, INDIRECT, Prescaler::Div1, filter, Polarity::Falling);
+                    // TS (bits 4-6) and SMS (bits 0-2), reset mode
+                    self.smcr.write(|w| unsafe { w.bits(trigger << 4 | 0b100) });
+                    start(&self);
+
+                    PwmInput {
+                        channel: PIN::CHANNEL,
+                        clock: clocks.$timclk(),
+                        _tim: PhantomData,
+                    }
+                }
+            }
+        )+
+    }
+}
+
+mod tim1 {
+    use super::*;
+
+    capture! {
+        TIM1: (timclk2, u16, [
+            (C1, ccr1, ccmr1_input, 0),
+            (C2, ccr2, ccmr1_input, 8),
+            (C3, ccr3, ccmr2_input, 0),
+            (C4, ccr4, ccmr2_input, 8),
+        ]),
+    }
+
+    pwm_input! {
+        TIM1: timclk2,
+    }
+}
+
+mod tim2 {
+    use super::*;
+
+    capture! {
+        TIM2: (timclk1, u32, [
+            (C1, ccr1, ccmr1_input, 0),
+            (C2, ccr2, ccmr1_input, 8),
+            (C3, ccr3, ccmr2_input, 0),
+            (C4, ccr4, ccmr2_input, 8),
+        ]),
+    }
+
+    pwm_input! {
+        TIM2: timclk1,
+    }
+}
+
+mod tim15 {
+    use super::*;
+
+    capture! {
+        TIM15: (timclk2, u16, [
+            (C1, ccr1, ccmr1_input, 0),
+        ]),
+    }
+}
+
+mod tim16 {
+    use super::*;
+
+    capture! {
+        TIM16: (timclk2, u16, [
+            (C1, ccr1, ccmr1_input, 0),
+        ]),
+    }
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+mod tim17 {
+    use super::*;
+
+    capture! {
+        TIM17: (timclk2, u16, [
+            (C1, ccr1, ccmr1_input, 0),
+        ]),
+    }
+}
@@ -0,0 +1,417 @@
+//! Comparators
+//!
+//! The outputs of COMP1 and COMP2 are connected to EXTI lines 21 and 22, which can wake
+//! the core from Stop 2 mode.
+//!
+//! Usage example:
+//! ```ignore
+//! let pc5 = gpioc.pc5.into_analog(&mut gpioc.moder, &mut gpioc.pupdr);
+//! let mut comps = dp.COMP.split(&mut rcc.apb2);
+//!
+//! // Trip when PC5 rises above half of VREFINT
+//! let config = comp::Config::default()
+//!     .non_inverting(NonInvertingInput::Io1)
+//!     .inverting(InvertingInput::VrefintHalf)
+//!     .hysteresis(Hysteresis::Low);
+//! comps.comp1.configure(config);
+//! comps.comp1.enable();
+//! comps.comp1.listen(&mut dp.EXTI, Edge::Rising);
+//! ```
+
+use crate::gpio::Edge;
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::pac::TIM8;
+use crate::pac::{COMP, EXTI, SYSCFG, TIM1};
+use crate::rcc::{Enable, APB2};
+
+/// Extension trait to split the COMP peripheral into the individual comparators
+pub trait CompExt {
+    /// Enables the clock of the comparators, shared with SYSCFG
+    fn split(self, apb2: &mut APB2) -> Parts;
+}
+
+impl CompExt for COMP {
+    fn split(self, apb2: &mut APB2) -> Parts {
+        SYSCFG::enable(apb2);
+
+        Parts {
+            comp1: Comp1 { _0: () },
+            comp2: Comp2 { _0: () },
+        }
+    }
+}
+
+/// The individual comparators
+pub struct Parts {
+    pub comp1: Comp1,
+    pub comp2: Comp2,
+}
+
+/// COMP1, with the inputs on PC5, PB2 (and PA1) and PB1, PC4
+pub struct Comp1 {
+    _0: (),
+}
+
+/// COMP2, with the inputs on PB4, PB6 (and PA3) and PB3, PB7
+pub struct Comp2 {
+    _0: (),
+}
+
+/// Non-inverting input of a comparator, the pin has to be in analog mode (INPSEL)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum NonInvertingInput {
+    /// PC5 for COMP1, PB4 for COMP2
+    Io1 = 0b00,
+    /// PB2 for COMP1, PB6 for COMP2
+    Io2 = 0b01,
+    /// PA1 for COMP1, PA3 for COMP2
+    #[cfg(any(
+        feature = "stm32l412",
+        feature = "stm32l422",
+        feature = "stm32l431",
+        feature = "stm32l432",
+        feature = "stm32l433",
+        feature = "stm32l442",
+        feature = "stm32l443",
+        feature = "stm32l451",
+        feature = "stm32l452",
+        feature = "stm32l462",
+    ))]
+    Io3 = 0b10,
+}
+
+/// Inverting input of a comparator (INMSEL)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum InvertingInput {
+    VrefintQuarter = 0b000,
+    VrefintHalf = 0b001,
+    VrefintThreeQuarters = 0b010,
+    Vrefint = 0b011,
+    /// Output of DAC channel 1
+    #[cfg(not(any(feature = "stm32l412", feature = "stm32l422",)))]
+    DacChannel1 = 0b100,
+    /// Output of DAC channel 2
+    #[cfg(not(any(
+        feature = "stm32l412",
+        feature = "stm32l422",
+        feature = "stm32l451",
+        feature = "stm32l452",
+        feature = "stm32l462",
+    )))]
+    DacChannel2 = 0b101,
+    /// PB1 for COMP1, PB3 for COMP2, the pin has to be in analog mode
+    Io1 = 0b110,
+    /// PC4 for COMP1, PB7 for COMP2, the pin has to be in analog mode
+    Io2 = 0b111,
+}
+
+/// Hysteresis of a comparator
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Hysteresis {
+    None = 0b00,
+    Low = 0b01,
+    Medium = 0b10,
+    High = 0b11,
+}
+
+/// Trade-off between speed and consumption of a comparator
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerMode {
+    HighSpeed = 0b00,
+    MediumSpeed = 0b01,
+    UltraLowPower = 0b11,
+}
+
+/// Timer output that masks the output of COMP1 while it is active
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Comp1Blanking {
+    None = 0b000,
+    Tim1Oc5 = 0b001,
+    Tim2Oc3 = 0b010,
+    Tim3Oc3 = 0b100,
+}
+
+/// Timer output that masks the output of COMP2 while it is active
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Comp2Blanking {
+    None = 0b000,
+    Tim3Oc4 = 0b001,
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    Tim8Oc5 = 0b010,
+    Tim15Oc1 = 0b100,
+}
+
+/// Break input of an advanced-control timer
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BreakInput {
+    Tim1Break,
+    Tim1Break2,
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    Tim8Break,
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    Tim8Break2,
+}
+
+/// Comparator configuration
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    non_inverting: NonInvertingInput,
+    inverting: InvertingInput,
+    hysteresis: Hysteresis,
+    inverted: bool,
+    power_mode: PowerMode,
+}
+
+impl Config {
+    /// Set the non-inverting input
+    pub fn non_inverting(mut self, input: NonInvertingInput) -> Self {
+        self.non_inverting = input;
+        self
+    }
+
+    /// Set the inverting input
+    pub fn inverting(mut self, input: InvertingInput) -> Self {
+        self.inverting = input;
+        self
+    }
+
+    /// Set the hysteresis
+    pub fn hysteresis(mut self, hysteresis: Hysteresis) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Invert the output, so it is high while the non-inverting input is below the
+    /// inverting input
+    pub fn inverted(mut self) -> Self {
+        self.inverted = true;
+        self
+    }
+
+    /// Set the power mode
+    pub fn power_mode(mut self, power_mode: PowerMode) -> Self {
+        self.power_mode = power_mode;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            non_inverting: NonInvertingInput::Io1,
+            inverting: InvertingInput::Vrefint,
+            hysteresis: Hysteresis::None,
+            inverted: false,
+            power_mode: PowerMode::HighSpeed,
+        }
+    }
+}
+
+macro_rules! comp {
+    ($COMPX:ident: ($csr:ident, $CSR:ident, $blanking:ty, $line:expr, $cmpe:expr)) => {
+        impl $COMPX {
+            /// Applies `config`, the comparator keeps its enabled state
+            pub fn configure(&mut self, config: Config) {
+                // The VREFINT scaler is only powered when it is used. SCALEN (bit 23) is
+                // needed for all VREFINT inputs, BRGEN (bit 22) for the fractions.
+                let scaler = match config.inverting {
+                    InvertingInput::VrefintQuarter
+                    | InvertingInput::VrefintHalf
+                    | InvertingInput::VrefintThreeQuarters => 0b11,
+                    InvertingInput::Vrefint => 0b10,
+                    _ => 0b00,
+                };
+
+                // PWRMODE (bits 2-3), INMSEL (bits 4-6), INPSEL (bits 7-8),
+                // POLARITY (bit 15), HYST (bits 16-17)
+                self.csr().modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits()
+                            & !(0b11 << 2 | 0b111 << 4 | 0b11 << 7 | 1 << 15)
+                            & !(0b11 << 16 | 0b11 << 22))
+                            | (config.power_mode as u32) << 2
+                            | (config.inverting as u32) << 4
+                            | (config.non_inverting as u32) << 7
+                            | (config.inverted as u32) << 15
+                            | (config.hysteresis as u32) << 16
+                            | scaler << 22,
+                    )
+                });
+            }
+
+            /// Enables the comparator
+            ///
+            /// The output is valid after the startup time of up to 80 µs in ultra-low
+            /// power mode.
+            pub fn enable(&mut self) {
+                // EN (bit 0)
+                self.csr().modify(|r, w| unsafe { w.bits(r.bits() | 1) });
+            }
+
+            /// Disables the comparator
+            pub fn disable(&mut self) {
+                self.csr().modify(|r, w| unsafe { w.bits(r.bits() & !1) });
+            }
+
+            /// Returns the output of the comparator, `true` while the non-inverting input
+            /// is above the inverting input unless the polarity is inverted
+            pub fn output(&self) -> bool {
+                // VALUE (bit 30)
+                self.csr().read().bits() & 1 << 30 != 0
+            }
+
+            /// Masks the output while the timer output `blanking` is active, e.g. to
+            /// suppress current spikes at the start of a PWM period
+            pub fn set_blanking(&mut self, blanking: $blanking) {
+                // BLANKING (bits 18-20)
+                self.csr().modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(0b111 << 18)) | (blanking as u32) << 18)
+                });
+            }
+
+            /// Connects the output to the break input `input` of a timer, or disconnects it
+            pub fn set_break(&mut self, input: BreakInput, enabled: bool) {
+                // BKCMPxE and BK2CMPxE (bit 1 / 2) of TIMx_OR2 and TIMx_OR3
+                let update = |r: u32| (r & !(1 << $cmpe)) | (enabled as u32) << $cmpe;
+                match input {
+                    BreakInput::Tim1Break => unsafe {
+                        (*TIM1::ptr()).or2.modify(|r, w| w.bits(update(r.bits())))
+                    },
+                    BreakInput::Tim1Break2 => unsafe {
+                        (*TIM1::ptr()).or3.modify(|r, w| w.bits(update(r.bits())))
+                    },
+                    #[cfg(any(
+                        feature = "stm32l475",
+                        feature = "stm32l476",
+                        feature = "stm32l486",
+                        feature = "stm32l496",
+                        feature = "stm32l4a6",
+                        feature = "stm32l4r9",
+                        feature = "stm32l4s9",
+                    ))]
+                    BreakInput::Tim8Break => unsafe {
+                        (*TIM8::ptr()).or2.modify(|r, w| w.bits(update(r.bits())))
+                    },
+                    #[cfg(any(
+                        feature = "stm32l475",
+                        feature = "stm32l476",
+                        feature = "stm32l486",
+                        feature = "stm32l496",
+                        feature = "stm32l4a6",
+                        feature = "stm32l4r9",
+                        feature = "stm32l4s9",
+                    ))]
+                    BreakInput::Tim8Break2 => unsafe {
+                        (*TIM8::ptr()).or3.modify(|r, w| w.bits(update(r.bits())))
+                    },
+                }
+            }
+
+            /// Makes the configuration read-only until the next system reset
+            ///
+            /// Later changes of the configuration, including enabling or disabling the
+            /// comparator, are ignored.
+            pub fn lock(&mut self) {
+                // LOCK (bit 31)
+                self.csr()
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << 31) });
+            }
+
+            /// Returns `true` if the configuration is locked
+            pub fn is_locked(&self) -> bool {
+                self.csr().read().bits() & 1 << 31 != 0
+            }
+
+            /// Generates an interrupt (`COMP`) and wakes up from Stop modes on `edge`
+            /// of the output
+            pub fn listen(&mut self, exti: &mut EXTI, edge: Edge) {
+                let (rising, falling) = match edge {
+                    Edge::Rising => (true, false),
+                    Edge::Falling => (false, true),
+                    Edge::RisingFalling => (true, true),
+                };
+                exti.rtsr1.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(1 << $line)) | (rising as u32) << $line)
+                });
+                exti.ftsr1.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(1 << $line)) | (falling as u32) << $line)
+                });
+                exti.imr1
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << $line) });
+            }
+
+            /// Disables the interrupt
+            pub fn unlisten(&mut self, exti: &mut EXTI) {
+                exti.imr1
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << $line)) });
+            }
+
+            /// Clears the interrupt pending bit of the EXTI line
+            pub fn clear_interrupt_pending_bit(&mut self) {
+                unsafe { (*EXTI::ptr()).pr1.write(|w| w.bits(1 << $line)) };
+            }
+
+            /// Reads the interrupt pending bit of the EXTI line
+            pub fn check_interrupt(&self) -> bool {
+                unsafe { (*EXTI::ptr()).pr1.read().bits() & 1 << $line != 0 }
+            }
+
+            fn csr(&self) -> &crate::pac::comp::$CSR {
+                unsafe { &(*COMP::ptr()).$csr }
+            }
+        }
+    };
+}
+
+comp!(Comp1: (comp1_csr, COMP1_CSR, Comp1Blanking, 21, 1));
+comp!(Comp2: (comp2_csr, COMP2_CSR, Comp2Blanking, 22, 2));
+
+impl Comp2 {
+    /// Connects the non-inverting input of COMP2 to the one of COMP1, so both comparators
+    /// watch the same signal against two thresholds
+    pub fn set_window_mode(&mut self, enabled: bool) {
+        // WINMODE (bit 9)
+        self.csr()
+            .modify(|r, w| unsafe { w.bits((r.bits() & !(1 << 9)) | (enabled as u32) << 9) });
+    }
+}
@@ -42,6 +42,7 @@ impl CrcExt for CRC {
 }
 
 /// Polynomial settings.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Polynomial {
     /// 7-bit polynomial, only the lowest 7 bits are valid
     L7(u8),
@@ -54,6 +55,7 @@ pub enum Polynomial {
 }
 
 /// Bit reversal settings.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BitReversal {
     /// Reverse bits by byte
     ByByte,
@@ -0,0 +1,204 @@
+//! Clock recovery system (CRS)
+//!
+//! The CRS trims the HSI48 against a precise synchronization signal, usually the
+//! start of frame packets of the USB, which allows crystal-less USB devices:
+//!
+//! ```ignore
+//! let clocks = rcc
+//!     .cfgr
+//!     .hsi48(true)
+//!     .clk48_source(Clk48Source::Hsi48)
+//!     .freeze(&mut flash.acr, &mut pwr);
+//!
+//! let crs = dp.CRS.enable(SyncSource::UsbSof, &mut rcc.apb1r1, clocks).unwrap();
+//! ```
+
+use crate::rcc::{Clocks, Enable, APB1R1};
+use crate::stm32::CRS;
+use crate::time::{div_ceil, Hertz};
+
+/// CRS error
+#[non_exhaustive]
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The HSI48 is not enabled in the clocks
+    Hsi48Disabled,
+    /// The LSE was selected as synchronization source, but is not enabled in the clocks
+    LseDisabled,
+    /// The synchronization frequency is out of the range of the counter, which is
+    /// 733 Hz to 48 MHz
+    SyncFrequency,
+}
+
+/// Synchronization signal the HSI48 is trimmed against
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SyncSource {
+    /// USB start of frame packets, 1 kHz
+    UsbSof,
+    /// The LSE, 32.768 kHz
+    Lse,
+    /// The CRS_SYNC pin with the given frequency
+    Gpio(Hertz),
+}
+
+impl SyncSource {
+    fn bits(self) -> u8 {
+        match self {
+            SyncSource::Gpio(_) => 0b00,
+            SyncSource::Lse => 0b01,
+            SyncSource::UsbSof => 0b10,
+        }
+    }
+
+    fn frequency(self) -> u32 {
+        match self {
+            SyncSource::Gpio(freq) => freq.raw(),
+            SyncSource::Lse => 32_768,
+            SyncSource::UsbSof => 1_000,
+        }
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for SyncSource {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            SyncSource::UsbSof => defmt::write!(f, "UsbSof"),
+            SyncSource::Lse => defmt::write!(f, "Lse"),
+            SyncSource::Gpio(freq) => defmt::write!(f, "Gpio({} Hz)", freq.raw()),
+        }
+    }
+}
+
+/// Counter reload and frequency error limit per RM0394 section 7.3.3, with a trimming
+/// step of 0.14 %
+///
+/// Returns `None` if the counter can not count a period of `frequency`.
+fn reload_felim(frequency: u32) -> Option<(u16, u8)> {
+    if frequency == 0 {
+        return None;
+    }
+    let ratio = 48_000_000 / frequency;
+    if !(1..=0x1_0000).contains(&ratio) {
+        return None;
+    }
+    let felim = div_ceil(u64::from(ratio) * 14, 20_000).clamp(1, 0xff);
+    Some(((ratio - 1) as u16, felim as u8))
+}
+
+/// Extension trait to activate the CRS
+pub trait CrsExt {
+    /// Enables the automatic trimming of the HSI48 against `source`
+    ///
+    /// Fails without touching the CRS if the HSI48, or the LSE as `source`, is not enabled,
+    /// or if the frequency of `source` is out of range.
+    fn enable(self, source: SyncSource, apb1r1: &mut APB1R1, clocks: Clocks) -> Result<Crs, Error>;
+}
+
+impl CrsExt for CRS {
+    fn enable(self, source: SyncSource, apb1r1: &mut APB1R1, clocks: Clocks) -> Result<Crs, Error> {
+        // The CRS only trims the HSI48
+        if !clocks.hsi48() {
+            return Err(Error::Hsi48Disabled);
+        }
+        if source == SyncSource::Lse && !clocks.lse() {
+            return Err(Error::LseDisabled);
+        }
+        let (reload, felim) = reload_felim(source.frequency()).ok_or(Error::SyncFrequency)?;
+
+        <CRS as Enable>::enable(apb1r1);
+
+        // The configuration can only be changed while the frequency error counter is off
+        self.cr
+            .modify(|_, w| w.cen().clear_bit().autotrimen().clear_bit());
+        self.cfgr.modify(|_, w| unsafe {
+            w.syncsrc()
+                .bits(source.bits())
+                .syncdiv()
+                .bits(0)
+                .reload()
+                .bits(reload)
+                .felim()
+                .bits(felim)
+        });
+        self.cr
+            .modify(|_, w| w.autotrimen().set_bit().cen().set_bit());
+
+        Ok(Crs { crs: self })
+    }
+}
+
+/// Constrained CRS peripheral
+pub struct Crs {
+    crs: CRS,
+}
+
+impl Crs {
+    /// Current HSI48 trimming value
+    pub fn trim(&self) -> u8 {
+        self.crs.cr.read().trim().bits()
+    }
+
+    /// Returns `true` if the last synchronization event was within the tolerance
+    pub fn is_synchronized(&self) -> bool {
+        self.crs.isr.read().syncokf().bit_is_set()
+    }
+
+    /// Returns `true` if a synchronization error or trimming overflow occurred
+    ///
+    /// This usually means the synchronization signal is missing, e.g. because the USB
+    /// is not connected. The flag is cleared by [`Crs::clear_error`].
+    pub fn has_error(&self) -> bool {
+        self.crs.isr.read().errf().bit_is_set()
+    }
+
+    /// Clears the status flags
+    pub fn clear_error(&mut self) {
+        self.crs.icr.write(|w| {
+            w.errc()
+                .set_bit()
+                .esyncc()
+                .set_bit()
+                .syncwarnc()
+                .set_bit()
+                .syncokc()
+                .set_bit()
+        });
+    }
+
+    /// Stops the trimming and releases the CRS peripheral
+    ///
+    /// The HSI48 keeps the last trimming value.
+    pub fn free(self) -> CRS {
+        self.crs
+            .cr
+            .modify(|_, w| w.cen().clear_bit().autotrimen().clear_bit());
+        self.crs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::reload_felim;
+
+    #[test]
+    fn usb_sof() {
+        // RM0394 section 7.3.3: RELOAD = 47999, FELIM = 34
+        assert_eq!(reload_felim(1_000), Some((47_999, 34)));
+    }
+
+    #[test]
+    fn lse() {
+        assert_eq!(reload_felim(32_768), Some((1_463, 2)));
+    }
+
+    #[test]
+    fn frequency_limits() {
+        assert_eq!(reload_felim(0), None);
+        assert_eq!(reload_felim(732), None);
+        assert_eq!(reload_felim(733), Some((65_483, 46)));
+        assert_eq!(reload_felim(48_000_000), Some((0, 1)));
+        assert_eq!(reload_felim(48_000_001), None);
+    }
+}
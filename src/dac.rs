@@ -0,0 +1,659 @@
+//! Digital-to-analog converter
+//!
+//! Usage example:
+//! ```ignore
+//! let pa4 = gpioa.pa4.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+//! let mut dac = dp.DAC1.constrain(pa4, &mut rcc.apb1r1);
+//!
+//! dac.enable();
+//! // Half of VREF+
+//! dac.set_value(2048);
+//! ```
+
+use crate::dma::dma1;
+use crate::dmamux::{DmaInput, DmaMux};
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+use crate::gpio::PA5;
+use crate::gpio::{Analog, PA4};
+use crate::rcc::{Enable, Reset, APB1R1};
+use core::sync::atomic::{self, Ordering};
+use embedded_dma::StaticReadBuffer;
+
+#[cfg(any(
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::pac::{dac::RegisterBlock, DAC};
+#[cfg(not(any(
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+)))]
+use crate::pac::{dac1::RegisterBlock, DAC1 as DAC};
+
+/// DAC channel 1, output on PA4
+pub struct C1 {
+    _0: (),
+}
+
+/// DAC channel 2, output on PA5
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+pub struct C2 {
+    _0: (),
+}
+
+/// Output pins of the DAC channels, a single pin or a `(PA4, PA5)` tuple
+pub trait Pins<DAC>: crate::Sealed {
+    /// The channels driving the pins
+    type Output;
+
+    #[doc(hidden)]
+    fn channels() -> Self::Output;
+}
+
+impl crate::Sealed for PA4<Analog> {}
+impl Pins<DAC> for PA4<Analog> {
+    type Output = C1;
+
+    fn channels() -> C1 {
+        C1 { _0: () }
+    }
+}
+
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+impl crate::Sealed for PA5<Analog> {}
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+impl Pins<DAC> for PA5<Analog> {
+    type Output = C2;
+
+    fn channels() -> C2 {
+        C2 { _0: () }
+    }
+}
+
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+impl crate::Sealed for (PA4<Analog>, PA5<Analog>) {}
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+impl Pins<DAC> for (PA4<Analog>, PA5<Analog>) {
+    type Output = (C1, C2);
+
+    fn channels() -> (C1, C2) {
+        (C1 { _0: () }, C2 { _0: () })
+    }
+}
+
+/// Extension trait to constrain the DAC peripheral
+pub trait DacExt {
+    /// Enables and resets the DAC and returns the channels driving `pins`
+    fn constrain<PINS>(self, pins: PINS, apb1r1: &mut APB1R1) -> PINS::Output
+    where
+        PINS: Pins<DAC>;
+}
+
+impl DacExt for DAC {
+    fn constrain<PINS>(self, _pins: PINS, apb1r1: &mut APB1R1) -> PINS::Output
+    where
+        PINS: Pins<DAC>,
+    {
+        DAC::enable(apb1r1);
+        DAC::reset(apb1r1);
+
+        PINS::channels()
+    }
+}
+
+/// Output buffer and connection of a channel (MCR MODEx)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OutputMode {
+    /// Output buffer enabled, connected to the pin (reset value)
+    Buffered = 0b000,
+    /// Output buffer enabled, connected to the pin and to on-chip peripherals
+    BufferedInternal = 0b001,
+    /// Output buffer disabled, connected to the pin
+    Unbuffered = 0b010,
+    /// Output buffer disabled, connected only to on-chip peripherals, e.g. to feed a
+    /// comparator or an opamp
+    Internal = 0b011,
+}
+
+/// Timings of the sample and hold mode, in cycles of the LSI or LSE clock
+///
+/// In sample and hold mode the channel only drives its output during the sample phase
+/// and is powered down during the hold phase, while an external capacitor holds the
+/// voltage. After `refresh` cycles of hold, the output is sampled again.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SampleAndHold {
+    /// Length of the sample phase, up to 1023 cycles
+    pub sample: u16,
+    /// Length of the hold phase, up to 1023 cycles
+    pub hold: u16,
+    /// Length of the refresh phase, up to 255 cycles
+    pub refresh: u8,
+}
+
+/// DAC error
+#[non_exhaustive]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// A trigger occurred before the DMA delivered the next value
+    Underrun,
+}
+
+/// Peak value of the triangle wave (CR MAMPx)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriangleAmplitude {
+    A1 = 0,
+    A3 = 1,
+    A7 = 2,
+    A15 = 3,
+    A31 = 4,
+    A63 = 5,
+    A127 = 6,
+    A255 = 7,
+    A511 = 8,
+    A1023 = 9,
+    A2047 = 10,
+    A4095 = 11,
+}
+
+/// Number of LFSR bits, starting from bit 0, added to the output in noise mode
+/// (CR MAMPx)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LfsrMask {
+    Bits1 = 0,
+    Bits2 = 1,
+    Bits3 = 2,
+    Bits4 = 3,
+    Bits5 = 4,
+    Bits6 = 5,
+    Bits7 = 6,
+    Bits8 = 7,
+    Bits9 = 8,
+    Bits10 = 9,
+    Bits11 = 10,
+    Bits12 = 11,
+}
+
+/// Event that starts a conversion of a channel (CR TSELx)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Trigger {
+    Tim6Trgo = 0,
+    #[cfg(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    Tim8Trgo = 1,
+    #[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+    Tim7Trgo = 2,
+    #[cfg(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    Tim5Trgo = 3,
+    Tim2Trgo = 4,
+    #[cfg(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    Tim4Trgo = 5,
+    Exti9 = 6,
+    /// Triggered by writing to the SWTRIGR register, see [`C1::trigger`]
+    Software = 7,
+}
+
+macro_rules! dac_channel {
+    ($CX:ident, $shift:expr, $dhr12r:ident, $dor:ident, $shsr:ident) => {
+        impl $CX {
+            /// Enables the channel
+            ///
+            /// With the output buffer enabled, the output settles after a wake-up time of
+            /// up to 8.5 µs.
+            pub fn enable(&mut self) {
+                let dac = unsafe { &*DAC::ptr() };
+                // ENx (bit 0 / 16)
+                dac.cr
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << $shift) });
+            }
+
+            /// Disables the channel
+            pub fn disable(&mut self) {
+                let dac = unsafe { &*DAC::ptr() };
+                dac.cr
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << $shift)) });
+            }
+
+            /// Returns `true` if the channel is enabled
+            pub fn is_enabled(&self) -> bool {
+                let dac = unsafe { &*DAC::ptr() };
+                dac.cr.read().bits() & 1 << $shift != 0
+            }
+
+            /// Sets the 12-bit right-aligned output value
+            pub fn set_value(&mut self, value: u16) {
+                let dac = unsafe { &*DAC::ptr() };
+                dac.$dhr12r
+                    .write(|w| unsafe { w.bits(u32::from(value) & 0xfff) });
+            }
+
+            /// Returns the value currently converted by the channel
+            pub fn get_value(&self) -> u16 {
+                let dac = unsafe { &*DAC::ptr() };
+                (dac.$dor.read().bits() & 0xfff) as u16
+            }
+
+            /// Selects the output buffer and connection of the channel
+            ///
+            /// The mode can only be changed while the channel is disabled, so an enabled
+            /// channel is disabled for the change.
+            pub fn set_output_mode(&mut self, mode: OutputMode) {
+                self.while_disabled(|dac| {
+                    // MODEx (bits 0-2 / 16-18), bit 2 selects sample and hold
+                    dac.mcr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0b011 << $shift)) | (mode as u32) << $shift)
+                    });
+                });
+            }
+
+            /// Switches the channel to sample and hold mode
+            ///
+            /// The mode is clocked by LSI or LSE, which has to be running, and allows the
+            /// DAC to keep its output in Stop modes. The output mode selected with
+            /// [`Self::set_output_mode`] is kept.
+            pub fn enable_sample_and_hold(&mut self, timing: SampleAndHold) {
+                self.while_disabled(|dac| {
+                    // TSAMPLEx (bits 0-9)
+                    dac.$shsr
+                        .write(|w| unsafe { w.bits(u32::from(timing.sample) & 0x3ff) });
+                    // THOLDx (bits 0-9 / 16-25)
+                    dac.shhr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !(0x3ff << $shift))
+                                | (u32::from(timing.hold) & 0x3ff) << $shift,
+                        )
+                    });
+                    // TREFRESHx (bits 0-7 / 16-23)
+                    dac.shrr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0xff << $shift)) | u32::from(timing.refresh) << $shift)
+                    });
+                    dac.mcr
+                        .modify(|r, w| unsafe { w.bits(r.bits() | 0b100 << $shift) });
+                });
+            }
+
+            /// Switches the channel back to normal mode
+            pub fn disable_sample_and_hold(&mut self) {
+                self.while_disabled(|dac| {
+                    dac.mcr
+                        .modify(|r, w| unsafe { w.bits(r.bits() & !(0b100 << $shift)) });
+                });
+            }
+
+            /// Converts the value written with [`Self::set_value`] only when `trigger`
+            /// occurs, instead of one APB clock cycle after the write
+            ///
+            /// The trigger can be changed while the channel is enabled.
+            pub fn enable_trigger(&mut self, trigger: Trigger) {
+                let dac = unsafe { &*DAC::ptr() };
+                // TENx (bit 2 / 18), TSELx (bits 3-5 / 19-21)
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0b1111 << (2 + $shift)))
+                            | (1 | (trigger as u32) << 1) << (2 + $shift),
+                    )
+                });
+            }
+
+            /// Converts written values right away again
+            pub fn disable_trigger(&mut self) {
+                let dac = unsafe { &*DAC::ptr() };
+                dac.cr
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(0b1111 << (2 + $shift))) });
+            }
+
+            /// Adds a triangle wave to the value set with [`Self::set_value`]
+            ///
+            /// Every `trigger` event moves the triangle counter one step up or down
+            /// between 0 and `amplitude`. The sum of the set value and the counter is
+            /// converted, and must not exceed 4095.
+            pub fn enable_triangle(&mut self, amplitude: TriangleAmplitude, trigger: Trigger) {
+                // WAVEx = 0b1x: triangle
+                self.set_waveform(0b10, amplitude as u32, Some(trigger));
+            }
+
+            /// Adds pseudo-noise to the value set with [`Self::set_value`]
+            ///
+            /// Every `trigger` event advances the LFSR, whose bits selected with `mask`
+            /// are added to the set value.
+            pub fn enable_noise(&mut self, mask: LfsrMask, trigger: Trigger) {
+                // WAVEx = 0b01: noise
+                self.set_waveform(0b01, mask as u32, Some(trigger));
+            }
+
+            /// Stops the triangle or noise generator and the trigger, so the set value is
+            /// converted as is again
+            pub fn disable_waveform(&mut self) {
+                self.set_waveform(0b00, 0, None);
+            }
+
+            /// Switches the wave generator while the channel keeps running
+            fn set_waveform(&mut self, wave: u32, mamp: u32, trigger: Option<Trigger>) {
+                let dac = unsafe { &*DAC::ptr() };
+                let ten_tsel = match trigger {
+                    Some(trigger) => 1 | (trigger as u32) << 1,
+                    None => 0,
+                };
+
+                // The generator is stopped first so that no step is taken with a partly
+                // written configuration. It then restarts from the set value.
+                // WAVEx (bits 6-7 / 22-23)
+                dac.cr
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << (6 + $shift))) });
+                // TENx, TSELx (bits 2-5 / 18-21), MAMPx (bits 8-11 / 24-27)
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits(
+                        (r.bits() & !(0b1111 << (2 + $shift)) & !(0b1111 << (8 + $shift)))
+                            | ten_tsel << (2 + $shift)
+                            | mamp << (8 + $shift),
+                    )
+                });
+                if wave != 0 {
+                    dac.cr
+                        .modify(|r, w| unsafe { w.bits(r.bits() | wave << (6 + $shift)) });
+                }
+            }
+
+            /// Generates the trigger event when [`Trigger::Software`] is selected
+            pub fn trigger(&mut self) {
+                let dac = unsafe { &*DAC::ptr() };
+                // SWTRIGx (bit 0 / 1), write only and cleared by hardware
+                dac.swtrigr.write(|w| unsafe { w.bits(1 << ($shift / 16)) });
+            }
+
+            /// Returns `Err(Error::Underrun)` and clears the flag if a trigger occurred
+            /// before the DMA delivered the previous value
+            ///
+            /// A DMA underrun stops the DMA requests of the channel, so the transfer has
+            /// to be restarted afterwards.
+            pub fn check_underrun(&mut self) -> Result<(), Error> {
+                let dac = unsafe { &*DAC::ptr() };
+                // DMAUDRx (bit 13 / 29), write 1 to clear
+                if dac.sr.read().bits() & 1 << (13 + $shift) != 0 {
+                    dac.sr.write(|w| unsafe { w.bits(1 << (13 + $shift)) });
+                    Err(Error::Underrun)
+                } else {
+                    Ok(())
+                }
+            }
+
+            /// Enables the DMA underrun interrupt (`TIM6_DACUNDER`)
+            pub fn listen_underrun(&mut self) {
+                let dac = unsafe { &*DAC::ptr() };
+                // DMAUDRIEx (bit 13 / 29)
+                dac.cr
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << (13 + $shift)) });
+            }
+
+            /// Disables the DMA underrun interrupt
+            pub fn unlisten_underrun(&mut self) {
+                let dac = unsafe { &*DAC::ptr() };
+                dac.cr
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << (13 + $shift))) });
+            }
+
+            /// Enables or disables the DMA requests of the channel
+            fn set_dma(&mut self, enabled: bool) {
+                let dac = unsafe { &*DAC::ptr() };
+                // DMAENx (bit 12 / 28)
+                dac.cr.modify(|r, w| unsafe {
+                    w.bits((r.bits() & !(1 << (12 + $shift))) | (enabled as u32) << (12 + $shift))
+                });
+            }
+
+            /// Runs `f` with the channel disabled and re-enables it if it was enabled
+            fn while_disabled(&mut self, f: impl FnOnce(&RegisterBlock)) {
+                let enabled = self.is_enabled();
+                if enabled {
+                    self.disable();
+                }
+
+                f(unsafe { &*DAC::ptr() });
+
+                if enabled {
+                    self.enable();
+                }
+            }
+        }
+    };
+}
+
+dac_channel!(C1, 0, dhr12r1, dor1, shsr1);
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+dac_channel!(C2, 16, dhr12r2, dor2, shsr2);
+
+/// Waveform output, a DMA transfer feeding a DAC channel one value per trigger event
+///
+/// ```ignore
+/// static SINE: [u16; 32] = [...];
+///
+/// let transfer = Transfer::from_c1(dac, dma_channels.3, &SINE, Trigger::Tim6Trgo, true);
+/// ```
+///
+/// With [`Transfer::from_dual`], both channels are fed at the same time from a buffer of
+/// `u32` words, each with the value of channel 1 in the lower and the value of channel 2
+/// in the upper half word.
+pub struct Transfer<CHANNEL, DMA, BUFFER> {
+    channel: CHANNEL,
+    dma: DMA,
+    buffer: BUFFER,
+}
+
+/// Configures `$dma` to write `$buffer` to the data register `$dhr` at every request of
+/// the DAC and starts it
+macro_rules! start_dma {
+    ($dma:expr, $buffer:expr, $dhr:ident, $msize:expr, $circular:expr) => {{
+        // SAFETY: the buffer is not accessed by `Transfer` until it is returned by `stop`,
+        // after the DMA stopped reading it
+        let (ptr, len) = unsafe { $buffer.read_buffer() };
+
+        let dac = unsafe { &*DAC::ptr() };
+        $dma.set_peripheral_address(&dac.$dhr as *const _ as u32, false);
+        $dma.set_memory_address(ptr as u32, true);
+        $dma.set_transfer_length(len as u16);
+
+        $dma.ccr().modify(|_, w| unsafe {
+            w.mem2mem()
+                .clear_bit()
+                // 00: Low, 01: Medium, 10: High, 11: Very high
+                .pl()
+                .bits(0b10)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .msize()
+                .bits($msize)
+                // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                .psize()
+                .bits(0b10)
+                // Mem -> Peripheral
+                .dir()
+                .set_bit()
+                .circ()
+                .bit($circular)
+        });
+
+        atomic::compiler_fence(Ordering::Release);
+
+        $dma.start();
+    }};
+}
+
+macro_rules! dac_transfer {
+    ($CX:ident, $DMA:ty, $input:expr, $dhr12r:ident, $from:ident) => {
+        impl<BUFFER> Transfer<$CX, $DMA, BUFFER>
+        where
+            BUFFER: StaticReadBuffer<Word = u16>,
+        {
+            /// Starts converting the values of `buffer`, one at every `trigger` event
+            ///
+            /// With `circular`, the buffer is played out over and over again, otherwise
+            /// once. The channel is enabled.
+            pub fn $from(
+                mut channel: $CX,
+                mut dma: $DMA,
+                buffer: BUFFER,
+                trigger: Trigger,
+                circular: bool,
+            ) -> Self {
+                channel.set_dma(false);
+                let _ = channel.check_underrun();
+                channel.enable_trigger(trigger);
+
+                dma.set_request_line($input).unwrap();
+                start_dma!(dma, buffer, $dhr12r, 0b01, circular);
+
+                channel.enable();
+                channel.set_dma(true);
+
+                Self {
+                    channel,
+                    dma,
+                    buffer,
+                }
+            }
+
+            /// Returns `true` once a non-circular transfer has converted the whole buffer
+            pub fn is_done(&self) -> bool {
+                !self.dma.in_progress()
+            }
+
+            /// Returns `Err(Error::Underrun)` if the DMA could not keep up with the
+            /// triggers, which stops the transfer
+            pub fn check_underrun(&mut self) -> Result<(), Error> {
+                self.channel.check_underrun()
+            }
+
+            /// Stops the DMA and the trigger and returns the parts
+            ///
+            /// The channel stays enabled and holds the last converted value.
+            pub fn stop(mut self) -> ($CX, $DMA, BUFFER) {
+                self.dma.stop();
+
+                atomic::compiler_fence(Ordering::SeqCst);
+
+                self.channel.set_dma(false);
+                self.channel.disable_trigger();
+
+                (self.channel, self.dma, self.buffer)
+            }
+        }
+    };
+}
+
+dac_transfer!(C1, dma1::C3, DmaInput::Dac1Ch1, dhr12r1, from_c1);
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+dac_transfer!(C2, dma1::C4, DmaInput::Dac1Ch2, dhr12r2, from_c2);
+
+/// Runs the same statements for both channels of a `(C1, C2)` tuple
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+macro_rules! for_each_channel {
+    ($channels:expr, |$c:ident| $body:expr) => {{
+        let $c = &mut $channels.0;
+        $body;
+        let $c = &mut $channels.1;
+        $body;
+    }};
+}
+
+#[cfg(not(any(feature = "stm32l451", feature = "stm32l452", feature = "stm32l462",)))]
+impl<BUFFER> Transfer<(C1, C2), dma1::C3, BUFFER>
+where
+    BUFFER: StaticReadBuffer<Word = u32>,
+{
+    /// Starts converting the paired values of `buffer` on both channels at the same time,
+    /// one pair at every `trigger` event
+    ///
+    /// With `circular`, the buffer is played out over and over again, otherwise once. The
+    /// channels are enabled.
+    pub fn from_dual(
+        mut channels: (C1, C2),
+        mut dma: dma1::C3,
+        buffer: BUFFER,
+        trigger: Trigger,
+        circular: bool,
+    ) -> Self {
+        for_each_channel!(channels, |c| {
+            c.set_dma(false);
+            let _ = c.check_underrun();
+            c.enable_trigger(trigger);
+        });
+
+        dma.set_request_line(DmaInput::Dac1Ch1).unwrap();
+        start_dma!(dma, buffer, dhr12rd, 0b10, circular);
+
+        for_each_channel!(channels, |c| c.enable());
+        // Channel 1 requests the pairs for both channels
+        channels.0.set_dma(true);
+
+        Self {
+            channel: channels,
+            dma,
+            buffer,
+        }
+    }
+
+    /// Returns `true` once a non-circular transfer has converted the whole buffer
+    pub fn is_done(&self) -> bool {
+        !self.dma.in_progress()
+    }
+
+    /// Returns `Err(Error::Underrun)` if the DMA could not keep up with the triggers,
+    /// which stops the transfer
+    pub fn check_underrun(&mut self) -> Result<(), Error> {
+        self.channel.0.check_underrun()
+    }
+
+    /// Stops the DMA and the triggers and returns the parts
+    ///
+    /// The channels stay enabled and hold the last converted values.
+    pub fn stop(mut self) -> ((C1, C2), dma1::C3, BUFFER) {
+        self.dma.stop();
+
+        atomic::compiler_fence(Ordering::SeqCst);
+
+        for_each_channel!(self.channel, |c| {
+            c.set_dma(false);
+            c.disable_trigger();
+        });
+
+        (self.channel, self.dma, self.buffer)
+    }
+}
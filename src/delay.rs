@@ -6,7 +6,7 @@ use cortex_m::peripheral::syst::SystClkSource;
 use cortex_m::peripheral::SYST;
 
 use crate::hal::blocking::delay::{DelayMs, DelayUs};
-use crate::rcc::Clocks;
+use crate::rcc::{Clocks, RateChange};
 use crate::time::Hertz;
 
 /// System timer (SysTick) as a delay provider
@@ -29,6 +29,12 @@ impl Delay {
     }
 }
 
+impl RateChange for Delay {
+    fn clocks_changed(&mut self, _old: &Clocks, new: &Clocks) {
+        self.clocks = *new;
+    }
+}
+
 /// System timer (SysTick) as a delay provider.
 impl DelayMs<u32> for Delay {
     fn delay_ms(&mut self, ms: u32) {
@@ -111,6 +117,12 @@ impl DelayCM {
     }
 }
 
+impl RateChange for DelayCM {
+    fn clocks_changed(&mut self, _old: &Clocks, new: &Clocks) {
+        self.sysclk = new.sysclk();
+    }
+}
+
 impl DelayMs<u32> for DelayCM {
     fn delay_ms(&mut self, ms: u32) {
         self.delay_us(ms * 1_000);
@@ -16,11 +16,13 @@ use stable_deref_trait::StableDeref;
 
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Overrun,
     BufferError,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
     HalfTransfer,
     TransferComplete,
@@ -333,6 +335,12 @@ where
     }
 }
 
+impl<BUFFER, PAYLOAD> CircBuffer<BUFFER, PAYLOAD> {
+    pub(crate) fn payload_mut(&mut self) -> &mut PAYLOAD {
+        &mut self.payload
+    }
+}
+
 pub trait DmaExt {
     type Channels;
 
@@ -406,6 +414,10 @@ impl<MODE, BUFFER, PAYLOAD> Transfer<MODE, BUFFER, PAYLOAD>
 where
     PAYLOAD: TransferPayload,
 {
+    pub(crate) fn payload(&self) -> &PAYLOAD {
+        &self.payload
+    }
+
     pub(crate) fn extract_inner_without_drop(self) -> (BUFFER, PAYLOAD) {
         // `Transfer` needs to have a `Drop` implementation, because we accept
         // managed buffers that can free their memory on drop. Because of that
@@ -422,6 +434,24 @@ where
             (buffer, payload)
         }
     }
+
+    pub(crate) fn payload_mut(&mut self) -> &mut PAYLOAD {
+        &mut self.payload
+    }
+
+    /// Stops the transfer, whether it is complete or not, and returns the buffer and the
+    /// payload
+    ///
+    /// How far the transfer got can be checked with `peek` before aborting it.
+    pub fn abort(mut self) -> (BUFFER, PAYLOAD) {
+        self.payload.stop();
+
+        // NOTE(compiler_fence) operations on `buffer` should not be reordered
+        // before the previous statement, which stops the DMA transfer
+        compiler_fence(Ordering::SeqCst);
+
+        self.extract_inner_without_drop()
+    }
 }
 
 /// Read transfer
@@ -970,21 +1000,12 @@ macro_rules! dma {
                             // Example:
                             // read = 2, write = 3, 2*capacity-2 words written => write = 1.
                             let capacity = self.buffer.as_ref().len();
-                            // We read the flags before reading the current write-index because if
-                            // another word is written between those two accesses, this ordering
-                            // prevents a false positive overrun error.
-                            let half_complete_flag = self.payload.channel.check_half_transfer_interrupt();
-                            let transfer_complete_flag = self.payload.channel.check_transfer_complete_interrupt();
-                            let write_current = capacity - self.payload.channel.get_cndtr() as usize;
+                            let (flags, write_current) = self.write_index(capacity);
                             // Copy the data before examining the overrun conditions. If the
                             // overrun happens shortly after the flags and write-index were read,
                             // we can not detect it anyways. So we can only hope that we have
                             // already read the word(s) that will be overwritten.
-                            let available = if write_current >= self.read_index {
-                                write_current - self.read_index
-                            } else {
-                                capacity + write_current - self.read_index
-                            };
+                            let available = self.available(write_current, capacity);
                             let read_len = core::cmp::min(available, buf.len());
                             if self.read_index + read_len <= capacity {
                                 // non-wrapping read
@@ -996,6 +1017,74 @@ macro_rules! dma {
                                 buf[..first_read_len].copy_from_slice(&self.buffer.as_ref()[self.read_index..]);
                                 buf[first_read_len..read_len].copy_from_slice(&self.buffer.as_ref()[..second_read_len]);
                             }
+                            self.advance(read_len, flags, write_current, capacity)?;
+                            Ok(read_len)
+                        }
+
+                        /// Returns the available contents of the dma buffer without removing
+                        /// them, as two slices since the contents may wrap around the end of
+                        /// the buffer.
+                        ///
+                        /// The DMA keeps writing while the slices are borrowed, so the contents
+                        /// are only valid if the following [`consume`](Self::consume) succeeds.
+                        pub fn peek<T>(&self) -> (&[T], &[T])
+                            where
+                            B: AsRef<[T]>,
+                        {
+                            let buffer = self.buffer.as_ref();
+                            let capacity = buffer.len();
+                            let write_current = capacity - self.payload.channel.get_cndtr() as usize;
+                            if write_current >= self.read_index {
+                                (&buffer[self.read_index..write_current], &[])
+                            } else {
+                                (&buffer[self.read_index..], &buffer[..write_current])
+                            }
+                        }
+
+                        /// Removes `len` words returned by [`peek`](Self::peek), fewer if
+                        /// fewer are available.
+                        /// Returns `Err(Error::Overrun)` if an overrun is detected since the last
+                        /// `read` or `consume`, the peeked contents may be corrupted then. As
+                        /// with `read`, there is no guarantee that every overrun can be detected.
+                        pub fn consume<T>(&mut self, len: usize) -> Result<(), Error>
+                            where
+                            B: AsRef<[T]>,
+                        {
+                            let capacity = self.buffer.as_ref().len();
+                            let (flags, write_current) = self.write_index(capacity);
+                            let len = core::cmp::min(self.available(write_current, capacity), len);
+                            self.advance(len, flags, write_current, capacity)
+                        }
+
+                        /// Returns the half transfer and transfer complete flags, and the write
+                        /// index
+                        fn write_index(&mut self, capacity: usize) -> ((bool, bool), usize) {
+                            // We read the flags before reading the current write-index because if
+                            // another word is written between those two accesses, this ordering
+                            // prevents a false positive overrun error.
+                            let half_complete_flag = self.payload.channel.check_half_transfer_interrupt();
+                            let transfer_complete_flag = self.payload.channel.check_transfer_complete_interrupt();
+                            let write_current = capacity - self.payload.channel.get_cndtr() as usize;
+                            ((half_complete_flag, transfer_complete_flag), write_current)
+                        }
+
+                        /// Returns the number of words between the read index and `write_current`
+                        fn available(&self, write_current: usize, capacity: usize) -> usize {
+                            if write_current >= self.read_index {
+                                write_current - self.read_index
+                            } else {
+                                capacity + write_current - self.read_index
+                            }
+                        }
+
+                        /// Moves the read index by `len` words unless an overrun is detected
+                        fn advance(
+                            &mut self,
+                            len: usize,
+                            (half_complete_flag, transfer_complete_flag): (bool, bool),
+                            write_current: usize,
+                            capacity: usize,
+                        ) -> Result<(), Error> {
                             // For checking the overrun conditions, it is important that we use the
                             // old read_index so do not increment it yet but check overrun
                             // conditions first.
@@ -1010,11 +1099,11 @@ macro_rules! dma {
                                 self.read_index = write_current;
                                 Err(Error::Overrun)
                             } else {
-                                self.read_index += read_len;
+                                self.read_index += len;
                                 if self.read_index >= capacity {
                                     self.read_index -= capacity;
                                 }
-                                Ok(read_len)
+                                Ok(())
                             }
                         }
 
@@ -1133,7 +1222,7 @@ macro_rules! dma {
                             feature = "stm32l4r9",
                             feature = "stm32l4s9"
                         ))]
-                        ahb.enr().modify(|_, w| w.dmamux1en().set_bit());
+                        <crate::pac::DMAMUX1>::enable(ahb);
 
                         // reset the DMA control registers (stops all on-going transfers)
                         $(
@@ -22,11 +22,13 @@ use crate::stm32::DMAMUX1 as DMAMUX;
 
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     Invalid,
 }
 
 /// Input DMA request line selected
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum DmaInput {
     Generator0,
     Generator1,
@@ -1,7 +1,7 @@
 //! General Purpose Input / Output
 
 pub use crate::hal::digital::v2::PinState;
-use crate::hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin, ToggleableOutputPin};
+use crate::hal::digital::v2::{InputPin, IoPin, OutputPin, StatefulOutputPin, ToggleableOutputPin};
 use core::convert::Infallible;
 use core::marker::PhantomData;
 
@@ -14,6 +14,12 @@ mod partially_erased;
 pub use partially_erased::{PEPin, PartiallyErasedPin};
 mod erased;
 pub use erased::{EPin, ErasedPin};
+mod dynamic;
+pub use dynamic::{Dynamic, DynamicMode, DynamicPin, PinModeError};
+mod lock;
+pub use lock::{LockError, LockedPin, LCKR};
+mod group;
+pub use group::PinGroup;
 
 /// Extension trait to split a GPIO peripheral in independent pins and registers
 pub trait GpioExt {
@@ -52,6 +58,8 @@ pub struct Analog;
 pub type Debugger = Alternate<PushPull, 0>;
 
 /// GPIO Pin speed selection
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Speed {
     Low = 0,
     Medium = 1,
@@ -73,6 +81,7 @@ pub struct Alternate<MODE, const A: u8> {
 }
 
 #[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Edge {
     Rising,
     Falling,
@@ -89,6 +98,11 @@ impl<MODE> Interruptable for Output<MODE> {}
 impl<MODE> Interruptable for Input<MODE> {}
 
 /// External Interrupt Pin
+///
+/// Pin `n` of every port is routed to EXTI line `n`, so only one port can use a given line at a
+/// time. Lines 0 to 4 have their own interrupt vector, while lines 5 to 9 share `EXTI9_5` and
+/// lines 10 to 15 share `EXTI15_10`. Handlers of the shared vectors must check which pin
+/// fired, either with [`ExtiPin::check_interrupt`] or with [`ExtiExt::pending_lines`].
 pub trait ExtiPin {
     fn make_interrupt_source(&mut self, syscfg: &mut SYSCFG, apb2: &mut APB2);
     fn trigger_on_edge(&mut self, exti: &mut EXTI, level: Edge);
@@ -205,6 +219,28 @@ where
     }
 }
 
+/// Extension trait for the EXTI peripheral
+pub trait ExtiExt {
+    /// Returns the pending bits of EXTI lines 0 to 31, bit `n` is set if line `n` fired
+    fn pending_lines(&self) -> u32;
+
+    /// Clears the pending bits of the EXTI lines set in `mask`
+    fn clear_pending_lines(&mut self, mask: u32);
+}
+
+impl ExtiExt for EXTI {
+    #[inline(always)]
+    fn pending_lines(&self) -> u32 {
+        self.pr1.read().bits()
+    }
+
+    #[inline(always)]
+    fn clear_pending_lines(&mut self, mask: u32) {
+        // NOTE(unsafe) writing 0 to a pending bit has no effect
+        self.pr1.write(|w| unsafe { w.bits(mask) });
+    }
+}
+
 /// Opaque MODER register
 pub struct MODER<const P: char> {
     _0: (),
@@ -257,7 +293,7 @@ macro_rules! gpio {
             use crate::stm32::$GPIOX;
 
             use crate::rcc::{AHB2, Enable, Reset};
-            use super::{Afr, Analog, GpioExt, Pin, H8, L8, MODER, OTYPER, OSPEEDR, PUPDR};
+            use super::{Afr, Analog, GpioExt, Pin, H8, L8, LCKR, MODER, OTYPER, OSPEEDR, PUPDR};
 
             /// GPIO parts
             pub struct Parts {
@@ -273,6 +309,8 @@ macro_rules! gpio {
                 pub ospeedr: OSPEEDR<$port_id>,
                 /// Opaque PUPDR register
                 pub pupdr: PUPDR<$port_id>,
+                /// Opaque LCKR register
+                pub lckr: LCKR<$port_id>,
                 $(
                     /// Pin
                     pub $pxi: $PXi<$MODE>,
@@ -291,6 +329,24 @@ macro_rules! gpio {
                     <$GPIOX>::reset(ahb);
                     $($pwrenable)?
 
+                    // NOTE(unsafe) the port has been enabled and reset above and
+                    // `self` is owned, so these are the only existing parts
+                    unsafe { Parts::new_unchecked() }
+                }
+            }
+
+            impl Parts {
+                /// Creates the parts of the port without enabling or resetting it
+                ///
+                /// # Safety
+                ///
+                /// - The port clock must already be enabled, and the port must be powered
+                ///   if it has an independent supply.
+                /// - No other `Parts`, pin or register proxy of this port may exist, and
+                ///   the raw peripheral must not be used while the parts are alive.
+                /// - The pins are assumed to be in their reset mode, as given by the
+                ///   types of the fields.
+                pub unsafe fn new_unchecked() -> Self {
                     Parts {
                         afrh: Afr::new(),
                         afrl: Afr::new(),
@@ -298,11 +354,43 @@ macro_rules! gpio {
                         otyper: OTYPER::new(),
                         ospeedr: OSPEEDR::new(),
                         pupdr: PUPDR::new(),
+                        lckr: LCKR::new(),
                         $(
                             $pxi: $PXi::new(),
                         )+
                     }
                 }
+
+                /// Releases the raw peripheral
+                ///
+                /// All pins have to be handed back in their reset mode. The port clock is
+                /// left enabled.
+                pub fn free(self) -> $GPIOX {
+                    // NOTE(unsafe) the parts are consumed, so the peripheral is not
+                    // accessed through them anymore
+                    unsafe { crate::pac::Peripherals::steal().$GPIOX }
+                }
+
+                /// Switches all pins of the port to analog mode without pull up or down,
+                /// except for the pins selected in `exclude`, and releases the raw peripheral
+                ///
+                /// Analog mode has the lowest leakage, so this is usually done for unused
+                /// ports before entering a low power mode. Pass [`SWD_PINS`](super::SWD_PINS)
+                /// as `exclude` on port A to keep the debugger attached.
+                pub fn into_all_analog(self, exclude: u16) -> $GPIOX {
+                    // Two bits per pin, set for the excluded pins
+                    let keep = (0..16)
+                        .filter(|i| exclude & (1 << i) != 0)
+                        .fold(0u32, |mask, i| mask | (0b11 << (2 * i)));
+                    // NOTE(unsafe) the parts are consumed, so no pin of the port can be
+                    // accessed concurrently
+                    unsafe {
+                        let gpio = &*<$GPIOX>::ptr();
+                        gpio.moder.modify(|r, w| w.bits((r.bits() & keep) | !keep));
+                        gpio.pupdr.modify(|r, w| w.bits(r.bits() & keep));
+                    }
+                    self.free()
+                }
             }
         }
 
@@ -343,21 +431,22 @@ impl<MODE, HL, const P: char, const N: u8> PinExt for Pin<MODE, HL, P, N> {
 
 impl<MODE, HL, const P: char, const N: u8> Pin<Output<MODE>, HL, P, N> {
     /// Set pin speed
-    pub fn set_speed(self, speed: Speed) -> Self {
-        let offset = 2 * { N };
-
-        unsafe {
-            (*Gpio::<P>::ptr())
-                .ospeedr
-                .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | ((speed as u32) << offset)))
-        };
+    pub fn set_speed(&mut self, speed: Speed) {
+        self._set_speed(speed)
+    }
 
+    /// Set pin speed
+    pub fn speed(mut self, speed: Speed) -> Self {
+        self.set_speed(speed);
         self
     }
 }
 
 impl<HL, const P: char, const N: u8> Pin<Output<OpenDrain>, HL, P, N> {
     /// Enables / disables the internal pull up
+    ///
+    /// The pull up stays enabled while the pin is used through [`IoPin`], which is
+    /// what sensors like the DHT22 expect on their data line.
     pub fn internal_pull_up(&mut self, _pupdr: &mut PUPDR<P>, on: bool) {
         let offset = 2 * { N };
         let value = if on { 0b01 } else { 0b00 };
@@ -371,19 +460,20 @@ impl<HL, const P: char, const N: u8> Pin<Output<OpenDrain>, HL, P, N> {
 
 impl<MODE, HL, const P: char, const N: u8, const A: u8> Pin<Alternate<MODE, A>, HL, P, N> {
     /// Set pin speed
-    pub fn set_speed(self, speed: Speed) -> Self {
-        let offset = 2 * { N };
-
-        unsafe {
-            (*Gpio::<P>::ptr())
-                .ospeedr
-                .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | ((speed as u32) << offset)))
-        };
+    pub fn set_speed(&mut self, speed: Speed) {
+        self._set_speed(speed)
+    }
 
+    /// Set pin speed
+    pub fn speed(mut self, speed: Speed) -> Self {
+        self.set_speed(speed);
         self
     }
 
     /// Enables / disables the internal pull up
+    ///
+    /// Only the pull bits of pin `N` are modified. The setting is kept when the pin is
+    /// switched to another alternate function mode.
     pub fn internal_pull_up(&mut self, _pupdr: &mut PUPDR<P>, on: bool) {
         let offset = 2 * { N };
         let value = if on { 0b01 } else { 0b00 };
@@ -427,6 +517,20 @@ impl<MODE, HL, const P: char, const N: u8> Pin<MODE, HL, P, N> {
     }
 }
 
+impl<MODE, HL, const P: char, const N: u8> From<Pin<MODE, HL, P, N>>
+    for PartiallyErasedPin<MODE, P>
+{
+    fn from(p: Pin<MODE, HL, P, N>) -> Self {
+        p.erase_number()
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> From<Pin<MODE, HL, P, N>> for ErasedPin<MODE> {
+    fn from(p: Pin<MODE, HL, P, N>) -> Self {
+        p.erase()
+    }
+}
+
 // Internal helper functions
 //
 // NOTE: The functions in this impl block are "safe", but they
@@ -455,6 +559,17 @@ impl<MODE, HL, const P: char, const N: u8> Pin<MODE, HL, P, N> {
         unsafe { (*Gpio::<P>::ptr()).bsrr.write(|w| w.bits(1 << (16 + N))) }
     }
 
+    #[inline(always)]
+    fn _set_speed(&mut self, speed: Speed) {
+        let offset = 2 * { N };
+
+        unsafe {
+            (*Gpio::<P>::ptr())
+                .ospeedr
+                .modify(|r, w| w.bits((r.bits() & !(0b11 << offset)) | ((speed as u32) << offset)))
+        };
+    }
+
     #[inline(always)]
     fn _is_set_low(&self) -> bool {
         // NOTE(unsafe) atomic read with no side effects
@@ -592,6 +707,25 @@ impl<HL, const P: char, const N: u8> InputPin for Pin<Output<OpenDrain>, HL, P,
     }
 }
 
+/// An open drain output can be read while it is driven, so switching between input and
+/// output only writes the output data register: releasing the line turns the pin into an
+/// input. The pin configuration, including an internal pull up, is left untouched.
+impl<HL, const P: char, const N: u8> IoPin<Self, Self> for Pin<Output<OpenDrain>, HL, P, N> {
+    type Error = Infallible;
+
+    #[inline]
+    fn into_input_pin(mut self) -> Result<Self, Self::Error> {
+        self.set_high();
+        Ok(self)
+    }
+
+    #[inline]
+    fn into_output_pin(mut self, state: PinState) -> Result<Self, Self::Error> {
+        self.set_state(state);
+        Ok(self)
+    }
+}
+
 /// Opaque AFR register
 pub struct Afr<HL, const P: char> {
     _afr: PhantomData<HL>,
@@ -622,6 +756,33 @@ macro_rules! af {
 af!(H8, AFRH, afrh);
 af!(L8, AFRL, afrl);
 
+/// Pin number `N` at the type level, see [`PinAfr`]
+pub struct PinNumber<const N: u8>;
+
+/// Half of the AFR registers, [`L8`] or [`H8`], that holds the alternate function of a pin
+///
+/// It is implemented for the pin numbers 0 to 15, so the `HL` parameter of a [`Pin`] can be
+/// derived from `N` as `<PinNumber<N> as PinAfr>::HL`.
+pub trait PinAfr {
+    type HL;
+}
+
+macro_rules! pin_afr {
+    ($HL:ident: $($N:literal),+) => {
+        $(
+            impl PinAfr for PinNumber<$N> {
+                type HL = $HL;
+            }
+        )+
+    };
+}
+
+pin_afr!(L8: 0, 1, 2, 3, 4, 5, 6, 7);
+pin_afr!(H8: 8, 9, 10, 11, 12, 13, 14, 15);
+
+/// `HL` parameter of pin `N`
+pub(crate) type PinHL<const N: u8> = <PinNumber<N> as PinAfr>::HL;
+
 gpio!(GPIOA, gpioa, PAx, 'A', 0, [
     PA0: (pa0, 0, Analog, L8, exticr1),
     PA1: (pa1, 1, Analog, L8, exticr1),
@@ -826,6 +987,113 @@ gpio!(GPIOH, gpioh, PHx, 'H', 7, [
     PH15: (ph15, 15, Analog, H8, exticr4),
 ]);
 
+/// Mask of the SWD pins PA13 (SWDIO) and PA14 (SWCLK)
+pub const SWD_PINS: u16 = (1 << 13) | (1 << 14);
+
+/// Splits all GPIO ports of the device at once
+///
+/// ```ignore
+/// let (gpioa, gpiob, gpioc, gpiod, gpioe) =
+///     gpio::split_all(dp.GPIOA, dp.GPIOB, dp.GPIOC, dp.GPIOD, dp.GPIOE, &mut rcc.ahb2);
+/// ```
+#[cfg(not(any(
+    // feature = "stm32l471",  // missing PAC support for Ports F, G and H
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+)))]
+pub fn split_all(
+    gpioa: pac::GPIOA,
+    gpiob: pac::GPIOB,
+    gpioc: pac::GPIOC,
+    gpiod: pac::GPIOD,
+    gpioe: pac::GPIOE,
+    ahb: &mut AHB2,
+) -> (
+    gpioa::Parts,
+    gpiob::Parts,
+    gpioc::Parts,
+    gpiod::Parts,
+    gpioe::Parts,
+) {
+    (
+        gpioa.split(ahb),
+        gpiob.split(ahb),
+        gpioc.split(ahb),
+        gpiod.split(ahb),
+        gpioe.split(ahb),
+    )
+}
+
+/// Splits all GPIO ports of the device at once
+///
+/// ```ignore
+/// let (gpioa, gpiob, gpioc, gpiod, gpioe, gpiof, gpiog, gpioh) = gpio::split_all(
+///     dp.GPIOA, dp.GPIOB, dp.GPIOC, dp.GPIOD, dp.GPIOE, dp.GPIOF, dp.GPIOG, dp.GPIOH,
+///     &mut rcc.ahb2,
+/// );
+/// ```
+#[cfg(any(
+    // feature = "stm32l471",  // missing PAC support for Ports F, G and H
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+#[allow(clippy::too_many_arguments)]
+pub fn split_all(
+    gpioa: pac::GPIOA,
+    gpiob: pac::GPIOB,
+    gpioc: pac::GPIOC,
+    gpiod: pac::GPIOD,
+    gpioe: pac::GPIOE,
+    gpiof: pac::GPIOF,
+    gpiog: pac::GPIOG,
+    gpioh: pac::GPIOH,
+    ahb: &mut AHB2,
+) -> (
+    gpioa::Parts,
+    gpiob::Parts,
+    gpioc::Parts,
+    gpiod::Parts,
+    gpioe::Parts,
+    gpiof::Parts,
+    gpiog::Parts,
+    gpioh::Parts,
+) {
+    (
+        gpioa.split(ahb),
+        gpiob.split(ahb),
+        gpioc.split(ahb),
+        gpiod.split(ahb),
+        gpioe.split(ahb),
+        gpiof.split(ahb),
+        gpiog.split(ahb),
+        gpioh.split(ahb),
+    )
+}
+
 struct Gpio<const P: char>;
 impl<const P: char> Gpio<P> {
     const fn ptr() -> *const crate::pac::gpioa::RegisterBlock {
@@ -8,7 +8,7 @@ impl<const L: u8, const R: u8> Assert<L, R> {
 }
 
 impl<MODE, HL, const P: char, const N: u8> Pin<MODE, HL, P, N> {
-    fn set_alternate<const A: u8>(&mut self) {
+    pub(super) fn set_alternate<const A: u8>(&mut self) {
         #[allow(path_statements, clippy::no_effect)]
         {
             Assert::<A, 16>::LESS;
@@ -143,6 +143,36 @@ impl<MODE, HL, const P: char, const N: u8> Pin<MODE, HL, P, N> {
         Pin::new()
     }
 
+    /// Configures the pin to operate as an open drain output pin with the given
+    /// output speed. Initial state will be low.
+    pub fn into_open_drain_output_with_speed(
+        mut self,
+        _moder: &mut MODER<P>,
+        _otyper: &mut OTYPER<P>,
+        _ospeedr: &mut OSPEEDR<P>,
+        speed: Speed,
+    ) -> Pin<Output<OpenDrain>, HL, P, N> {
+        self._set_low();
+        self._set_speed(speed);
+        self.mode::<Output<OpenDrain>>();
+        Pin::new()
+    }
+
+    /// Configures the pin to operate as a push pull output pin with the given
+    /// output speed. Initial state will be low.
+    pub fn into_push_pull_output_with_speed(
+        mut self,
+        _moder: &mut MODER<P>,
+        _otyper: &mut OTYPER<P>,
+        _ospeedr: &mut OSPEEDR<P>,
+        speed: Speed,
+    ) -> Pin<Output<PushPull>, HL, P, N> {
+        self._set_low();
+        self._set_speed(speed);
+        self.mode::<Output<PushPull>>();
+        Pin::new()
+    }
+
     /// Configures the pin to operate as an analog input pin
     pub fn into_analog(
         mut self,
@@ -153,12 +183,24 @@ impl<MODE, HL, const P: char, const N: u8> Pin<MODE, HL, P, N> {
         Pin::new()
     }
 
+    /// Configures the pin as a pin that can change between input
+    /// and output without changing the type. It starts out
+    /// as a floating input
+    pub fn into_dynamic(
+        mut self,
+        _moder: &mut MODER<P>,
+        _pupdr: &mut PUPDR<P>,
+    ) -> DynamicPin<P, N> {
+        self.mode::<Input<Floating>>();
+        DynamicPin::new(Dynamic::InputFloating)
+    }
+
     /// Puts `self` into mode `M`.
     ///
     /// This violates the type state constraints from `MODE`, so callers must
     /// ensure they use this properly.
     #[inline(always)]
-    fn mode<M: PinMode>(&mut self) {
+    pub(super) fn mode<M: PinMode>(&mut self) {
         let offset = 2 * N;
         unsafe {
             (*Gpio::<P>::ptr())
@@ -0,0 +1,927 @@
+use super::convert::PinMode;
+use super::*;
+
+use core::fmt;
+use core::sync::atomic::{self, Ordering};
+
+/// Pin type with dynamic mode
+///
+/// - `P` is port name: `A` for GPIOA, `B` for GPIOB, etc.
+/// - `N` is pin number: from `0` to `15`.
+pub struct DynamicPin<const P: char, const N: u8> {
+    /// Current pin mode
+    pub(crate) mode: Dynamic,
+}
+
+/// Tracks the current pin state for dynamic pins
+///
+/// The `make_*` methods of [`DynamicPin`] only return after the new configuration
+/// has been written to the GPIO registers, so every read or write that follows a
+/// transition uses the new mode:
+///
+/// - output writes are driven in the new output mode immediately,
+/// - input reads return the level sampled in the new mode. Note that the pin itself
+///   may need some time to settle after an output is released, e.g. until an
+///   external or internal pull resistor has charged the line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Dynamic {
+    InputFloating,
+    InputPullUp,
+    InputPullDown,
+    OutputPushPull,
+    OutputOpenDrain,
+    Analog,
+    /// Push pull alternate function, with the AF number
+    Alternate(u8),
+    /// Open drain alternate function, with the AF number
+    AlternateOpenDrain(u8),
+}
+
+/// Error for [DynamicPin]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PinModeError {
+    /// Operation is not supported in the current pin mode
+    IncorrectMode,
+}
+
+impl fmt::Display for Dynamic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dynamic::InputFloating => f.write_str("floating input"),
+            Dynamic::InputPullUp => f.write_str("pull up input"),
+            Dynamic::InputPullDown => f.write_str("pull down input"),
+            Dynamic::OutputPushPull => f.write_str("push pull output"),
+            Dynamic::OutputOpenDrain => f.write_str("open drain output"),
+            Dynamic::Analog => f.write_str("analog"),
+            Dynamic::Alternate(af) => write!(f, "push pull alternate function {}", af),
+            Dynamic::AlternateOpenDrain(af) => write!(f, "open drain alternate function {}", af),
+        }
+    }
+}
+
+impl fmt::Display for PinModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PinModeError::IncorrectMode => f.write_str("operation not supported in this pin mode"),
+        }
+    }
+}
+
+impl Dynamic {
+    /// The pin can be read in this mode
+    pub fn is_input(&self) -> bool {
+        use Dynamic::*;
+        match self {
+            InputFloating | InputPullUp | InputPullDown | OutputOpenDrain => true,
+            OutputPushPull | Analog | Alternate(_) | AlternateOpenDrain(_) => false,
+        }
+    }
+
+    /// The pin can be an EXTI interrupt source in this mode
+    pub fn is_interruptable(&self) -> bool {
+        use Dynamic::*;
+        match self {
+            InputFloating | InputPullUp | InputPullDown | OutputOpenDrain => true,
+            Alternate(_) | AlternateOpenDrain(_) => true,
+            OutputPushPull | Analog => false,
+        }
+    }
+
+    /// The pin can be driven in this mode
+    pub fn is_output(&self) -> bool {
+        use Dynamic::*;
+        match self {
+            InputFloating | InputPullUp | InputPullDown | Analog => false,
+            Alternate(_) | AlternateOpenDrain(_) => false,
+            OutputPushPull | OutputOpenDrain => true,
+        }
+    }
+}
+
+/// Type state modes that have an equivalent [`Dynamic`] mode.
+///
+/// It can not be implemented by outside types.
+pub trait DynamicMode: crate::Sealed {
+    #[doc(hidden)]
+    const DYNAMIC: Dynamic;
+}
+
+impl DynamicMode for Input<Floating> {
+    const DYNAMIC: Dynamic = Dynamic::InputFloating;
+}
+
+impl DynamicMode for Input<PullUp> {
+    const DYNAMIC: Dynamic = Dynamic::InputPullUp;
+}
+
+impl DynamicMode for Input<PullDown> {
+    const DYNAMIC: Dynamic = Dynamic::InputPullDown;
+}
+
+impl DynamicMode for Output<PushPull> {
+    const DYNAMIC: Dynamic = Dynamic::OutputPushPull;
+}
+
+impl DynamicMode for Output<OpenDrain> {
+    const DYNAMIC: Dynamic = Dynamic::OutputOpenDrain;
+}
+
+impl DynamicMode for Analog {
+    const DYNAMIC: Dynamic = Dynamic::Analog;
+}
+
+impl<const A: u8> crate::Sealed for Alternate<PushPull, A> {}
+impl<const A: u8> DynamicMode for Alternate<PushPull, A> {
+    const DYNAMIC: Dynamic = Dynamic::Alternate(A);
+}
+
+impl<const A: u8> crate::Sealed for Alternate<OpenDrain, A> {}
+impl<const A: u8> DynamicMode for Alternate<OpenDrain, A> {
+    const DYNAMIC: Dynamic = Dynamic::AlternateOpenDrain(A);
+}
+
+/// Converts a typed pin into a dynamic pin, keeping its current configuration
+impl<MODE: DynamicMode, HL, const P: char, const N: u8> From<Pin<MODE, HL, P, N>>
+    for DynamicPin<P, N>
+{
+    fn from(_pin: Pin<MODE, HL, P, N>) -> Self {
+        DynamicPin::new(MODE::DYNAMIC)
+    }
+}
+
+// Pin with no particular mode, used to reach the raw register helpers
+struct Unknown;
+
+impl Interruptable for Unknown {}
+
+type RawPin<const P: char, const N: u8> = Pin<Unknown, Unknown, P, N>;
+
+/// Mode configuration registers of a port, as far as one pin is concerned
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Config {
+    moder: u32,
+    otyper: u32,
+    pupdr: u32,
+    /// AFRL for pins 0 to 7, AFRH for pins 8 to 15
+    afr: u32,
+}
+
+impl Config {
+    /// Returns the configuration with pin `n` switched to `mode`
+    ///
+    /// Like the typed pin conversions, the inputs and analog mode keep the output
+    /// type and the alternate functions keep the pull resistors.
+    fn with_mode(self, n: u8, mode: Dynamic) -> Self {
+        match mode {
+            Dynamic::InputFloating => self.with::<Input<Floating>>(n),
+            Dynamic::InputPullUp => self.with::<Input<PullUp>>(n),
+            Dynamic::InputPullDown => self.with::<Input<PullDown>>(n),
+            Dynamic::OutputPushPull => self.with::<Output<PushPull>>(n),
+            Dynamic::OutputOpenDrain => self.with::<Output<OpenDrain>>(n),
+            Dynamic::Analog => self.with::<Analog>(n),
+            Dynamic::Alternate(af) => self.with_alternate(n, af, false),
+            Dynamic::AlternateOpenDrain(af) => self.with_alternate(n, af, true),
+        }
+    }
+
+    fn with<M: PinMode>(self, n: u8) -> Self {
+        let offset = 2 * n;
+        Self {
+            moder: (self.moder & !(0b11 << offset)) | (M::MODER << offset),
+            otyper: match M::OTYPER {
+                Some(otyper) => (self.otyper & !(0b1 << n)) | (otyper << n),
+                None => self.otyper,
+            },
+            pupdr: (self.pupdr & !(0b11 << offset)) | (M::PUPDR << offset),
+            afr: self.afr,
+        }
+    }
+
+    fn with_alternate(self, n: u8, af: u8, open_drain: bool) -> Self {
+        let offset = 2 * n;
+        let offset2 = 4 * (n % 8);
+        Self {
+            moder: (self.moder & !(0b11 << offset)) | (0b10 << offset),
+            otyper: (self.otyper & !(0b1 << n)) | (u32::from(open_drain) << n),
+            pupdr: self.pupdr,
+            afr: (self.afr & !(0b1111 << offset2)) | (u32::from(af & 0b1111) << offset2),
+        }
+    }
+}
+
+/// Register accesses of a [`DynamicPin`]
+trait Port {
+    /// Reads the mode configuration of pin `n`
+    fn config(&self, n: u8) -> Config;
+
+    /// Writes the registers that differ between `old` and `new`, MODER last,
+    /// and only returns once the writes have completed
+    fn configure(&mut self, n: u8, old: Config, new: Config);
+
+    /// Writes `state` to the output data register of pin `n`
+    fn set_state(&mut self, n: u8, state: PinState);
+
+    /// Samples the input data register of pin `n`
+    fn is_low(&self, n: u8) -> bool;
+}
+
+impl<const P: char> Port for Gpio<P> {
+    fn config(&self, n: u8) -> Config {
+        // NOTE(unsafe) atomic reads with no side effects
+        let gpio = unsafe { &*Gpio::<P>::ptr() };
+        Config {
+            moder: gpio.moder.read().bits(),
+            otyper: gpio.otyper.read().bits(),
+            pupdr: gpio.pupdr.read().bits(),
+            afr: if n < 8 {
+                gpio.afrl.read().bits()
+            } else {
+                gpio.afrh.read().bits()
+            },
+        }
+    }
+
+    fn configure(&mut self, n: u8, old: Config, new: Config) {
+        // NOTE(unsafe) the callers hold the configuration register proxies
+        unsafe {
+            let gpio = &*Gpio::<P>::ptr();
+            if new.pupdr != old.pupdr {
+                gpio.pupdr.write(|w| w.bits(new.pupdr));
+            }
+            if new.otyper != old.otyper {
+                gpio.otyper.write(|w| w.bits(new.otyper));
+            }
+            if new.afr != old.afr {
+                if n < 8 {
+                    gpio.afrl.write(|w| w.bits(new.afr));
+                } else {
+                    gpio.afrh.write(|w| w.bits(new.afr));
+                }
+            }
+            if new.moder != old.moder {
+                gpio.moder.write(|w| w.bits(new.moder));
+            }
+        }
+        // Make sure the configuration writes are neither reordered after
+        // subsequent pin accesses nor still in flight on the bus
+        atomic::compiler_fence(Ordering::SeqCst);
+        cortex_m::asm::dsb();
+    }
+
+    fn set_state(&mut self, n: u8, state: PinState) {
+        let bit = match state {
+            PinState::High => 1 << n,
+            PinState::Low => 1 << (16 + n),
+        };
+        // NOTE(unsafe) atomic write to a stateless register
+        unsafe { (*Gpio::<P>::ptr()).bsrr.write(|w| w.bits(bit)) }
+    }
+
+    fn is_low(&self, n: u8) -> bool {
+        // NOTE(unsafe) atomic read with no side effects
+        unsafe { (*Gpio::<P>::ptr()).idr.read().bits() & (1 << n) == 0 }
+    }
+}
+
+impl<const P: char, const N: u8> DynamicPin<P, N> {
+    pub(super) const fn new(mode: Dynamic) -> Self {
+        Self { mode }
+    }
+
+    /// Returns the current mode of the pin
+    #[inline(always)]
+    pub fn mode(&self) -> Dynamic {
+        self.mode
+    }
+
+    /// The pin can be read in the current mode
+    #[inline(always)]
+    pub fn is_input(&self) -> bool {
+        self.mode.is_input()
+    }
+
+    /// The pin can be driven in the current mode
+    #[inline(always)]
+    pub fn is_output(&self) -> bool {
+        self.mode.is_output()
+    }
+
+    /// Converts the pin back into a typed pin.
+    ///
+    /// Fails with [`PinModeError::IncorrectMode`] if the current mode of the pin
+    /// is not `MODE`. Make sure to switch into the right mode first, e.g.
+    ///
+    /// ```ignore
+    /// pin.make_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper);
+    /// let pin: PA5<Output<PushPull>> = pin.try_into_typed()?;
+    /// ```
+    pub fn try_into_typed<MODE: DynamicMode>(
+        self,
+    ) -> Result<Pin<MODE, PinHL<N>, P, N>, PinModeError>
+    where
+        PinNumber<N>: PinAfr,
+    {
+        if self.mode == MODE::DYNAMIC {
+            Ok(Pin::new())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
+    /// Switches the pin to `mode` and records the new mode once the register
+    /// writes have completed
+    #[inline(always)]
+    fn reconfigure(&mut self, port: &mut impl Port, mode: Dynamic) {
+        let config = port.config(N);
+        port.configure(N, config, config.with_mode(N, mode));
+        self.mode = mode;
+    }
+
+    /// Switch the pin to a floating input
+    #[inline]
+    pub fn make_floating_input(&mut self, _moder: &mut MODER<P>, _pupdr: &mut PUPDR<P>) {
+        self.reconfigure(&mut Gpio::<P>, Dynamic::InputFloating);
+    }
+
+    /// Switch the pin to a pulled up input
+    #[inline]
+    pub fn make_pull_up_input(&mut self, _moder: &mut MODER<P>, _pupdr: &mut PUPDR<P>) {
+        self.reconfigure(&mut Gpio::<P>, Dynamic::InputPullUp);
+    }
+
+    /// Switch the pin to a pulled down input
+    #[inline]
+    pub fn make_pull_down_input(&mut self, _moder: &mut MODER<P>, _pupdr: &mut PUPDR<P>) {
+        self.reconfigure(&mut Gpio::<P>, Dynamic::InputPullDown);
+    }
+
+    /// Switch the pin to a push pull output
+    ///
+    /// The output data register is set low before the mode changes,
+    /// so the pin never drives a stale level.
+    #[inline]
+    pub fn make_push_pull_output(&mut self, moder: &mut MODER<P>, otyper: &mut OTYPER<P>) {
+        self.make_push_pull_output_in_state(moder, otyper, PinState::Low);
+    }
+
+    /// Switch the pin to a push pull output.
+    /// `state` is written to the output data register before the mode changes.
+    #[inline]
+    pub fn make_push_pull_output_in_state(
+        &mut self,
+        _moder: &mut MODER<P>,
+        _otyper: &mut OTYPER<P>,
+        state: PinState,
+    ) {
+        let mut port = Gpio::<P>;
+        port.set_state(N, state);
+        self.reconfigure(&mut port, Dynamic::OutputPushPull);
+    }
+
+    /// Switch the pin to an open drain output
+    ///
+    /// The output data register is set low before the mode changes,
+    /// so the pin never releases or pulls the line to a stale level.
+    #[inline]
+    pub fn make_open_drain_output(&mut self, moder: &mut MODER<P>, otyper: &mut OTYPER<P>) {
+        self.make_open_drain_output_in_state(moder, otyper, PinState::Low);
+    }
+
+    /// Switch the pin to an open drain output.
+    /// `state` is written to the output data register before the mode changes.
+    #[inline]
+    pub fn make_open_drain_output_in_state(
+        &mut self,
+        _moder: &mut MODER<P>,
+        _otyper: &mut OTYPER<P>,
+        state: PinState,
+    ) {
+        let mut port = Gpio::<P>;
+        port.set_state(N, state);
+        self.reconfigure(&mut port, Dynamic::OutputOpenDrain);
+    }
+
+    /// Switch the pin to analog mode, disconnecting the digital input
+    /// and the pull resistors
+    #[inline]
+    pub fn make_analog(&mut self, _moder: &mut MODER<P>, _pupdr: &mut PUPDR<P>) {
+        self.reconfigure(&mut Gpio::<P>, Dynamic::Analog);
+    }
+
+    /// Switch the pin to push pull alternate function `A`
+    ///
+    /// Takes AFRL for pins 0 to 7 and AFRH for pins 8 to 15.
+    #[inline]
+    pub fn make_alternate<const A: u8>(
+        &mut self,
+        _moder: &mut MODER<P>,
+        _otyper: &mut OTYPER<P>,
+        _afr: &mut Afr<PinHL<N>, P>,
+    ) where
+        PinNumber<N>: PinAfr,
+    {
+        self.reconfigure(&mut Gpio::<P>, Dynamic::Alternate(A));
+    }
+
+    /// Switch the pin to open drain alternate function `A`
+    ///
+    /// Takes AFRL for pins 0 to 7 and AFRH for pins 8 to 15.
+    #[inline]
+    pub fn make_alternate_open_drain<const A: u8>(
+        &mut self,
+        _moder: &mut MODER<P>,
+        _otyper: &mut OTYPER<P>,
+        _afr: &mut Afr<PinHL<N>, P>,
+    ) where
+        PinNumber<N>: PinAfr,
+    {
+        self.reconfigure(&mut Gpio::<P>, Dynamic::AlternateOpenDrain(A));
+    }
+
+    /// Puts the pin back into `mode` after a temporary mode change
+    ///
+    /// The output modes keep the current output data register contents. The
+    /// alternate function modes write their function number to AFR again.
+    fn restore_mode(&mut self, _afr: &mut Afr<PinHL<N>, P>, mode: Dynamic)
+    where
+        PinNumber<N>: PinAfr,
+    {
+        self.reconfigure(&mut Gpio::<P>, mode);
+    }
+
+    /// Temporarily switches the pin to a push pull output in `state`, calls `f`
+    /// and then restores the previous mode.
+    ///
+    /// The previous mode is restored even if `f` changed the mode itself. Takes AFRL
+    /// for pins 0 to 7 and AFRH for pins 8 to 15, as an alternate function mode
+    /// is restored through AFR.
+    pub fn with_push_pull_output_in_state<R>(
+        &mut self,
+        moder: &mut MODER<P>,
+        otyper: &mut OTYPER<P>,
+        _pupdr: &mut PUPDR<P>,
+        afr: &mut Afr<PinHL<N>, P>,
+        state: PinState,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R
+    where
+        PinNumber<N>: PinAfr,
+    {
+        let previous = self.mode;
+        self.make_push_pull_output_in_state(moder, otyper, state);
+        let ret = f(self);
+        self.restore_mode(afr, previous);
+        ret
+    }
+
+    /// Temporarily switches the pin to an open drain output in `state`, calls `f`
+    /// and then restores the previous mode.
+    ///
+    /// This is the usual turnaround for single wire buses, e.g. with the pin idling
+    /// as a pull up input:
+    ///
+    /// ```ignore
+    /// pin.with_open_drain_output_in_state(moder, otyper, pupdr, afr, PinState::Low, |_| {
+    ///     delay.delay_us(480u32);
+    /// });
+    /// let presence = pin.is_low()?;
+    /// ```
+    ///
+    /// The previous mode is restored even if `f` changed the mode itself. Takes AFRL
+    /// for pins 0 to 7 and AFRH for pins 8 to 15, as an alternate function mode
+    /// is restored through AFR.
+    pub fn with_open_drain_output_in_state<R>(
+        &mut self,
+        moder: &mut MODER<P>,
+        otyper: &mut OTYPER<P>,
+        _pupdr: &mut PUPDR<P>,
+        afr: &mut Afr<PinHL<N>, P>,
+        state: PinState,
+        f: impl FnOnce(&mut Self) -> R,
+    ) -> R
+    where
+        PinNumber<N>: PinAfr,
+    {
+        let previous = self.mode;
+        self.make_open_drain_output_in_state(moder, otyper, state);
+        let ret = f(self);
+        self.restore_mode(afr, previous);
+        ret
+    }
+
+    /// Calls `f` with a typed analog pin if the pin is currently in analog mode.
+    ///
+    /// This allows sampling a dynamic pin with the ADC, which only accepts
+    /// typed analog pins as channels:
+    ///
+    /// ```ignore
+    /// let sample = pin.with_analog_pin(|p: &mut PA0<Analog>| adc.read(p))?;
+    /// ```
+    pub fn with_analog_pin<R>(
+        &mut self,
+        f: impl FnOnce(&mut Pin<Analog, PinHL<N>, P, N>) -> R,
+    ) -> Result<R, PinModeError>
+    where
+        PinNumber<N>: PinAfr,
+    {
+        match self.mode {
+            Dynamic::Analog => Ok(f(&mut Pin::new())),
+            _ => Err(PinModeError::IncorrectMode),
+        }
+    }
+
+    /// Set the output speed of the pin
+    ///
+    /// Only the speed bits of pin `N` are modified.
+    #[inline]
+    pub fn set_speed(&mut self, speed: Speed) {
+        RawPin::<P, N>::new()._set_speed(speed);
+    }
+
+    /// Drive the pin high
+    pub fn set_high(&mut self) -> Result<(), PinModeError> {
+        self.set_state(PinState::High)
+    }
+
+    /// Drive the pin low
+    pub fn set_low(&mut self) -> Result<(), PinModeError> {
+        self.set_state(PinState::Low)
+    }
+
+    /// Drive the pin to `state`
+    pub fn set_state(&mut self, state: PinState) -> Result<(), PinModeError> {
+        self.drive(&mut Gpio::<P>, state)
+    }
+
+    #[inline(always)]
+    fn drive(&mut self, port: &mut impl Port, state: PinState) -> Result<(), PinModeError> {
+        if self.mode.is_output() {
+            port.set_state(N, state);
+            Ok(())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
+    /// Returns the state last written to the output data register
+    #[inline(always)]
+    pub fn get_state(&self) -> PinState {
+        if self.is_set_low() {
+            PinState::Low
+        } else {
+            PinState::High
+        }
+    }
+
+    /// Flip the driven state of the pin
+    ///
+    /// The new state is written through BSRR, so other pins on the same port
+    /// are not affected.
+    pub fn toggle(&mut self) -> Result<(), PinModeError> {
+        match self.get_state() {
+            PinState::Low => self.set_high(),
+            PinState::High => self.set_low(),
+        }
+    }
+
+    /// Is the pin driven high?
+    ///
+    /// Reads the output data register, i.e. what the pin is being driven to,
+    /// which may differ from the actual pin level (see [`read_input`](Self::read_input)).
+    #[inline]
+    pub fn is_set_high(&self) -> bool {
+        !self.is_set_low()
+    }
+
+    /// Is the pin driven low?
+    ///
+    /// Reads the output data register, i.e. what the pin is being driven to,
+    /// which may differ from the actual pin level (see [`read_input`](Self::read_input)).
+    #[inline]
+    pub fn is_set_low(&self) -> bool {
+        RawPin::<P, N>::new()._is_set_low()
+    }
+
+    /// Samples the input data register regardless of the pin mode and
+    /// returns `true` if the pin is high.
+    ///
+    /// In push pull output mode this reports the actual electrical level
+    /// of the pin, so comparing it with [`is_set_high`](Self::is_set_high)
+    /// detects an output that is shorted or overloaded.
+    #[inline]
+    pub fn read_input(&self) -> bool {
+        !RawPin::<P, N>::new()._is_low()
+    }
+
+    /// Is the input pin high?
+    pub fn is_high(&self) -> Result<bool, PinModeError> {
+        self.is_low().map(|b| !b)
+    }
+
+    /// Is the input pin low?
+    pub fn is_low(&self) -> Result<bool, PinModeError> {
+        self.sample(&Gpio::<P>)
+    }
+
+    #[inline(always)]
+    fn sample(&self, port: &impl Port) -> Result<bool, PinModeError> {
+        if self.mode.is_input() {
+            Ok(port.is_low(N))
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+}
+
+/// External interrupts
+///
+/// These mirror [`ExtiPin`] but fail with [`PinModeError::IncorrectMode`]
+/// when the current mode can not generate interrupts.
+impl<const P: char, const N: u8> DynamicPin<P, N> {
+    #[inline(always)]
+    fn check_interruptable(&self) -> Result<(), PinModeError> {
+        if self.mode.is_interruptable() {
+            Ok(())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
+    /// Make corresponding EXTI line sensitive to this pin
+    pub fn make_interrupt_source(
+        &mut self,
+        syscfg: &mut SYSCFG,
+        apb2: &mut APB2,
+    ) -> Result<(), PinModeError> {
+        self.check_interruptable()?;
+        RawPin::<P, N>::new().make_interrupt_source(syscfg, apb2);
+        Ok(())
+    }
+
+    /// Generate interrupt on rising edge, falling edge or both
+    pub fn trigger_on_edge(&mut self, exti: &mut EXTI, edge: Edge) -> Result<(), PinModeError> {
+        self.check_interruptable()?;
+        RawPin::<P, N>::new().trigger_on_edge(exti, edge);
+        Ok(())
+    }
+
+    /// Enable external interrupts from this pin.
+    pub fn enable_interrupt(&mut self, exti: &mut EXTI) -> Result<(), PinModeError> {
+        self.check_interruptable()?;
+        RawPin::<P, N>::new().enable_interrupt(exti);
+        Ok(())
+    }
+
+    /// Disable external interrupts from this pin
+    ///
+    /// This is possible in every mode.
+    pub fn disable_interrupt(&mut self, exti: &mut EXTI) {
+        RawPin::<P, N>::new().disable_interrupt(exti);
+    }
+
+    /// Clear the interrupt pending bit for this pin
+    pub fn clear_interrupt_pending_bit(&mut self) {
+        RawPin::<P, N>::new().clear_interrupt_pending_bit();
+    }
+
+    /// Reads the interrupt pending bit for this pin
+    pub fn check_interrupt(&self) -> bool {
+        RawPin::<P, N>::new().check_interrupt()
+    }
+
+    /// Get the `Interrupt` for this pin.
+    pub fn interrupt(&self) -> Interrupt {
+        RawPin::<P, N>::new().interrupt()
+    }
+}
+
+impl<const P: char, const N: u8> OutputPin for DynamicPin<P, N> {
+    type Error = PinModeError;
+
+    #[inline]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_high()
+    }
+
+    #[inline]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_low()
+    }
+}
+
+impl<const P: char, const N: u8> StatefulOutputPin for DynamicPin<P, N> {
+    #[inline]
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        if self.mode.is_output() {
+            Ok(self.is_set_high())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+
+    #[inline]
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        if self.mode.is_output() {
+            Ok(self.is_set_low())
+        } else {
+            Err(PinModeError::IncorrectMode)
+        }
+    }
+}
+
+impl<const P: char, const N: u8> ToggleableOutputPin for DynamicPin<P, N> {
+    type Error = PinModeError;
+
+    #[inline]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.toggle()
+    }
+}
+
+impl<const P: char, const N: u8> InputPin for DynamicPin<P, N> {
+    type Error = PinModeError;
+
+    #[inline]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        self.is_high()
+    }
+
+    #[inline]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        self.is_low()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Dynamic::{self, *};
+    use super::{Config, DynamicPin, PinModeError, PinState, Port};
+
+    // Port with the configuration registers in memory. The ordering of the register
+    // writes and the barriers need the GPIO peripheral.
+    struct MockPort {
+        config: Config,
+        odr: u32,
+        idr: u32,
+    }
+
+    impl Port for MockPort {
+        fn config(&self, _n: u8) -> Config {
+            self.config
+        }
+
+        fn configure(&mut self, _n: u8, old: Config, new: Config) {
+            assert_eq!(old, self.config);
+            self.config = new;
+        }
+
+        fn set_state(&mut self, n: u8, state: PinState) {
+            match state {
+                PinState::High => self.odr |= 1 << n,
+                PinState::Low => self.odr &= !(1 << n),
+            }
+        }
+
+        fn is_low(&self, n: u8) -> bool {
+            self.idr & (1 << n) == 0
+        }
+    }
+
+    // Every pin in analog mode, as after reset, with some other settings for the
+    // neighbouring pins that must not change
+    const RESET: Config = Config {
+        moder: 0xffff_ffff,
+        otyper: 0x0000_a5a5,
+        pupdr: 0x6666_6666,
+        afr: 0x1234_5678,
+    };
+
+    // MODER, OTYPER, PUPDR and AFR fields of pin `n`
+    fn fields(config: &Config, n: u8) -> (u32, u32, u32, u32) {
+        (
+            (config.moder >> (2 * n)) & 0b11,
+            (config.otyper >> n) & 0b1,
+            (config.pupdr >> (2 * n)) & 0b11,
+            (config.afr >> (4 * (n % 8))) & 0b1111,
+        )
+    }
+
+    // Fails if any pin other than `n` was reconfigured
+    fn assert_others_unchanged(config: &Config, n: u8) {
+        assert_eq!(
+            config.moder & !(0b11 << (2 * n)),
+            RESET.moder & !(0b11 << (2 * n))
+        );
+        assert_eq!(config.otyper & !(0b1 << n), RESET.otyper & !(0b1 << n));
+        assert_eq!(
+            config.pupdr & !(0b11 << (2 * n)),
+            RESET.pupdr & !(0b11 << (2 * n))
+        );
+        let offset2 = 4 * (n % 8);
+        assert_eq!(
+            config.afr & !(0b1111 << offset2),
+            RESET.afr & !(0b1111 << offset2)
+        );
+    }
+
+    #[test]
+    fn back_to_back_transitions() {
+        let mut port = MockPort {
+            config: RESET,
+            odr: 0,
+            idr: 1 << 10,
+        };
+        let mut pin = DynamicPin::<'A', 10>::new(Analog);
+
+        pin.reconfigure(&mut port, OutputPushPull);
+        assert_eq!(pin.mode(), OutputPushPull);
+        assert_eq!(fields(&port.config, 10), (0b01, 0, 0b00, 6));
+        assert_eq!(pin.drive(&mut port, PinState::High), Ok(()));
+        assert_eq!(port.odr, 1 << 10);
+        assert_eq!(pin.sample(&port), Err(PinModeError::IncorrectMode));
+
+        pin.reconfigure(&mut port, OutputOpenDrain);
+        assert_eq!(pin.mode(), OutputOpenDrain);
+        assert_eq!(fields(&port.config, 10), (0b01, 1, 0b00, 6));
+        assert_eq!(pin.sample(&port), Ok(false));
+        assert_eq!(pin.drive(&mut port, PinState::Low), Ok(()));
+        assert_eq!(port.odr, 0);
+
+        pin.reconfigure(&mut port, InputPullUp);
+        assert_eq!(pin.mode(), InputPullUp);
+        assert_eq!(fields(&port.config, 10), (0b00, 1, 0b01, 6));
+        port.idr = 0;
+        assert_eq!(pin.sample(&port), Ok(true));
+        assert_eq!(
+            pin.drive(&mut port, PinState::High),
+            Err(PinModeError::IncorrectMode)
+        );
+        assert_eq!(port.odr, 0);
+
+        pin.reconfigure(&mut port, Alternate(7));
+        assert_eq!(pin.mode(), Alternate(7));
+        assert_eq!(fields(&port.config, 10), (0b10, 0, 0b01, 7));
+
+        pin.reconfigure(&mut port, AlternateOpenDrain(4));
+        assert_eq!(fields(&port.config, 10), (0b10, 1, 0b01, 4));
+
+        pin.reconfigure(&mut port, Analog);
+        assert_eq!(pin.mode(), Analog);
+        assert_eq!(fields(&port.config, 10), (0b11, 1, 0b00, 4));
+
+        assert_others_unchanged(&port.config, 10);
+    }
+
+    #[test]
+    fn low_pins_use_afrl() {
+        let config = RESET.with_mode(3, Alternate(12));
+        assert_eq!(fields(&config, 3), (0b10, 0, 0b01, 12));
+        assert_eq!(config.afr, 0x1234_c678);
+        assert_others_unchanged(&config, 3);
+    }
+
+    #[test]
+    fn reapplying_a_mode_changes_nothing() {
+        for &mode in MODES.iter() {
+            let config = RESET.with_mode(5, mode);
+            assert_eq!(config.with_mode(5, mode), config, "{}", mode);
+        }
+    }
+
+    const MODES: [Dynamic; 8] = [
+        InputFloating,
+        InputPullUp,
+        InputPullDown,
+        OutputPushPull,
+        OutputOpenDrain,
+        Analog,
+        Alternate(7),
+        AlternateOpenDrain(4),
+    ];
+
+    #[test]
+    fn reads_only_in_input_modes() {
+        let readable = [true, true, true, false, true, false, false, false];
+        for (mode, &expected) in MODES.iter().zip(readable.iter()) {
+            assert_eq!(mode.is_input(), expected, "{}", mode);
+        }
+    }
+
+    #[test]
+    fn writes_only_in_output_modes() {
+        let drivable = [false, false, false, true, true, false, false, false];
+        for (mode, &expected) in MODES.iter().zip(drivable.iter()) {
+            assert_eq!(mode.is_output(), expected, "{}", mode);
+        }
+    }
+
+    #[test]
+    fn exti_sources() {
+        let sources = [true, true, true, false, true, false, true, true];
+        for (mode, &expected) in MODES.iter().zip(sources.iter()) {
+            assert_eq!(mode.is_interruptable(), expected, "{}", mode);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+use super::*;
+
+/// Group of up to 16 pins of the same port that are accessed together
+///
+/// Writes set and clear all pins of the group with a single BSRR write, so all
+/// of them change in the same cycle. This is useful for parallel buses, e.g.
+/// for character LCDs or resistor ladder DACs.
+///
+/// Bit `i` of the written or read value corresponds to `pins[i]`, which may be any
+/// pin of port `P`:
+///
+/// ```ignore
+/// let mut bus = PinGroup::new([
+///     gpioa.pa0.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper).erase_number(),
+///     gpioa.pa1.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper).erase_number(),
+///     gpioa.pa4.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper).erase_number(),
+///     gpioa.pa5.into_push_pull_output(&mut gpioa.moder, &mut gpioa.otyper).erase_number(),
+/// ]);
+/// bus.write(0b1010);
+/// ```
+pub struct PinGroup<MODE, const P: char, const W: usize> {
+    pins: [PartiallyErasedPin<MODE, P>; W],
+}
+
+impl<MODE, const P: char, const W: usize> PinGroup<MODE, P, W> {
+    /// Creates a group from pins of port `P`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the group has more than 16 pins.
+    pub fn new(pins: [PartiallyErasedPin<MODE, P>; W]) -> Self {
+        assert!(W <= 16);
+        Self { pins }
+    }
+
+    /// Releases the pins of the group
+    pub fn release(self) -> [PartiallyErasedPin<MODE, P>; W] {
+        self.pins
+    }
+
+    /// Returns the port bit mask of all pins in the group
+    pub fn mask(&self) -> u16 {
+        self.pins
+            .iter()
+            .fold(0, |mask, pin| mask | (1 << pin.pin_id()))
+    }
+
+    /// Packs the port bits of the group members into a value in group order
+    #[inline]
+    fn pack(&self, port_bits: u32) -> u16 {
+        self.pins.iter().enumerate().fold(0, |value, (i, pin)| {
+            value | ((((port_bits >> pin.pin_id()) & 1) as u16) << i)
+        })
+    }
+}
+
+impl<MODE, const P: char, const W: usize> PinGroup<Output<MODE>, P, W> {
+    /// Drives all pins of the group at once, bit `i` of `value` is written to `pins[i]`
+    #[inline]
+    pub fn write(&mut self, value: u16) {
+        let mut set = 0u32;
+        let mut reset = 0u32;
+        for (i, pin) in self.pins.iter().enumerate() {
+            if value & (1 << i) != 0 {
+                set |= 1 << pin.pin_id();
+            } else {
+                reset |= 1 << pin.pin_id();
+            }
+        }
+        // NOTE(unsafe) atomic write to a stateless register
+        unsafe {
+            (*Gpio::<P>::ptr())
+                .bsrr
+                .write(|w| w.bits(set | (reset << 16)))
+        }
+    }
+
+    /// Returns the value last written to the group
+    #[inline]
+    pub fn get_state(&self) -> u16 {
+        // NOTE(unsafe) atomic read with no side effects
+        self.pack(unsafe { (*Gpio::<P>::ptr()).odr.read().bits() })
+    }
+}
+
+impl<MODE, const P: char, const W: usize> PinGroup<Input<MODE>, P, W> {
+    /// Reads all pins of the group at once, bit `i` of the result is the level of `pins[i]`
+    #[inline]
+    pub fn read(&self) -> u16 {
+        // NOTE(unsafe) atomic read with no side effects
+        self.pack(unsafe { (*Gpio::<P>::ptr()).idr.read().bits() })
+    }
+}
+
+impl<const P: char, const W: usize> PinGroup<Output<OpenDrain>, P, W> {
+    /// Reads all pins of the group at once, bit `i` of the result is the level of `pins[i]`
+    #[inline]
+    pub fn read(&self) -> u16 {
+        // NOTE(unsafe) atomic read with no side effects
+        self.pack(unsafe { (*Gpio::<P>::ptr()).idr.read().bits() })
+    }
+}
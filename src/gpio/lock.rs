@@ -0,0 +1,176 @@
+use super::*;
+
+use core::ops::Deref;
+
+const LCKK: u32 = 1 << 16;
+
+/// Opaque LCKR register
+pub struct LCKR<const P: char> {
+    _0: (),
+}
+
+/// Error returned by the pin configuration lock sequence
+#[derive(Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LockError {
+    /// The port configuration was already locked by an earlier lock sequence.
+    /// No more pins of this port can be locked until the next reset.
+    AlreadyLocked,
+    /// The lock key sequence did not take effect
+    KeyRejected,
+}
+
+impl<const P: char> LCKR<P> {
+    pub(crate) fn new() -> Self {
+        Self { _0: () }
+    }
+
+    /// Locks the configuration of the pins selected by `mask` until the next reset.
+    ///
+    /// The lock key sequence can only be run once per port after a reset, so all
+    /// pins that should be locked must be included in the same call.
+    pub fn lock(&mut self, mask: u16) -> Result<(), LockError> {
+        let gpio = unsafe { &*Gpio::<P>::ptr() };
+
+        if self.is_locked() {
+            return Err(LockError::AlreadyLocked);
+        }
+
+        let key = LCKK | mask as u32;
+        unsafe {
+            // Lock key write sequence, see RM0351 section 8.4.8.
+            // The value of LCKR[15:0] must not change during the sequence.
+            gpio.lckr.write(|w| w.bits(key));
+            gpio.lckr.write(|w| w.bits(mask as u32));
+            gpio.lckr.write(|w| w.bits(key));
+        }
+        // The mandatory read completes the sequence
+        let _ = gpio.lckr.read().bits();
+
+        if gpio.lckr.read().bits() & key == key {
+            Ok(())
+        } else {
+            Err(LockError::KeyRejected)
+        }
+    }
+
+    /// Locks the configuration of all pins of the port until the next reset.
+    pub fn lock_all(&mut self) -> Result<(), LockError> {
+        self.lock(0xffff)
+    }
+
+    /// Returns `true` if the lock key sequence has already been run on this port.
+    pub fn is_locked(&self) -> bool {
+        // NOTE(unsafe) atomic read with no side effects
+        unsafe { (*Gpio::<P>::ptr()).lckr.read().bits() & LCKK != 0 }
+    }
+}
+
+/// Pin whose configuration is locked until the next reset
+///
+/// The pin can still be read and, in output mode, driven, but it can no
+/// longer be converted into another mode.
+pub struct LockedPin<MODE, HL, const P: char, const N: u8> {
+    pin: Pin<MODE, HL, P, N>,
+}
+
+impl<MODE, HL, const P: char, const N: u8> Pin<MODE, HL, P, N> {
+    /// Locks the configuration of the pin until the next reset.
+    ///
+    /// This runs the lock key sequence for the whole port, which is only possible once
+    /// per reset. Use [`LCKR::lock`] to lock several pins of the same port.
+    pub fn lock(self, lckr: &mut LCKR<P>) -> Result<LockedPin<MODE, HL, P, N>, LockError> {
+        lckr.lock(1 << N)?;
+        Ok(LockedPin { pin: self })
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> Deref for LockedPin<MODE, HL, P, N> {
+    type Target = Pin<MODE, HL, P, N>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.pin
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> LockedPin<Output<MODE>, HL, P, N> {
+    #[inline]
+    pub fn set_high(&mut self) {
+        self.pin.set_high()
+    }
+    #[inline]
+    pub fn set_low(&mut self) {
+        self.pin.set_low()
+    }
+    #[inline(always)]
+    pub fn set_state(&mut self, state: PinState) {
+        self.pin.set_state(state)
+    }
+    #[inline]
+    pub fn toggle(&mut self) {
+        self.pin.toggle()
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> OutputPin for LockedPin<Output<MODE>, HL, P, N> {
+    type Error = Infallible;
+    #[inline]
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.set_high();
+        Ok(())
+    }
+    #[inline]
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.set_low();
+        Ok(())
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> StatefulOutputPin for LockedPin<Output<MODE>, HL, P, N> {
+    #[inline]
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_set_high())
+    }
+    #[inline]
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_set_low())
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> ToggleableOutputPin
+    for LockedPin<Output<MODE>, HL, P, N>
+{
+    type Error = Infallible;
+
+    #[inline(always)]
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        self.toggle();
+        Ok(())
+    }
+}
+
+impl<MODE, HL, const P: char, const N: u8> InputPin for LockedPin<Input<MODE>, HL, P, N> {
+    type Error = Infallible;
+    #[inline]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_high())
+    }
+
+    #[inline]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_low())
+    }
+}
+
+impl<HL, const P: char, const N: u8> InputPin for LockedPin<Output<OpenDrain>, HL, P, N> {
+    type Error = Infallible;
+    #[inline]
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_high())
+    }
+
+    #[inline]
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_low())
+    }
+}
@@ -20,6 +20,22 @@ impl<MODE, const P: char> PartiallyErasedPin<MODE, P> {
     }
 }
 
+impl<MODE, const P: char> PartiallyErasedPin<MODE, P> {
+    /// Erases the port from the type
+    ///
+    /// This is useful when you want to collect pins of different ports into an
+    /// array where you need all the elements to have the same type
+    pub fn erase(self) -> ErasedPin<MODE> {
+        ErasedPin::new(P as u8 - b'A', self.i)
+    }
+}
+
+impl<MODE, const P: char> From<PartiallyErasedPin<MODE, P>> for ErasedPin<MODE> {
+    fn from(p: PartiallyErasedPin<MODE, P>) -> Self {
+        p.erase()
+    }
+}
+
 impl<MODE, const P: char> PinExt for PartiallyErasedPin<MODE, P> {
     type Mode = MODE;
 
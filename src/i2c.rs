@@ -2,7 +2,28 @@
 //! [stm32h7xx-hal](https://github.com/stm32-rs/stm32h7xx-hal) implementation,
 //! as of 2021-02-25.
 
-use crate::hal::blocking::i2c::{Read, Write, WriteRead};
+#[cfg(any(
+    feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::dma::dma2;
+use crate::dma::{self, dma1, TransferPayload};
+use crate::dmamux::{DmaInput, DmaMux};
+use crate::hal::blocking::delay::DelayUs;
+pub use crate::hal::blocking::i2c::Operation;
+use crate::hal::blocking::i2c::{Read, Transactional, Write, WriteRead};
+use crate::hal::digital::v2::{InputPin, OutputPin};
 
 #[cfg(any(
     feature = "stm32l451",
@@ -22,14 +43,19 @@ use crate::hal::blocking::i2c::{Read, Write, WriteRead};
 use crate::pac::I2C4;
 use crate::pac::{i2c1, I2C1, I2C2, I2C3};
 
-use crate::rcc::{Clocks, Enable, RccBus, Reset};
-use crate::time::Hertz;
-use cast::{u16, u8};
+use crate::rcc::{Clocks, Enable, I2cClockSource, KernelClock, RccBus, Reset};
+use crate::time::{div_ceil, Hertz, MicroSeconds, MonoTimer};
+use cast::u16;
+use core::cmp;
 use core::ops::Deref;
+use core::sync::atomic::{self, Ordering};
+use cortex_m::peripheral::DWT;
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
 
 /// I2C error
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Bus error
     Bus,
@@ -37,9 +63,11 @@ pub enum Error {
     Arbitration,
     /// NACK
     Nack,
+    /// The bus or the slave did not respond in time, see [`Config::timeout`]
+    Timeout,
+    /// The PEC byte does not match, see [`SmBusConfig::pec`]
+    Pec,
     // Overrun, // slave mode only
-    // Pec, // SMBUS mode only
-    // Timeout, // SMBUS mode only
     // Alert, // SMBUS mode only
 }
 
@@ -54,6 +82,23 @@ pub trait SclPin<I2C>: private::Sealed {}
 /// SDA pin. This trait is sealed and cannot be implemented.
 pub trait SdaPin<I2C>: private::Sealed {}
 
+/// I2C peripheral instance. This trait is sealed and cannot be implemented.
+pub trait Instance: private::Sealed + Deref<Target = i2c1::RegisterBlock> + Enable + Reset {
+    #[doc(hidden)]
+    /// Returns `true` if the HSI16 is the kernel clock
+    fn is_hsi16_kernel_clock() -> bool;
+}
+
+/// I2C address
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Address {
+    /// 7-bit address
+    Seven(u8),
+    /// 10-bit address
+    Ten(u16),
+}
+
 macro_rules! pins {
     ($spi:ident, $af:literal, SCL: [$($scl:ident),*], SDA: [$($sda:ident),*]) => {
         $(
@@ -67,10 +112,21 @@ macro_rules! pins {
     }
 }
 
+/// Master events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A device pulled SMBALERT low, see [`SmBusConfig::alert`]. The interrupt is shared
+    /// with the bus errors.
+    Alert,
+}
+
 /// I2C peripheral operating in master mode
 pub struct I2c<I2C, PINS> {
     i2c: I2C,
     pins: PINS,
+    timeout: Option<u32>,
+    pec: bool,
 }
 
 pub struct Config {
@@ -79,87 +135,132 @@ pub struct Config {
     scll: u8,
     scldel: u8,
     sdadel: u8,
+    timeout: Option<u32>,
+    wakeup: bool,
 }
 
 impl Config {
+    /// Computes the timing for bus frequency `freq`, assuming the I2C runs on its
+    /// reset kernel clock, the APB1 clock
     pub fn new(freq: Hertz, clocks: Clocks) -> Self {
-        let freq = freq.raw();
-        assert!(freq <= 1_000_000);
-
-        // TODO review compliance with the timing requirements of I2C
-        // t_I2CCLK = 1 / PCLK1
-        // t_PRESC  = (PRESC + 1) * t_I2CCLK
-        // t_SCLL   = (SCLL + 1) * t_PRESC
-        // t_SCLH   = (SCLH + 1) * t_PRESC
-        //
-        // t_SYNC1 + t_SYNC2 > 4 * t_I2CCLK
-        // t_SCL ~= t_SYNC1 + t_SYNC2 + t_SCLL + t_SCLH
-        let i2cclk = clocks.pclk1().raw();
-        let ratio = i2cclk / freq - 4;
-        let (presc, scll, sclh, sdadel, scldel) = if freq >= 100_000 {
-            // fast-mode or fast-mode plus
-            // here we pick SCLL + 1 = 2 * (SCLH + 1)
-            let presc = ratio / 387;
-
-            let sclh = ((ratio / (presc + 1)) - 3) / 3;
-            let scll = 2 * (sclh + 1) - 1;
-
-            let (sdadel, scldel) = if freq > 400_000 {
-                // fast-mode plus
-                let sdadel = 0;
-                let scldel = i2cclk / 4_000_000 / (presc + 1) - 1;
-
-                (sdadel, scldel)
-            } else {
-                // fast-mode
-                let sdadel = i2cclk / 8_000_000 / (presc + 1);
-                let scldel = i2cclk / 2_000_000 / (presc + 1) - 1;
-
-                (sdadel, scldel)
-            };
+        Self::with_kernel_clock(freq, clocks.pclk1())
+    }
 
-            (presc, scll, sclh, sdadel, scldel)
+    /// Computes the timing for bus frequency `freq` from the kernel clock `i2cclk`
+    ///
+    /// The speed mode follows from `freq`, the rise and fall times are those of
+    /// [`BusTiming::new`]. Use this if another kernel clock was selected with
+    /// [`CCIPR::set_kernel_clock`](crate::rcc::CCIPR::set_kernel_clock):
+    ///
+    /// ```ignore
+    /// rcc.ccipr.set_kernel_clock::<I2C1>(I2cClockSource::Hsi16, &clocks);
+    /// let config = Config::with_kernel_clock(100.kHz(), clocks.kernel_clock::<I2C1>());
+    /// ```
+    pub fn with_kernel_clock(freq: Hertz, i2cclk: Hertz) -> Self {
+        let speed = if freq <= Speed::Standard.max_frequency() {
+            Speed::Standard
+        } else if freq <= Speed::Fast.max_frequency() {
+            Speed::Fast
         } else {
-            // standard-mode
-            // here we pick SCLL = SCLH
-            let presc = ratio / 514;
+            Speed::FastPlus
+        };
 
-            let sclh = ((ratio / (presc + 1)) - 2) / 2;
-            let scll = sclh;
+        Self::with_bus_timing(BusTiming::new(speed).frequency(freq), i2cclk)
+    }
 
-            let sdadel = i2cclk / 2_000_000 / (presc + 1);
-            let scldel = i2cclk / 800_000 / (presc + 1) - 1;
+    /// Computes the timing meeting the requirements of the I2C specification for
+    /// `timing` from the kernel clock `i2cclk`
+    ///
+    /// The bus frequency is the closest to the one of `timing` that isn't higher. The
+    /// analog filter is taken into account, the digital filter is off.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no timing meets the requirements, e.g. if `i2cclk` is too slow.
+    pub fn with_bus_timing(timing: BusTiming, i2cclk: Hertz) -> Self {
+        // Delay of the analog filter, in picoseconds as the other durations
+        const T_AF_MIN: u64 = 50_000;
+        const T_AF_MAX: u64 = 260_000;
 
-            (presc, scll, sclh, sdadel, scldel)
-        };
+        // t_PRESC  = (PRESC + 1) * t_I2CCLK
+        // t_SCLL   = (SCLL + 1) * t_PRESC + t_SYNC1
+        // t_SCLH   = (SCLH + 1) * t_PRESC + t_SYNC2
+        // t_SCL    = t_SCLL + t_SCLH + t_r + t_f
+        //
+        // t_SYNC1, t_SYNC2 >= t_AF(min) + 2 * t_I2CCLK
+        // t_SDADEL >= t_f - t_HD;DAT(min) - t_AF(min) - 3 * t_I2CCLK
+        // t_SDADEL <= t_VD;DAT(max) - t_r - t_AF(max) - 4 * t_I2CCLK
+        // t_SCLDEL >= t_r + t_SU;DAT(min)
+        let spec = timing.speed.spec();
+        let t_i2cclk = 1_000_000_000_000 / u64::from(i2cclk.raw());
+        let t_scl_min = 1_000_000_000_000 / u64::from(timing.frequency.raw());
+        // Accept down to 80% of the bus frequency
+        let t_scl_max = t_scl_min * 5 / 4;
+        let t_r = u64::from(timing.rise_time) * 1000;
+        let t_f = u64::from(timing.fall_time) * 1000;
+        let t_sync = T_AF_MIN + 2 * t_i2cclk;
+
+        let sdadel_min = t_f.saturating_sub(T_AF_MIN + 3 * t_i2cclk);
+        // Saturates when I2CCLK is slow, no delay is the best effort then
+        let sdadel_max = spec.vd_dat.saturating_sub(t_r + T_AF_MAX + 4 * t_i2cclk);
+        let scldel_min = t_r + spec.su_dat;
+
+        // The timing of the bus period closest to t_scl_min
+        let mut best = None;
+        let mut best_error = u64::MAX;
+        for presc in 0..16 {
+            let t_presc = (presc + 1) * t_i2cclk;
+
+            // The shortest delays that fit
+            let scldel = cmp::max(div_ceil(scldel_min, t_presc), 1) - 1;
+            let sdadel = div_ceil(sdadel_min, t_presc);
+            if scldel > 15 || sdadel > 15 || sdadel * t_presc > sdadel_max {
+                continue;
+            }
 
-        macro_rules! u8_or_panic {
-            ($value: expr, $message: literal) => {
-                match u8($value) {
-                    Ok(value) => value,
-                    Err(_) => panic!($message),
+            for scll in 0..256 {
+                let t_low = (scll + 1) * t_presc + t_sync;
+                // t_SCLL has to exceed 4 * t_I2CCLK on top of the analog filter delay
+                if t_low < spec.low || t_low - T_AF_MIN <= 4 * t_i2cclk {
+                    continue;
                 }
-            };
-        }
 
-        let presc = u8_or_panic!(presc, "I2C pres");
-        assert!(presc < 16);
-
-        let scldel = u8_or_panic!(scldel, "I2C scldel");
-        assert!(scldel < 16);
+                // The shortest high time that keeps the bus frequency below the target,
+                // t_SCLH has to exceed t_I2CCLK
+                let t_high = cmp::max(
+                    t_scl_min.saturating_sub(t_low + t_r + t_f),
+                    cmp::max(spec.high, t_i2cclk + 1),
+                );
+                let sclh = cmp::max(div_ceil(t_high.saturating_sub(t_sync), t_presc), 1) - 1;
+                if sclh > 255 {
+                    continue;
+                }
 
-        let sdadel = u8_or_panic!(sdadel, "I2C sdadel");
-        assert!(sdadel < 16);
+                let t_scl = t_low + (sclh + 1) * t_presc + t_sync + t_r + t_f;
+                if t_scl > t_scl_max {
+                    // Only gets longer with SCLL
+                    break;
+                }
 
-        let sclh = u8_or_panic!(sclh, "I2C sclh");
-        let scll = u8_or_panic!(scll, "I2C scll");
+                let error = t_scl - t_scl_min;
+                if error < best_error {
+                    best_error = error;
+                    best = Some(Config {
+                        presc: presc as u8,
+                        sclh: sclh as u8,
+                        scll: scll as u8,
+                        scldel: scldel as u8,
+                        sdadel: sdadel as u8,
+                        timeout: None,
+                        wakeup: false,
+                    });
+                }
+            }
+        }
 
-        Self {
-            presc,
-            sclh,
-            scll,
-            scldel,
-            sdadel,
+        match best {
+            Some(config) => config,
+            None => panic!("I2C timing"),
         }
     }
 
@@ -171,13 +272,263 @@ impl Config {
             sdadel: ((timing_bits >> 16) & 0xf) as u8,
             sclh: ((timing_bits >> 8) & 0xff) as u8,
             scll: (timing_bits & 0xff) as u8,
+            timeout: None,
+            wakeup: false,
+        }
+    }
+
+    /// Fails the blocking transfers with [`Error::Timeout`] if the bus or the slave do
+    /// not respond within `timeout`, e.g. a slave that stretches SCL forever
+    ///
+    /// The timeout applies to each wait for the bus, e.g. for the next byte. It is
+    /// measured with the cycle counter, which `timer` enables. After a timeout the
+    /// peripheral is reset, so that the next transfer can start over. Without a timeout
+    /// the transfers wait forever.
+    pub fn timeout(mut self, timeout: MicroSeconds, timer: MonoTimer) -> Self {
+        let cycles = u64::from(timeout.ticks()) * u64::from(timer.frequency().raw()) / 1_000_000;
+        self.timeout = Some(cmp::min(cycles, u64::from(u32::MAX)) as u32);
+        self
+    }
+
+    /// Wakes the system up from Stop modes when the master addresses the slave, see
+    /// [`I2c::new_slave`]
+    ///
+    /// Only the HSI16 keeps running in Stop modes on request of the I2C, so it has to be
+    /// the kernel clock:
+    ///
+    /// ```ignore
+    /// rcc.ccipr.set_kernel_clock::<I2C1>(I2cClockSource::Hsi16, &clocks);
+    /// let config = Config::with_kernel_clock(100.kHz(), clocks.kernel_clock::<I2C1>())
+    ///     .wakeup_from_stop(true);
+    /// ```
+    pub fn wakeup_from_stop(mut self, wakeup: bool) -> Self {
+        self.wakeup = wakeup;
+        self
+    }
+}
+
+/// Speed modes of the I2C specification
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Speed {
+    /// Up to 100 kHz
+    Standard,
+    /// Up to 400 kHz
+    Fast,
+    /// Up to 1 MHz, the pins usually need the Fast-mode Plus drive of SYSCFG_CFGR1
+    FastPlus,
+}
+
+/// Characteristics of a speed mode, the durations are in picoseconds
+struct SpeedSpec {
+    /// Highest bus frequency
+    frequency: u32,
+    /// t_LOW(min)
+    low: u64,
+    /// t_HIGH(min)
+    high: u64,
+    /// t_SU;DAT(min)
+    su_dat: u64,
+    /// t_VD;DAT(max)
+    vd_dat: u64,
+}
+
+impl Speed {
+    /// Returns the highest bus frequency of the mode
+    pub fn max_frequency(self) -> Hertz {
+        Hertz::from_raw(self.spec().frequency)
+    }
+
+    fn spec(self) -> SpeedSpec {
+        match self {
+            Speed::Standard => SpeedSpec {
+                frequency: 100_000,
+                low: 4_700_000,
+                high: 4_000_000,
+                su_dat: 250_000,
+                vd_dat: 3_450_000,
+            },
+            Speed::Fast => SpeedSpec {
+                frequency: 400_000,
+                low: 1_300_000,
+                high: 600_000,
+                su_dat: 100_000,
+                vd_dat: 900_000,
+            },
+            Speed::FastPlus => SpeedSpec {
+                frequency: 1_000_000,
+                low: 500_000,
+                high: 260_000,
+                su_dat: 50_000,
+                vd_dat: 450_000,
+            },
+        }
+    }
+}
+
+/// Requirements of the bus to compute the timing from, see [`Config::with_bus_timing`]
+#[derive(Clone, Copy, Debug)]
+pub struct BusTiming {
+    speed: Speed,
+    frequency: Hertz,
+    rise_time: u32,
+    fall_time: u32,
+}
+
+impl BusTiming {
+    /// Runs the bus at the highest frequency of `speed`, assuming a rise time of 100 ns
+    /// and a fall time of 10 ns
+    pub fn new(speed: Speed) -> Self {
+        BusTiming {
+            speed,
+            frequency: speed.max_frequency(),
+            rise_time: 100,
+            fall_time: 10,
         }
     }
+
+    /// Runs the bus at `frequency`, which may not exceed the frequency of the speed mode
+    pub fn frequency(mut self, frequency: Hertz) -> Self {
+        assert!(frequency.raw() > 0 && frequency <= self.speed.max_frequency());
+        self.frequency = frequency;
+        self
+    }
+
+    /// Sets the rise time of SCL and SDA in nanoseconds, which depends on the pull-ups
+    /// and the capacitance of the bus
+    ///
+    /// A bus that measures slower than configured likely rises slower than assumed.
+    pub fn rise_time(mut self, rise_time: u32) -> Self {
+        self.rise_time = rise_time;
+        self
+    }
+
+    /// Sets the fall time of SCL and SDA in nanoseconds
+    pub fn fall_time(mut self, fall_time: u32) -> Self {
+        self.fall_time = fall_time;
+        self
+    }
+}
+
+/// SMBus configuration of a host, see [`I2c::smbus`]
+#[derive(Clone, Copy, Debug)]
+pub struct SmBusConfig {
+    i2cclk: u32,
+    pec: bool,
+    alert: bool,
+    scl_low_timeout: Option<u16>,
+    clock_extension_timeout: Option<u16>,
+}
+
+impl SmBusConfig {
+    /// Creates a configuration without SMBus features, the timeouts are computed from the
+    /// kernel clock `i2cclk`, e.g. `clocks.pclk1()`
+    pub fn new(i2cclk: Hertz) -> Self {
+        SmBusConfig {
+            i2cclk: i2cclk.raw(),
+            pec: false,
+            alert: false,
+            scl_low_timeout: None,
+            clock_extension_timeout: None,
+        }
+    }
+
+    /// Appends the packet error checking byte to writes and checks it at the end of
+    /// reads, a mismatch fails the read with [`Error::Pec`]
+    ///
+    /// This applies to the blocking and the DMA transfers. The PEC byte covers the whole
+    /// transaction, e.g. the write and the read of a write-read.
+    pub fn pec(mut self, pec: bool) -> Self {
+        self.pec = pec;
+        self
+    }
+
+    /// Enables the SMBALERT input, see [`Event::Alert`]
+    ///
+    /// The SMBA pin of the I2C has to be put in open drain alternate mode.
+    pub fn alert(mut self, alert: bool) -> Self {
+        self.alert = alert;
+        self
+    }
+
+    /// Fails transfers with [`Error::Timeout`] if SCL stays low for `timeout`, 25 ms
+    /// (t<sub>TIMEOUT</sub>) by the SMBus specification
+    ///
+    /// STOP is sent once the timeout is reached.
+    pub fn scl_low_timeout(mut self, timeout: MicroSeconds) -> Self {
+        self.scl_low_timeout = Some(self.timeout_bits(timeout));
+        self
+    }
+
+    /// Fails transfers with [`Error::Timeout`] if the master stretches SCL for more
+    /// than `timeout` in total per byte, 10 ms (t<sub>LOW:MEXT</sub>) by the SMBus
+    /// specification
+    pub fn clock_extension_timeout(mut self, timeout: MicroSeconds) -> Self {
+        self.clock_extension_timeout = Some(self.timeout_bits(timeout));
+        self
+    }
+
+    /// Computes TIMEOUTA or TIMEOUTB, which count in periods of 2048 kernel clock cycles
+    fn timeout_bits(&self, timeout: MicroSeconds) -> u16 {
+        let periods = u64::from(timeout.ticks()) * u64::from(self.i2cclk) / 1_000_000 / 2048;
+        assert!((1..=0x1000).contains(&periods), "I2C SMBus timeout");
+        (periods - 1) as u16
+    }
+}
+
+/// Waits up to the timeout of a transfer
+#[derive(Clone, Copy)]
+struct Deadline {
+    start: u32,
+    timeout: Option<u32>,
+}
+
+impl Deadline {
+    fn new(timeout: Option<u32>) -> Self {
+        Deadline {
+            start: match timeout {
+                Some(_) => DWT::cycle_count(),
+                None => 0,
+            },
+            timeout,
+        }
+    }
+
+    fn expired(&self) -> bool {
+        match self.timeout {
+            Some(timeout) => DWT::cycle_count().wrapping_sub(self.start) > timeout,
+            None => false,
+        }
+    }
+}
+
+/// Resets the communication and the flags of the peripheral, the configuration is kept
+fn software_reset(i2c: &i2c1::RegisterBlock) {
+    i2c.cr1.modify(|_, w| w.pe().clear_bit());
+    // PE has to stay low for 3 APB clock cycles, reading it back takes care of that
+    while i2c.cr1.read().pe().bit_is_set() {}
+    i2c.cr1.modify(|_, w| w.pe().set_bit());
 }
 
 macro_rules! hal {
     ($i2c_type: ident, $i2cX: ident) => {
+        impl private::Sealed for $i2c_type {}
+
         impl<SCL, SDA> I2c<$i2c_type, (SCL, SDA)> {
+            /// Enables and resets the peripheral and configures it to work in master mode
+            ///
+            /// Both pins have to be in open drain alternate mode. Without external pull up
+            /// resistors, e.g. while prototyping, the internal pull ups can be enabled:
+            ///
+            /// ```ignore
+            /// let mut scl = gpioa
+            ///     .pa9
+            ///     .into_alternate_open_drain(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh);
+            /// scl.internal_pull_up(&mut gpioa.pupdr, true);
+            /// ```
+            ///
+            /// The internal pull ups are weak (about 40 kΩ), so they are only suited for
+            /// short buses at low speeds.
             pub fn $i2cX(
                 i2c: $i2c_type,
                 pins: (SCL, SDA),
@@ -200,6 +551,24 @@ hal!(I2C1, i2c1);
 hal!(I2C2, i2c2);
 hal!(I2C3, i2c3);
 
+macro_rules! instance {
+    ($($i2c_type: ident,)+) => {
+        $(
+            impl Instance for $i2c_type {
+                fn is_hsi16_kernel_clock() -> bool {
+                    $i2c_type::kernel_clock_source() == I2cClockSource::Hsi16
+                }
+            }
+        )+
+    };
+}
+
+instance! {
+    I2C1,
+    I2C2,
+    I2C3,
+}
+
 #[cfg(any(
     feature = "stm32l451",
     feature = "stm32l452",
@@ -217,6 +586,32 @@ hal!(I2C3, i2c3);
 ))]
 hal!(I2C4, i2c4);
 
+#[cfg(any(
+    feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+impl Instance for I2C4 {
+    fn is_hsi16_kernel_clock() -> bool {
+        // I2C4SEL of RCC_CCIPR2, which most PACs lack
+        const CCIPR2: usize = 0x9c;
+        let ccipr2 = unsafe {
+            ((crate::pac::RCC::ptr() as *const u8).add(CCIPR2) as *const u32).read_volatile()
+        };
+        ccipr2 & 0b11 == I2cClockSource::Hsi16 as u32
+    }
+}
+
 impl<SCL, SDA, I2C> I2c<I2C, (SCL, SDA)>
 where
     I2C: Deref<Target = i2c1::RegisterBlock>,
@@ -246,7 +641,80 @@ where
         // Enable the peripheral
         i2c.cr1.write(|w| w.pe().set_bit());
 
-        I2c { i2c, pins }
+        I2c {
+            i2c,
+            pins,
+            timeout: config.timeout,
+            pec: false,
+        }
+    }
+
+    /// Enables the SMBus features of a host
+    pub fn smbus(&mut self, config: SmBusConfig) {
+        // The timeouts may only change while they are disabled, PEC and the alert while
+        // the peripheral is disabled
+        self.i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        self.i2c.timeoutr.reset();
+        self.i2c.timeoutr.write(|w| {
+            w.timeouta()
+                .bits(config.scl_low_timeout.unwrap_or(0))
+                .tidle()
+                .clear_bit()
+                .timouten()
+                .bit(config.scl_low_timeout.is_some())
+                .timeoutb()
+                .bits(config.clock_extension_timeout.unwrap_or(0))
+                .texten()
+                .bit(config.clock_extension_timeout.is_some())
+        });
+        // SMBHEN: the alert input is only available to the host
+        self.i2c.cr1.modify(|_, w| {
+            w.pecen()
+                .bit(config.pec)
+                .smbhen()
+                .bit(config.alert)
+                .alerten()
+                .bit(config.alert)
+        });
+        self.i2c.cr1.modify(|_, w| w.pe().set_bit());
+
+        self.pec = config.pec;
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        match event {
+            Event::Alert => self.i2c.cr1.modify(|_, w| w.errie().set_bit()),
+        }
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        match event {
+            Event::Alert => self.i2c.cr1.modify(|_, w| w.errie().clear_bit()),
+        }
+    }
+
+    /// Returns `true` if `event` is pending, whether it is listened for or not
+    pub fn is_event_triggered(&self, event: Event) -> bool {
+        match event {
+            Event::Alert => self.i2c.isr.read().alert().is_alert(),
+        }
+    }
+
+    /// Clears a pending [`Event::Alert`]
+    ///
+    /// Reading the alert response address tells which device raised the alert.
+    pub fn clear_alert(&mut self) {
+        self.i2c.icr.write(|w| w.alertcf().set_bit());
+    }
+
+    /// Resets the communication and the flags, the configuration is kept
+    ///
+    /// Use it after an error the peripheral does not recover from by itself, e.g.
+    /// after [`recover_bus`] freed the bus.
+    pub fn software_reset(&mut self) {
+        software_reset(&self.i2c);
     }
 
     /// Releases the I2C peripheral and associated pins
@@ -255,6 +723,357 @@ where
     }
 }
 
+/// Frees the bus from a slave that holds SDA low, e.g. after it was reset in the middle
+/// of a transfer
+///
+/// SCL is clocked up to 9 times, until the slave releases SDA, then STOP is sent. Both
+/// pins have to be open drain outputs, e.g. once they were released by [`I2c::free`]:
+///
+/// ```ignore
+/// let (i2c1, (scl, sda)) = i2c.free();
+/// let mut scl = scl.into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+/// let mut sda = sda.into_open_drain_output(&mut gpioa.moder, &mut gpioa.otyper);
+/// i2c::recover_bus(&mut scl, &mut sda, &mut delay)?;
+/// ```
+///
+/// The bus is clocked at 100 kHz. Returns [`Error::Bus`] if SDA or SCL are still low
+/// afterwards.
+pub fn recover_bus<SCL, SDA, D>(scl: &mut SCL, sda: &mut SDA, delay: &mut D) -> Result<(), Error>
+where
+    SCL: OutputPin + InputPin,
+    SDA: OutputPin + InputPin,
+    D: DelayUs<u32>,
+{
+    // Half of a 100 kHz SCL period
+    const HALF_PERIOD_US: u32 = 5;
+
+    sda.set_high().ok();
+    scl.set_high().ok();
+    delay.delay_us(HALF_PERIOD_US);
+
+    for _ in 0..9 {
+        if sda.is_high().unwrap_or(false) {
+            break;
+        }
+        scl.set_low().ok();
+        delay.delay_us(HALF_PERIOD_US);
+        scl.set_high().ok();
+        delay.delay_us(HALF_PERIOD_US);
+    }
+
+    // STOP: SDA goes high while SCL is high
+    scl.set_low().ok();
+    delay.delay_us(HALF_PERIOD_US);
+    sda.set_low().ok();
+    delay.delay_us(HALF_PERIOD_US);
+    scl.set_high().ok();
+    delay.delay_us(HALF_PERIOD_US);
+    sda.set_high().ok();
+    delay.delay_us(HALF_PERIOD_US);
+
+    if sda.is_high().unwrap_or(false) && scl.is_high().unwrap_or(false) {
+        Ok(())
+    } else {
+        Err(Error::Bus)
+    }
+}
+
+impl<SCL, SDA, I2C: Instance> I2c<I2C, (SCL, SDA)> {
+    /// Enables and resets the peripheral and configures it to work in slave mode,
+    /// answering to `own_address`
+    ///
+    /// Only the setup and hold times of `config` are used by a slave, the bus frequency
+    /// is given by the master. The slave stretches SCL while it waits for the
+    /// application, see [`I2cSlave`].
+    ///
+    /// With [`Config::wakeup_from_stop`], an address match wakes the system up from Stop
+    /// modes, in Stop 2 only the I2C3 is powered. The wakeup goes through the I2C EXTI
+    /// line (23 to 25 for the I2C1 to I2C3, 40 for the I2C4), a direct line which is
+    /// unmasked in EXTI_IMR1/2 at reset and needs no further configuration. The core wakes
+    /// up into the event interrupt of the I2C, with [`SlaveEvent::AddressMatch`] pending,
+    /// so [`I2cSlave::address_match`] returns the match which caused the wakeup. Stop mode
+    /// may be entered again once the transfer ended with [`SlaveEvent::Stop`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the wakeup from Stop modes is enabled but the HSI16 is not the kernel
+    /// clock.
+    pub fn new_slave(
+        i2c: I2C,
+        pins: (SCL, SDA),
+        own_address: Address,
+        config: Config,
+        apb1: &mut <I2C as RccBus>::Bus,
+    ) -> I2cSlave<I2C, (SCL, SDA)>
+    where
+        SCL: SclPin<I2C>,
+        SDA: SdaPin<I2C>,
+    {
+        assert!(
+            !config.wakeup || I2C::is_hsi16_kernel_clock(),
+            "I2C wakeup from Stop without the HSI16 kernel clock"
+        );
+
+        I2C::enable(apb1);
+        I2C::reset(apb1);
+
+        // The timing, the own addresses and NOSTRETCH may only change while the
+        // peripheral is disabled
+        i2c.cr1.modify(|_, w| w.pe().clear_bit());
+        i2c.timingr.write(|w| {
+            w.presc()
+                .bits(config.presc)
+                .scll()
+                .bits(config.scll)
+                .sclh()
+                .bits(config.sclh)
+                .sdadel()
+                .bits(config.sdadel)
+                .scldel()
+                .bits(config.scldel)
+        });
+
+        let (oa1, ten_bit) = match own_address {
+            Address::Seven(address) => {
+                assert!(address < 0x80);
+                (u16::from(address) << 1, false)
+            }
+            Address::Ten(address) => {
+                assert!(address < 0x400);
+                (address, true)
+            }
+        };
+        i2c.oar1
+            .write(|w| w.oa1().bits(oa1).oa1mode().bit(ten_bit).oa1en().set_bit());
+
+        // The wakeup needs clock stretching and the analog filter, both stay enabled
+        i2c.cr1.write(|w| {
+            w.nostretch()
+                .clear_bit()
+                .wupen()
+                .bit(config.wakeup)
+                .pe()
+                .set_bit()
+        });
+
+        I2cSlave { i2c, pins }
+    }
+}
+
+/// Slave events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveEvent {
+    /// The master addressed one of the own addresses, see [`I2cSlave::address_match`]
+    AddressMatch,
+    /// A byte was received, see [`I2cSlave::slave_read`]
+    RxNotEmpty,
+    /// The master reads the next byte, see [`I2cSlave::slave_write`]
+    TxEmpty,
+    /// The master ended the transfer, see [`I2cSlave::clear_stop`]
+    Stop,
+}
+
+/// Direction of a transfer, as requested by the master
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Direction {
+    /// The master writes, the slave receives
+    Write,
+    /// The master reads, the slave transmits
+    Read,
+}
+
+/// The address a master addressed the slave with
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AddressMatch {
+    /// The 7-bit address, or the two most significant bits of a 10-bit address in
+    /// the 10-bit header `0b11110xx`
+    pub address: u8,
+    /// The direction of the transfer that follows
+    pub direction: Direction,
+}
+
+/// I2C peripheral operating in slave mode
+///
+/// A transfer is handled in the order of its events, typically in the interrupt
+/// handler:
+///
+/// 1. On [`SlaveEvent::AddressMatch`], [`address_match`](Self::address_match) tells
+///    the direction and releases SCL
+/// 2. On [`SlaveEvent::RxNotEmpty`] or [`SlaveEvent::TxEmpty`], for each byte,
+///    [`slave_read`](Self::slave_read) or [`slave_write`](Self::slave_write)
+/// 3. On [`SlaveEvent::Stop`], [`clear_stop`](Self::clear_stop)
+///
+/// A repeated start, e.g. the read of a register after the master wrote its
+/// address, shows up as another address match without a stop.
+pub struct I2cSlave<I2C, PINS> {
+    i2c: I2C,
+    pins: PINS,
+}
+
+impl<I2C: Instance, PINS> I2cSlave<I2C, PINS> {
+    /// Additionally answers to the 7-bit `address`, ignoring its `masked_bits` least
+    /// significant bits, e.g. 0x40 to 0x43 with 2 masked bits
+    ///
+    /// The reserved addresses are never acknowledged.
+    pub fn set_own_address2(&mut self, address: u8, masked_bits: u8) {
+        assert!(address < 0x80);
+        assert!(masked_bits < 8);
+
+        // OA2 may only change while it is disabled
+        self.i2c.oar2.modify(|_, w| w.oa2en().clear_bit());
+        self.i2c.oar2.write(|w| {
+            w.oa2()
+                .bits(address)
+                .oa2msk()
+                .bits(masked_bits)
+                .oa2en()
+                .set_bit()
+        });
+    }
+
+    /// Stops answering to the second own address
+    pub fn disable_own_address2(&mut self) {
+        self.i2c.oar2.modify(|_, w| w.oa2en().clear_bit());
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: SlaveEvent) {
+        match event {
+            SlaveEvent::AddressMatch => self.i2c.cr1.modify(|_, w| w.addrie().set_bit()),
+            SlaveEvent::RxNotEmpty => self.i2c.cr1.modify(|_, w| w.rxie().set_bit()),
+            SlaveEvent::TxEmpty => self.i2c.cr1.modify(|_, w| w.txie().set_bit()),
+            SlaveEvent::Stop => self.i2c.cr1.modify(|_, w| w.stopie().set_bit()),
+        }
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: SlaveEvent) {
+        match event {
+            SlaveEvent::AddressMatch => self.i2c.cr1.modify(|_, w| w.addrie().clear_bit()),
+            SlaveEvent::RxNotEmpty => self.i2c.cr1.modify(|_, w| w.rxie().clear_bit()),
+            SlaveEvent::TxEmpty => self.i2c.cr1.modify(|_, w| w.txie().clear_bit()),
+            SlaveEvent::Stop => self.i2c.cr1.modify(|_, w| w.stopie().clear_bit()),
+        }
+    }
+
+    /// Returns `true` if `event` is pending, whether it is listened for or not
+    pub fn is_event_triggered(&self, event: SlaveEvent) -> bool {
+        let isr = self.i2c.isr.read();
+        match event {
+            SlaveEvent::AddressMatch => isr.addr().bit_is_set(),
+            SlaveEvent::RxNotEmpty => isr.rxne().bit_is_set(),
+            SlaveEvent::TxEmpty => isr.txis().bit_is_set(),
+            SlaveEvent::Stop => isr.stopf().bit_is_set(),
+        }
+    }
+
+    /// Returns the address match, if the master addressed the slave
+    ///
+    /// SCL is stretched from the address match until this is called. The transmit
+    /// data register is flushed, so that the first byte of a read is the one passed
+    /// to the next [`slave_write`](Self::slave_write).
+    pub fn address_match(&mut self) -> Option<AddressMatch> {
+        let isr = self.i2c.isr.read();
+        if isr.addr().bit_is_clear() {
+            return None;
+        }
+
+        let direction = if isr.dir().bit_is_set() {
+            Direction::Read
+        } else {
+            Direction::Write
+        };
+
+        match direction {
+            Direction::Write => {
+                // SBC with a reload after each byte stretches SCL before the ACK,
+                // so that `slave_read` can NACK a byte that does not fit
+                self.i2c.cr1.modify(|_, w| w.sbc().set_bit());
+                self.i2c
+                    .cr2
+                    .modify(|_, w| w.reload().set_bit().nbytes().bits(1));
+            }
+            Direction::Read => {
+                self.i2c.cr1.modify(|_, w| w.sbc().clear_bit());
+                self.i2c.isr.write(|w| w.txe().set_bit());
+            }
+        }
+        self.i2c.icr.write(|w| w.addrcf().set_bit());
+
+        Some(AddressMatch {
+            address: isr.addcode().bits(),
+            direction,
+        })
+    }
+
+    /// Receives the byte written by the master, if any, into `buffer`, and returns the
+    /// number of bytes received, 0 or 1
+    ///
+    /// The byte is acknowledged if it fits into `buffer`, otherwise it is discarded and
+    /// not acknowledged, which tells the master to stop writing.
+    pub fn slave_read(&mut self, buffer: &mut [u8]) -> usize {
+        if self.i2c.isr.read().rxne().bit_is_clear() {
+            return 0;
+        }
+
+        let byte = self.i2c.rxdr.read().rxdata().bits();
+        let received = match buffer.first_mut() {
+            Some(slot) => {
+                *slot = byte;
+                1
+            }
+            None => {
+                self.i2c.cr2.modify(|_, w| w.nack().set_bit());
+                0
+            }
+        };
+        // Writing NBYTES sends the ACK or NACK and releases SCL
+        self.i2c.cr2.modify(|_, w| w.nbytes().bits(1));
+
+        received
+    }
+
+    /// Transmits the first byte of `bytes` if the master reads the next byte, and returns
+    /// the number of bytes transmitted, 0 or 1
+    ///
+    /// The master decides how many bytes it reads, 0xFF is transmitted once `bytes` is
+    /// empty.
+    pub fn slave_write(&mut self, bytes: &[u8]) -> usize {
+        if self.i2c.isr.read().txis().bit_is_clear() {
+            return 0;
+        }
+
+        match bytes.first() {
+            Some(byte) => {
+                self.i2c.txdr.write(|w| w.txdata().bits(*byte));
+                1
+            }
+            None => {
+                self.i2c.txdr.write(|w| w.txdata().bits(0xff));
+                0
+            }
+        }
+    }
+
+    /// Clears the stop condition at the end of a transfer
+    ///
+    /// The byte the master did not read anymore is discarded.
+    pub fn clear_stop(&mut self) {
+        self.i2c
+            .icr
+            .write(|w| w.stopcf().set_bit().nackcf().set_bit());
+        self.i2c.isr.write(|w| w.txe().set_bit());
+    }
+
+    /// Releases the I2C peripheral and associated pins
+    pub fn free(self) -> (I2C, PINS) {
+        (self.i2c, self.pins)
+    }
+}
+
 /// Sequence to flush the TXDR register. This resets the TXIS and TXE
 // flags
 macro_rules! flush_txdr {
@@ -272,7 +1091,8 @@ macro_rules! flush_txdr {
 }
 
 macro_rules! busy_wait {
-    ($i2c:expr, $flag:ident, $variant:ident) => {
+    ($i2c:expr, $flag:ident, $variant:ident, $timeout:expr) => {
+        let deadline = Deadline::new($timeout);
         loop {
             let isr = $i2c.isr.read();
 
@@ -288,6 +1108,15 @@ macro_rules! busy_wait {
                 $i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
                 flush_txdr!($i2c);
                 return Err(Error::Nack);
+            } else if isr.timeout().is_timeout() {
+                // The hardware sent STOP
+                $i2c.icr
+                    .write(|w| w.timoutcf().set_bit().stopcf().set_bit());
+                flush_txdr!($i2c);
+                return Err(Error::Timeout);
+            } else if deadline.expired() {
+                software_reset(&$i2c);
+                return Err(Error::Timeout);
             } else {
                 // try again
             }
@@ -295,102 +1124,266 @@ macro_rules! busy_wait {
     };
 }
 
-impl<PINS, I2C> Write for I2c<I2C, PINS>
+/// Waits for the address sequence of the previous transfer to end
+macro_rules! wait_for_start {
+    ($i2c:expr, $timeout:expr) => {
+        let deadline = Deadline::new($timeout);
+        while $i2c.cr2.read().start().bit_is_set() {
+            if deadline.expired() {
+                software_reset(&$i2c);
+                return Err(Error::Timeout);
+            }
+        }
+    };
+}
+
+/// Progress of the bytes of adjacent operations of the same direction, which are
+/// transferred in chunks of up to [`MAX_NBYTES`] bytes
+struct Chunks {
+    /// Bytes of the following chunks
+    remaining: usize,
+    /// Bytes left in the current chunk
+    in_chunk: usize,
+    /// Whether the operations end the transaction
+    last: bool,
+    /// Whether the PEC byte ends the operations
+    pec: bool,
+    read: bool,
+}
+
+impl<PINS, I2C> I2c<I2C, PINS>
 where
     I2C: Deref<Target = i2c1::RegisterBlock>,
 {
-    type Error = Error;
+    /// Executes `operations` on the device at the 7-bit or 10-bit `address` as one
+    /// transaction, see [`Transactional`]
+    ///
+    /// Adjacent operations of the same direction are transferred without a repeated
+    /// START in between. A write of 0 bytes only addresses the device, e.g. to probe it.
+    pub fn transaction(
+        &mut self,
+        address: Address,
+        operations: &mut [Operation],
+    ) -> Result<(), Error> {
+        let mut first = 0;
+        while first < operations.len() {
+            let read = matches!(operations[first], Operation::Read(_));
+            let end = first
+                + operations[first..]
+                    .iter()
+                    .take_while(|op| matches!(op, Operation::Read(_)) == read)
+                    .count();
+            let len = operations[first..end]
+                .iter()
+                .map(|op| match op {
+                    Operation::Read(buffer) => buffer.len(),
+                    Operation::Write(bytes) => bytes.len(),
+                })
+                .sum();
+            // A read after a write to the same 10-bit address only needs the header
+            let restart = first > 0;
+
+            let mut chunks = self.start(address, read, len, restart, end == operations.len())?;
+            for op in &mut operations[first..end] {
+                match op {
+                    Operation::Read(buffer) => {
+                        for byte in buffer.iter_mut() {
+                            self.next_chunk(&mut chunks)?;
+                            // Wait until we have received something
+                            busy_wait!(self.i2c, rxne, is_not_empty, self.timeout);
+
+                            *byte = self.i2c.rxdr.read().rxdata().bits();
+                        }
+                    }
+                    Operation::Write(bytes) => {
+                        for byte in bytes.iter() {
+                            self.next_chunk(&mut chunks)?;
+                            // Wait until we are allowed to send data
+                            // (START has been ACKed or last byte went through)
+                            busy_wait!(self.i2c, txis, is_empty, self.timeout);
+
+                            // Put byte on the wire
+                            self.i2c.txdr.write(|w| w.txdata().bits(*byte));
+                        }
+                    }
+                }
+            }
+            self.end(&mut chunks)?;
 
-    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
-        // TODO support transfers of more than 255 bytes
-        assert!(bytes.len() < 256);
+            first = end;
+        }
+
+        Ok(())
+    }
+
+    /// Sets START, or a repeated START, to transfer `len` bytes in the direction `read`
+    ///
+    /// The START bit can be set even if the bus is BUSY or I2C is in slave mode.
+    fn start(
+        &mut self,
+        address: Address,
+        read: bool,
+        len: usize,
+        restart: bool,
+        last: bool,
+    ) -> Result<Chunks, Error> {
+        // Without a byte to NACK, a read can't end
+        assert!(!read || len > 0, "I2C read of 0 bytes");
+
+        let (sadd, add10) = match address {
+            Address::Seven(address) => {
+                assert!(address < 0x80, "I2C 7-bit address");
+                (u16(address << 1), false)
+            }
+            Address::Ten(address) => {
+                assert!(address < 0x400, "I2C 10-bit address");
+                (address, true)
+            }
+        };
+        let mut chunks = Chunks::new(len, read, last, self.pec);
+        let (nbytes, reload) = chunks.next();
 
         // Wait for any previous address sequence to end
         // automatically. This could be up to 50% of a bus
         // cycle (ie. up to 0.5/freq)
-        while self.i2c.cr2.read().start().bit_is_set() {}
+        wait_for_start!(self.i2c, self.timeout);
 
-        // Set START and prepare to send `bytes`. The
-        // START bit can be set even if the bus is BUSY or
-        // I2C is in slave mode.
         self.i2c.cr2.write(|w| {
             w.start()
                 .set_bit()
                 .sadd()
-                .bits(u16(addr << 1 | 0))
+                .bits(sadd)
                 .add10()
-                .clear_bit()
+                .bit(add10)
+                .head10r()
+                .bit(add10 && read && restart)
                 .rd_wrn()
-                .write()
+                .bit(read)
                 .nbytes()
-                .bits(bytes.len() as u8)
+                .bits(nbytes)
+                .reload()
+                .bit(reload)
+                .pecbyte()
+                .bit(chunks.pecbyte(reload))
                 .autoend()
-                .software()
+                .bit(chunks.autoend(reload))
         });
 
-        for byte in bytes {
-            // Wait until we are allowed to send data
-            // (START has been ACKed or last byte when
-            // through)
-            busy_wait!(self.i2c, txis, is_empty);
+        Ok(chunks)
+    }
 
-            // Put byte on the wire
-            self.i2c.txdr.write(|w| w.txdata().bits(*byte));
+    /// Reloads NBYTES with the next chunk once the current chunk went through
+    fn next_chunk(&mut self, chunks: &mut Chunks) -> Result<(), Error> {
+        if chunks.in_chunk == 0 {
+            busy_wait!(self.i2c, tcr, bit_is_set, self.timeout);
+
+            let (nbytes, reload) = chunks.next();
+            self.i2c.cr2.modify(|_, w| {
+                w.nbytes()
+                    .bits(nbytes)
+                    .reload()
+                    .bit(reload)
+                    .pecbyte()
+                    .bit(chunks.pecbyte(reload))
+                    .autoend()
+                    .bit(chunks.autoend(reload))
+            });
         }
+        chunks.in_chunk -= 1;
 
-        // Wait until the write finishes
-        busy_wait!(self.i2c, tc, is_complete);
+        Ok(())
+    }
+
+    /// Ends the operations of a direction, with STOP if they end the transaction
+    fn end(&mut self, chunks: &mut Chunks) -> Result<(), Error> {
+        if chunks.pec {
+            // The hardware sends the PEC byte of a write by itself
+            self.next_chunk(chunks)?;
+            if chunks.read {
+                busy_wait!(self.i2c, rxne, is_not_empty, self.timeout);
+                // The hardware compares the PEC byte when it is received
+                self.i2c.rxdr.read();
+                if self.i2c.isr.read().pecerr().bit_is_set() {
+                    self.i2c.icr.write(|w| w.peccf().set_bit());
+                    return Err(Error::Pec);
+                }
+            }
+        }
 
-        // Stop
-        self.i2c.cr2.write(|w| w.stop().set_bit());
+        if chunks.read && chunks.last {
+            // automatic STOP
+        } else {
+            // Wait until the transfer finishes, before a STOP or a repeated START
+            busy_wait!(self.i2c, tc, is_complete, self.timeout);
+
+            if chunks.last {
+                self.i2c.cr2.write(|w| w.stop().set_bit());
+            }
+        }
 
         Ok(())
-        // Tx::new(&self.i2c)?.write(addr, bytes)
     }
 }
 
-impl<PINS, I2C> Read for I2c<I2C, PINS>
-where
-    I2C: Deref<Target = i2c1::RegisterBlock>,
-{
-    type Error = Error;
+impl Chunks {
+    /// Splits `len` bytes in direction `read` into chunks
+    ///
+    /// With PEC, the operations that end the transaction end with the PEC byte, which
+    /// covers the whole transaction.
+    fn new(len: usize, read: bool, last: bool, pec: bool) -> Self {
+        let pec = pec && last;
+        Chunks {
+            remaining: len + usize::from(pec),
+            in_chunk: 0,
+            last,
+            pec,
+            read,
+        }
+    }
 
-    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
-        // TODO support transfers of more than 255 bytes
-        assert!(buffer.len() < 256 && buffer.len() > 0);
+    /// Returns NBYTES and RELOAD of the next chunk
+    fn next(&mut self) -> (u8, bool) {
+        let nbytes = cmp::min(self.remaining, MAX_NBYTES);
+        self.remaining -= nbytes;
+        self.in_chunk = nbytes;
 
-        // Wait for any previous address sequence to end
-        // automatically. This could be up to 50% of a bus
-        // cycle (ie. up to 0.5/freq)
-        while self.i2c.cr2.read().start().bit_is_set() {}
+        (nbytes as u8, self.remaining > 0)
+    }
 
-        // Set START and prepare to receive bytes into
-        // `buffer`. The START bit can be set even if the bus
-        // is BUSY or I2C is in slave mode.
-        self.i2c.cr2.write(|w| {
-            w.sadd()
-                .bits((addr << 1 | 0) as u16)
-                .rd_wrn()
-                .read()
-                .nbytes()
-                .bits(buffer.len() as u8)
-                .start()
-                .set_bit()
-                .autoend()
-                .automatic()
-        });
+    /// Whether the hardware sends STOP after the chunk
+    ///
+    /// A write ends with STOP from the software, once the write completed.
+    fn autoend(&self, reload: bool) -> bool {
+        self.read && self.last && !reload
+    }
 
-        for byte in buffer {
-            // Wait until we have received something
-            busy_wait!(self.i2c, rxne, is_not_empty);
+    /// Whether the last byte of the chunk is the PEC byte
+    ///
+    /// PECBYTE has no effect while RELOAD is set, so the PEC byte is in the last chunk.
+    fn pecbyte(&self, reload: bool) -> bool {
+        self.pec && !reload
+    }
+}
 
-            *byte = self.i2c.rxdr.read().rxdata().bits();
-        }
+impl<PINS, I2C> Write for I2c<I2C, PINS>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    type Error = Error;
 
-        // automatic STOP
+    fn write(&mut self, addr: u8, bytes: &[u8]) -> Result<(), Error> {
+        self.transaction(Address::Seven(addr), &mut [Operation::Write(bytes)])
+    }
+}
 
-        Ok(())
-        // Rx::new(&self.i2c)?.read(addr, buffer)
+impl<PINS, I2C> Read for I2c<I2C, PINS>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    type Error = Error;
+
+    fn read(&mut self, addr: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.transaction(Address::Seven(addr), &mut [Operation::Read(buffer)])
     }
 }
 
@@ -401,72 +1394,560 @@ where
     type Error = Error;
 
     fn write_read(&mut self, addr: u8, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
-        // TODO support transfers of more than 255 bytes
-        assert!(bytes.len() < 256 && bytes.len() > 0);
-        assert!(buffer.len() < 256 && buffer.len() > 0);
+        self.transaction(
+            Address::Seven(addr),
+            &mut [Operation::Write(bytes), Operation::Read(buffer)],
+        )
+    }
+}
+
+impl<PINS, I2C> Transactional for I2c<I2C, PINS>
+where
+    I2C: Deref<Target = i2c1::RegisterBlock>,
+{
+    type Error = Error;
+
+    fn exec(&mut self, addr: u8, operations: &mut [Operation<'_>]) -> Result<(), Error> {
+        self.transaction(Address::Seven(addr), operations)
+    }
+}
+
+/// Largest NBYTES, longer transfers are split into chunks with NBYTES reloads
+const MAX_NBYTES: usize = 255;
+
+/// A master transfer with DMA, the DMA moves the bytes and [`poll`](dma::Transfer::poll)
+/// programs the chunks of the transfer
+pub struct I2cPayload<I2C, PINS> {
+    i2c: I2c<I2C, PINS>,
+    address: u8,
+    // Chunks of the current direction
+    chunks: Chunks,
+    // Length of the read that follows the write of a write-read
+    read_len: usize,
+}
+
+pub type I2cRxDma<I2C, PINS, CHANNEL> = dma::RxDma<I2cPayload<I2C, PINS>, CHANNEL>;
+
+pub type I2cTxDma<I2C, PINS, CHANNEL> = dma::TxDma<I2cPayload<I2C, PINS>, CHANNEL>;
+
+pub type I2cRxTxDma<I2C, PINS, RXCH, TXCH> = dma::RxTxDma<I2cPayload<I2C, PINS>, RXCH, TXCH>;
+
+impl<I2C: Instance, PINS> I2cPayload<I2C, PINS> {
+    fn new(i2c: I2c<I2C, PINS>) -> Self {
+        I2cPayload {
+            i2c,
+            address: 0,
+            chunks: Chunks::new(0, false, false, false),
+            read_len: 0,
+        }
+    }
+
+    /// Sends START and the address, followed by the first chunk of `len` bytes
+    ///
+    /// A write of `read_len` > 0 bytes is followed by a repeated start and a read of
+    /// `read_len` bytes.
+    fn begin(&mut self, address: u8, read: bool, len: usize, read_len: usize) {
+        let i2c = &self.i2c.i2c;
 
         // Wait for any previous address sequence to end
         // automatically. This could be up to 50% of a bus
         // cycle (ie. up to 0.5/freq)
-        while self.i2c.cr2.read().start().bit_is_set() {}
+        while i2c.cr2.read().start().bit_is_set() {}
 
-        // Set START and prepare to send `bytes`. The
-        // START bit can be set even if the bus is BUSY or
-        // I2C is in slave mode.
-        self.i2c.cr2.write(|w| {
-            w.start()
+        // Flags left over from a previous transfer would end this one
+        i2c.icr.write(|w| {
+            w.stopcf()
+                .set_bit()
+                .nackcf()
+                .set_bit()
+                .berrcf()
+                .set_bit()
+                .arlocf()
                 .set_bit()
-                .sadd()
-                .bits(u16(addr << 1 | 0))
-                .add10()
-                .clear_bit()
-                .rd_wrn()
-                .write()
-                .nbytes()
-                .bits(bytes.len() as u8)
-                .autoend()
-                .software()
         });
 
-        for byte in bytes {
-            // Wait until we are allowed to send data
-            // (START has been ACKed or last byte went through)
-            busy_wait!(self.i2c, txis, is_empty);
-
-            // Put byte on the wire
-            self.i2c.txdr.write(|w| w.txdata().bits(*byte));
-        }
-
-        // Wait until the write finishes before beginning to read.
-        busy_wait!(self.i2c, tc, is_complete);
+        self.address = address;
+        self.read_len = read_len;
+        self.start_direction(read, len, true);
+    }
 
-        // reSTART and prepare to receive bytes into `buffer`
-        self.i2c.cr2.write(|w| {
+    /// Programs CR2 for the first chunk of `len` bytes in direction `read`
+    ///
+    /// With PEC, NBYTES of the direction that ends the transaction includes the PEC byte,
+    /// which the hardware sends or checks by itself.
+    fn start_direction(&mut self, read: bool, len: usize, start: bool) {
+        // A write followed by a read ends with a repeated start instead of STOP
+        let last = read || self.read_len == 0;
+        self.chunks = Chunks::new(len, read, last, self.i2c.pec);
+        let (nbytes, reload) = self.chunks.next();
+        let pecbyte = self.chunks.pecbyte(reload);
+
+        self.i2c.i2c.cr2.write(|w| {
             w.sadd()
-                .bits(u16(addr << 1 | 1))
+                .bits(u16(self.address << 1))
                 .add10()
                 .clear_bit()
                 .rd_wrn()
-                .read()
+                .bit(read)
                 .nbytes()
-                .bits(buffer.len() as u8)
-                .start()
-                .set_bit()
+                .bits(nbytes)
+                .reload()
+                .bit(reload)
+                .pecbyte()
+                .bit(pecbyte)
                 .autoend()
-                .automatic()
+                .bit(!reload && last)
+                .start()
+                .bit(start)
         });
+    }
 
-        for byte in buffer {
-            // Wait until we have received something
-            busy_wait!(self.i2c, rxne, is_not_empty);
+    /// Advances the transfer, returns `Ok` once STOP was sent
+    fn poll(&mut self) -> nb::Result<(), Error> {
+        let i2c = &self.i2c.i2c;
+        let isr = i2c.isr.read();
+
+        if isr.berr().is_error() {
+            i2c.icr.write(|w| w.berrcf().set_bit());
+            return Err(nb::Error::Other(Error::Bus));
+        } else if isr.arlo().is_lost() {
+            i2c.icr.write(|w| w.arlocf().set_bit());
+            return Err(nb::Error::Other(Error::Arbitration));
+        } else if isr.nackf().bit_is_set() {
+            // STOP is only sent automatically at the end of the last chunk
+            let cr2 = i2c.cr2.read();
+            if cr2.autoend().bit_is_clear() || cr2.reload().bit_is_set() {
+                i2c.cr2.modify(|_, w| w.stop().set_bit());
+            }
+            while i2c.isr.read().stopf().bit_is_clear() {}
+            i2c.icr.write(|w| w.stopcf().set_bit().nackcf().set_bit());
+            flush_txdr!(i2c);
+            return Err(nb::Error::Other(Error::Nack));
+        }
 
-            *byte = self.i2c.rxdr.read().rxdata().bits();
+        if isr.tcr().bit_is_set() {
+            // The chunk is done, program the next one, which clears TCR
+            let (nbytes, reload) = self.chunks.next();
+            let pecbyte = self.chunks.pecbyte(reload);
+            let autoend = !reload && self.chunks.last;
+            i2c.cr2.modify(|_, w| {
+                w.nbytes()
+                    .bits(nbytes)
+                    .reload()
+                    .bit(reload)
+                    .pecbyte()
+                    .bit(pecbyte)
+                    .autoend()
+                    .bit(autoend)
+            });
+        } else if isr.tc().bit_is_set() {
+            // The write of a write-read is done, the repeated start clears TC
+            let len = self.read_len;
+            self.read_len = 0;
+            self.start_direction(true, len, true);
+        } else if isr.stopf().bit_is_set() {
+            i2c.icr.write(|w| w.stopcf().set_bit());
+            if self.chunks.pec && self.chunks.read {
+                // The DMA only reads the data bytes, the hardware compared the PEC byte
+                // when it was received
+                i2c.rxdr.read();
+                if i2c.isr.read().pecerr().bit_is_set() {
+                    i2c.icr.write(|w| w.peccf().set_bit());
+                    return Err(nb::Error::Other(Error::Pec));
+                }
+            }
+            return Ok(());
         }
 
-        Ok(())
+        Err(nb::Error::WouldBlock)
     }
 }
 
+macro_rules! i2c_dma {
+    ($I2CX:ident, $RX_CH:path, $RX_CHSEL:path, $TX_CH:path, $TX_CHSEL:path) => {
+        impl<PINS> dma::Receive for I2cRxDma<$I2CX, PINS, $RX_CH> {
+            type RxChannel = $RX_CH;
+            type TransmittedWord = u8;
+        }
+
+        impl<PINS> dma::Transmit for I2cTxDma<$I2CX, PINS, $TX_CH> {
+            type TxChannel = $TX_CH;
+            type ReceivedWord = u8;
+        }
+
+        impl<PINS> dma::ReceiveTransmit for I2cRxTxDma<$I2CX, PINS, $RX_CH, $TX_CH> {
+            type RxChannel = $RX_CH;
+            type TxChannel = $TX_CH;
+            type TransferedWord = u8;
+        }
+
+        impl<PINS> I2c<$I2CX, PINS> {
+            /// Reads with DMA, see [`I2cRxDma::read`]
+            pub fn with_rx_dma(self, mut channel: $RX_CH) -> I2cRxDma<$I2CX, PINS, $RX_CH> {
+                let payload = I2cPayload::new(self);
+
+                // Perform one-time setup actions to keep the work minimal when using the driver.
+
+                channel.set_peripheral_address(
+                    unsafe { &(*$I2CX::ptr()).rxdr as *const _ as u32 },
+                    false,
+                );
+                channel.set_request_line($RX_CHSEL).unwrap();
+                channel.ccr().modify(|_, w| {
+                    w
+                        // memory to memory mode disabled
+                        .mem2mem()
+                        .clear_bit()
+                        // medium channel priority level
+                        .pl()
+                        .medium()
+                        // 8-bit memory size
+                        .msize()
+                        .bits8()
+                        // 8-bit peripheral size
+                        .psize()
+                        .bits8()
+                        // circular mode disabled
+                        .circ()
+                        .clear_bit()
+                        // write to memory
+                        .dir()
+                        .clear_bit()
+                });
+
+                I2cRxDma { payload, channel }
+            }
+
+            /// Writes with DMA, see [`I2cTxDma::write`]
+            pub fn with_tx_dma(self, mut channel: $TX_CH) -> I2cTxDma<$I2CX, PINS, $TX_CH> {
+                let payload = I2cPayload::new(self);
+
+                // Perform one-time setup actions to keep the work minimal when using the driver.
+
+                channel.set_peripheral_address(
+                    unsafe { &(*$I2CX::ptr()).txdr as *const _ as u32 },
+                    false,
+                );
+                channel.set_request_line($TX_CHSEL).unwrap();
+                channel.ccr().modify(|_, w| {
+                    w
+                        // memory to memory mode disabled
+                        .mem2mem()
+                        .clear_bit()
+                        // medium channel priority level
+                        .pl()
+                        .medium()
+                        // 8-bit memory size
+                        .msize()
+                        .bits8()
+                        // 8-bit peripheral size
+                        .psize()
+                        .bits8()
+                        // circular mode disabled
+                        .circ()
+                        .clear_bit()
+                        // write to peripheral
+                        .dir()
+                        .set_bit()
+                });
+
+                I2cTxDma { payload, channel }
+            }
+
+            /// Writes and reads with DMA, see [`I2cRxTxDma::write_read`]
+            pub fn with_rxtx_dma(
+                self,
+                rx_channel: $RX_CH,
+                tx_channel: $TX_CH,
+            ) -> I2cRxTxDma<$I2CX, PINS, $RX_CH, $TX_CH> {
+                // The channels are set up the same as for a read or write alone
+                let I2cRxDma {
+                    payload,
+                    channel: rx_channel,
+                } = self.with_rx_dma(rx_channel);
+                let I2cTxDma {
+                    payload,
+                    channel: tx_channel,
+                } = payload.i2c.with_tx_dma(tx_channel);
+
+                I2cRxTxDma {
+                    payload,
+                    rx_channel,
+                    tx_channel,
+                }
+            }
+        }
+
+        impl<PINS> I2cRxDma<$I2CX, PINS, $RX_CH> {
+            /// Reads `buffer.len()` bytes from the slave at `addr`
+            ///
+            /// The transfer is advanced by [`poll`](dma::Transfer::poll), or waited for with
+            /// [`complete`](dma::Transfer::complete).
+            pub fn read<B>(mut self, addr: u8, mut buffer: B) -> dma::Transfer<dma::W, B, Self>
+            where
+                B: StaticWriteBuffer<Word = u8>,
+            {
+                // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                // until the end of the transfer.
+                let (ptr, len) = unsafe { buffer.static_write_buffer() };
+                assert!(len > 0 && len <= u16::MAX as usize);
+
+                self.channel.set_memory_address(ptr as u32, true);
+                self.channel.set_transfer_length(len as u16);
+
+                // Fences and start
+                atomic::compiler_fence(Ordering::Release);
+                self.start();
+                self.payload.begin(addr, true, len, 0);
+
+                dma::Transfer::w(buffer, self)
+            }
+
+            pub fn split(mut self) -> (I2c<$I2CX, PINS>, $RX_CH) {
+                self.stop();
+                (self.payload.i2c, self.channel)
+            }
+        }
+
+        impl<PINS> I2cTxDma<$I2CX, PINS, $TX_CH> {
+            /// Writes `buffer` to the slave at `addr`
+            ///
+            /// An empty buffer only sends the address, e.g. to probe for the slave. The
+            /// transfer is advanced by [`poll`](dma::Transfer::poll), or waited for with
+            /// [`complete`](dma::Transfer::complete).
+            pub fn write<B>(mut self, addr: u8, buffer: B) -> dma::Transfer<dma::R, B, Self>
+            where
+                B: StaticReadBuffer<Word = u8>,
+            {
+                // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                // until the end of the transfer.
+                let (ptr, len) = unsafe { buffer.static_read_buffer() };
+                assert!(len <= u16::MAX as usize);
+
+                self.channel.set_memory_address(ptr as u32, true);
+                self.channel.set_transfer_length(len as u16);
+
+                // Fences and start
+                atomic::compiler_fence(Ordering::Release);
+                self.start();
+                self.payload.begin(addr, false, len, 0);
+
+                dma::Transfer::r(buffer, self)
+            }
+
+            pub fn split(mut self) -> (I2c<$I2CX, PINS>, $TX_CH) {
+                self.stop();
+                (self.payload.i2c, self.channel)
+            }
+        }
+
+        impl<PINS> I2cRxTxDma<$I2CX, PINS, $RX_CH, $TX_CH> {
+            /// Writes `bytes` to the slave at `addr`, then reads `buffer.len()` bytes after a
+            /// repeated start, e.g. to read a register of a sensor
+            ///
+            /// The transfer is advanced by [`poll`](dma::Transfer::poll), or waited for with
+            /// [`complete`](dma::Transfer::complete).
+            pub fn write_read<TXB, RXB>(
+                mut self,
+                addr: u8,
+                bytes: TXB,
+                mut buffer: RXB,
+            ) -> dma::Transfer<dma::RW, (TXB, RXB), Self>
+            where
+                TXB: StaticReadBuffer<Word = u8>,
+                RXB: StaticWriteBuffer<Word = u8>,
+            {
+                // NOTE(unsafe) We own the buffers now and we won't call other `&mut` on them
+                // until the end of the transfer.
+                let (tx_ptr, tx_len) = unsafe { bytes.static_read_buffer() };
+                let (rx_ptr, rx_len) = unsafe { buffer.static_write_buffer() };
+                assert!(tx_len <= u16::MAX as usize);
+                assert!(rx_len > 0 && rx_len <= u16::MAX as usize);
+
+                self.tx_channel.set_memory_address(tx_ptr as u32, true);
+                self.tx_channel.set_transfer_length(tx_len as u16);
+                self.rx_channel.set_memory_address(rx_ptr as u32, true);
+                self.rx_channel.set_transfer_length(rx_len as u16);
+
+                // Fences and start
+                atomic::compiler_fence(Ordering::Release);
+                self.start();
+                self.payload.begin(addr, false, tx_len, rx_len);
+
+                dma::Transfer::rw((bytes, buffer), self)
+            }
+
+            pub fn split(mut self) -> (I2c<$I2CX, PINS>, $RX_CH, $TX_CH) {
+                self.stop();
+                (self.payload.i2c, self.rx_channel, self.tx_channel)
+            }
+        }
+
+        impl<PINS> dma::TransferPayload for I2cRxDma<$I2CX, PINS, $RX_CH> {
+            fn start(&mut self) {
+                self.channel.start();
+                self.payload
+                    .i2c
+                    .i2c
+                    .cr1
+                    .modify(|_, w| w.rxdmaen().set_bit());
+            }
+
+            fn stop(&mut self) {
+                self.channel.stop();
+                self.payload
+                    .i2c
+                    .i2c
+                    .cr1
+                    .modify(|_, w| w.rxdmaen().clear_bit());
+            }
+        }
+
+        impl<PINS> dma::TransferPayload for I2cTxDma<$I2CX, PINS, $TX_CH> {
+            fn start(&mut self) {
+                self.channel.start();
+                self.payload
+                    .i2c
+                    .i2c
+                    .cr1
+                    .modify(|_, w| w.txdmaen().set_bit());
+            }
+
+            fn stop(&mut self) {
+                self.channel.stop();
+                self.payload
+                    .i2c
+                    .i2c
+                    .cr1
+                    .modify(|_, w| w.txdmaen().clear_bit());
+            }
+        }
+
+        impl<PINS> dma::TransferPayload for I2cRxTxDma<$I2CX, PINS, $RX_CH, $TX_CH> {
+            fn start(&mut self) {
+                self.rx_channel.start();
+                self.tx_channel.start();
+                self.payload
+                    .i2c
+                    .i2c
+                    .cr1
+                    .modify(|_, w| w.rxdmaen().set_bit().txdmaen().set_bit());
+            }
+
+            fn stop(&mut self) {
+                self.rx_channel.stop();
+                self.tx_channel.stop();
+                self.payload
+                    .i2c
+                    .i2c
+                    .cr1
+                    .modify(|_, w| w.rxdmaen().clear_bit().txdmaen().clear_bit());
+            }
+        }
+
+        impl<B, PINS> dma::Transfer<dma::W, B, I2cRxDma<$I2CX, PINS, $RX_CH>> {
+            /// Advances the transfer, returns `Ok` once it is complete
+            ///
+            /// Call it until it does not return `WouldBlock` anymore, e.g. on the I2C event
+            /// and error interrupts or in a loop. The transfer is stopped on an error.
+            pub fn poll(&mut self) -> nb::Result<(), Error> {
+                let dma = self.payload_mut();
+                let result = dma.payload.poll();
+                match result {
+                    // The DMA reads the last byte before the master sends STOP
+                    Ok(()) => while dma.channel.in_progress() {},
+                    Err(nb::Error::Other(_)) => dma.stop(),
+                    Err(nb::Error::WouldBlock) => {}
+                }
+                result
+            }
+
+            /// Waits for the transfer to complete or fail, and returns the buffer and the
+            /// payload
+            pub fn complete(mut self) -> (Result<(), Error>, B, I2cRxDma<$I2CX, PINS, $RX_CH>) {
+                let result = nb::block!(self.poll());
+                let (buffer, dma) = self.abort();
+                (result, buffer, dma)
+            }
+        }
+
+        impl<B, PINS> dma::Transfer<dma::R, B, I2cTxDma<$I2CX, PINS, $TX_CH>> {
+            /// Advances the transfer, returns `Ok` once it is complete
+            ///
+            /// Call it until it does not return `WouldBlock` anymore, e.g. on the I2C event
+            /// and error interrupts or in a loop. The transfer is stopped on an error.
+            pub fn poll(&mut self) -> nb::Result<(), Error> {
+                let dma = self.payload_mut();
+                let result = dma.payload.poll();
+                if let Err(nb::Error::Other(_)) = result {
+                    dma.stop();
+                }
+                result
+            }
+
+            /// Waits for the transfer to complete or fail, and returns the buffer and the
+            /// payload
+            pub fn complete(mut self) -> (Result<(), Error>, B, I2cTxDma<$I2CX, PINS, $TX_CH>) {
+                let result = nb::block!(self.poll());
+                let (buffer, dma) = self.abort();
+                (result, buffer, dma)
+            }
+        }
+
+        impl<B, PINS> dma::Transfer<dma::RW, B, I2cRxTxDma<$I2CX, PINS, $RX_CH, $TX_CH>> {
+            /// Advances the transfer, returns `Ok` once it is complete
+            ///
+            /// Call it until it does not return `WouldBlock` anymore, e.g. on the I2C event
+            /// and error interrupts or in a loop. The transfer is stopped on an error.
+            pub fn poll(&mut self) -> nb::Result<(), Error> {
+                let dma = self.payload_mut();
+                let result = dma.payload.poll();
+                match result {
+                    // The DMA reads the last byte before the master sends STOP
+                    Ok(()) => while dma.rx_channel.in_progress() {},
+                    Err(nb::Error::Other(_)) => dma.stop(),
+                    Err(nb::Error::WouldBlock) => {}
+                }
+                result
+            }
+
+            /// Waits for the transfer to complete or fail, and returns the buffers and the
+            /// payload
+            pub fn complete(
+                mut self,
+            ) -> (
+                Result<(), Error>,
+                B,
+                I2cRxTxDma<$I2CX, PINS, $RX_CH, $TX_CH>,
+            ) {
+                let result = nb::block!(self.poll());
+                let (buffer, dma) = self.abort();
+                (result, buffer, dma)
+            }
+        }
+    };
+}
+
+i2c_dma!(I2C1, dma1::C7, DmaInput::I2c1Rx, dma1::C6, DmaInput::I2c1Tx);
+i2c_dma!(I2C2, dma1::C5, DmaInput::I2c2Rx, dma1::C4, DmaInput::I2c2Tx);
+i2c_dma!(I2C3, dma1::C3, DmaInput::I2c3Rx, dma1::C2, DmaInput::I2c3Tx);
+#[cfg(any(
+    feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+i2c_dma!(I2C4, dma2::C1, DmaInput::I2c4Rx, dma2::C2, DmaInput::I2c4Tx);
+
 #[cfg(any(feature = "stm32l431", feature = "stm32l451", feature = "stm32l471"))]
 mod stm32l4x1_pins {
     #[cfg(any(feature = "stm32l451"))]
@@ -645,3 +2126,135 @@ mod stm32l4r9_pins {
     pins!(I2C4, 3, SCL: [PD12, PF14], SDA: [PD13, PF15]);
     pins!(I2C4, 5, SCL: [PB6], SDA: [PB7]);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{BusTiming, Chunks, Config, Speed};
+    use crate::time::Hertz;
+
+    /// Checks `config` against the I2C specification and returns the bus frequency
+    fn check_timing(config: &Config, i2cclk: u32, timing: BusTiming) -> u64 {
+        let spec = timing.speed.spec();
+        let t_i2cclk = 1_000_000_000_000 / u64::from(i2cclk);
+        let t_presc = (u64::from(config.presc) + 1) * t_i2cclk;
+        let t_sync = 50_000 + 2 * t_i2cclk;
+        let t_r = u64::from(timing.rise_time) * 1000;
+        let t_f = u64::from(timing.fall_time) * 1000;
+
+        let t_low = (u64::from(config.scll) + 1) * t_presc + t_sync;
+        let t_high = (u64::from(config.sclh) + 1) * t_presc + t_sync;
+        assert!(t_low >= spec.low);
+        assert!(t_high >= spec.high);
+
+        let t_sdadel = u64::from(config.sdadel) * t_presc;
+        assert!(t_sdadel + 50_000 + 3 * t_i2cclk >= t_f);
+        assert!(t_sdadel <= spec.vd_dat.saturating_sub(t_r + 260_000 + 4 * t_i2cclk));
+        assert!((u64::from(config.scldel) + 1) * t_presc >= t_r + spec.su_dat);
+
+        let frequency = 1_000_000_000_000 / (t_low + t_high + t_r + t_f);
+        assert!(frequency <= u64::from(timing.frequency.raw()));
+        frequency
+    }
+
+    #[test]
+    fn bus_timing() {
+        // TIMINGR of STM32CubeMX and RM0394 section 37.4.10, which leave more margin
+        // and at times exceed the bus frequency with the rise and fall times here
+        let cubemx = [
+            (8_000_000, Speed::Standard, Some(0x1042_0f13)),
+            (8_000_000, Speed::Fast, Some(0x0031_0309)),
+            (8_000_000, Speed::FastPlus, None),
+            (16_000_000, Speed::Standard, Some(0x3042_0f13)),
+            (16_000_000, Speed::Fast, Some(0x1032_0309)),
+            (16_000_000, Speed::FastPlus, Some(0x0020_0204)),
+            (80_000_000, Speed::Standard, Some(0x1090_9cec)),
+            (80_000_000, Speed::Fast, Some(0x0070_2991)),
+            (80_000_000, Speed::FastPlus, Some(0x0030_0f33)),
+        ];
+        for &(i2cclk, speed, timingr) in cubemx.iter() {
+            let timing = BusTiming::new(speed);
+            let config = Config::with_bus_timing(timing, Hertz::from_raw(i2cclk));
+            let frequency = check_timing(&config, i2cclk, timing);
+            // At most 20% below the bus frequency
+            assert!(frequency * 5 >= u64::from(speed.max_frequency().raw()) * 4);
+
+            // At least as fast as the TIMINGR of STM32CubeMX, if that one meets the
+            // bus frequency
+            if let Some(timingr) = timingr {
+                let spec = timing.speed.spec();
+                let reference = Config::with_timing(timingr);
+                let t_i2cclk = 1_000_000_000_000 / u64::from(i2cclk);
+                let t_presc = (u64::from(reference.presc) + 1) * t_i2cclk;
+                let t_scl = (u64::from(reference.scll) + u64::from(reference.sclh) + 2) * t_presc
+                    + 2 * (50_000 + 2 * t_i2cclk)
+                    + u64::from(timing.rise_time + timing.fall_time) * 1000;
+                if t_scl >= 1_000_000_000_000 / u64::from(spec.frequency) {
+                    assert!(frequency >= 1_000_000_000_000 / t_scl);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn bus_timing_slow_bus() {
+        let timing = BusTiming::new(Speed::Standard)
+            .frequency(Hertz::from_raw(10_000))
+            .rise_time(1000)
+            .fall_time(300);
+        let config = Config::with_bus_timing(timing, Hertz::from_raw(16_000_000));
+        let frequency = check_timing(&config, 16_000_000, timing);
+        assert!(frequency >= 8_000);
+    }
+
+    #[test]
+    #[should_panic]
+    fn bus_timing_slow_kernel_clock() {
+        // t_SCLL has to exceed 4 kernel clock cycles, which is longer than a 1 MHz period
+        Config::with_bus_timing(BusTiming::new(Speed::FastPlus), Hertz::from_raw(1_000_000));
+    }
+
+    #[test]
+    fn chunks_pec() {
+        let mut chunks = Chunks::new(10, false, true, true);
+        assert_eq!(chunks.next(), (11, false));
+        assert!(chunks.pecbyte(false));
+        assert!(!chunks.autoend(false));
+
+        // The PEC byte fills the first chunk
+        let mut chunks = Chunks::new(254, true, true, true);
+        assert_eq!(chunks.next(), (255, false));
+        assert!(chunks.pecbyte(false));
+        assert!(chunks.autoend(false));
+    }
+
+    #[test]
+    fn chunks_pec_reload() {
+        let mut chunks = Chunks::new(300, true, true, true);
+        assert_eq!(chunks.next(), (255, true));
+        assert!(!chunks.pecbyte(true));
+        assert!(!chunks.autoend(true));
+        assert_eq!(chunks.next(), (46, false));
+        assert!(chunks.pecbyte(false));
+        assert!(chunks.autoend(false));
+
+        // The PEC byte alone in the last chunk
+        let mut chunks = Chunks::new(255, false, true, true);
+        assert_eq!(chunks.next(), (255, true));
+        assert_eq!(chunks.next(), (1, false));
+        assert!(chunks.pecbyte(false));
+    }
+
+    #[test]
+    fn chunks_without_pec() {
+        // The write of a write-read does not end the transaction
+        let mut chunks = Chunks::new(10, false, false, true);
+        assert_eq!(chunks.next(), (10, false));
+        assert!(!chunks.pecbyte(false));
+
+        let mut chunks = Chunks::new(510, true, true, false);
+        assert_eq!(chunks.next(), (255, true));
+        assert_eq!(chunks.next(), (255, false));
+        assert!(!chunks.pecbyte(false));
+        assert!(chunks.autoend(false));
+    }
+}
@@ -126,7 +126,19 @@ pub mod adc;
 #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
 #[cfg(not(any(feature = "stm32l412",)))]
 pub mod can;
+pub mod capture;
+pub mod comp;
 pub mod crc;
+// Devices with the HSI48
+#[cfg(not(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+)))]
+pub mod crs;
+#[cfg(not(any(feature = "stm32l412", feature = "stm32l422",)))]
+pub mod dac;
 pub mod delay;
 pub mod dma;
 pub mod dmamux;
@@ -134,6 +146,10 @@ pub mod flash;
 pub mod gpio;
 pub mod i2c;
 pub mod lptimer;
+#[cfg(feature = "rtic")]
+pub mod monotonic;
+pub mod one_pulse;
+pub mod opamp;
 #[cfg(all(
     feature = "otg_fs",
     any(
@@ -148,6 +164,7 @@ pub mod otg_fs;
 pub mod prelude;
 pub mod pwm;
 pub mod pwr;
+pub mod qei;
 #[cfg(not(any(
     feature = "stm32l433",
     feature = "stm32l443",
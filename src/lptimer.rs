@@ -1,9 +1,24 @@
 //! Low power timers
+//!
+//! The LPTIMs run on the LSE or the LSI in Stop 2 as well, where LPTIM1 wakes the system
+//! up. Besides counting the kernel clock, they drive a PWM signal on their OUT pin, see
+//! [`LowPowerTimer::enable_pwm`], or count the pulses on their Input1 pin, see
+//! [`LowPowerTimer::count_pulses`].
 use crate::rcc::{Clocks, Enable, RccBus, Reset, CCIPR};
 
+pub use crate::capture::Polarity;
+pub use crate::pwm::Filter;
+
+use crate::gpio::gpioa::{PA14, PA4, PA8};
+use crate::gpio::gpiob::{PB1, PB2, PB5};
+use crate::gpio::gpioc::{PC0, PC1};
+use crate::gpio::Alternate;
+use crate::pwm;
 use crate::stm32::{LPTIM1, LPTIM2, RCC};
 
 /// Clock sources available for timers
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ClockSource {
     /// Use PCLK as clock source
     PCLK = 0b00,
@@ -19,6 +34,7 @@ pub enum ClockSource {
 ///
 /// Allow missing docs because the type is self explanatory
 #[allow(missing_docs)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum PreScaler {
     U1 = 0b000,
     U2 = 0b001,
@@ -34,6 +50,7 @@ pub enum PreScaler {
 ///
 /// All ClockSources currently supported require the Internal count mode
 #[derive(PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum CountMode {
     /// Use an internal clock source (which also includes LSE)
     Internal,
@@ -41,6 +58,7 @@ pub enum CountMode {
 }
 
 /// All currently supported interrupt events
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
     /// Occurs when the compare value is the same as the counter value
     CompareMatch,
@@ -102,11 +120,38 @@ impl LowPowerTimerConfig {
     }
 }
 
+/// Marks pins as the Input1 of a low power timer, which [`LowPowerTimer::count_pulses`]
+/// counts
+pub trait Input1Pin<LPTIM> {}
+
+/// Marks pins as the output of a low power timer, see [`LowPowerTimer::enable_pwm`]
+pub trait OutPin<LPTIM> {}
+
+macro_rules! pins {
+    ( $( $LPTIMX:ident: $PIN:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> $PIN<$LPTIMX> for $PINX<Alternate<OTYPE, $AF>> {}
+            )+
+        )+
+    };
+}
+
+pins! {
+    LPTIM1: Input1Pin: (PB5, 1), (PC0, 1);
+    LPTIM1: OutPin: (PA14, 1), (PB2, 1), (PC1, 1);
+
+    LPTIM2: Input1Pin: (PB1, 14), (PC0, 14);
+    LPTIM2: OutPin: (PA4, 14), (PA8, 14);
+}
+
 /// A low power hardware timer
 ///
 /// Supported things:
 /// * Compare match
 /// * Auto reload matches
+/// * PWM output
+/// * Counting of external pulses
 pub struct LowPowerTimer<LPTIM> {
     lptim: LPTIM,
 }
@@ -290,7 +335,14 @@ macro_rules! hal {
             /// Get the current counter value for this LowPowerTimer
             #[inline]
             pub fn get_counter(&self) -> u16 {
-                self.lptim.cnt.read().bits() as u16
+                // The counter runs on the kernel clock, two equal reads are a valid count
+                // (see RM0394 Rev 4, sec 30.7.8)
+                loop {
+                    let count = self.lptim.cnt.read().bits();
+                    if self.lptim.cnt.read().bits() == count {
+                        break count as u16;
+                    }
+                }
             }
 
             /// Get the value of the ARR register for this
@@ -308,6 +360,78 @@ macro_rules! hal {
                 self.enable();
                 self.start_continuous_mode();
             }
+
+            /// Drives `pin` with a PWM signal, with a period of the auto reload value + 1
+            ///
+            /// The output is inactive up to the compare value, and active from there to the
+            /// auto reload value, so [`LowPowerTimer::set_compare_match`] sets the duty.
+            pub fn enable_pwm<PIN>(&mut self, _pin: PIN, polarity: pwm::Polarity)
+            where
+                PIN: OutPin<$timer_type>,
+            {
+                // LPTIM_CFGR may only be modified when LPTIM is disabled
+                self.disable();
+                self.lptim.cfgr.modify(|_, w| {
+                    w.wave()
+                        .clear_bit()
+                        .wavpol()
+                        .bit(polarity == pwm::Polarity::ActiveLow)
+                });
+                self.enable();
+                self.start_continuous_mode();
+            }
+
+            /// Counts the `polarity` edges of `pin` instead of the kernel clock, e.g. in
+            /// Stop 2
+            ///
+            /// The kernel clock samples the input, so the pulses need to last at least two
+            /// of its cycles, or the samples of `filter`. For both edges, the kernel clock
+            /// needs to be four times as fast as the pulses.
+            ///
+            /// # Panics
+            ///
+            /// Panics for filters on the prescaled timer clock, which the LPTIM does not
+            /// have.
+            pub fn count_pulses<PIN>(&mut self, _pin: PIN, polarity: Polarity, filter: Filter)
+            where
+                PIN: Input1Pin<$timer_type>,
+            {
+                let ckpol = match polarity {
+                    Polarity::Rising => 0b00,
+                    Polarity::Falling => 0b01,
+                    Polarity::Both => 0b10,
+                };
+                let ckflt = match filter {
+                    Filter::None => 0b00,
+                    Filter::TimerClockN2 => 0b01,
+                    Filter::TimerClockN4 => 0b10,
+                    Filter::TimerClockN8 => 0b11,
+                    _ => panic!("LPTIM input filter not available"),
+                };
+
+                // LPTIM_CFGR may only be modified when LPTIM is disabled
+                self.disable();
+                // This operation is sound as `ckpol` and `ckflt` only hold valid values
+                self.lptim.cfgr.modify(|_, w| unsafe {
+                    w.countmode()
+                        .set_bit()
+                        .ckpol()
+                        .bits(ckpol)
+                        .ckflt()
+                        .bits(ckflt)
+                });
+                self.enable();
+                self.start_continuous_mode();
+            }
+
+            /// Counts the kernel clock again, after [`LowPowerTimer::count_pulses`]
+            pub fn count_kernel_clock(&mut self) {
+                // LPTIM_CFGR may only be modified when LPTIM is disabled
+                self.disable();
+                self.lptim.cfgr.modify(|_, w| w.countmode().clear_bit());
+                self.enable();
+                self.start_continuous_mode();
+            }
         }
     };
 }
@@ -0,0 +1,377 @@
+//! Monotonic timers for the RTIC scheduler
+//!
+//! [`MonoTimer`] counts on the 32-bit TIM2 or TIM5, and wakes the scheduler with a compare
+//! match of channel 1. [`LpMonoTimer`] counts on LPTIM1, which keeps running on the LSE or
+//! the LSI in Stop 2, so the application can sleep between the tasks.
+//!
+//! Usage example:
+//! ```ignore
+//! use stm32l4xx_hal::monotonic::{ExtU32, MonoTimer};
+//!
+//! #[monotonic(binds = TIM2, default = true)]
+//! type Mono = MonoTimer<TIM2, 1_000_000>;
+//!
+//! let mono = MonoTimer::tim2(cx.device.TIM2, clocks, &mut rcc.apb1r1);
+//! blink::spawn_after(500.millis()).unwrap();
+//! ```
+
+use cortex_m::peripheral::NVIC;
+pub use fugit::{self, ExtU32};
+pub use rtic_monotonic::Monotonic;
+
+use crate::lptimer::{ClockSource, PreScaler};
+use crate::rcc::{Clocks, Enable, Reset, APB1R1, CCIPR};
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::stm32::TIM5;
+use crate::stm32::{Interrupt, LPTIM1, RCC, TIM2};
+
+/// Prescaler of a timer counting at `freq` from the timer clock `clock`, `None` if
+/// `clock` is not a multiple of `freq` within its range
+fn prescaler(clock: u32, freq: u32) -> Option<u32> {
+    if freq > 0 && clock % freq == 0 && clock / freq <= 0x1_0000 {
+        Some(clock / freq - 1)
+    } else {
+        None
+    }
+}
+
+/// Monotonic timer on a 32-bit timer, counting at `FREQ`
+///
+/// The counter wraps around after 2^32 ticks, tasks can be scheduled up to half of that
+/// ahead, e.g. 35 minutes at 1 MHz.
+pub struct MonoTimer<TIM, const FREQ: u32> {
+    tim: TIM,
+}
+
+macro_rules! mono {
+    ($($TIM:ident: ($tim:ident, $apb:ident, $timclk:ident),)+) => {
+        $(
+            impl<const FREQ: u32> MonoTimer<$TIM, FREQ> {
+                /// Starts the counter at `FREQ`
+                ///
+                /// # Panics
+                ///
+                /// Panics if the timer clock is not a multiple of `FREQ` within the range
+                /// of the prescaler.
+                pub fn $tim(tim: $TIM, clocks: Clocks, apb: &mut $apb) -> Self {
+                    <$TIM>::enable(apb);
+                    <$TIM>::reset(apb);
+
+                    let psc = prescaler(clocks.$timclk().raw(), FREQ)
+                        .expect("timer clock not a multiple of the monotonic frequency");
+                    tim.psc.write(|w| unsafe { w.bits(psc) });
+                    tim.arr.write(|w| unsafe { w.bits(u32::MAX) });
+
+                    // Loads the prescaler, the update flag is not used
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.sr.write(|w| unsafe { w.bits(0) });
+
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    MonoTimer { tim }
+                }
+
+                /// Stops the timer and releases the TIM peripheral
+                pub fn release(self) -> $TIM {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim.dier.modify(|_, w| w.cc1ie().clear_bit());
+                    self.tim
+                }
+            }
+
+            impl<const FREQ: u32> Monotonic for MonoTimer<$TIM, FREQ> {
+                type Instant = fugit::TimerInstantU32<FREQ>;
+                type Duration = fugit::TimerDurationU32<FREQ>;
+
+                fn now(&mut self) -> Self::Instant {
+                    // CNT is a single 32-bit register, read in one access
+                    Self::Instant::from_ticks(self.tim.cnt.read().bits())
+                }
+
+                fn zero() -> Self::Instant {
+                    Self::Instant::from_ticks(0)
+                }
+
+                unsafe fn reset(&mut self) {
+                    self.tim.cnt.write(|w| w.bits(0));
+                    self.clear_compare_flag();
+                }
+
+                fn set_compare(&mut self, instant: Self::Instant) {
+                    self.tim.ccr1.write(|w| unsafe { w.bits(instant.ticks()) });
+
+                    // A target in the past only matches after the counter wrapped around,
+                    // CC1G raises the compare flag right away instead
+                    if instant <= self.now() {
+                        self.tim.egr.write(|w| w.cc1g().set_bit());
+                    }
+                }
+
+                fn clear_compare_flag(&mut self) {
+                    // CC1IF (bit 1) is cleared by writing 0, the others are left alone
+                    self.tim.sr.write(|w| unsafe { w.bits(!(1 << 1)) });
+                }
+
+                fn enable_timer(&mut self) {
+                    self.tim.dier.modify(|_, w| w.cc1ie().set_bit());
+                }
+
+                fn disable_timer(&mut self) {
+                    self.tim.dier.modify(|_, w| w.cc1ie().clear_bit());
+                }
+            }
+        )+
+    }
+}
+
+mono! {
+    TIM2: (tim2, APB1R1, timclk1),
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+mono! {
+    TIM5: (tim5, APB1R1, timclk1),
+}
+
+/// Ticks of the extended counter, from the auto reload matches counted in `overflows`
+/// and the LPTIM counter `count`
+///
+/// The extended counter steps to the next 2^16 ticks at the auto reload match, when the
+/// LPTIM counter gets to 0xffff, so it is one tick ahead of the LPTIM counter. A match
+/// flagged in `arrm` is only added if it was before `count` was read, a count in the upper
+/// half was read before it.
+fn extended_ticks(overflows: u16, count: u16, arrm: bool) -> u32 {
+    let pending = arrm && !(0x8000..0xffff).contains(&count);
+    let overflows = overflows.wrapping_add(u16::from(pending));
+    u32::from(overflows) << 16 | u32::from(count.wrapping_add(1))
+}
+
+/// CMP of the LPTIM for the extended counter to match `target` from `now`, `None` if
+/// `target` is beyond the next 2^16 ticks
+///
+/// The match is at the LPTIM counter of one tick before the target.
+fn compare_value(target: u32, now: u32) -> Option<u16> {
+    if target.wrapping_sub(now) < 0x1_0000 {
+        Some((target as u16).wrapping_sub(1))
+    } else {
+        None
+    }
+}
+
+/// Monotonic timer on LPTIM1, counting its kernel clock at `FREQ` in Stop 2 as well
+///
+/// The 16-bit counter is extended to 32 bits with its auto reload matches, so the
+/// interrupt of LPTIM1 fires at least every 2^16 ticks, e.g. every 2 s on the LSE. A
+/// compare target is written in sync with the kernel clock, which blocks for up to three
+/// of its cycles.
+pub struct LpMonoTimer<LPTIM, const FREQ: u32> {
+    lptim: LPTIM,
+    overflows: u16,
+}
+
+impl<const FREQ: u32> LpMonoTimer<LPTIM1, FREQ> {
+    /// Starts the counter on `clock_source` divided by `prescaler`, which has to be `FREQ`
+    ///
+    /// # Panics
+    ///
+    /// Panics if the divided clock is not `FREQ`, or if the clock source is HSI16, LSI, or
+    /// LSE and that clock is not enabled.
+    pub fn lptim1(
+        lptim: LPTIM1,
+        clock_source: ClockSource,
+        prescaler: PreScaler,
+        apb1r1: &mut APB1R1,
+        ccipr: &mut CCIPR,
+        clocks: Clocks,
+    ) -> Self {
+        let clock = match clock_source {
+            ClockSource::LSE => {
+                assert!(clocks.lse());
+                32_768
+            }
+            ClockSource::LSI => {
+                assert!(clocks.lsi());
+                32_000
+            }
+            ClockSource::HSI16 => {
+                // This operation is sound, as it is an atomic memory access that does not
+                // modify the memory/read value
+                assert!(unsafe { (*RCC::ptr()).cr.read().hsion().bit_is_set() });
+                16_000_000
+            }
+            ClockSource::PCLK => clocks.pclk1().raw(),
+        };
+        let prescaler = prescaler as u8;
+        assert!(
+            clock >> prescaler == FREQ && clock % (1 << prescaler) == 0,
+            "LPTIM clock not the monotonic frequency"
+        );
+
+        <LPTIM1>::enable(apb1r1);
+        <LPTIM1>::reset(apb1r1);
+
+        // This operation is sound as `ClockSource as u8` only produces valid values
+        ccipr
+            .ccipr()
+            .modify(|_, w| unsafe { w.lptim1sel().bits(clock_source as u8) });
+
+        // LPTIM_CFGR and LPTIM_IER may only be modified when LPTIM is disabled
+        lptim.cfgr.write(|w| unsafe { w.presc().bits(prescaler) });
+        lptim.ier.write(|w| w.cmpmie().set_bit().arrmie().set_bit());
+
+        // ARR can only be written while the LPTIM is enabled
+        lptim.cr.write(|w| w.enable().set_bit());
+        lptim.icr.write(|w| w.arrokcf().set_bit());
+        lptim.arr.write(|w| unsafe { w.bits(0xffff) });
+        while lptim.isr.read().arrok().bit_is_clear() {}
+
+        lptim.cr.modify(|_, w| w.cntstrt().set_bit());
+
+        LpMonoTimer {
+            lptim,
+            overflows: 0,
+        }
+    }
+
+    /// Stops the timer and releases the LPTIM peripheral
+    pub fn release(self) -> LPTIM1 {
+        self.lptim.cr.write(|w| w.enable().clear_bit());
+        self.lptim
+    }
+
+    fn counter(&self) -> u16 {
+        // The counter runs on the kernel clock, two equal reads are a valid count
+        loop {
+            let count = self.lptim.cnt.read().bits();
+            if self.lptim.cnt.read().bits() == count {
+                break count as u16;
+            }
+        }
+    }
+}
+
+/// The counter is extended to 32 bits with the auto reload matches.
+impl<const FREQ: u32> Monotonic for LpMonoTimer<LPTIM1, FREQ> {
+    // The auto reload matches extend the counter, the interrupt has to stay enabled
+    const DISABLE_INTERRUPT_ON_EMPTY_QUEUE: bool = false;
+
+    type Instant = fugit::TimerInstantU32<FREQ>;
+    type Duration = fugit::TimerDurationU32<FREQ>;
+
+    fn now(&mut self) -> Self::Instant {
+        let count = self.counter();
+        // An auto reload match which `on_interrupt` has not counted yet
+        let arrm = self.lptim.isr.read().arrm().bit_is_set();
+        Self::Instant::from_ticks(extended_ticks(self.overflows, count, arrm))
+    }
+
+    fn zero() -> Self::Instant {
+        Self::Instant::from_ticks(0)
+    }
+
+    unsafe fn reset(&mut self) {
+        // Disabling the LPTIM clears the counter, ARR is written again once it is enabled
+        self.lptim.cr.write(|w| w.enable().clear_bit());
+        self.lptim.cr.write(|w| w.enable().set_bit());
+        self.lptim.icr.write(|w| w.bits(0x7f));
+        self.lptim.arr.write(|w| w.bits(0xffff));
+        while self.lptim.isr.read().arrok().bit_is_clear() {}
+        self.lptim.icr.write(|w| w.bits(0x7f));
+        self.overflows = 0;
+
+        self.lptim.cr.modify(|_, w| w.cntstrt().set_bit());
+    }
+
+    fn set_compare(&mut self, instant: Self::Instant) {
+        // A target beyond the next 2^16 ticks is set again after an auto reload match
+        if let Some(cmp) = compare_value(instant.ticks(), self.now().ticks()) {
+            self.lptim.icr.write(|w| w.cmpokcf().set_bit());
+            self.lptim.cmp.write(|w| unsafe { w.bits(u32::from(cmp)) });
+            while self.lptim.isr.read().cmpok().bit_is_clear() {}
+        }
+
+        // A target in the past, or passed while CMP was written, does not match any more
+        if instant <= self.now() {
+            NVIC::pend(Interrupt::LPTIM1);
+        }
+    }
+
+    fn clear_compare_flag(&mut self) {
+        self.lptim.icr.write(|w| w.cmpmcf().set_bit());
+    }
+
+    fn on_interrupt(&mut self) {
+        if self.lptim.isr.read().arrm().bit_is_set() {
+            self.lptim.icr.write(|w| w.arrmcf().set_bit());
+            self.overflows = self.overflows.wrapping_add(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compare_value, extended_ticks, prescaler};
+
+    #[test]
+    fn timer_prescaler() {
+        assert_eq!(prescaler(80_000_000, 1_000_000), Some(79));
+        assert_eq!(prescaler(80_000_000, 80_000_000), Some(0));
+        assert_eq!(prescaler(65_536_000, 1_000), Some(0xffff));
+        assert_eq!(prescaler(65_537_000, 1_000), None);
+        assert_eq!(prescaler(80_000_000, 3_000_000), None);
+        assert_eq!(prescaler(80_000_000, 0), None);
+    }
+
+    #[test]
+    fn extended_counter() {
+        assert_eq!(extended_ticks(0, 0, false), 1);
+        assert_eq!(extended_ticks(0, 0xfffe, false), 0xffff);
+        // The match at 0xffff steps the extended counter, before `on_interrupt` counts it
+        assert_eq!(extended_ticks(0, 0xffff, true), 0x1_0000);
+        assert_eq!(extended_ticks(1, 0xffff, false), 0x1_0000);
+        assert_eq!(extended_ticks(1, 0, false), 0x1_0001);
+        assert_eq!(extended_ticks(0, 0x10, true), 0x1_0011);
+        // A match after a count in the upper half was read is left to the next call
+        assert_eq!(extended_ticks(0, 0x8000, true), 0x8001);
+        assert_eq!(extended_ticks(0xffff, 0xffff, true), 0);
+    }
+
+    #[test]
+    fn compare() {
+        assert_eq!(compare_value(0x1_0010, 0x1_0000), Some(0x000f));
+        assert_eq!(compare_value(0x2_0000, 0x1_0001), Some(0xffff));
+        assert_eq!(compare_value(0x2_0000, 0x1_0000), None);
+        // Across the wrap around of the extended counter
+        assert_eq!(compare_value(5, 0xffff_fff0), Some(4));
+    }
+}
@@ -0,0 +1,317 @@
+//! One-pulse mode
+//!
+//! The timer generates a single pulse on channel 1, after a delay, and stops. The pulse
+//! starts by software with [`OnePulse::fire`], or in hardware on an edge of the channel 2
+//! input with [`OnePulse::enable_trigger`], without the latency of an interrupt.
+//!
+//! Usage example:
+//! ```ignore
+//! let strobe = gpioa
+//!     .pa2
+//!     .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+//! let mut pulse = dp.TIM15.one_pulse(
+//!     strobe,
+//!     2_000.nanos(),
+//!     10_000.nanos(),
+//!     clocks,
+//!     &mut rcc.apb2,
+//! );
+//! pulse.fire();
+//! ```
+
+use core::marker::PhantomData;
+use core::ptr;
+
+pub use crate::capture::{Filter, Polarity};
+
+use crate::gpio::gpioa::{PA0, PA1, PA15, PA2, PA3, PA5, PA8, PA9};
+use crate::gpio::gpiob::{PB14, PB15, PB3};
+use crate::gpio::Alternate;
+use crate::rcc::{Clocks, Enable, RccBus, Reset};
+use crate::stm32::{TIM1, TIM15, TIM2};
+use crate::time::{Hertz, NanoSeconds};
+use crate::timer;
+
+/// Marks pins as the pulse output (channel 1)
+pub trait PulsePin<TIM> {}
+
+/// Marks pins as the trigger input (channel 2)
+pub trait TriggerPin<TIM> {}
+
+macro_rules! pins {
+    ( $( $TIMX:ident: $PIN:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> $PIN<$TIMX> for $PINX<Alternate<OTYPE, $AF>> {}
+            )+
+        )+
+    };
+}
+
+pins! {
+    TIM1: PulsePin: (PA8, 1);
+    TIM1: TriggerPin: (PA9, 1);
+
+    TIM2: PulsePin: (PA0, 1), (PA5, 1), (PA15, 1);
+    TIM2: TriggerPin: (PA1, 1), (PB3, 1);
+
+    TIM15: PulsePin: (PA2, 14), (PB14, 14);
+    TIM15: TriggerPin: (PA3, 14), (PB15, 14);
+}
+
+/// Extension trait to generate single pulses with a timer
+pub trait OnePulseExt: RccBus + Sized {
+    /// Generates a pulse of `width` on `pin`, `delay` after the start
+    ///
+    /// The delay is at least one tick of the timer clock, both are rounded to ticks.
+    fn one_pulse<PIN>(
+        self,
+        pin: PIN,
+        delay: NanoSeconds,
+        width: NanoSeconds,
+        clocks: Clocks,
+        apb: &mut Self::Bus,
+    ) -> OnePulse<Self>
+    where
+        PIN: PulsePin<Self>;
+}
+
+/// Single pulse generator, see [`OnePulseExt::one_pulse`]
+pub struct OnePulse<TIM> {
+    clock: Hertz,
+    _tim: PhantomData<TIM>,
+}
+
+/// Returns the ticks of the timer clock `clock` in `duration`, rounded
+fn ticks(duration: NanoSeconds, clock: Hertz) -> u64 {
+    (u64::from(duration.ticks()) * u64::from(clock.raw()) + 500_000_000) / 1_000_000_000
+}
+
+/// Returns the prescaler, CCR1 and the auto-reload value for a pulse `delay` ticks
+/// after the start, ending `total` ticks after the start
+///
+/// # Panics
+///
+/// Panics if the pulse ends before two ticks, or too late for the counter.
+fn prescale_pulse(delay: u64, total: u64, max_arr: u64) -> (u16, u32, u32) {
+    match timer::prescale(total, max_arr) {
+        Ok((psc, arr)) => (psc, (delay / (u64::from(psc) + 1)) as u32, arr),
+        Err(timer::Error::PeriodTooShort) => panic!("one-pulse delay and width too short"),
+        Err(timer::Error::PeriodTooLong) => panic!("one-pulse delay and width too long"),
+    }
+}
+
+/// Writes SMCR of TIM15, which is missing from the PAC
+fn tim15_set_smcr(bits: u32) {
+    let smcr = (TIM15::ptr() as *mut u8).wrapping_add(0x08) as *mut u32;
+    unsafe { ptr::write_volatile(smcr, bits) }
+}
+
+macro_rules! one_pulse {
+    ($($TIMX:ident: ($timclk:ident, $width:ident, $set_smcr:expr, $enable_outputs:expr),)+) => {
+        $(
+            impl OnePulseExt for $TIMX {
+                fn one_pulse<PIN>(
+                    self,
+                    _pin: PIN,
+                    delay: NanoSeconds,
+                    width: NanoSeconds,
+                    clocks: Clocks,
+                    apb: &mut Self::Bus,
+                ) -> OnePulse<Self>
+                where
+                    PIN: PulsePin<Self>,
+                {
+                    <$TIMX>::enable(apb);
+                    <$TIMX>::reset(apb);
+
+                    // OC1M (bits 4-6), PWM mode 2, inactive for the delay
+                    self.ccmr1_output().write(|w| unsafe { w.bits(0b111 << 4) });
+                    // CC1E (bit 0)
+                    self.ccer.write(|w| unsafe { w.bits(1) });
+                    self.cr1.write(|w| w.opm().set_bit().urs().set_bit());
+                    let enable_outputs: fn(&Self) = $enable_outputs;
+                    enable_outputs(&self);
+
+                    let mut pulse: OnePulse<Self> = OnePulse {
+                        clock: clocks.$timclk(),
+                        _tim: PhantomData,
+                    };
+                    pulse.set_pulse(delay, width);
+                    pulse
+                }
+            }
+
+            impl OnePulse<$TIMX> {
+                /// Sets the delay and the width of the next pulses
+                ///
+                /// A pulse which is running meanwhile is stopped.
+                pub fn set_pulse(&mut self, delay: NanoSeconds, width: NanoSeconds) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let delay = ticks(delay, self.clock).max(1);
+                    let total = delay + ticks(width, self.clock);
+                    let (psc, ccr1, arr) =
+                        prescale_pulse(delay, total, u64::from(<$width>::MAX));
+                    tim.psc.write(|w| unsafe { w.bits(psc.into()) });
+                    tim.ccr1.write(|w| unsafe { w.bits(ccr1) });
+                    tim.arr.write(|w| unsafe { w.bits(arr) });
+                    // Loads the prescaler, without an update interrupt
+                    tim.egr.write(|w| w.ug().set_bit());
+                }
+
+                /// Starts a pulse, unless one is running
+                pub fn fire(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+
+                /// Checks whether a pulse is running, from the start of the delay to the end
+                /// of the pulse
+                pub fn is_running(&self) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.cr1.read().cen().bit_is_set()
+                }
+
+                /// Starts a pulse on the `polarity` edges of `pin`, in hardware
+                ///
+                /// The delay starts a few ticks of the timer clock after the edge, without
+                /// any jitter. Edges during a pulse are ignored. The pin stays in use as the
+                /// trigger input.
+                pub fn enable_trigger<PIN>(&mut self, _pin: PIN, polarity: Polarity, filter: Filter)
+                where
+                    PIN: TriggerPin<$TIMX>,
+                {
+                    self.set_trigger(polarity, filter);
+                    // TS (bits 4-6) TI2FP2 and SMS (bits 0-2) trigger mode
+                    let set_smcr: fn(u32) = $set_smcr;
+                    set_smcr(0b110 << 4 | 0b110);
+                }
+
+                /// Stops starting pulses on the trigger input
+                pub fn disable_trigger(&mut self) {
+                    let set_smcr: fn(u32) = $set_smcr;
+                    set_smcr(0);
+                }
+
+                /// Connects channel 2 to its input with `polarity` and `filter`
+                fn set_trigger(&mut self, polarity: Polarity, filter: Filter) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    // CC2S (bits 8-9) TI2 and IC2F (bits 12-15)
+                    tim.ccmr1_output().modify(|r, w| unsafe {
+                        w.bits((r.bits() & !0xff00) | 0b01 << 8 | (filter as u32) << 12)
+                    });
+                    // CC2P and CC2NP (bits 5 and 7), the CC2E capture is not needed
+                    let bits = match polarity {
+                        Polarity::Rising => 0b0000,
+                        Polarity::Falling => 0b0010,
+                        Polarity::Both => 0b1010,
+                    };
+                    tim.ccer
+                        .modify(|r, w| unsafe { w.bits((r.bits() & !0xf0) | bits << 4) });
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! retriggerable {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl OnePulse<$TIMX> {
+                /// Restarts the delay on every edge of `pin`, also during a pulse
+                ///
+                /// The pulse ends `width` after the last edge which came before its start,
+                /// a new edge during the pulse ends it and starts the delay again.
+                pub fn enable_retriggerable<PIN>(
+                    &mut self,
+                    _pin: PIN,
+                    polarity: Polarity,
+                    filter: Filter,
+                ) where
+                    PIN: TriggerPin<$TIMX>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    self.set_trigger(polarity, filter);
+                    // OC1M (bits 4-6 and 16), retriggerable OPM mode 2
+                    tim.ccmr1_output().modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0b111 << 4)) | 0b001 << 4 | 1 << 16)
+                    });
+                    // TS (bits 4-6) TI2FP2 and SMS (bits 0-2 and 16) combined reset and
+                    // trigger mode
+                    tim.smcr.write(|w| unsafe { w.bits(0b110 << 4 | 1 << 16) });
+                }
+
+                /// Stops the retriggerable mode and the trigger input
+                pub fn disable_retriggerable(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.smcr.write(|w| unsafe { w.bits(0) });
+                    tim.ccmr1_output().modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0b111 << 4 | 1 << 16)) | 0b111 << 4)
+                    });
+                }
+            }
+        )+
+    }
+}
+
+one_pulse! {
+    // MOE gates the outputs of TIM1 and TIM15
+    TIM1: (
+        timclk2,
+        u16,
+        |bits| unsafe { (*TIM1::ptr()).smcr.write(|w| w.bits(bits)) },
+        |tim| tim.bdtr.write(|w| w.moe().set_bit())
+    ),
+    TIM2: (
+        timclk1,
+        u32,
+        |bits| unsafe { (*TIM2::ptr()).smcr.write(|w| w.bits(bits)) },
+        |_| ()
+    ),
+    TIM15: (
+        timclk2,
+        u16,
+        tim15_set_smcr,
+        |tim| tim.bdtr.write(|w| w.moe().set_bit())
+    ),
+}
+
+retriggerable! {
+    TIM1,
+    TIM2,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prescale_pulse;
+
+    #[test]
+    fn pulse() {
+        let max = u64::from(u16::MAX);
+        // The output is active from CCR1 up to and including ARR
+        assert_eq!(prescale_pulse(1, 2, max), (0, 1, 1));
+        assert_eq!(prescale_pulse(100, 0x1_0000, max), (0, 100, 0xffff));
+        // Both are rounded down to prescaled ticks
+        assert_eq!(prescale_pulse(101, 0x1_0001, max), (1, 50, 0x7fff));
+        assert_eq!(
+            prescale_pulse(5, 0x10_0000, u64::from(u32::MAX)),
+            (0, 5, 0xf_ffff)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "one-pulse delay and width too short")]
+    fn pulse_too_short() {
+        prescale_pulse(1, 1, u64::from(u16::MAX));
+    }
+
+    #[test]
+    #[should_panic(expected = "one-pulse delay and width too long")]
+    fn pulse_too_long() {
+        prescale_pulse(1, 0x1_0000 * 0x1_0000 + 1, u64::from(u16::MAX));
+    }
+}
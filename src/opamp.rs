@@ -0,0 +1,305 @@
+//! Operational amplifiers
+//!
+//! Usage example:
+//! ```ignore
+//! let opamps = dp.OPAMP.split(PowerRange::High, &mut rcc.apb1r1);
+//!
+//! let pa0 = gpioa.pa0.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+//! let pa3 = gpioa.pa3.into_analog(&mut gpioa.moder, &mut gpioa.pupdr);
+//! let mut pga = opamps.opamp1.pga(pa0, pa3, PgaGain::X4);
+//! pga.calibrate(&mut delay);
+//!
+//! // The output pin is converted by the ADC
+//! let amplified: u16 = adc.read(&mut pga).unwrap();
+//! ```
+
+#[cfg(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::gpio::{PA6, PA7, PB0};
+#[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+use crate::{adc, hal::adc::Channel as EmbeddedHalChannel};
+use crate::{
+    gpio::{Analog, PA0, PA1, PA3},
+    hal::blocking::delay::DelayUs,
+    pac::OPAMP,
+    rcc::{Enable, Reset, APB1R1},
+};
+
+/// Extension trait to split the OPAMP peripheral into the individual opamps
+pub trait OpampExt {
+    /// Enables the OPAMP peripheral and selects the power supply `range` for all opamps
+    fn split(self, range: PowerRange, apb1r1: &mut APB1R1) -> Parts;
+}
+
+impl OpampExt for OPAMP {
+    fn split(self, range: PowerRange, apb1r1: &mut APB1R1) -> Parts {
+        OPAMP::enable(apb1r1);
+        OPAMP::reset(apb1r1);
+
+        // OPA_RANGE can only be written while all opamps are disabled
+        self.opamp1_csr
+            .modify(|_, w| w.opa_range().bit(range == PowerRange::High));
+
+        Parts {
+            opamp1: Opamp1 { _0: () },
+            #[cfg(any(
+                feature = "stm32l471",
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486",
+                feature = "stm32l496",
+                feature = "stm32l4a6",
+                feature = "stm32l4r9",
+                feature = "stm32l4s9",
+            ))]
+            opamp2: Opamp2 { _0: () },
+        }
+    }
+}
+
+/// The individual opamps
+pub struct Parts {
+    pub opamp1: Opamp1,
+    #[cfg(any(
+        feature = "stm32l471",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub opamp2: Opamp2,
+}
+
+/// Power supply range of the opamps (OPAMP1_CSR OPA_RANGE)
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PowerRange {
+    /// VDDA below 2.4 V
+    Low,
+    /// VDDA above 2.4 V
+    High,
+}
+
+/// Gain of the programmable gain amplifier mode
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PgaGain {
+    X2 = 0b00,
+    X4 = 0b01,
+    X8 = 0b10,
+    X16 = 0b11,
+}
+
+/// Disabled OPAMP1, with the non-inverting input on PA0, the inverting input on PA1 and
+/// the output on PA3
+pub struct Opamp1 {
+    _0: (),
+}
+
+/// Disabled OPAMP2, with the non-inverting input on PA6, the inverting input on PA7 and
+/// the output on PB0
+#[cfg(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+pub struct Opamp2 {
+    _0: (),
+}
+
+/// An enabled opamp `OP`, with the input pins `INPUTS` and output pin `OUTPUT` it uses
+///
+/// The output pin can be converted by the ADC by passing the opamp as channel.
+pub struct Opamp<OP, INPUTS, OUTPUT> {
+    opamp: OP,
+    inputs: INPUTS,
+    output: OUTPUT,
+}
+
+#[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+impl<ADC, OP, INPUTS, OUTPUT> EmbeddedHalChannel<ADC> for Opamp<OP, INPUTS, OUTPUT>
+where
+    OUTPUT: EmbeddedHalChannel<ADC, ID = u8>,
+{
+    type ID = u8;
+
+    fn channel() -> Self::ID {
+        OUTPUT::channel()
+    }
+}
+
+#[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9",)))]
+impl<ADC, OP, INPUTS, OUTPUT> adc::Channel<ADC> for Opamp<OP, INPUTS, OUTPUT>
+where
+    OUTPUT: adc::Channel<ADC>,
+{
+    #[inline]
+    fn set_sample_time(&mut self, adc: &mut ADC, sample_time: adc::SampleTime) {
+        self.output.set_sample_time(adc, sample_time)
+    }
+}
+
+macro_rules! opamp {
+    ($OPX:ident: ($vinp:ty, $vinm:ty, $vout:ty, $csr:ident, $otr:ident, $lpotr:ident)) => {
+        impl $OPX {
+            /// Enables the opamp as voltage follower, the output follows `vinp`
+            pub fn follower(self, vinp: $vinp, vout: $vout) -> Opamp<$OPX, $vinp, $vout> {
+                // OPAMODE = 0b11: follower, VM_SEL = 0b10: not connected to a pin,
+                // VP_SEL = 0: pin
+                self.enable(0b11, 0b00, 0b10).with_pins(vinp, vout)
+            }
+
+            /// Enables the opamp as programmable gain amplifier, the output is `vinp`
+            /// multiplied by `gain`
+            pub fn pga(self, vinp: $vinp, vout: $vout, gain: PgaGain) -> Opamp<$OPX, $vinp, $vout> {
+                // OPAMODE = 0b10: PGA, VM_SEL = 0b10: not connected to a pin
+                self.enable(0b10, gain as u8, 0b10).with_pins(vinp, vout)
+            }
+
+            /// Enables the opamp with both inputs on their pins, the gain is set by
+            /// external components
+            pub fn standalone(
+                self,
+                vinp: $vinp,
+                vinm: $vinm,
+                vout: $vout,
+            ) -> Opamp<$OPX, ($vinp, $vinm), $vout> {
+                // OPAMODE = 0b00: standalone, VM_SEL = 0b00: pin
+                self.enable(0b00, 0b00, 0b00).with_pins((vinp, vinm), vout)
+            }
+
+            fn enable(self, mode: u8, gain: u8, vm_sel: u8) -> Self {
+                let opamp = unsafe { &*OPAMP::ptr() };
+                opamp.$csr.modify(|_, w| unsafe {
+                    w.opamode()
+                        .bits(mode)
+                        .pga_gain()
+                        .bits(gain)
+                        .vm_sel()
+                        .bits(vm_sel)
+                        .vp_sel()
+                        .clear_bit()
+                });
+                opamp.$csr.modify(|_, w| w.opaen().set_bit());
+                self
+            }
+
+            fn with_pins<INPUTS>(
+                self,
+                inputs: INPUTS,
+                output: $vout,
+            ) -> Opamp<$OPX, INPUTS, $vout> {
+                Opamp {
+                    opamp: self,
+                    inputs,
+                    output,
+                }
+            }
+        }
+
+        impl<INPUTS> Opamp<$OPX, INPUTS, $vout> {
+            /// Switches between normal and low-power mode
+            ///
+            /// The opamp is disabled for the change. Each mode uses its own offset
+            /// trimming, [`Self::calibrate`] trims the one of the current mode.
+            pub fn set_low_power(&mut self, low_power: bool) {
+                let opamp = unsafe { &*OPAMP::ptr() };
+                opamp.$csr.modify(|_, w| w.opaen().clear_bit());
+                opamp.$csr.modify(|_, w| w.opalpm().bit(low_power));
+                opamp.$csr.modify(|_, w| w.opaen().set_bit());
+            }
+
+            /// Trims the input offset of the differential pairs for the current power mode
+            ///
+            /// Takes about 20 ms, during which the output is not valid.
+            pub fn calibrate(&mut self, delay: &mut impl DelayUs<u32>) {
+                let opamp = unsafe { &*OPAMP::ptr() };
+                let low_power = opamp.$csr.read().opalpm().bit_is_set();
+
+                opamp
+                    .$csr
+                    .modify(|_, w| w.usertrim().set_bit().calon().set_bit());
+
+                // CALSEL = 0: NMOS pair, trimmed with TRIMOFFSETN (bits 0-4),
+                // CALSEL = 1: PMOS pair, trimmed with TRIMOFFSETP (bits 8-12)
+                let mut otr = 0;
+                for &(calsel, shift) in [(false, 0), (true, 8)].iter() {
+                    opamp.$csr.modify(|_, w| w.calsel().bit(calsel));
+
+                    // Binary search for the trimming value at which CALOUT toggles
+                    let mut trim = 16;
+                    let mut step = 8;
+                    let mut write_trim = |trim: u32| {
+                        let bits = otr | trim << shift;
+                        if low_power {
+                            opamp.$lpotr.write(|w| unsafe { w.bits(bits) });
+                        } else {
+                            opamp.$otr.write(|w| unsafe { w.bits(bits) });
+                        }
+                        // Settling time of CALOUT after a trimming change
+                        delay.delay_us(2000);
+                        opamp.$csr.read().calout().bit_is_set()
+                    };
+
+                    while step != 0 {
+                        if write_trim(trim) {
+                            trim += step;
+                        } else {
+                            trim -= step;
+                        }
+                        step >>= 1;
+                    }
+                    if write_trim(trim) {
+                        trim += 1;
+                    }
+
+                    otr |= trim.min(31) << shift;
+                }
+
+                if low_power {
+                    opamp.$lpotr.write(|w| unsafe { w.bits(otr) });
+                } else {
+                    opamp.$otr.write(|w| unsafe { w.bits(otr) });
+                }
+                opamp.$csr.modify(|_, w| w.calon().clear_bit());
+            }
+
+            /// Disables the opamp and returns it with the input and output pins
+            pub fn release(self) -> ($OPX, INPUTS, $vout) {
+                let opamp = unsafe { &*OPAMP::ptr() };
+                opamp.$csr.modify(|_, w| w.opaen().clear_bit());
+
+                (self.opamp, self.inputs, self.output)
+            }
+        }
+    };
+}
+
+opamp!(Opamp1: (PA0<Analog>, PA1<Analog>, PA3<Analog>, opamp1_csr, opamp1_otr, opamp1_lpotr));
+#[cfg(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+opamp!(Opamp2: (PA6<Analog>, PA7<Analog>, PB0<Analog>, opamp2_csr, opamp2_otr, opamp2_lpotr));
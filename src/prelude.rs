@@ -3,14 +3,30 @@
 pub use crate::hal::digital::v2::*;
 pub use crate::hal::prelude::*; // embedded hal traits // for some reason v2 is not exported in the ehal prelude
 
+pub use crate::capture::CaptureExt as _stm32l4_hal_CaptureExt;
+pub use crate::capture::PwmInputExt as _stm32l4_hal_PwmInputExt;
+pub use crate::comp::CompExt as _stm32l4_hal_CompExt;
 pub use crate::crc::CrcExt as _stm32l4_hal_CrcExt;
+#[cfg(not(any(
+    feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l486",
+)))]
+pub use crate::crs::CrsExt as _stm32l4_hal_CrsExt;
+#[cfg(not(any(feature = "stm32l412", feature = "stm32l422",)))]
+pub use crate::dac::DacExt as _stm32l4_hal_DacExt;
 pub use crate::dma::DmaExt as _stm32l4_hal_DmaExt;
 pub use crate::flash::FlashExt as _stm32l4_hal_FlashExt;
+pub use crate::gpio::ExtiExt as _stm32l4_hal_ExtiExt;
 pub use crate::gpio::ExtiPin as _stm32l4_hal_ExtiPin;
 pub use crate::gpio::GpioExt as _stm32l4_hal_GpioExt;
+pub use crate::one_pulse::OnePulseExt as _stm32l4_hal_OnePulseExt;
+pub use crate::opamp::OpampExt as _stm32l4_hal_OpampExt;
 pub use crate::pwm::PwmExt1 as _stm32l4_hal_PwmExt1;
 pub use crate::pwm::PwmExt2 as _stm32l4_hal_PwmExt2;
 pub use crate::pwr::PwrExt as _stm32l4_hal_PwrExt;
+pub use crate::rcc::RateChange as _stm32l4_hal_RateChange;
 pub use crate::rcc::RccExt as _stm32l4_hal_RccExt;
 pub use crate::rng::RngExt as _stm32l4_hal_RngExt;
 pub use crate::time::U32Ext as _stm32l4_hal_time_U32Ext;
@@ -1,16 +1,82 @@
 //! # Pulse Width Modulation
+//!
+//! `pwm` configures the timer and returns the channels of the pins, `pwm_timer` returns
+//! the [`PwmTimer`] as well, which changes the frequency of all channels.
+//!
+//! The duty of a channel has `get_max_duty() + 1` steps, from 0 for always inactive to
+//! `get_max_duty()` for always active. At high frequencies the timer clock divided by
+//! the frequency leaves few steps, e.g. 80 at 1 MHz from 80 MHz.
+//!
+//! The channels of TIM1, and the single channel of TIM16 and TIM17, drive a complementary
+//! output as well, with the dead time of [`PwmTimer::set_dead_time`], see
+//! [`Pwm::with_complementary`]. Their break inputs disable the outputs in hardware, see
+//! [`PwmTimer::enable_break`]. Their repetition counter spaces the update events by a
+//! number of periods, see [`PwmTimer::set_repetition`].
+//!
+//! The trigger output of one timer starts, resets or gates the counter of another one,
+//! see [`PwmTimer::set_master_mode`] and [`PwmTimer::set_slave_mode`].
+//!
+//! The counters of TIM1 and TIM2 count up and down in the center-aligned modes, so the
+//! pulses of the channels are centered in the period instead of starting together, see
+//! [`PwmTimer::set_alignment`]. The asymmetric mode shifts a pulse within the period, see
+//! [`Pwm::set_asymmetric_duty`].
+//!
+//! [`PwmTimer::dma_burst`] streams the duties from a buffer in memory at every update
+//! event, without the CPU.
 
 use core::marker::PhantomData;
 use core::mem;
+use core::sync::atomic::{self, Ordering};
 
-use crate::hal;
-use crate::stm32::{TIM1, TIM15, TIM2};
+use embedded_dma::StaticReadBuffer;
 
-use crate::gpio::gpioa::{PA0, PA1, PA10, PA11, PA15, PA2, PA3, PA8, PA9};
-use crate::gpio::gpiob::{PB10, PB11, PB14, PB3};
+use crate::dma::dma1;
+use crate::dmamux::{DmaInput, DmaMux};
+use crate::hal;
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::stm32::TIM17;
+use crate::stm32::{TIM1, TIM15, TIM16, TIM2};
+
+use crate::gpio::gpioa::{PA0, PA1, PA10, PA11, PA15, PA2, PA3, PA6, PA7, PA8, PA9};
+use crate::gpio::gpiob::{PB0, PB1, PB10, PB11, PB12, PB13, PB14, PB15, PB3, PB5, PB6, PB8};
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::gpio::gpiob::{PB4, PB7, PB9};
 use crate::gpio::Alternate;
 use crate::rcc::{Clocks, Enable, Reset, APB1R1, APB2};
-use crate::time::Hertz;
+use crate::time::{div_ceil, Hertz};
+use crate::timer::{self, MasterTimer, SlaveTimer, Timeout, Trgo2Timer};
+
+pub use crate::timer::{MasterMode, MasterMode2, SlaveMode, TriggerSource};
 
 // NB: REMAP is not implemented!
 pub trait Pins<TIM> {
@@ -101,10 +167,157 @@ pins_to_channels_mapping! {
     // TIM15: (PB14, PA3), (C1, C2), (14, 14);
     // TIM15: (PA2, PB15), (C1, C2), (14, 14);
     // TIM15: (PA2, PA3), (C1, C2), (14, 14);
+
+    // TIM16
+    TIM16: (PA6), (C1), (14);
+    TIM16: (PB8), (C1), (14);
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+pins_to_channels_mapping! {
+    // TIM17
+    TIM17: (PA7), (C1), (14);
+    TIM17: (PB9), (C1), (14);
+}
+
+/// Marks pins as the complementary outputs of a PWM channel
+pub trait ComplementaryPin<TIM, CHANNEL> {}
+
+macro_rules! complementary_pins {
+    ( $( $TIMX:ident: $CX:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> ComplementaryPin<$TIMX, $CX> for $PINX<Alternate<OTYPE, $AF>> {}
+            )+
+        )+
+    };
+}
+
+complementary_pins! {
+    TIM1: C1: (PA7, 1), (PB13, 1);
+    TIM1: C2: (PB0, 1), (PB14, 1);
+    TIM1: C3: (PB1, 1), (PB15, 1);
+
+    TIM16: C1: (PB6, 14);
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+complementary_pins! {
+    TIM17: C1: (PB7, 14);
+}
+
+/// Marks pins as the break input (BKIN) of a timer
+pub trait BreakPin<TIM> {
+    #[doc(hidden)]
+    const CONNECTED: bool = true;
+}
+
+/// Marks pins as the second break input (BKIN2) of a timer
+pub trait Break2Pin<TIM> {
+    #[doc(hidden)]
+    const CONNECTED: bool = true;
+}
+
+/// Stands in for the pin of a break input driven by the comparators only
+pub struct NoBreakPin;
+
+impl<TIM> BreakPin<TIM> for NoBreakPin {
+    const CONNECTED: bool = false;
+}
+
+impl<TIM> Break2Pin<TIM> for NoBreakPin {
+    const CONNECTED: bool = false;
+}
+
+macro_rules! break_pins {
+    ( $( $TIMX:ident: $BREAK:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> $BREAK<$TIMX> for $PINX<Alternate<OTYPE, $AF>> {}
+            )+
+        )+
+    };
+}
+
+break_pins! {
+    TIM1: BreakPin: (PA6, 1), (PB12, 1);
+    TIM1: Break2Pin: (PA11, 2);
+
+    TIM16: BreakPin: (PB5, 14);
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+break_pins! {
+    TIM17: BreakPin: (PA10, 14), (PB4, 14);
 }
 
 pub trait PwmExt1: Sized {
-    fn pwm<PINS>(self, _: PINS, frequency: Hertz, clocks: Clocks, apb: &mut APB2) -> PINS::Channels
+    fn pwm<PINS>(
+        self,
+        pins: PINS,
+        frequency: Hertz,
+        clocks: Clocks,
+        apb: &mut APB2,
+    ) -> PINS::Channels
+    where
+        PINS: Pins<Self>,
+    {
+        self.pwm_timer(pins, frequency, clocks, apb).1
+    }
+
+    /// Configures the PWM as `pwm` does, and returns the timer with the channels
+    fn pwm_timer<PINS>(
+        self,
+        _: PINS,
+        frequency: Hertz,
+        clocks: Clocks,
+        apb: &mut APB2,
+    ) -> (PwmTimer<Self>, PINS::Channels)
     where
         PINS: Pins<Self>;
 }
@@ -112,17 +325,37 @@ pub trait PwmExt1: Sized {
 pub trait PwmExt2: Sized {
     fn pwm<PINS>(
         self,
-        _: PINS,
+        pins: PINS,
         frequency: Hertz,
         clocks: Clocks,
         apb: &mut APB1R1,
     ) -> PINS::Channels
+    where
+        PINS: Pins<Self>,
+    {
+        self.pwm_timer(pins, frequency, clocks, apb).1
+    }
+
+    /// Configures the PWM as `pwm` does, and returns the timer with the channels
+    fn pwm_timer<PINS>(
+        self,
+        _: PINS,
+        frequency: Hertz,
+        clocks: Clocks,
+        apb: &mut APB1R1,
+    ) -> (PwmTimer<Self>, PINS::Channels)
     where
         PINS: Pins<Self>;
 }
 
 impl PwmExt1 for TIM1 {
-    fn pwm<PINS>(self, _pins: PINS, freq: Hertz, clocks: Clocks, apb: &mut APB2) -> PINS::Channels
+    fn pwm_timer<PINS>(
+        self,
+        _pins: PINS,
+        freq: Hertz,
+        clocks: Clocks,
+        apb: &mut APB2,
+    ) -> (PwmTimer<Self>, PINS::Channels)
     where
         PINS: Pins<Self>,
     {
@@ -131,7 +364,13 @@ impl PwmExt1 for TIM1 {
 }
 
 impl PwmExt1 for TIM15 {
-    fn pwm<PINS>(self, _pins: PINS, freq: Hertz, clocks: Clocks, apb: &mut APB2) -> PINS::Channels
+    fn pwm_timer<PINS>(
+        self,
+        _pins: PINS,
+        freq: Hertz,
+        clocks: Clocks,
+        apb: &mut APB2,
+    ) -> (PwmTimer<Self>, PINS::Channels)
     where
         PINS: Pins<Self>,
     {
@@ -139,8 +378,60 @@ impl PwmExt1 for TIM15 {
     }
 }
 
+impl PwmExt1 for TIM16 {
+    fn pwm_timer<PINS>(
+        self,
+        _pins: PINS,
+        freq: Hertz,
+        clocks: Clocks,
+        apb: &mut APB2,
+    ) -> (PwmTimer<Self>, PINS::Channels)
+    where
+        PINS: Pins<Self>,
+    {
+        tim16(self, _pins, freq, clocks, apb)
+    }
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+impl PwmExt1 for TIM17 {
+    fn pwm_timer<PINS>(
+        self,
+        _pins: PINS,
+        freq: Hertz,
+        clocks: Clocks,
+        apb: &mut APB2,
+    ) -> (PwmTimer<Self>, PINS::Channels)
+    where
+        PINS: Pins<Self>,
+    {
+        tim17(self, _pins, freq, clocks, apb)
+    }
+}
+
 impl PwmExt2 for TIM2 {
-    fn pwm<PINS>(self, _pins: PINS, freq: Hertz, clocks: Clocks, apb: &mut APB1R1) -> PINS::Channels
+    fn pwm_timer<PINS>(
+        self,
+        _pins: PINS,
+        freq: Hertz,
+        clocks: Clocks,
+        apb: &mut APB1R1,
+    ) -> (PwmTimer<Self>, PINS::Channels)
     where
         PINS: Pins<Self>,
     {
@@ -164,8 +455,298 @@ pub struct C2;
 pub struct C3;
 pub struct C4;
 
+/// Channel of a [`PwmTimer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Channel {
+    C1,
+    C2,
+    C3,
+    C4,
+}
+
+/// Output polarity of a PWM channel
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Polarity {
+    /// The active level is high
+    ActiveHigh,
+    /// The active level is low
+    ActiveLow,
+}
+
+/// PWM mode of a channel, in which part of the period the output is active
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PwmMode {
+    /// Active from the start of the period for the duty
+    Mode1,
+    /// Inactive from the start of the period for the duty, then active
+    Mode2,
+}
+
+/// Counting mode of a [`PwmTimer`], see [`PwmTimer::set_alignment`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alignment {
+    /// Counts up, the pulses start together at the start of the period
+    Edge = 0,
+    /// Counts up and down, the compare flags are set while counting down
+    Center1 = 1,
+    /// Counts up and down, the compare flags are set while counting up
+    Center2 = 2,
+    /// Counts up and down, the compare flags are set while counting up and down
+    Center3 = 3,
+}
+
+/// PWM channel with its complementary output, see [`Pwm::with_complementary`]
+///
+/// `enable` and `disable` switch the main output only, the complementary output is
+/// switched with `enable_complementary` and `disable_complementary`.
+pub struct ComplementaryPwm<TIM, CHANNEL, PIN> {
+    pwm: Pwm<TIM, CHANNEL>,
+    pin: PIN,
+}
+
+impl<TIM, CHANNEL> Pwm<TIM, CHANNEL> {
+    /// Adds the complementary output on `pin`, which is active while the main output
+    /// is inactive, apart from the dead time
+    pub fn with_complementary<PIN>(self, pin: PIN) -> ComplementaryPwm<TIM, CHANNEL, PIN>
+    where
+        PIN: ComplementaryPin<TIM, CHANNEL>,
+    {
+        ComplementaryPwm { pwm: self, pin }
+    }
+}
+
+impl<TIM, CHANNEL, PIN> ComplementaryPwm<TIM, CHANNEL, PIN> {
+    /// Returns the channel and the complementary pin
+    ///
+    /// The complementary output keeps its enable state.
+    pub fn release(self) -> (Pwm<TIM, CHANNEL>, PIN) {
+        (self.pwm, self.pin)
+    }
+}
+
+impl<TIM, CHANNEL, PIN> hal::PwmPin for ComplementaryPwm<TIM, CHANNEL, PIN>
+where
+    Pwm<TIM, CHANNEL>: hal::PwmPin,
+{
+    type Duty = <Pwm<TIM, CHANNEL> as hal::PwmPin>::Duty;
+
+    fn disable(&mut self) {
+        self.pwm.disable()
+    }
+
+    fn enable(&mut self) {
+        self.pwm.enable()
+    }
+
+    fn get_duty(&self) -> Self::Duty {
+        self.pwm.get_duty()
+    }
+
+    fn get_max_duty(&self) -> Self::Duty {
+        self.pwm.get_max_duty()
+    }
+
+    fn set_duty(&mut self, duty: Self::Duty) {
+        self.pwm.set_duty(duty)
+    }
+}
+
+/// Encodes the dead time of `ticks` of the dead-time clock into the DTG field, rounded
+/// up
+///
+/// DTG has a resolution of 1 tick up to 127 ticks, 2 ticks up to 254, 8 ticks up to 504
+/// and 16 ticks up to 1008.
+fn dead_time_generator(ticks: u32) -> Option<u8> {
+    let steps = |step: u64| div_ceil(u64::from(ticks), step) as u8;
+    match ticks {
+        0..=127 => Some(ticks as u8),
+        128..=254 => Some(0b1000_0000 | (steps(2) - 64)),
+        255..=504 => Some(0b1100_0000 | (steps(8) - 32)),
+        505..=1008 => Some(0b1110_0000 | (steps(16) - 32)),
+        _ => None,
+    }
+}
+
+/// Interrupt events of a [`PwmTimer`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// A break input disabled the outputs
+    Break,
+    /// The update event, at the end of the period, or of the repetitions of
+    /// [`PwmTimer::set_repetition`]
+    Update,
+}
+
+/// Digital filter of an input, which takes a level after `N` consecutive samples
+///
+/// The samples are taken with the timer clock (`TimerClock`) or the dead-time clock
+/// divided by a factor (`DeadTimeClock`), see [`PwmTimer::set_dead_time`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Filter {
+    /// Every level is taken at once
+    None = 0,
+    TimerClockN2 = 1,
+    TimerClockN4 = 2,
+    TimerClockN8 = 3,
+    DeadTimeClock2N6 = 4,
+    DeadTimeClock2N8 = 5,
+    DeadTimeClock4N6 = 6,
+    DeadTimeClock4N8 = 7,
+    DeadTimeClock8N6 = 8,
+    DeadTimeClock8N8 = 9,
+    DeadTimeClock16N5 = 10,
+    DeadTimeClock16N6 = 11,
+    DeadTimeClock16N8 = 12,
+    DeadTimeClock32N5 = 13,
+    DeadTimeClock32N6 = 14,
+    DeadTimeClock32N8 = 15,
+}
+
+/// Break input configuration
+///
+/// The break input is the OR of the pin and the enabled comparator outputs, see
+/// `comp::Config::inverted` for the polarity of a comparator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct BreakConfig {
+    polarity: Polarity,
+    filter: Filter,
+    comparator1: bool,
+    comparator2: bool,
+}
+
+impl BreakConfig {
+    /// Sets the level which breaks, `ActiveLow` by default
+    pub fn polarity(mut self, polarity: Polarity) -> Self {
+        self.polarity = polarity;
+        self
+    }
+
+    /// Sets the digital filter, unfiltered by default
+    ///
+    /// The filter delays the break, an unfiltered input disables the outputs
+    /// asynchronously, without any clock.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// Breaks on the output of COMP1 as well
+    pub fn comparator1(mut self, enabled: bool) -> Self {
+        self.comparator1 = enabled;
+        self
+    }
+
+    /// Breaks on the output of COMP2 as well
+    pub fn comparator2(mut self, enabled: bool) -> Self {
+        self.comparator2 = enabled;
+        self
+    }
+}
+
+impl Default for BreakConfig {
+    fn default() -> Self {
+        BreakConfig {
+            polarity: Polarity::ActiveLow,
+            filter: Filter::None,
+            comparator1: false,
+            comparator2: false,
+        }
+    }
+}
+
+/// Protection of the break and dead-time configuration against software errors, see
+/// [`PwmTimer::set_lock_level`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LockLevel {
+    /// Everything stays writable
+    Off = 0,
+    /// Locks the dead time, the break configuration, the automatic output enable and
+    /// the idle states
+    Level1 = 1,
+    /// Locks the channel polarities and the off states as well
+    Level2 = 2,
+    /// Locks the output modes of the channels as well
+    Level3 = 3,
+}
+
+/// The timer of PWM channels, which sets the frequency of all of them
+///
+/// It implements [`hal::Pwm`], whose `set_period` takes the frequency. The frequency
+/// changes glitch free at the next update event, with the duty cycles kept, see
+/// [`PwmTimer::set_period`].
+pub struct PwmTimer<TIM> {
+    clock: Hertz,
+    _tim: PhantomData<TIM>,
+}
+
+impl<TIM: MasterTimer> PwmTimer<TIM> {
+    /// Selects the output of the timer on TRGO, see [`Timer::set_master_mode`]
+    ///
+    /// [`Timer::set_master_mode`]: crate::timer::Timer::set_master_mode
+    pub fn set_master_mode(&mut self, mode: MasterMode) {
+        timer::set_master_mode::<TIM>(mode);
+    }
+}
+
+impl<TIM: Trgo2Timer> PwmTimer<TIM> {
+    /// Selects the output of the timer on TRGO2
+    pub fn set_master_mode2(&mut self, mode: MasterMode2) {
+        timer::set_master_mode2::<TIM>(mode);
+    }
+}
+
+impl<TIM: SlaveTimer> PwmTimer<TIM> {
+    /// Controls the counter with the trigger `source`, see [`Timer::set_slave_mode`]
+    ///
+    /// In [`SlaveMode::Reset`] with the TRGO of another PWM timer on
+    /// [`MasterMode::Update`], both periods start at once.
+    ///
+    /// [`Timer::set_slave_mode`]: crate::timer::Timer::set_slave_mode
+    pub fn set_slave_mode(&mut self, mode: SlaveMode, source: TriggerSource) {
+        timer::set_slave_mode::<TIM>(mode, source);
+    }
+
+    /// Delays the trigger input until TRGO, so this timer and its slaves start at once
+    pub fn set_master_slave_mode(&mut self, enabled: bool) {
+        timer::set_master_slave_mode::<TIM>(enabled);
+    }
+}
+
+/// Splits the timer clock ticks of a period into the prescaler and the auto-reload
+/// values, with the auto-reload value as large as possible for the finest duty
+///
+/// The auto-reload value stays below the largest value `max_arr` of the register, so
+/// the duty of 100 % fits the duty type.
+fn psc_arr(ticks: u32, max_arr: u32) -> (u32, u32) {
+    match timer::prescale(ticks.into(), u64::from(max_arr) - 1) {
+        Ok((psc, arr)) => (psc.into(), arr),
+        Err(timer::Error::PeriodTooShort) => panic!("PWM frequency too high"),
+        Err(timer::Error::PeriodTooLong) => panic!("PWM frequency too low"),
+    }
+}
+
+/// Splits the ticks of a period like `psc_arr`, for a counter which counts up to the
+/// auto-reload value and down again if `center` is set
+fn psc_arr_aligned(ticks: u32, max_arr: u32, center: bool) -> (u32, u32) {
+    if center {
+        // The period is twice the auto-reload value
+        let (psc, arr) = psc_arr(ticks / 2, max_arr - 1);
+        (psc, arr + 1)
+    } else {
+        psc_arr(ticks, max_arr)
+    }
+}
+
 macro_rules! advanced_timer {
-    ($($TIMX:ident: ($timX:ident, $apb:ident, $psc_width:ident, $arr_width:ident),)+) => {
+    ($($TIMX:ident: ($timX:ident, $apb:ident, $timclk:ident, $psc_width:ident, $arr_width:ident),)+) => {
         $(
             fn $timX<PINS>(
                 tim: $TIMX,
@@ -173,7 +754,7 @@ macro_rules! advanced_timer {
                 freq: Hertz,
                 clocks: Clocks,
                 apb: &mut $apb,
-            ) -> PINS::Channels
+            ) -> (PwmTimer<$TIMX>, PINS::Channels)
             where
                 PINS: Pins<$TIMX>,
             {
@@ -196,13 +777,9 @@ macro_rules! advanced_timer {
                     tim.ccmr2_output().modify(|_, w| w.oc4pe().set_bit().oc4m().bits(6));
                 }
 
-                let clk = clocks.pclk2();
-                let ticks = clk / freq;
-
-                // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
-                let psc = ticks / (1 << 16);
+                let clk = clocks.$timclk();
+                let (psc, arr) = psc_arr(clk / freq, <$arr_width>::MAX.into());
                 tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
-                let arr = ticks / (psc + 1);
                 tim.arr.write(|w| { w.arr().bits(arr as $arr_width) });
 
                 // Only for the advanced control timer
@@ -218,14 +795,26 @@ macro_rules! advanced_timer {
                         .arpe().set_bit()
                 });
 
-                unsafe { mem::MaybeUninit::uninit().assume_init() }
+                let timer = PwmTimer {
+                    clock: clk,
+                    _tim: PhantomData,
+                };
+                (timer, unsafe { mem::MaybeUninit::uninit().assume_init() })
             }
 
             pwm_channels! {
-                $TIMX:  (C1, $arr_width, cc1e, ccr1, ccr),
-                        (C2, $arr_width, cc2e, ccr2, ccr),
-                        (C3, $arr_width, cc3e, ccr3, ccr),
-                        (C4, $arr_width, cc4e, ccr4, ccr),
+                $TIMX:  (C1, $arr_width, cc1e, cc1p, ccr1, ccr, ccmr1_output, oc1m, 16),
+                        (C2, $arr_width, cc2e, cc2p, ccr2, ccr, ccmr1_output, oc2m, 24),
+                        (C3, $arr_width, cc3e, cc3p, ccr3, ccr, ccmr2_output, oc3m, 16),
+                        (C4, $arr_width, cc4e, cc4p, ccr4, ccr, ccmr2_output, oc4m, 24),
+            }
+
+            pwm_timer! {
+                $TIMX: ($psc_width, $arr_width),
+                    (C1, cc1e, ccr1, ccr),
+                    (C2, cc2e, ccr2, ccr),
+                    (C3, cc3e, ccr3, ccr),
+                    (C4, cc4e, ccr4, ccr),
             }
 
         )+
@@ -233,7 +822,7 @@ macro_rules! advanced_timer {
 }
 
 macro_rules! standard_timer {
-    ($($TIMX:ident: ($timX:ident, $apb:ident, $psc_width:ident, $arr_width:ident),)+) => {
+    ($($TIMX:ident: ($timX:ident, $apb:ident, $timclk:ident, $psc_width:ident, $arr_width:ident),)+) => {
         $(
             fn $timX<PINS>(
                 tim: $TIMX,
@@ -241,7 +830,7 @@ macro_rules! standard_timer {
                 freq: Hertz,
                 clocks: Clocks,
                 apb: &mut $apb,
-            ) -> PINS::Channels
+            ) -> (PwmTimer<$TIMX>, PINS::Channels)
             where
                 PINS: Pins<$TIMX>,
             {
@@ -264,13 +853,9 @@ macro_rules! standard_timer {
                     tim.ccmr2_output().modify(|_, w| w.oc4pe().set_bit().oc4m().bits(6));
                 }
 
-                let clk = clocks.pclk1();
-                let ticks = clk / freq;
-
-                // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
-                let psc = ticks / (1 << 16);
+                let clk = clocks.$timclk();
+                let (psc, arr) = psc_arr(clk / freq, <$arr_width>::MAX.into());
                 tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
-                let arr = ticks / (psc + 1);
                 tim.arr.write(|w| { w.arr().bits(arr as $arr_width) });
 
                 tim.cr1.write(|w| {
@@ -282,14 +867,26 @@ macro_rules! standard_timer {
                         .arpe().set_bit()
                 });
 
-                unsafe { mem::MaybeUninit::uninit().assume_init() }
+                let timer = PwmTimer {
+                    clock: clk,
+                    _tim: PhantomData,
+                };
+                (timer, unsafe { mem::MaybeUninit::uninit().assume_init() })
             }
 
             pwm_channels! {
-                $TIMX:  (C1, $arr_width, cc1e, ccr1, ccr),
-                        (C2, $arr_width, cc2e, ccr2, ccr),
-                        (C3, $arr_width, cc3e, ccr3, ccr),
-                        (C4, $arr_width, cc4e, ccr4, ccr),
+                $TIMX:  (C1, $arr_width, cc1e, cc1p, ccr1, ccr, ccmr1_output, oc1m, 16),
+                        (C2, $arr_width, cc2e, cc2p, ccr2, ccr, ccmr1_output, oc2m, 24),
+                        (C3, $arr_width, cc3e, cc3p, ccr3, ccr, ccmr2_output, oc3m, 16),
+                        (C4, $arr_width, cc4e, cc4p, ccr4, ccr, ccmr2_output, oc4m, 24),
+            }
+
+            pwm_timer! {
+                $TIMX: ($psc_width, $arr_width),
+                    (C1, cc1e, ccr1, ccr),
+                    (C2, cc2e, ccr2, ccr),
+                    (C3, cc3e, ccr3, ccr),
+                    (C4, cc4e, ccr4, ccr),
             }
 
         )+
@@ -297,7 +894,7 @@ macro_rules! standard_timer {
 }
 
 macro_rules! small_timer {
-    ($($TIMX:ident: ($timX:ident, $apb:ident, $psc_width:ident, $arr_width:ident),)+) => {
+    ($($TIMX:ident: ($timX:ident, $apb:ident, $timclk:ident, $psc_width:ident, $arr_width:ident),)+) => {
         $(
             fn $timX<PINS>(
                 tim: $TIMX,
@@ -305,7 +902,7 @@ macro_rules! small_timer {
                 freq: Hertz,
                 clocks: Clocks,
                 apb: &mut $apb,
-            ) -> PINS::Channels
+            ) -> (PwmTimer<$TIMX>, PINS::Channels)
             where
                 PINS: Pins<$TIMX>,
             {
@@ -321,13 +918,9 @@ macro_rules! small_timer {
                 //     tim.ccmr1_output().modify(|_, w| w.oc2pe().set_bit().oc2m().bits(6));
                 // }
 
-                let clk = clocks.pclk1();
-                let ticks = clk / freq;
-
-                // maybe this is all u32? also, why no `- 1` vs `timer.rs`?
-                let psc = ticks / (1 << 16);
+                let clk = clocks.$timclk();
+                let (psc, arr) = psc_arr(clk / freq, <$arr_width>::MAX.into());
                 tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
-                let arr = ticks / (psc + 1);
                 unsafe { tim.arr.write(|w| { w.arr().bits(arr as $arr_width) }); }
 
                 tim.bdtr.write(|w| w.moe().set_bit());
@@ -339,13 +932,22 @@ macro_rules! small_timer {
                         .arpe().set_bit()
                 });
 
-                unsafe { mem::MaybeUninit::uninit().assume_init() }
+                let timer = PwmTimer {
+                    clock: clk,
+                    _tim: PhantomData,
+                };
+                (timer, unsafe { mem::MaybeUninit::uninit().assume_init() })
             }
 
             pwm_channels! {
-                $TIMX:  (C1, $arr_width, cc1e, ccr1, ccr),
+                $TIMX:  (C1, $arr_width, cc1e, cc1p, ccr1, ccr, ccmr1_output, oc1m, 16),
                 // TODO: The uncommented line is awaiting PAC updates to be valid.
-                //        (C2, $arr_width, cc2e, ccr2, ccr2),
+                //        (C2, $arr_width, cc2e, cc2p, ccr2, ccr2, ccmr1_output, oc2m, 24),
+            }
+
+            pwm_timer! {
+                $TIMX: ($psc_width, $arr_width),
+                    (C1, cc1e, ccr1, ccr),
             }
 
         )+
@@ -353,8 +955,33 @@ macro_rules! small_timer {
 }
 
 macro_rules! pwm_channels {
-    ($TIMX:ident: $(($channel:ident, $arr_width:ident, $ccXe:ident, $ccrX:ident, $ccr:ident),)+) => {
+    ($TIMX:ident: $(($channel:ident, $arr_width:ident, $ccXe:ident, $ccXp:ident, $ccrX:ident, $ccr:ident, $ccmrX:ident, $ocXm:ident, $ocXm_3:literal),)+) => {
         $(
+            impl Pwm<$TIMX, $channel> {
+                /// Sets the level of the output while it is active
+                pub fn set_polarity(&mut self, polarity: Polarity) {
+                    unsafe {
+                        (*$TIMX::ptr())
+                            .ccer
+                            .modify(|_, w| w.$ccXp().bit(polarity == Polarity::ActiveLow))
+                    }
+                }
+
+                /// Sets in which part of the period the output is active
+                pub fn set_mode(&mut self, mode: PwmMode) {
+                    let bits = match mode {
+                        PwmMode::Mode1 => 6,
+                        PwmMode::Mode2 => 7,
+                    };
+                    // Clears OCxM[3] (bit 16 or 24) of the asymmetric modes
+                    unsafe {
+                        (*$TIMX::ptr())
+                            .$ccmrX()
+                            .modify(|r, w| w.bits(r.bits() & !(1 << $ocXm_3)).$ocXm().bits(bits))
+                    }
+                }
+            }
+
             impl hal::PwmPin for Pwm<$TIMX, $channel> {
                 type Duty = $arr_width;
 
@@ -373,9 +1000,10 @@ macro_rules! pwm_channels {
                     unsafe { (*$TIMX::ptr()).$ccrX.read().$ccr().bits() }
                 }
 
+                /// Returns the duty for always active, the auto-reload value plus 1
                 #[inline(always)]
                 fn get_max_duty(&self) -> Self::Duty {
-                    unsafe { (*$TIMX::ptr()).arr.read().arr().bits() }
+                    unsafe { (*$TIMX::ptr()).arr.read().arr().bits() + 1 }
                 }
 
                 #[inline(always)]
@@ -387,14 +1015,901 @@ macro_rules! pwm_channels {
     }
 }
 
+macro_rules! pwm_timer {
+    ($TIMX:ident: ($psc_width:ident, $arr_width:ident), $(($channel:ident, $ccXe:ident, $ccrX:ident, $ccr:ident),)+) => {
+        impl PwmTimer<$TIMX> {
+            /// Sets the period of all channels, from the next update event on
+            ///
+            /// `period` is a frequency or a duration. The prescaler, the auto-reload value
+            /// and the duties are preloaded, so the running period ends as it is and the
+            /// next one starts with the new values, without a glitch. The duties of the
+            /// enabled channels are scaled to keep their duty cycle, rounded down, see
+            /// [`PwmTimer::trigger_update`] to apply the change at once.
+            ///
+            /// Panics if the period is out of the range of the timer.
+            pub fn set_period<T>(&mut self, period: T)
+            where
+                T: Into<Timeout>,
+            {
+                let ticks = period.into().ticks(self.clock);
+                let steps = self.duty_steps();
+                self.reload(ticks, steps);
+            }
+
+            /// Sets the frequency of all channels, see [`PwmTimer::set_period`]
+            pub fn set_frequency(&mut self, frequency: Hertz) {
+                self.set_period(frequency);
+            }
+
+            /// Starts a new period at once, with the preloaded prescaler, auto-reload value
+            /// and duties (UG)
+            ///
+            /// The running period is cut short. The update flag, the interrupt and the DMA
+            /// request of the update event are left out for it (URS), they stay with the
+            /// ends of the periods.
+            pub fn trigger_update(&mut self) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.cr1.modify(|_, w| w.urs().set_bit());
+                tim.egr.write(|w| w.ug().set_bit());
+            }
+
+            /// Checks whether the counter counts up and down
+            fn is_center_aligned(&self) -> bool {
+                let tim = unsafe { &*$TIMX::ptr() };
+                // CMS (bits 5-6), reserved on TIM15, TIM16 and TIM17
+                tim.cr1.read().bits() & 0b11 << 5 != 0
+            }
+
+            /// Returns the steps of the duty from inactive to active in a period
+            fn duty_steps(&self) -> u64 {
+                let tim = unsafe { &*$TIMX::ptr() };
+                let arr = u64::from(tim.arr.read().arr().bits());
+                if self.is_center_aligned() {
+                    arr
+                } else {
+                    arr + 1
+                }
+            }
+
+            /// Returns the ticks of the timer clock in a period
+            fn period_ticks(&self) -> u64 {
+                let tim = unsafe { &*$TIMX::ptr() };
+                let psc = u64::from(tim.psc.read().psc().bits());
+                let arr = u64::from(tim.arr.read().arr().bits());
+                if self.is_center_aligned() {
+                    (psc + 1) * 2 * arr
+                } else {
+                    (psc + 1) * (arr + 1)
+                }
+            }
+
+            /// Preloads a period of `ticks`, and scales the duties of the enabled channels
+            /// from `steps` to the steps of the new period
+            fn reload(&mut self, ticks: u64, steps: u64) {
+                let tim = unsafe { &*$TIMX::ptr() };
+
+                assert!(ticks <= u64::from(u32::MAX), "PWM frequency too low");
+                let center = self.is_center_aligned();
+                let (psc, arr) = psc_arr_aligned(ticks as u32, <$arr_width>::MAX.into(), center);
+
+                // Keeps the auto-reload value of the running period until the update event
+                tim.cr1.modify(|_, w| w.arpe().set_bit());
+
+                // No overflow as both are at most 2^32
+                let new = if center { u64::from(arr) } else { u64::from(arr) + 1 };
+                $(
+                    if tim.ccer.read().$ccXe().bit_is_set() {
+                        let duty = u64::from(tim.$ccrX.read().$ccr().bits());
+                        // Always active stays always active
+                        let duty = if duty >= steps {
+                            u64::from(arr) + 1
+                        } else {
+                            duty * new / steps
+                        };
+                        #[allow(unused_unsafe)]
+                        unsafe { tim.$ccrX.write(|w| w.$ccr().bits(duty as $arr_width)) }
+                    }
+                )+
+
+                tim.psc.write(|w| { w.psc().bits(psc as $psc_width) });
+                #[allow(unused_unsafe)]
+                unsafe { tim.arr.write(|w| { w.arr().bits(arr as $arr_width) }); }
+            }
+        }
+
+        impl hal::Pwm for PwmTimer<$TIMX> {
+            type Channel = Channel;
+            type Time = Hertz;
+            type Duty = $arr_width;
+
+            fn disable(&mut self, channel: Channel) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                match channel {
+                    $(Channel::$channel => tim.ccer.modify(|_, w| w.$ccXe().clear_bit()),)+
+                    #[allow(unreachable_patterns)]
+                    _ => panic!("PWM channel of another timer"),
+                }
+            }
+
+            fn enable(&mut self, channel: Channel) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                match channel {
+                    $(Channel::$channel => tim.ccer.modify(|_, w| w.$ccXe().set_bit()),)+
+                    #[allow(unreachable_patterns)]
+                    _ => panic!("PWM channel of another timer"),
+                }
+            }
+
+            /// Returns the frequency, rounded down
+            ///
+            /// In the center-aligned modes, the frequency is half the frequency of the
+            /// update events.
+            fn get_period(&self) -> Hertz {
+                Hertz::from_raw((u64::from(self.clock.raw()) / self.period_ticks()) as u32)
+            }
+
+            fn get_duty(&self, channel: Channel) -> $arr_width {
+                let tim = unsafe { &*$TIMX::ptr() };
+                match channel {
+                    $(Channel::$channel => tim.$ccrX.read().$ccr().bits(),)+
+                    #[allow(unreachable_patterns)]
+                    _ => panic!("PWM channel of another timer"),
+                }
+            }
+
+            /// Returns the duty for always active, the auto-reload value plus 1
+            fn get_max_duty(&self) -> $arr_width {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.arr.read().arr().bits() + 1
+            }
+
+            fn set_duty(&mut self, channel: Channel, duty: $arr_width) {
+                unsafe {
+                    match channel {
+                        $(Channel::$channel => (*$TIMX::ptr()).$ccrX.write(|w| w.$ccr().bits(duty)),)+
+                        #[allow(unreachable_patterns)]
+                        _ => panic!("PWM channel of another timer"),
+                    }
+                }
+            }
+
+            /// Sets the frequency of all channels, from the next period on, see
+            /// [`PwmTimer::set_period`]
+            ///
+            /// The duties of the enabled channels are scaled to keep their duty cycle.
+            fn set_period<P>(&mut self, frequency: P)
+            where
+                P: Into<Hertz>,
+            {
+                PwmTimer::<$TIMX>::set_frequency(self, frequency.into());
+            }
+        }
+    }
+}
+
+macro_rules! alignment {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl PwmTimer<$TIMX> {
+                /// Sets the counting mode, and keeps the frequency and the duty cycles
+                ///
+                /// In the center-aligned modes the counter counts up to the auto-reload
+                /// value and down again, so the pulses are centered in the period, and the
+                /// edges of the channels do not coincide. The duty has half the steps of
+                /// the edge-aligned mode for a frequency.
+                ///
+                /// There are two update events per period then, at the top and at the
+                /// bottom of the count, which clock the TRGO of [`MasterMode::Update`], e.g.
+                /// to trigger the ADC, at twice the PWM frequency. On TIM1,
+                /// [`PwmTimer::set_repetition`] of 1 keeps one of them. The counter is
+                /// stopped for the change, and restarts a period at once.
+                pub fn set_alignment(&mut self, alignment: Alignment) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    let ticks = self.period_ticks();
+                    let steps = self.duty_steps();
+                    // CMS can only be switched from edge-aligned while the counter stops
+                    tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    tim.cr1
+                        .modify(|_, w| w.dir().clear_bit().cms().bits(alignment as u8));
+                    self.reload(ticks, steps);
+                    self.trigger_update();
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! asymmetric {
+    ($($TIMX:ident: $(($channel:ident, $duty:ident, $ccmrX:ident, $ccrX:ident, $ccrY:ident, $partner_doc:literal),)+)+) => {
+        $(
+            $(
+                impl Pwm<$TIMX, $channel> {
+                    /// Switches the channel to the asymmetric `mode`, with the compare value
+                    /// `up` while counting up and `down` while counting down
+                    ///
+                    /// Only the center-aligned modes count down, see
+                    /// [`PwmTimer::set_alignment`]. In [`PwmMode::Mode2`], the output is
+                    /// active from `up` counting up to `down` counting down, so the pulse
+                    /// moves within the period as they differ, e.g. for the phase shift of
+                    /// a full bridge. [`PwmMode::Mode1`] inverts the output.
+                    ///
+                    #[doc = $partner_doc]
+                    pub fn set_asymmetric_duty(&mut self, mode: PwmMode, up: $duty, down: $duty) {
+                        let tim = unsafe { &*$TIMX::ptr() };
+
+                        tim.$ccrX.write(|w| unsafe { w.bits(up.into()) });
+                        tim.$ccrY.write(|w| unsafe { w.bits(down.into()) });
+                        // OCxM (bits 4-6 and 16) of the channel, asymmetric PWM mode 1 or 2,
+                        // and of the partner (bits 12-14 and 24), PWM mode 1 or 2, with OCxPE
+                        // (bits 3 and 11) of both
+                        let bits = match mode {
+                            PwmMode::Mode1 => 0b110,
+                            PwmMode::Mode2 => 0b111,
+                        };
+                        tim.$ccmrX().modify(|r, w| unsafe {
+                            w.bits(
+                                (r.bits() & !0x0101_7070)
+                                    | 1 << 16
+                                    | bits << 4
+                                    | bits << 12
+                                    | 1 << 3
+                                    | 1 << 11,
+                            )
+                        });
+                    }
+                }
+            )+
+        )+
+    }
+}
+
+macro_rules! break_input {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl PwmTimer<$TIMX> {
+                /// Enables the break input from `pin` and the comparators of `config`
+                ///
+                /// A break disables the outputs at once, to their off state, see
+                /// [`PwmTimer::set_off_state`]. They stay disabled until they are enabled
+                /// with [`PwmTimer::enable_outputs`], or at the next update event with
+                /// [`PwmTimer::set_automatic_output_enable`] once the break input is
+                /// inactive again. The pin stays in use as the break input, [`NoBreakPin`]
+                /// stands in for an unused one.
+                pub fn enable_break<PIN>(&mut self, _pin: PIN, config: BreakConfig)
+                where
+                    PIN: BreakPin<$TIMX>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.or2.modify(|_, w| {
+                        w.bkine()
+                            .bit(PIN::CONNECTED)
+                            .bkinp()
+                            .clear_bit()
+                            .bkcmp1e()
+                            .bit(config.comparator1)
+                            .bkcmp1p()
+                            .clear_bit()
+                            .bkcmp2e()
+                            .bit(config.comparator2)
+                            .bkcmp2p()
+                            .clear_bit()
+                    });
+                    // BKF (bits 16-19) is missing from the PAC
+                    tim.bdtr.modify(|r, w| unsafe {
+                        w.bits((r.bits() & !(0b1111 << 16)) | (config.filter as u32) << 16)
+                    });
+                    tim.bdtr.modify(|_, w| {
+                        w.bkp()
+                            .bit(config.polarity == Polarity::ActiveHigh)
+                            .bke()
+                            .set_bit()
+                    });
+                }
+
+                /// Disables the break input
+                pub fn disable_break(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|_, w| w.bke().clear_bit());
+                }
+
+                /// Checks whether a break occurred, and clears the flag if `clear` is set
+                ///
+                /// The flag is set again as long as the break input is active.
+                pub fn is_break(&mut self, clear: bool) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let event = tim.sr.read().bif().bit_is_set();
+                    if event && clear {
+                        // The flags are cleared by writing 0, writing 1 has no effect
+                        tim.sr.write(|w| unsafe { w.bits(!(1 << 7)) });
+                    }
+                    event
+                }
+
+                /// Enables the dead time, break and output mode protection of `level`,
+                /// until the next reset
+                ///
+                /// The lock level can only be set once, later calls have no effect.
+                pub fn set_lock_level(&mut self, level: LockLevel) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|_, w| unsafe { w.lock().bits(level as u8) });
+                }
+
+                /// Starts listening for an interrupt event
+                pub fn listen(&mut self, event: Event) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    match event {
+                        Event::Break => tim.dier.modify(|_, w| w.bie().set_bit()),
+                        Event::Update => tim.dier.modify(|_, w| w.uie().set_bit()),
+                    }
+                }
+
+                /// Stops listening for an interrupt event
+                pub fn unlisten(&mut self, event: Event) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    match event {
+                        Event::Break => tim.dier.modify(|_, w| w.bie().clear_bit()),
+                        Event::Update => tim.dier.modify(|_, w| w.uie().clear_bit()),
+                    }
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! break2_input {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl PwmTimer<$TIMX> {
+                /// Enables the second break input from `pin` and the comparators of
+                /// `config`, like [`PwmTimer::enable_break`]
+                ///
+                /// Both break inputs raise [`Event::Break`].
+                pub fn enable_break2<PIN>(&mut self, _pin: PIN, config: BreakConfig)
+                where
+                    PIN: Break2Pin<$TIMX>,
+                {
+                    let tim = unsafe { &*$TIMX::ptr() };
+
+                    tim.or3.modify(|_, w| {
+                        w.bk2ine()
+                            .bit(PIN::CONNECTED)
+                            .bk2inp()
+                            .clear_bit()
+                            .bk2cmp1e()
+                            .bit(config.comparator1)
+                            .bk2cmp1p()
+                            .clear_bit()
+                            .bk2cmp2e()
+                            .bit(config.comparator2)
+                            .bk2cmp2p()
+                            .clear_bit()
+                    });
+                    // BK2F (bits 20-23), BK2E (bit 24) and BK2P (bit 25) are missing from
+                    // the PAC
+                    tim.bdtr.modify(|r, w| unsafe {
+                        w.bits(
+                            (r.bits() & !(0b11_1111 << 20))
+                                | (config.filter as u32) << 20
+                                | 1 << 24
+                                | ((config.polarity == Polarity::ActiveHigh) as u32) << 25,
+                        )
+                    });
+                }
+
+                /// Disables the second break input
+                pub fn disable_break2(&mut self) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.bdtr.modify(|r, w| unsafe { w.bits(r.bits() & !(1 << 24)) });
+                }
+
+                /// Checks whether a break of the second input occurred, and clears the flag
+                /// if `clear` is set
+                pub fn is_break2(&mut self, clear: bool) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    // B2IF (bit 8) is missing from the PAC
+                    let event = tim.sr.read().bits() & 1 << 8 != 0;
+                    if event && clear {
+                        tim.sr.write(|w| unsafe { w.bits(!(1 << 8)) });
+                    }
+                    event
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! repetition_counter {
+    ($($TIMX:ident,)+) => {
+        $(
+            impl PwmTimer<$TIMX> {
+                /// Raises the update event only every `repetitions + 1` periods, e.g. for
+                /// an interrupt or a DMA burst every N periods
+                ///
+                /// The repetition counter is loaded at the next update event, the duties
+                /// and the frequency are only updated at the update events as well.
+                pub fn set_repetition(&mut self, repetitions: u8) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.rcr.write(|w| unsafe { w.rep().bits(repetitions.into()) });
+                }
+
+                /// Checks whether an update event occurred, and clears the flag if `clear`
+                /// is set
+                pub fn is_update(&mut self, clear: bool) -> bool {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    let event = tim.sr.read().uif().bit_is_set();
+                    if event && clear {
+                        // The flags are cleared by writing 0, writing 1 has no effect
+                        tim.sr.write(|w| unsafe { w.bits(!1) });
+                    }
+                    event
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! complementary {
+    ($TIMX:ident: $(($channel:ident, $ccXne:ident, $ccXnp:ident, $oisX:ident, $oisXn:ident),)+) => {
+        $(
+            impl<PIN> ComplementaryPwm<$TIMX, $channel, PIN> {
+                /// Enables the complementary output
+                pub fn enable_complementary(&mut self) {
+                    unsafe { (*$TIMX::ptr()).ccer.modify(|_, w| w.$ccXne().set_bit()) }
+                }
+
+                /// Disables the complementary output
+                pub fn disable_complementary(&mut self) {
+                    unsafe { (*$TIMX::ptr()).ccer.modify(|_, w| w.$ccXne().clear_bit()) }
+                }
+
+                /// Sets the level of the main output while it is active
+                pub fn set_polarity(&mut self, polarity: Polarity) {
+                    self.pwm.set_polarity(polarity)
+                }
+
+                /// Sets the level of the complementary output while it is active
+                pub fn set_complementary_polarity(&mut self, polarity: Polarity) {
+                    unsafe {
+                        (*$TIMX::ptr())
+                            .ccer
+                            .modify(|_, w| w.$ccXnp().bit(polarity == Polarity::ActiveLow))
+                    }
+                }
+
+                /// Sets in which part of the period the main output is active
+                pub fn set_mode(&mut self, mode: PwmMode) {
+                    self.pwm.set_mode(mode)
+                }
+
+                /// Sets the levels of the main and the complementary output while the
+                /// outputs are disabled, after the dead time, e.g. by a break, see
+                /// [`PwmTimer::set_off_state`]
+                pub fn set_idle_state(&mut self, main_high: bool, complementary_high: bool) {
+                    unsafe {
+                        (*$TIMX::ptr())
+                            .cr2
+                            .modify(|_, w| w.$oisX().bit(main_high).$oisXn().bit(complementary_high))
+                    }
+                }
+            }
+        )+
+
+        impl PwmTimer<$TIMX> {
+            /// Sets the dead time between the deactivation of one output and the
+            /// activation of the other output of the complementary channels, rounded up
+            ///
+            /// The dead time is counted with the timer clock, up to 1008 ticks. A longer
+            /// dead time uses the timer clock divided by 2 or 4, which the digital input
+            /// filters use as well. Panics if the dead time is too long.
+            pub fn set_dead_time(&mut self, nanoseconds: u32) {
+                let tim = unsafe { &*$TIMX::ptr() };
+
+                let (ckd, dtg) = (0..3)
+                    .find_map(|ckd| {
+                        let clock = u64::from(self.clock.raw() >> ckd);
+                        let product = u64::from(nanoseconds) * clock;
+                        let ticks = product / 1_000_000_000;
+                        let ticks = if ticks * 1_000_000_000 < product {
+                            ticks + 1
+                        } else {
+                            ticks
+                        };
+                        dead_time_generator(ticks as u32).map(|dtg| (ckd, dtg))
+                    })
+                    .expect("PWM dead time too long");
+
+                tim.cr1.modify(|_, w| unsafe { w.ckd().bits(ckd) });
+                tim.bdtr.modify(|_, w| unsafe { w.dtg().bits(dtg) });
+            }
+
+            /// Re-enables the outputs automatically at the update event after a break,
+            /// instead of with [`PwmTimer::enable_outputs`]
+            ///
+            /// Without it, a break disables the outputs until they are enabled again.
+            pub fn set_automatic_output_enable(&mut self, automatic: bool) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.bdtr.modify(|_, w| w.aoe().bit(automatic));
+            }
+
+            /// Enables the outputs (MOE), e.g. after a break
+            pub fn enable_outputs(&mut self) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.bdtr.modify(|_, w| w.moe().set_bit());
+            }
+
+            /// Disables all outputs (MOE) at once, to their off state
+            pub fn disable_outputs(&mut self) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.bdtr.modify(|_, w| w.moe().clear_bit());
+            }
+
+            /// Checks whether the outputs are enabled, a break disables them
+            pub fn outputs_enabled(&self) -> bool {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.bdtr.read().moe().bit_is_set()
+            }
+
+            /// Sets the off state of the enabled outputs: `running` drives the inactive
+            /// level while the outputs are enabled and the channel is disabled (OSSR),
+            /// `idle` drives the idle state of [`ComplementaryPwm::set_idle_state`] while
+            /// the outputs are disabled (OSSI)
+            ///
+            /// Otherwise, the outputs float in these states.
+            pub fn set_off_state(&mut self, running: bool, idle: bool) {
+                let tim = unsafe { &*$TIMX::ptr() };
+                tim.bdtr.modify(|_, w| w.ossr().bit(running).ossi().bit(idle));
+            }
+        }
+    }
+}
+
+alignment! {
+    TIM1,
+    TIM2,
+}
+
+asymmetric! {
+    TIM1: (C1, u16, ccmr1_output, ccr1, ccr2, "`down` takes the duty register of C2, whose output keeps running in the same PWM mode."),
+          (C3, u16, ccmr2_output, ccr3, ccr4, "`down` takes the duty register of C4, whose output keeps running in the same PWM mode."),
+    TIM2: (C1, u32, ccmr1_output, ccr1, ccr2, "`down` takes the duty register of C2, whose output keeps running in the same PWM mode."),
+          (C3, u32, ccmr2_output, ccr3, ccr4, "`down` takes the duty register of C4, whose output keeps running in the same PWM mode."),
+}
+
+complementary! {
+    TIM1: (C1, cc1ne, cc1np, ois1, ois1n),
+          (C2, cc2ne, cc2np, ois2, ois2n),
+          (C3, cc3ne, cc3np, ois3, ois3n),
+}
+
+complementary! {
+    TIM16: (C1, cc1ne, cc1np, ois1, ois1n),
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+complementary! {
+    TIM17: (C1, cc1ne, cc1np, ois1, ois1n),
+}
+
+break_input! {
+    TIM1,
+    TIM16,
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+break_input! {
+    TIM17,
+}
+
+repetition_counter! {
+    TIM1,
+    TIM16,
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+repetition_counter! {
+    TIM17,
+}
+
+break2_input! {
+    TIM1,
+}
+
 advanced_timer! {
-    TIM1: (tim1, APB2, u16, u16),
+    TIM1: (tim1, APB2, timclk2, u16, u16),
 }
 
 standard_timer! {
-    TIM2: (tim2, APB1R1, u16, u32),
+    TIM2: (tim2, APB1R1, timclk1, u16, u32),
 }
 
 small_timer! {
-    TIM15: (tim15, APB2, u16, u16),
+    TIM15: (tim15, APB2, timclk2, u16, u16),
+    TIM16: (tim16, APB2, timclk2, u16, u16),
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+small_timer! {
+    TIM17: (tim17, APB2, timclk2, u16, u16),
+}
+
+/// Duty updates streamed by the DMA at every update event, see [`PwmTimer::dma_burst`]
+pub struct BurstTransfer<TIM, DMA, BUFFER> {
+    timer: PwmTimer<TIM>,
+    dma: DMA,
+    buffer: BUFFER,
+}
+
+macro_rules! dma_burst {
+    ($($TIMX:ident: ($DMA:ty, $input:ident, $duty:ident, $msize:expr, $channels:literal),)+) => {
+        $(
+            impl PwmTimer<$TIMX> {
+                /// Writes the duties of the first `channels` channels from `buffer` at
+                /// every update event, with the DMA burst of the timer
+                ///
+                /// `buffer` holds blocks of `channels` duties, from C1 on, and a new block
+                /// takes effect with each period. With `circular`, the buffer is played
+                /// out over and over again, otherwise once, and the channels keep the
+                /// duties of the last block.
+                pub fn dma_burst<BUFFER>(
+                    self,
+                    mut dma: $DMA,
+                    buffer: BUFFER,
+                    channels: u8,
+                    circular: bool,
+                ) -> BurstTransfer<$TIMX, $DMA, BUFFER>
+                where
+                    BUFFER: StaticReadBuffer<Word = $duty>,
+                {
+                    assert!(
+                        (1..=$channels).contains(&channels),
+                        "DMA burst of a missing PWM channel"
+                    );
+                    // SAFETY: the buffer is not accessed by `BurstTransfer` until it is
+                    // returned by `stop`, after the DMA stopped reading it
+                    let (ptr, len) = unsafe { buffer.read_buffer() };
+                    assert!(
+                        len % usize::from(channels) == 0 && len <= usize::from(u16::MAX),
+                        "DMA burst buffer of a partial block"
+                    );
+
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.dier.modify(|_, w| w.ude().clear_bit());
+                    // DBA in words from the start of the registers, to CCR1 at 0x34, and
+                    // DBL, the transfers per burst minus 1
+                    tim.dcr
+                        .write(|w| unsafe { w.dba().bits(0x34 / 4).dbl().bits(channels - 1) });
+
+                    dma.set_request_line(DmaInput::$input).unwrap();
+                    dma.set_peripheral_address(&tim.dmar as *const _ as u32, false);
+                    dma.set_memory_address(ptr as u32, true);
+                    dma.set_transfer_length(len as u16);
+                    dma.ccr().modify(|_, w| unsafe {
+                        w.mem2mem()
+                            .clear_bit()
+                            // 00: Low, 01: Medium, 10: High, 11: Very high
+                            .pl()
+                            .bits(0b10)
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .msize()
+                            .bits($msize)
+                            // 00: 8-bits, 01: 16-bits, 10: 32-bits, 11: Reserved
+                            .psize()
+                            .bits(0b10)
+                            // Mem -> Peripheral
+                            .dir()
+                            .set_bit()
+                            .circ()
+                            .bit(circular)
+                    });
+
+                    atomic::compiler_fence(Ordering::Release);
+
+                    dma.start();
+                    tim.dier.modify(|_, w| w.ude().set_bit());
+
+                    BurstTransfer {
+                        timer: self,
+                        dma,
+                        buffer,
+                    }
+                }
+            }
+
+            impl<BUFFER> BurstTransfer<$TIMX, $DMA, BUFFER> {
+                /// Returns `true` once a non-circular transfer has written the whole buffer
+                pub fn is_done(&self) -> bool {
+                    !self.dma.in_progress()
+                }
+
+                /// Stops the DMA and returns the parts
+                ///
+                /// The channels keep the last duties written.
+                pub fn stop(mut self) -> (PwmTimer<$TIMX>, $DMA, BUFFER) {
+                    let tim = unsafe { &*$TIMX::ptr() };
+                    tim.dier.modify(|_, w| w.ude().clear_bit());
+                    self.dma.stop();
+
+                    atomic::compiler_fence(Ordering::SeqCst);
+
+                    (self.timer, self.dma, self.buffer)
+                }
+            }
+        )+
+    }
+}
+
+dma_burst! {
+    TIM1: (dma1::C6, Tim1Up, u16, 0b01, 4),
+    TIM2: (dma1::C2, Tim2Up, u32, 0b10, 4),
+    TIM15: (dma1::C5, Tim15Up, u16, 0b01, 2),
+    TIM16: (dma1::C3, Tim16Up, u16, 0b01, 1),
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+dma_burst! {
+    TIM17: (dma1::C1, Tim17Up, u16, 0b01, 1),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{dead_time_generator, psc_arr, psc_arr_aligned};
+
+    // Ticks of the dead time encoded in the DTG field of BDTR
+    fn dead_time(dtg: u8) -> u32 {
+        let dtg = u32::from(dtg);
+        match dtg >> 5 {
+            0..=3 => dtg,
+            4 | 5 => (64 + (dtg & 0x3f)) * 2,
+            6 => (32 + (dtg & 0x1f)) * 8,
+            _ => (32 + (dtg & 0x1f)) * 16,
+        }
+    }
+
+    #[test]
+    fn dead_time_ranges() {
+        // Each range starts with the first value in its resolution and ends with the
+        // last one
+        assert_eq!(dead_time_generator(0), Some(0));
+        assert_eq!(dead_time_generator(127), Some(0x7f));
+        assert_eq!(dead_time_generator(128), Some(0x80));
+        assert_eq!(dead_time_generator(129), Some(0x81));
+        assert_eq!(dead_time_generator(254), Some(0xbf));
+        assert_eq!(dead_time_generator(255), Some(0xc0));
+        assert_eq!(dead_time_generator(504), Some(0xdf));
+        assert_eq!(dead_time_generator(505), Some(0xe0));
+        assert_eq!(dead_time_generator(1008), Some(0xff));
+    }
+
+    #[test]
+    fn dead_time_saturation() {
+        assert_eq!(dead_time_generator(1009), None);
+        assert_eq!(dead_time_generator(u32::MAX), None);
+    }
+
+    #[test]
+    fn dead_time_rounding() {
+        // Rounded up to the next step of the range
+        for ticks in 0..=1008 {
+            let step = match ticks {
+                0..=127 => 1,
+                128..=254 => 2,
+                255..=504 => 8,
+                _ => 16,
+            };
+            let encoded = dead_time(dead_time_generator(ticks).unwrap());
+            assert!(
+                encoded >= ticks && encoded - ticks < step,
+                "{} ticks",
+                ticks
+            );
+        }
+    }
+
+    #[test]
+    fn duty_fits() {
+        // The auto-reload value stays below the maximum duty
+        assert_eq!(psc_arr(2, 0xffff), (0, 1));
+        assert_eq!(psc_arr(0xffff, 0xffff), (0, 0xfffe));
+        assert_eq!(psc_arr(0x1_0000, 0xffff), (1, 0x7fff));
+        assert_eq!(psc_arr(0x1_0000, u32::MAX), (0, 0xffff));
+        assert_eq!(psc_arr(u32::MAX, u32::MAX), (0, 0xffff_fffe));
+    }
+
+    #[test]
+    fn center_aligned() {
+        // The counter counts up to the auto-reload value and down again
+        assert_eq!(psc_arr_aligned(0x1_fffc, 0xffff, true), (0, 0xfffe));
+        assert_eq!(psc_arr_aligned(0x2_0000, 0xffff, true), (1, 0x8000));
+        assert_eq!(psc_arr_aligned(0x2_0000, 0xffff, false), (2, 0xaaa9));
+    }
+
+    #[test]
+    #[should_panic(expected = "PWM frequency too high")]
+    fn frequency_too_high() {
+        psc_arr(1, 0xffff);
+    }
+
+    #[test]
+    #[should_panic(expected = "PWM frequency too low")]
+    fn frequency_too_low() {
+        psc_arr(u32::MAX, 0xffff);
+    }
 }
@@ -1,22 +1,28 @@
 //! Power management
 
+use crate::gpio::Pin;
 use crate::rcc::{Clocks, Enable, APB1R1};
-use crate::stm32::{pwr, PWR};
-use bitfield::{bitfield, BitRange};
+use crate::stm32::{pwr, FLASH, PWR};
+use bitfield::bitfield;
+use core::ptr;
 use cortex_m::peripheral::SCB;
 use fugit::RateExtU32;
 
 /// PWR error
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Power regulator con not be switched to the low-power voltage due to the system clock frequency being higher than 26MHz
     SysClkTooHighVos,
     /// System can not be switched to the low-power run mode due to the system clock frequency being higher than 2MHz
     SysClkTooHighLpr,
+    /// The voltage range can not be changed in low-power run mode
+    LowPowerRunActive,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum VosRange {
     #[doc = "High-Performance range, 1.2V, up to 80 MHz"]
     HighPerformance = 0b01,
@@ -24,6 +30,92 @@ pub enum VosRange {
     LowPower = 0b10,
 }
 
+/// Low power mode entered on `WFI`/`WFE` while `SLEEPDEEP` is set
+///
+/// The discriminants are the LPMS encodings of PWR_CR1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LowPowerMode {
+    /// Stop 0, with the main regulator on
+    Stop0 = 0b000,
+    /// Stop 1, with the low-power regulator
+    Stop1 = 0b001,
+    /// Stop 2, with most peripherals powered down
+    Stop2 = 0b010,
+    /// Standby, the device resets on wakeup
+    Standby = 0b011,
+    /// Shutdown, the device resets on wakeup and the regulator is off
+    Shutdown = 0b100,
+}
+
+/// Wakeup pin, WKUP1 to WKUP5
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WakeupPin {
+    /// WKUP1, PA0
+    Pin1 = 0,
+    /// WKUP2, PC13
+    Pin2 = 1,
+    /// WKUP3, PE6
+    Pin3 = 2,
+    /// WKUP4, PA2
+    Pin4 = 3,
+    /// WKUP5, PC5
+    Pin5 = 4,
+}
+
+/// Wakeup pin polarity
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Polarity {
+    /// Wakeup on the rising edge
+    High,
+    /// Wakeup on the falling edge
+    Low,
+}
+
+/// Pull applied to a pin in Standby and Shutdown mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum StandbyPull {
+    /// No pull
+    Floating,
+    /// Pull up
+    Up,
+    /// Pull down
+    Down,
+}
+
+/// Wakeup flags of PWR_SR1, see [`Pwr::read_wakeup_reason`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeupReason {
+    bits: u16,
+}
+
+impl WakeupReason {
+    /// Returns `true` if the wakeup pin `pin` caused a wakeup
+    pub fn wakeup_pin(&self, pin: WakeupPin) -> bool {
+        self.bits & (1 << pin as u8) != 0
+    }
+
+    /// Returns `true` if the system was reset after Standby mode
+    pub fn standby(&self) -> bool {
+        self.bits & (1 << 8) != 0
+    }
+
+    /// Returns `true` if an internal wakeup source, e.g. the RTC alarm or wakeup timer,
+    /// caused a wakeup
+    pub fn internal(&self) -> bool {
+        self.bits & (1 << 15) != 0
+    }
+
+    /// Returns `true` if no wakeup flag is set, e.g. after a power-on reset
+    pub fn is_empty(&self) -> bool {
+        self.bits & 0x811f == 0
+    }
+}
+
 bitfield! {
   pub struct WakeUpSource(u16);
   impl Debug;
@@ -43,26 +135,55 @@ pub struct Pwr {
     pub cr4: CR4,
     pub scr: SCR,
     pub sr1: SR1,
+    pub sr2: SR2,
 }
 
 impl Pwr {
     /// Configures dynamic voltage regulator range
     ///
-    /// Will panic if low-power range is selected for higher system clock
+    /// Fails if the low-power range is selected for a system clock above 26 MHz, or if
+    /// the system is in low-power run mode. Switching to the high-performance range
+    /// waits until the regulator is ready, so the clocks may be raised afterwards.
     pub fn set_power_range(&mut self, range: VosRange, clocks: &Clocks) -> Result<(), Error> {
+        if self.is_low_power_run() {
+            return Err(Error::LowPowerRunActive);
+        }
+
         match range {
-            VosRange::HighPerformance => unsafe {
-                {
+            VosRange::HighPerformance => {
+                unsafe {
                     self.cr1
                         .reg()
                         .modify(|_, w| w.vos().bits(VosRange::HighPerformance as u8))
                 }
+                // Wait until the regulator reached the new voltage
+                while self.sr2.reg().read().vosf().bit_is_set() {}
                 Ok(())
-            },
+            }
             VosRange::LowPower => {
                 if clocks.sysclk() > 26.MHz::<1, 1>() {
                     Err(Error::SysClkTooHighVos)
                 } else {
+                    // The voltage range 2 needs more flash wait states for the same clock,
+                    // RM0394 Table 9
+                    let hclk = clocks.hclk().raw();
+                    let latency = if hclk <= 6_000_000 {
+                        0b000
+                    } else if hclk <= 12_000_000 {
+                        0b001
+                    } else if hclk <= 18_000_000 {
+                        0b010
+                    } else {
+                        0b011
+                    };
+                    // NOTE(unsafe) the wait states are only ever raised here, which is
+                    // always safe, and `CFGR::freeze` sets them for the current range
+                    let acr = unsafe { &(*FLASH::ptr()).acr };
+                    if acr.read().latency().bits() < latency {
+                        acr.modify(|_, w| unsafe { w.latency().bits(latency) });
+                        while acr.read().latency().bits() != latency {}
+                    }
+
                     unsafe {
                         self.cr1
                             .reg()
@@ -74,7 +195,18 @@ impl Pwr {
         }
     }
 
+    /// Returns the current dynamic voltage regulator range
+    pub fn power_range(&mut self) -> VosRange {
+        if self.cr1.reg().read().vos().bits() == VosRange::LowPower as u8 {
+            VosRange::LowPower
+        } else {
+            VosRange::HighPerformance
+        }
+    }
+
     /// Switches the system into low power run mode
+    ///
+    /// The clocks can not be changed until [`Pwr::exit_low_power_run`] is called.
     pub fn low_power_run(&mut self, clocks: &Clocks) -> Result<(), Error> {
         if clocks.sysclk() > 2.MHz::<1, 1>() {
             Err(Error::SysClkTooHighLpr)
@@ -84,17 +216,126 @@ impl Pwr {
         }
     }
 
+    /// Switches the system back from low power run mode into run mode
+    ///
+    /// Waits until the main regulator is ready, after which the clocks may be raised.
+    pub fn exit_low_power_run(&mut self) {
+        self.cr1.reg().modify(|_, w| w.lpr().clear_bit());
+        while self.sr2.reg().read().reglpf().bit_is_set() {}
+    }
+
+    /// Returns `true` if the system is in low power run mode
+    pub fn is_low_power_run(&mut self) -> bool {
+        self.cr1.reg().read().lpr().bit_is_set()
+    }
+
+    /// Selects the mode entered by `WFI`/`WFE` while `SLEEPDEEP` is set
+    ///
+    /// This is useful for idle loops that sleep with [`cortex_m::asm::wfi`] themselves.
+    /// The `stop*` functions below select the mode on their own.
+    pub fn set_low_power_mode(&mut self, mode: LowPowerMode) {
+        unsafe { self.cr1.reg().modify(|_, w| w.lpms().bits(mode as u8)) };
+    }
+
+    /// Enters 'Stop 0' low power mode and returns after the wakeup
+    ///
+    /// The main regulator stays on, which gives the fastest wakeup. All clocks but the
+    /// LSE and LSI stop, the system resumes on the MSI or the HSI16 as selected by
+    /// STOPWUCK in RCC_CFGR, and has to be clocked up again, see [`Clocks::release`].
+    ///
+    /// Any EXTI line configured for interrupts or events wakes the system up. The wakeup
+    /// pin and internal wakeup flags of PWR_SR1 are returned, and cleared before entry.
+    pub fn stop0(&mut self, scb: &mut SCB) -> WakeupReason {
+        self.stop(LowPowerMode::Stop0, scb)
+    }
+
+    /// Enters 'Stop 1' low power mode and returns after the wakeup
+    ///
+    /// Like [`Pwr::stop0`], but with the low-power regulator. This is also the mode to
+    /// use in low-power run mode.
+    pub fn stop1(&mut self, scb: &mut SCB) -> WakeupReason {
+        self.stop(LowPowerMode::Stop1, scb)
+    }
+
+    /// Enters 'Stop 2' low power mode and returns after the wakeup
+    ///
+    /// Like [`Pwr::stop1`], but most peripherals are powered down as well, only the
+    /// LPTIM1, LPUART1, I2C3, the RTC and the comparators can wake the system up.
+    /// Stop 2 can not be entered from low-power run mode, so Stop 1 is entered instead.
+    pub fn stop2(&mut self, scb: &mut SCB) -> WakeupReason {
+        self.stop(LowPowerMode::Stop2, scb)
+    }
+
+    fn stop(&mut self, mode: LowPowerMode, scb: &mut SCB) -> WakeupReason {
+        let mode = if mode == LowPowerMode::Stop2 && self.is_low_power_run() {
+            LowPowerMode::Stop1
+        } else {
+            mode
+        };
+
+        self.clear_wakeup_flags();
+        self.set_low_power_mode(mode);
+        scb.set_sleepdeep();
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+        scb.clear_sleepdeep();
+
+        self.read_wakeup_reason()
+    }
+
+    /// Enters 'Standby' low power mode.
+    ///
+    /// The wakeup pins and the internal wakeup line set in `wkup` are enabled, configure
+    /// their polarity with [`Pwr::enable_wakeup_pin`]. The device resets on wakeup, with
+    /// only the backup domain and, if enabled, the SRAM2 contents retained.
+    ///
+    /// With `clear_flags` the wakeup flags are cleared before entry. A flag that is still
+    /// set wakes the device up again immediately, so only pass `false` if the flags were
+    /// already cleared, e.g. after evaluating them with [`Pwr::read_wakeup_reason`].
+    pub fn standby(&mut self, wkup: &WakeUpSource, clear_flags: bool, scb: &mut SCB) -> ! {
+        self.enter_reset_mode(LowPowerMode::Standby, wkup, clear_flags, scb)
+    }
+
     /// Enters 'Shutdown' low power mode.
-    pub fn shutdown(&mut self, wkup: &WakeUpSource, scb: &mut SCB) -> ! {
-        unsafe {
-            self.cr3.reg().modify(|_, w| w.bits(wkup.bit_range(0, 7)));
-        }
+    ///
+    /// See [`Pwr::standby`] for `wkup` and `clear_flags`.
+    pub fn shutdown(&mut self, wkup: &WakeUpSource, clear_flags: bool, scb: &mut SCB) -> ! {
+        self.enter_reset_mode(LowPowerMode::Shutdown, wkup, clear_flags, scb)
+    }
+
+    fn enter_reset_mode(
+        &mut self,
+        mode: LowPowerMode,
+        wkup: &WakeUpSource,
+        clear_flags: bool,
+        scb: &mut SCB,
+    ) -> ! {
+        // Only touch the EWUPx bits, CR3 also holds the pull configuration (APC) and the
+        // SRAM2 retention (RRS)
+        let ewup = u32::from(wkup.0 & 0x1f);
+        self.cr3
+            .reg()
+            .modify(|r, w| unsafe { w.bits((r.bits() & !0x1f) | ewup) });
 
         if wkup.internal_wkup() {
-            // Can't apply directly due to the APC and RPS bits
             self.cr3.reg().modify(|_, w| w.ewf().set_bit())
         }
         scb.set_sleepdeep();
+        if clear_flags {
+            self.clear_wakeup_flags();
+        }
+        self.set_low_power_mode(mode);
+        cortex_m::asm::dsb();
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Clears the wakeup pin and Standby flags of PWR_SR1
+    ///
+    /// The internal wakeup flag is cleared by clearing the flag of its source, e.g. the
+    /// RTC wakeup timer.
+    pub fn clear_wakeup_flags(&mut self) {
         self.scr.reg().write(|w| {
             w.wuf1()
                 .set_bit()
@@ -109,16 +350,78 @@ impl Pwr {
                 .sbf()
                 .set_bit()
         });
-        unsafe { self.cr1.reg().modify(|_, w| w.lpms().bits(0b111)) };
-        cortex_m::asm::dsb();
-        cortex_m::asm::wfi();
-        loop {}
     }
 
-    /// Returns the reason, why wakeup from shutdown happened. In case there is more then one,
-    /// a single random reason will be returned
-    pub fn read_wakeup_reason(&mut self) -> WakeUpSource {
-        WakeUpSource(self.sr1.reg().read().bits() as u16)
+    /// Enables the wakeup from Standby and Shutdown on the edge of `pin` to `polarity`
+    ///
+    /// The pins only have to be set to analog, or not be configured at all.
+    pub fn enable_wakeup_pin(&mut self, pin: WakeupPin, polarity: Polarity) {
+        let bit = 1 << pin as u8;
+        // The polarity has to be set first, changing it while enabled may set the flag
+        self.cr4.reg().modify(|r, w| unsafe {
+            w.bits(match polarity {
+                Polarity::High => r.bits() & !bit,
+                Polarity::Low => r.bits() | bit,
+            })
+        });
+        self.cr3
+            .reg()
+            .modify(|r, w| unsafe { w.bits(r.bits() | bit) });
+        self.scr.reg().write(|w| unsafe { w.bits(bit) });
+    }
+
+    /// Disables the wakeup from Standby and Shutdown on `pin`
+    pub fn disable_wakeup_pin(&mut self, pin: WakeupPin) {
+        let bit = 1 << pin as u8;
+        self.cr3
+            .reg()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !bit) });
+    }
+
+    /// Selects the pull applied to `pin` in Standby and Shutdown mode
+    ///
+    /// The pull is only applied once enabled with [`Pwr::enable_standby_pulls`]. Not every
+    /// pin supports both directions, e.g. PA13 and PA14 only support their debug reset
+    /// pulls; writes to unsupported directions are ignored.
+    pub fn set_standby_pull<MODE, HL, const P: char, const N: u8>(
+        &mut self,
+        _pin: &Pin<MODE, HL, P, N>,
+        pull: StandbyPull,
+    ) {
+        // PUCRx and PDCRx follow each other for every port, starting at PUCRA
+        let port = P as usize - 'A' as usize;
+        let bit = 1 << N;
+        // NOTE(unsafe) `Pwr` grants exclusive access to the PWR registers, and the
+        // offsets are within the register block for every port of the device
+        unsafe {
+            let pucr = (PWR::ptr() as *mut u32).add(0x20 / 4 + 2 * port);
+            let pdcr = pucr.add(1);
+            let (up, down) = match pull {
+                StandbyPull::Floating => (false, false),
+                StandbyPull::Up => (true, false),
+                StandbyPull::Down => (false, true),
+            };
+            for &(reg, set) in [(pucr, up), (pdcr, down)].iter() {
+                let bits = ptr::read_volatile(reg);
+                ptr::write_volatile(reg, if set { bits | bit } else { bits & !bit });
+            }
+        }
+    }
+
+    /// Enables or disables the pulls of [`Pwr::set_standby_pull`] in Standby and Shutdown mode
+    pub fn enable_standby_pulls(&mut self, enable: bool) {
+        self.cr3.reg().modify(|_, w| w.apc().bit(enable));
+    }
+
+    /// Returns the wakeup flags of PWR_SR1, which tell why the system woke up from a low
+    /// power mode
+    ///
+    /// The flags stay set until cleared with [`Pwr::clear_wakeup_flags`], also across the
+    /// reset after Standby and Shutdown.
+    pub fn read_wakeup_reason(&mut self) -> WakeupReason {
+        WakeupReason {
+            bits: self.sr1.reg().read().bits() as u16,
+        }
     }
 }
 
@@ -139,6 +442,7 @@ impl PwrExt for PWR {
             cr4: CR4 { _0: () },
             scr: SCR { _0: () },
             sr1: SR1 { _0: () },
+            sr2: SR2 { _0: () },
         }
     }
 }
@@ -215,3 +519,15 @@ impl SR1 {
         unsafe { &(*PWR::ptr()).sr1 }
     }
 }
+
+/// SR2
+pub struct SR2 {
+    _0: (),
+}
+
+impl SR2 {
+    pub(crate) fn reg(&mut self) -> &pwr::SR2 {
+        // NOTE(unsafe) this proxy grants exclusive access to this register
+        unsafe { &(*PWR::ptr()).sr2 }
+    }
+}
@@ -0,0 +1,537 @@
+//! Quadrature encoder interface
+//!
+//! The encoder mode of a timer counts the edges of the A and B signals of an encoder on
+//! channel 1 and 2, up or down depending on the order of the edges. The count wraps
+//! around at the maximum of the timer, 16 bits, or 32 bits on TIM2 and TIM5.
+//!
+//! The timers cannot reset the count on the index pulse of an encoder in hardware, but
+//! they capture the count on it instead, see [`Qei::enable_index`]. The absolute
+//! position is the count minus the captured count.
+//!
+//! LPTIM1 counts both edges of both signals in Stop modes as well, on its kernel clock.
+//!
+//! Usage example:
+//! ```ignore
+//! let a = gpioa.pa0.into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+//! let b = gpioa.pa1.into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrl);
+//! let qei = Qei::new(dp.TIM2, (a, b), qei::Config::default(), &mut rcc.apb1r1);
+//!
+//! let count: u32 = qei.count();
+//! ```
+
+use core::cell::Cell;
+
+use crate::hal::{self, Direction};
+
+pub use crate::pwm::Filter;
+
+#[cfg(any(
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+use crate::gpio::gpioa::{PA6, PA7};
+#[cfg(any(
+    // feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    // feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+use crate::gpio::gpiob::{PB0, PB4};
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::gpio::gpiob::{PB6, PB8};
+#[cfg(any(
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+use crate::gpio::gpioc::{PC6, PC7, PC8};
+#[cfg(any(
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+use crate::stm32::TIM3;
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+use crate::stm32::{TIM4, TIM5};
+
+use crate::gpio::gpioa::{PA0, PA1, PA10, PA15, PA2, PA5, PA8, PA9};
+use crate::gpio::gpiob::{PB10, PB3, PB5, PB7};
+use crate::gpio::gpioc::{PC0, PC2};
+use crate::gpio::Alternate;
+use crate::rcc::{Enable, RccBus, Reset};
+use crate::stm32::{LPTIM1, TIM1, TIM2};
+
+/// Edges which are counted
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Mode {
+    /// Both edges of the A signal, 2 counts per cycle
+    A,
+    /// Both edges of the B signal, 2 counts per cycle
+    B,
+    /// Both edges of both signals, 4 counts per cycle
+    Both,
+}
+
+/// Encoder interface configuration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Config {
+    mode: Mode,
+    invert_a: bool,
+    invert_b: bool,
+    filter: Filter,
+}
+
+impl Config {
+    /// Sets the counted edges, both edges of both signals by default
+    pub fn mode(mut self, mode: Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Inverts the A signal, which reverses the counting direction
+    pub fn invert_a(mut self, invert: bool) -> Self {
+        self.invert_a = invert;
+        self
+    }
+
+    /// Inverts the B signal, which reverses the counting direction
+    pub fn invert_b(mut self, invert: bool) -> Self {
+        self.invert_b = invert;
+        self
+    }
+
+    /// Sets the digital filter of both signals, unfiltered by default
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mode: Mode::Both,
+            invert_a: false,
+            invert_b: false,
+            filter: Filter::None,
+        }
+    }
+}
+
+/// Interrupt events of a timer encoder interface
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// The index pulse captured the count
+    Index,
+    /// The count wrapped around
+    Overflow,
+}
+
+/// Marks pins as the A signal input (channel 1)
+pub trait APin<TIM> {}
+
+/// Marks pins as the B signal input (channel 2)
+pub trait BPin<TIM> {}
+
+/// Marks pins as the index pulse input (channel 3)
+pub trait IndexPin<TIM> {}
+
+/// The A and B signal pins of an encoder interface
+pub trait Pins<TIM> {}
+
+impl<TIM, A, B> Pins<TIM> for (A, B)
+where
+    A: APin<TIM>,
+    B: BPin<TIM>,
+{
+}
+
+macro_rules! pins {
+    ( $( $TIMX:ident: $PIN:ident: $( ($PINX:ident, $AF:literal) ),+; )+ ) => {
+        $(
+            $(
+                impl<OTYPE> $PIN<$TIMX> for $PINX<Alternate<OTYPE, $AF>> {}
+            )+
+        )+
+    };
+}
+
+pins! {
+    TIM1: APin: (PA8, 1);
+    TIM1: BPin: (PA9, 1);
+    TIM1: IndexPin: (PA10, 1);
+
+    TIM2: APin: (PA0, 1), (PA5, 1), (PA15, 1);
+    TIM2: BPin: (PA1, 1), (PB3, 1);
+    TIM2: IndexPin: (PA2, 1), (PB10, 1);
+
+    LPTIM1: APin: (PB5, 1), (PC0, 1);
+    LPTIM1: BPin: (PB7, 1), (PC2, 1);
+}
+
+#[cfg(any(
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+pins! {
+    TIM3: APin: (PA6, 2), (PB4, 2), (PC6, 2);
+    TIM3: BPin: (PA7, 2), (PB5, 2), (PC7, 2);
+    TIM3: IndexPin: (PB0, 2), (PC8, 2);
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+pins! {
+    TIM4: APin: (PB6, 2);
+    TIM4: BPin: (PB7, 2);
+    TIM4: IndexPin: (PB8, 2);
+
+    TIM5: APin: (PA0, 2);
+    TIM5: BPin: (PA1, 2);
+    TIM5: IndexPin: (PA2, 2);
+}
+
+/// Timer with an encoder mode
+pub trait Instance: RccBus + Sized {
+    /// Type of the count, `u16` or `u32` for the 32-bit timers
+    type Count: Copy;
+
+    #[doc(hidden)]
+    fn configure(&self, config: &Config, apb: &mut Self::Bus);
+
+    #[doc(hidden)]
+    fn count(&self) -> Self::Count;
+
+    #[doc(hidden)]
+    fn direction(&self, last: &Cell<Direction>) -> Direction;
+}
+
+/// Quadrature encoder interface
+pub struct Qei<TIM, PINS> {
+    tim: TIM,
+    pins: PINS,
+    /// Last counting direction, which the LPTIM only signals on a change
+    direction: Cell<Direction>,
+}
+
+impl<TIM, PINS> Qei<TIM, PINS>
+where
+    TIM: Instance,
+    PINS: Pins<TIM>,
+{
+    /// Counts the edges of the A and B signals on `pins`, starting from 0
+    pub fn new(tim: TIM, pins: PINS, config: Config, apb: &mut TIM::Bus) -> Self {
+        tim.configure(&config, apb);
+        Qei {
+            tim,
+            pins,
+            direction: Cell::new(Direction::Upcounting),
+        }
+    }
+}
+
+impl<TIM, PINS> Qei<TIM, PINS>
+where
+    TIM: Instance,
+{
+    /// Returns the count
+    pub fn count(&self) -> TIM::Count {
+        self.tim.count()
+    }
+
+    /// Returns the direction of the last count
+    pub fn direction(&self) -> Direction {
+        self.tim.direction(&self.direction)
+    }
+
+    /// Releases the timer and the pins
+    pub fn release(self) -> (TIM, PINS) {
+        (self.tim, self.pins)
+    }
+}
+
+impl<TIM, PINS> hal::Qei for Qei<TIM, PINS>
+where
+    TIM: Instance,
+{
+    type Count = TIM::Count;
+
+    fn count(&self) -> Self::Count {
+        self.tim.count()
+    }
+
+    fn direction(&self) -> Direction {
+        self.tim.direction(&self.direction)
+    }
+}
+
+macro_rules! qei {
+    ($($TIMX:ident: $width:ident,)+) => {
+        $(
+            impl Instance for $TIMX {
+                type Count = $width;
+
+                fn configure(&self, config: &Config, apb: &mut Self::Bus) {
+                    <$TIMX>::enable(apb);
+                    <$TIMX>::reset(apb);
+
+                    // CC1S and CC2S (bits 0-1 and 8-9) to TI1 and TI2, IC1F and IC2F (bits
+                    // 4-7 and 12-15)
+                    let filter = config.filter as u32;
+                    self.ccmr1_input().write(|w| unsafe {
+                        w.bits(0b01 | filter << 4 | 0b01 << 8 | filter << 12)
+                    });
+                    // CC1P and CC2P (bits 1 and 5) invert TI1 and TI2
+                    self.ccer.write(|w| unsafe {
+                        w.bits((config.invert_a as u32) << 1 | (config.invert_b as u32) << 5)
+                    });
+                    // SMS (bits 0-2), encoder mode 1, 2 or 3
+                    let mode = match config.mode {
+                        Mode::A => 0b001,
+                        Mode::B => 0b010,
+                        Mode::Both => 0b011,
+                    };
+                    self.smcr.write(|w| unsafe { w.bits(mode) });
+                    self.arr.write(|w| unsafe { w.bits(<$width>::MAX as u32) });
+
+                    self.cr1.write(|w| w.cen().set_bit());
+                }
+
+                fn count(&self) -> $width {
+                    self.cnt.read().bits() as $width
+                }
+
+                fn direction(&self, _last: &Cell<Direction>) -> Direction {
+                    if self.cr1.read().dir().bit_is_set() {
+                        Direction::Downcounting
+                    } else {
+                        Direction::Upcounting
+                    }
+                }
+            }
+
+            impl<PINS> Qei<$TIMX, PINS> {
+                /// Sets the count
+                pub fn set_count(&mut self, count: $width) {
+                    self.tim.cnt.write(|w| unsafe { w.bits(count as u32) });
+                }
+
+                /// Captures the count on the rising edge of the index pulse on `pin`,
+                /// with the filter of `filter`
+                ///
+                /// The pin stays in use as the index input.
+                pub fn enable_index<PIN>(&mut self, _pin: PIN, filter: Filter)
+                where
+                    PIN: IndexPin<$TIMX>,
+                {
+                    // CC3S (bits 0-1) to TI3 and IC3F (bits 4-7)
+                    self.tim.ccmr2_input().write(|w| unsafe {
+                        w.bits(0b01 | (filter as u32) << 4)
+                    });
+                    // CC3E (bit 8)
+                    self.tim
+                        .ccer
+                        .modify(|r, w| unsafe { w.bits(r.bits() | 1 << 8) });
+                }
+
+                /// Returns the count captured on the last index pulse, if there has
+                /// been one since the last call
+                pub fn index_count(&mut self) -> Option<$width> {
+                    if self.tim.sr.read().cc3if().bit_is_set() {
+                        // Reading the captured count clears the flag
+                        Some(self.tim.ccr3.read().bits() as $width)
+                    } else {
+                        None
+                    }
+                }
+
+                /// Checks whether the count wrapped around, and clears the flag if
+                /// `clear` is set
+                pub fn is_overflow(&mut self, clear: bool) -> bool {
+                    let event = self.tim.sr.read().uif().bit_is_set();
+                    if event && clear {
+                        // The flags are cleared by writing 0, writing 1 has no effect
+                        self.tim.sr.write(|w| unsafe { w.bits(!1) });
+                    }
+                    event
+                }
+
+                /// Starts listening for an interrupt event
+                pub fn listen(&mut self, event: Event) {
+                    match event {
+                        Event::Index => self.tim.dier.modify(|_, w| w.cc3ie().set_bit()),
+                        Event::Overflow => self.tim.dier.modify(|_, w| w.uie().set_bit()),
+                    }
+                }
+
+                /// Stops listening for an interrupt event
+                pub fn unlisten(&mut self, event: Event) {
+                    match event {
+                        Event::Index => self.tim.dier.modify(|_, w| w.cc3ie().clear_bit()),
+                        Event::Overflow => self.tim.dier.modify(|_, w| w.uie().clear_bit()),
+                    }
+                }
+            }
+        )+
+    }
+}
+
+qei! {
+    TIM1: u16,
+    TIM2: u32,
+}
+
+#[cfg(any(
+    feature = "stm32l452",
+    feature = "stm32l462",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+qei! {
+    TIM3: u16,
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+qei! {
+    TIM4: u16,
+    TIM5: u32,
+}
+
+/// The LPTIM only counts both edges of both signals, and cannot invert them
+///
+/// The filter takes up to 8 samples of the kernel clock, there is no prescaled filter
+/// clock.
+impl Instance for LPTIM1 {
+    type Count = u16;
+
+    fn configure(&self, config: &Config, apb: &mut Self::Bus) {
+        assert!(
+            config.mode == Mode::Both && !config.invert_a && !config.invert_b,
+            "LPTIM encoder mode counts both signals uninverted"
+        );
+        let filter = match config.filter {
+            Filter::None => 0b00,
+            Filter::TimerClockN2 => 0b01,
+            Filter::TimerClockN4 => 0b10,
+            Filter::TimerClockN8 => 0b11,
+            _ => panic!("LPTIM encoder filter not available"),
+        };
+
+        <LPTIM1>::enable(apb);
+        <LPTIM1>::reset(apb);
+
+        // CKPOL encoder sub-mode 3 counts both edges, on the internal kernel clock
+        self.cfgr.write(|w| unsafe {
+            w.enc()
+                .set_bit()
+                .ckpol()
+                .bits(0b10)
+                .ckflt()
+                .bits(filter)
+                .cksel()
+                .clear_bit()
+        });
+
+        // ARR can only be written while the LPTIM is enabled
+        self.cr.write(|w| w.enable().set_bit());
+        self.arr.write(|w| unsafe { w.bits(0xffff) });
+        while self.isr.read().arrok().bit_is_clear() {}
+        self.icr.write(|w| w.arrokcf().set_bit());
+
+        self.cr.modify(|_, w| w.cntstrt().set_bit());
+    }
+
+    fn count(&self) -> u16 {
+        // The counter runs on the kernel clock, two equal reads are a valid count
+        loop {
+            let count = self.cnt.read().bits();
+            if self.cnt.read().bits() == count {
+                break count as u16;
+            }
+        }
+    }
+
+    fn direction(&self, last: &Cell<Direction>) -> Direction {
+        // UP and DOWN flag the changes of the direction only
+        let isr = self.isr.read();
+        self.icr.write(|w| w.upcf().set_bit().downcf().set_bit());
+        let direction = match (isr.up().bit_is_set(), isr.down().bit_is_set()) {
+            (true, false) => Direction::Upcounting,
+            (false, true) => Direction::Downcounting,
+            // Two changes since the last call end in the last direction
+            _ => last.get(),
+        };
+        last.set(direction);
+        direction
+    }
+}
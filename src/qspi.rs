@@ -66,7 +66,7 @@ macro_rules! pins {
             impl private::Sealed for $clk<Alternate<PushPull, $af>> {}
             impl ClkPin<$qspi> for $clk<Alternate<PushPull, $af>> {
                 fn set_speed(self, speed: Speed) -> Self{
-                    self.set_speed(speed)
+                    self.speed(speed)
                 }
             }
         )*
@@ -74,7 +74,7 @@ macro_rules! pins {
             impl private::Sealed for $ncs<Alternate<PushPull, $af>> {}
             impl NCSPin<$qspi> for $ncs<Alternate<PushPull, $af>> {
                 fn set_speed(self, speed: Speed) -> Self{
-                    self.set_speed(speed)
+                    self.speed(speed)
                 }
             }
         )*
@@ -82,7 +82,7 @@ macro_rules! pins {
             impl private::Sealed for $io0<Alternate<PushPull, $af>> {}
             impl IO0Pin<$qspi> for $io0<Alternate<PushPull, $af>> {
                 fn set_speed(self, speed: Speed) -> Self{
-                    self.set_speed(speed)
+                    self.speed(speed)
                 }
             }
         )*
@@ -90,7 +90,7 @@ macro_rules! pins {
             impl private::Sealed for $io1<Alternate<PushPull, $af>> {}
             impl IO1Pin<$qspi> for $io1<Alternate<PushPull, $af>> {
                 fn set_speed(self, speed: Speed) -> Self{
-                    self.set_speed(speed)
+                    self.speed(speed)
                 }
             }
         )*
@@ -98,7 +98,7 @@ macro_rules! pins {
             impl private::Sealed for $io2<Alternate<PushPull, $af>> {}
             impl IO2Pin<$qspi> for $io2<Alternate<PushPull, $af>> {
                 fn set_speed(self, speed: Speed) -> Self{
-                    self.set_speed(speed)
+                    self.speed(speed)
                 }
             }
         )*
@@ -106,7 +106,7 @@ macro_rules! pins {
             impl private::Sealed for $io3<Alternate<PushPull, $af>> {}
             impl IO3Pin<$qspi> for $io3<Alternate<PushPull, $af>> {
                 fn set_speed(self, speed: Speed) -> Self{
-                    self.set_speed(speed)
+                    self.speed(speed)
                 }
             }
         )*
@@ -114,6 +114,7 @@ macro_rules! pins {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum QspiMode {
     SingleChannel = 0b01,
@@ -122,6 +123,7 @@ pub enum QspiMode {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum AddressSize {
     Addr8Bit = 0b00,
@@ -131,18 +133,21 @@ pub enum AddressSize {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum SampleShift {
     None,
     HalfACycle,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ClockMode {
     Mode0,
     Mode3,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum QspiError {
     Busy,
     Address,
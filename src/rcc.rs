@@ -4,13 +4,19 @@ use crate::stm32::{rcc, RCC};
 use cast::u32;
 
 use crate::flash::ACR;
-use crate::pwr::Pwr;
+use crate::pwr::{Pwr, VosRange};
 use crate::time::Hertz;
 use fugit::RateExtU32;
 
 mod enable;
+mod kernel;
+mod mco;
+
+pub use kernel::{I2cClockSource, KernelClock, UsartClockSource};
+pub use mco::{Mco, McoPrescaler, McoSource};
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MsiFreq {
     #[doc = "range 0 around 100 kHz"]
     RANGE100K = 0,
@@ -39,6 +45,24 @@ pub enum MsiFreq {
 }
 
 impl MsiFreq {
+    fn from_bits(bits: u8) -> Option<Self> {
+        Some(match bits {
+            0 => Self::RANGE100K,
+            1 => Self::RANGE200K,
+            2 => Self::RANGE400K,
+            3 => Self::RANGE800K,
+            4 => Self::RANGE1M,
+            5 => Self::RANGE2M,
+            6 => Self::RANGE4M,
+            7 => Self::RANGE8M,
+            8 => Self::RANGE16M,
+            9 => Self::RANGE24M,
+            10 => Self::RANGE32M,
+            11 => Self::RANGE48M,
+            _ => return None,
+        })
+    }
+
     fn to_hertz(self) -> Hertz {
         (match self {
             Self::RANGE100K => 100_000,
@@ -77,19 +101,7 @@ impl RccExt for RCC {
             csr: CSR { _0: () },
             crrcr: CRRCR { _0: () },
             ccipr: CCIPR { _0: () },
-            cfgr: CFGR {
-                hse: None,
-                lse: None,
-                msi: None,
-                hsi48: false,
-                lsi: false,
-                hclk: None,
-                pclk1: None,
-                pclk2: None,
-                sysclk: None,
-                pll_source: None,
-                pll_config: None,
-            },
+            cfgr: CFGR::new(),
         }
     }
 }
@@ -169,6 +181,30 @@ impl CCIPR {
         // NOTE(unsafe) this proxy grants exclusive access to this register
         unsafe { &(*RCC::ptr()).ccipr }
     }
+
+    /// Selects the ADC kernel clock
+    ///
+    /// This has to be done before the ADC is created, which otherwise selects the
+    /// system clock. The selected PLLSAI output has to be enabled, see [`CFGR::pllsai1`].
+    pub fn set_adc_clock_source(&mut self, source: AdcClockSource) {
+        // NOTE(unsafe) all `AdcClockSource` values are valid for this field
+        self.ccipr()
+            .modify(|_, w| unsafe { w.adcsel().bits(source as u8) });
+    }
+}
+
+/// ADC kernel clock source
+///
+/// The discriminants are the ADCSEL encodings of RCC_CCIPR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AdcClockSource {
+    /// R output of PLLSAI1
+    PllSai1R = 0b01,
+    /// R output of PLLSAI2, only available on devices with PLLSAI2
+    PllSai2R = 0b10,
+    /// System clock
+    Sysclk = 0b11,
 }
 
 /// BDCR Backup domain control register registers
@@ -316,10 +352,68 @@ struct LseConfig {
     bypass: CrystalBypass,
     /// Clock Security System enable/disable
     css: ClockSecuritySystem,
+    /// Drive capability of the oscillator, unused in bypass mode
+    drive: LseDrive,
+}
+
+/// LSE oscillator drive capability
+///
+/// Higher drive levels start more crystals reliably but draw more current, see
+/// AN2867 for choosing the level for a given crystal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LseDrive {
+    /// Lower driving capability
+    Low = 0b00,
+    /// Medium low driving capability
+    MediumLow = 0b01,
+    /// Medium high driving capability
+    MediumHigh = 0b10,
+    /// Higher driving capability
+    High = 0b11,
 }
 
+/// RCC error
+#[non_exhaustive]
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// The LSE did not become ready in time, e.g. because the crystal is missing
+    LseTimeout,
+    /// The selected 48 MHz clock source is not enabled or does not run at 48 MHz
+    Clk48Frequency,
+    /// A PLLSAI configuration exceeds the input, VCO or output frequency limits, or uses
+    /// an output the PLLSAI does not have
+    PllSaiConfig,
+    /// The clocks exceed the limits of the voltage range 2, which are 26 MHz for the
+    /// system clock and the HSE, 24 MHz for the MSI and 128 MHz for the PLL and PLLSAI VCOs
+    VoltageRange,
+    /// The clocks can not be changed in low-power run mode
+    LowPowerRun,
+}
+
+/// Source of the 48 MHz clock for USB, RNG and SDMMC
+///
+/// The discriminants are the CLK48SEL encodings of RCC_CCIPR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Clk48Source {
+    /// 48 MHz internal clock, only available on devices with the HSI48
+    Hsi48 = 0b00,
+    /// Q output of PLLSAI1
+    PllSai1Q = 0b01,
+    /// Q output of the main PLL
+    PllQ = 0b10,
+    /// MSI, which has to run in the 48 MHz range
+    Msi = 0b11,
+}
+
+/// Maximum LSE crystal startup time from the datasheet, in milliseconds
+const LSE_STARTUP_TIME_MS: u32 = 2_000;
+
 /// Crystal bypass selector
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum CrystalBypass {
     /// If the clock driving circuitry is bypassed i.e. using an oscillator
     Enable,
@@ -330,9 +424,10 @@ pub enum CrystalBypass {
 /// Clock Security System (CSS) selector
 ///
 /// When this is enabled on HSE it will fire of the NMI interrupt on failure and for the LSE the
-/// MCU will be woken if in Standby and then the LSECSS interrupt will fire. See datasheet on how
-/// to recover for CSS failures.
+/// MCU will be woken if in Standby and then the LSECSS interrupt will fire. See
+/// [`css_interrupt_handler`] on how to recover from CSS failures.
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ClockSecuritySystem {
     /// Enable the clock security system to detect clock failures
     Enable,
@@ -340,13 +435,89 @@ pub enum ClockSecuritySystem {
     Disable,
 }
 
+/// Clock failure detected by the clock security system
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ClockFailure {
+    /// The HSE failed
+    Hse,
+    /// The LSE failed
+    Lse,
+}
+
+/// Determines and clears the cause of a clock security system event
+///
+/// Call this from the `NMI` handler for the HSE and from the `RCC` handler for the LSE,
+/// the HSE flag has to be cleared or the NMI fires again right away. Only stateless flag
+/// registers are accessed, so this is safe to call from any context.
+///
+/// After an HSE failure the HSE is switched off. If it was used as the system clock,
+/// directly or through the PLL, the system clock is switched to the MSI or the HSI16,
+/// depending on `STOPWUCK`, and the PLL is stopped if it was fed from the HSE. The
+/// prescalers are not changed, so the bus clocks drop accordingly. Recovery code can
+/// re-run [`CFGR::freeze`] once the HSE is back, or with a configuration without it.
+///
+/// After an LSE failure the LSE is no longer used as the RTC clock and the LSE
+/// oscillator has to be switched off before the RTC clock is changed, e.g. to the LSI.
+pub fn css_interrupt_handler() -> Option<ClockFailure> {
+    // NOTE(unsafe) CIFR is read-only and CICR is write 1 to clear
+    let rcc = unsafe { &*RCC::ptr() };
+    let flags = rcc.cifr.read();
+
+    if flags.cssf().bit_is_set() {
+        rcc.cicr.write(|w| w.cssc().set_bit());
+        Some(ClockFailure::Hse)
+    } else if flags.lsecssf().bit_is_set() {
+        rcc.cicr.write(|w| w.lsecssc().set_bit());
+        Some(ClockFailure::Lse)
+    } else {
+        None
+    }
+}
+
+/// Clears all clock security system flags
+pub fn clear_css_flags() {
+    // NOTE(unsafe) CICR is write 1 to clear
+    unsafe {
+        (*RCC::ptr())
+            .cicr
+            .write(|w| w.cssc().set_bit().lsecssc().set_bit())
+    };
+}
+
+/// Returns `true` if the LSE clock security system detected an LSE failure
+///
+/// Unlike the interrupt flag this stays set until the LSE CSS is disabled.
+pub fn is_lse_failure_detected() -> bool {
+    // NOTE(unsafe) atomic read with no side effects
+    unsafe { (*RCC::ptr()).bdcr.read().lsecssd().bit_is_set() }
+}
+
 const HSI: u32 = 16_000_000; // Hz
 
+/// Reads the current MSI range, `None` if the MSI is off
+fn msi_range(rcc: &rcc::RegisterBlock) -> Option<MsiFreq> {
+    let cr = rcc.cr.read();
+    if cr.msion().bit_is_set() {
+        // The MSI starts from the standby range MSISRANGE until MSIRGSEL is set
+        // MSIRGSEL is write-only in the PAC, it is bit 3
+        let range = if cr.bits() & (1 << 3) != 0 {
+            cr.msirange().bits()
+        } else {
+            rcc.csr.read().msisrange().bits()
+        };
+        MsiFreq::from_bits(range)
+    } else {
+        None
+    }
+}
+
 /// Clock configuration
 pub struct CFGR {
     hse: Option<HseConfig>,
     lse: Option<LseConfig>,
     msi: Option<MsiFreq>,
+    msi_pll: Option<bool>,
     hsi48: bool,
     lsi: bool,
     hclk: Option<u32>,
@@ -355,9 +526,34 @@ pub struct CFGR {
     sysclk: Option<u32>,
     pll_source: Option<PllSource>,
     pll_config: Option<PllConfig>,
+    mco: Option<(McoSource, McoPrescaler)>,
+    clk48: Option<Clk48Source>,
+    pllsai1: Option<PllSaiConfig>,
+    pllsai2: Option<PllSaiConfig>,
 }
 
 impl CFGR {
+    fn new() -> Self {
+        CFGR {
+            hse: None,
+            lse: None,
+            msi: None,
+            msi_pll: None,
+            hsi48: false,
+            lsi: false,
+            hclk: None,
+            pclk1: None,
+            pclk2: None,
+            sysclk: None,
+            pll_source: None,
+            pll_config: None,
+            mco: None,
+            clk48: None,
+            pllsai1: None,
+            pllsai2: None,
+        }
+    }
+
     /// Add an HSE to the system
     pub fn hse(mut self, freq: Hertz, bypass: CrystalBypass, css: ClockSecuritySystem) -> Self {
         self.hse = Some(HseConfig {
@@ -369,9 +565,22 @@ impl CFGR {
         self
     }
 
-    /// Add an 32.768 kHz LSE to the system
-    pub fn lse(mut self, bypass: CrystalBypass, css: ClockSecuritySystem) -> Self {
-        self.lse = Some(LseConfig { bypass, css });
+    /// Add an 32.768 kHz LSE to the system, using the highest drive capability
+    pub fn lse(self, bypass: CrystalBypass, css: ClockSecuritySystem) -> Self {
+        self.lse_with_drive(bypass, css, LseDrive::High)
+    }
+
+    /// Add an 32.768 kHz LSE to the system with some drive capability
+    ///
+    /// With [`CrystalBypass::Enable`] an external clock, e.g. from a TCXO, is fed into
+    /// OSC32_IN and `drive` has no effect.
+    pub fn lse_with_drive(
+        mut self,
+        bypass: CrystalBypass,
+        css: ClockSecuritySystem,
+        drive: LseDrive,
+    ) -> Self {
+        self.lse = Some(LseConfig { bypass, css, drive });
 
         self
     }
@@ -394,6 +603,30 @@ impl CFGR {
         self
     }
 
+    /// Enables or disables the automatic calibration of the MSI against the LSE (MSIPLLEN)
+    ///
+    /// By default the calibration is enabled whenever an LSE is configured. With the LSE
+    /// calibration the MSI in the 48 MHz range is accurate enough to clock the USB.
+    ///
+    /// # Panics
+    ///
+    /// [`CFGR::freeze`] panics if the calibration is enabled without an LSE.
+    pub fn msi_pll_mode(mut self, on: bool) -> Self {
+        self.msi_pll = Some(on);
+        self
+    }
+
+    /// Selects the source of the 48 MHz clock for USB, RNG and SDMMC
+    ///
+    /// If no source is selected, the MSI is used when it runs in the 48 MHz range.
+    /// [`CFGR::try_freeze`] fails with [`Error::Clk48Frequency`] if the source does not
+    /// run at 48 MHz. [`Clk48Source::PllQ`] enables the Q output of the main PLL, which
+    /// divides the VCO by 2.
+    pub fn clk48_source(mut self, source: Clk48Source) -> Self {
+        self.clk48 = Some(source);
+        self
+    }
+
     /// Sets LSI clock on (the default) or off
     pub fn lsi(mut self, on: bool) -> Self {
         self.lsi = on;
@@ -431,146 +664,104 @@ impl CFGR {
         self
     }
 
-    /// Freezes the clock configuration, making it effective
-    pub fn freeze(&self, acr: &mut ACR, pwr: &mut Pwr) -> Clocks {
-        let rcc = unsafe { &*RCC::ptr() };
-
-        // Switch to MSI to prevent problems with PLL configuration.
-        if rcc.cr.read().msion().bit_is_clear() {
-            // Turn on MSI and configure it to 4MHz.
-            rcc.cr.modify(|_, w| {
-                w.msirgsel().set_bit(); // MSI Range is provided by MSIRANGE[3:0].
-                w.msirange().range4m();
-                w.msipllen().clear_bit();
-                w.msion().set_bit()
-            });
-
-            // Wait until MSI is running
-            while rcc.cr.read().msirdy().bit_is_clear() {}
-        }
-        if rcc.cfgr.read().sws().bits() != 0 {
-            // Set MSI as a clock source, reset prescalers.
-            rcc.cfgr.reset();
-            // Wait for clock switch status bits to change.
-            while rcc.cfgr.read().sws().bits() != 0 {}
-        }
-
-        //
-        // 1. Setup clocks
-        //
-
-        // Turn on the internal 32 kHz LSI oscillator
-        let lsi_used = match (self.lsi, &self.lse) {
-            (true, _)
-            | (
-                _,
-                &Some(LseConfig {
-                    bypass: _,
-                    css: ClockSecuritySystem::Enable,
-                }),
-            ) => {
-                rcc.csr.modify(|_, w| w.lsion().set_bit());
-
-                // Wait until LSI is running
-                while rcc.csr.read().lsirdy().bit_is_clear() {}
-
-                true
-            }
-            _ => false,
-        };
-
-        if let Some(lse_cfg) = &self.lse {
-            // 1. Unlock the backup domain
-            pwr.cr1.reg().modify(|_, w| w.dbp().set_bit());
-
-            // 2. Setup the LSE
-            rcc.bdcr.modify(|_, w| {
-                w.lseon().set_bit(); // Enable LSE
-
-                if lse_cfg.bypass == CrystalBypass::Enable {
-                    w.lsebyp().set_bit();
-                } else {
-                    unsafe {
-                        w.lsedrv().bits(0b11);
-                    } // Max drive strength, TODO: should probably be settable
-                }
-
-                w
-            });
-
-            // Wait until LSE is running
-            while rcc.bdcr.read().lserdy().bit_is_clear() {}
-
-            // Setup CSS
-            if lse_cfg.css == ClockSecuritySystem::Enable {
-                // Enable CSS and interrupt
-                rcc.bdcr.modify(|_, w| w.lsecsson().set_bit());
-                rcc.cier.modify(|_, w| w.lsecssie().set_bit());
-            }
-        }
-
-        // If HSE is available, set it up
-        if let Some(hse_cfg) = &self.hse {
-            rcc.cr.write(|w| {
-                w.hseon().set_bit();
-
-                if hse_cfg.bypass == CrystalBypass::Enable {
-                    w.hsebyp().set_bit();
-                }
+    /// Configures PLLSAI1, e.g. as the ADC or SAI clock
+    ///
+    /// PLLSAI1 shares the source and the input divider of the main PLL. When the main
+    /// PLL is not used, the input divider is chosen to keep the VCO input at or below
+    /// 16 MHz.
+    pub fn pllsai1(mut self, config: PllSaiConfig) -> Self {
+        self.pllsai1 = Some(config);
+        self
+    }
 
-                w
-            });
+    /// Configures PLLSAI2, which has no Q output except on the STM32L4+
+    ///
+    /// PLLSAI2 shares the source and the input divider of the main PLL, see
+    /// [`CFGR::pllsai1`].
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    pub fn pllsai2(mut self, config: PllSaiConfig) -> Self {
+        self.pllsai2 = Some(config);
+        self
+    }
 
-            while rcc.cr.read().hserdy().bit_is_clear() {}
+    /// Routes `source` divided by `prescaler` to the microcontroller clock output
+    ///
+    /// See [`Mco`] for taking the PA8 output pin.
+    pub fn mco(mut self, source: McoSource, prescaler: McoPrescaler) -> Self {
+        self.mco = Some((source, prescaler));
+        self
+    }
 
-            // Setup CSS
-            if hse_cfg.css == ClockSecuritySystem::Enable {
-                // Enable CSS
-                rcc.cr.modify(|_, w| w.csson().set_bit());
-            }
+    /// Freezes the clock configuration, making it effective
+    ///
+    /// # Panics
+    ///
+    /// Panics if a configured LSE does not start, if the 48 MHz clock source or a
+    /// PLLSAI configuration is invalid, or if the clocks are not allowed in the current
+    /// power mode, see [`CFGR::try_freeze`].
+    pub fn freeze(&self, acr: &mut ACR, pwr: &mut Pwr) -> Clocks {
+        match self.try_freeze(acr, pwr) {
+            Ok(clocks) => clocks,
+            Err(Error::LseTimeout) => panic!("LSE did not start"),
+            Err(Error::Clk48Frequency) => panic!("48 MHz clock source is not at 48 MHz"),
+            Err(Error::PllSaiConfig) => panic!("Invalid PLLSAI configuration"),
+            Err(Error::VoltageRange) => panic!("Clocks too fast for the voltage range 2"),
+            Err(Error::LowPowerRun) => panic!("Clocks can not be changed in low-power run mode"),
         }
+    }
 
-        if let Some(msi) = self.msi {
-            unsafe {
-                rcc.cr.modify(|_, w| {
-                    w.msirange()
-                        .bits(msi as u8)
-                        .msirgsel()
-                        .set_bit()
-                        .msion()
-                        .set_bit();
-
-                    // If LSE is enabled, enable calibration of MSI
-                    if self.lse.is_some() {
-                        w.msipllen().set_bit();
-                    }
-
-                    w
-                })
-            };
+    /// Freezes the clock configuration, making it effective
+    ///
+    /// Returns [`Error::LseTimeout`] instead of waiting forever when a configured LSE
+    /// does not start, [`Error::Clk48Frequency`] for an invalid 48 MHz clock source and
+    /// [`Error::PllSaiConfig`] for an invalid PLLSAI configuration.
+    ///
+    /// The clocks are checked against the current voltage range, see
+    /// [`Pwr::set_power_range`], and fail with [`Error::VoltageRange`] if they are too fast
+    /// for the range 2. In low-power run mode the clocks can not be changed at all and
+    /// [`Error::LowPowerRun`] is returned.
+    ///
+    /// The configuration is checked before any clock is changed, so these errors leave the
+    /// clocks as they were. Only [`Error::LseTimeout`] is detected while switching, the
+    /// system clock then runs from the MSI with the prescalers reset.
+    ///
+    /// This may also be called again at runtime to switch to another configuration,
+    /// see [`Clocks::release`].
+    pub fn try_freeze(&self, acr: &mut ACR, pwr: &mut Pwr) -> Result<Clocks, Error> {
+        let rcc = unsafe { &*RCC::ptr() };
 
-            // Wait until MSI is running
-            while rcc.cr.read().msirdy().bit_is_clear() {}
+        if pwr.is_low_power_run() {
+            return Err(Error::LowPowerRun);
         }
-
-        // Turn on USB, RNG Clock using the HSI48 CLK source
-        if self.hsi48 {
-            // p. 180 in ref-manual
-            rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
-
-            // Wait until HSI48 is running
-            while rcc.crrcr.read().hsi48rdy().bit_is_clear() {}
+        if self.msi.is_some() && self.msi_pll == Some(true) {
+            assert!(
+                self.lse.is_some(),
+                "MSI PLL mode selected, but LSE not enabled"
+            );
         }
 
-        // Select MSI as clock source for usb48, rng ...
-        if let Some(MsiFreq::RANGE48M) = self.msi {
-            unsafe { rcc.ccipr.modify(|_, w| w.clk48sel().bits(0b11)) };
+        // Select the clock source for usb48, rng ...
+        let clk48 = match (self.clk48, self.msi) {
+            (Some(source), _) => Some(source),
+            (None, Some(MsiFreq::RANGE48M)) => Some(Clk48Source::Msi),
+            (None, _) => None,
+        };
+        match clk48 {
+            Some(Clk48Source::Hsi48) if !self.hsi48 => return Err(Error::Clk48Frequency),
+            Some(Clk48Source::Msi) if self.msi != Some(MsiFreq::RANGE48M) => {
+                return Err(Error::Clk48Frequency)
+            }
+            _ => {}
         }
 
-        //
-        // 2. Setup PLL
-        //
-
         // Select PLL source
         let (clock_speed, pll_source) = if let Some(source) = self.pll_source {
             match source {
@@ -607,12 +798,6 @@ impl CFGR {
             }
         };
 
-        // Check if HSI should be started
-        if pll_source == PllSource::HSI16 || (self.msi.is_none() && self.hse.is_none()) {
-            rcc.cr.write(|w| w.hsion().set_bit());
-            while rcc.cr.read().hsirdy().bit_is_clear() {}
-        }
-
         let pllconf = if self.pll_config.is_none() {
             if let Some(sysclk) = self.sysclk {
                 // Calculate PLL multiplier and create a best effort pll config, just multiply n
@@ -699,25 +884,6 @@ impl CFGR {
 
         assert!(pclk2 <= sysclk);
 
-        // adjust flash wait states
-        unsafe {
-            acr.acr().write(|w| {
-                w.latency().bits(if hclk <= 16_000_000 {
-                    0b000
-                } else if hclk <= 32_000_000 {
-                    0b001
-                } else if hclk <= 48_000_000 {
-                    0b010
-                } else if hclk <= 64_000_000 {
-                    0b011
-                } else {
-                    0b100
-                })
-            })
-        }
-
-        let sysclk_src_bits;
-        let mut msi = self.msi;
         if let Some(pllconf) = pllconf {
             // Sanity-checks per RM0394, 6.4.4 PLL configuration register (RCC_PLLCFGR)
             let r = pllconf.r.to_division_factor();
@@ -734,6 +900,236 @@ impl CFGR {
             assert!(vco <= 334_000_000); // VCO output max
             assert!(output_clock <= 80_000_000); // Max output clock
 
+            if clk48 == Some(Clk48Source::PllQ) && vco / 2 != 48_000_000 {
+                return Err(Error::Clk48Frequency);
+            }
+        } else if clk48 == Some(Clk48Source::PllQ) {
+            return Err(Error::Clk48Frequency);
+        }
+
+        // The PLLSAIs share the source and input divider of the main PLL. If the main PLL
+        // is unused, the divider is free.
+        let pllsai_m = match pllconf {
+            Some(pllconf) => pllconf.m,
+            None => ((clock_speed - 1) / 16_000_000) as u8,
+        };
+        let pllsai_input = clock_speed / (pllsai_m as u32 + 1);
+
+        let pllsai1 = match self.pllsai1 {
+            Some(config) => config.outputs(pllsai_input)?,
+            None => PllSaiClocks::default(),
+        };
+        #[allow(unused_mut)] // only assigned on devices with PLLSAI2
+        let mut pllsai2 = PllSaiClocks::default();
+        #[cfg(any(
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486",
+            feature = "stm32l496",
+            feature = "stm32l4a6",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        ))]
+        if let Some(config) = self.pllsai2 {
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9")))]
+            if config.q.is_some() {
+                return Err(Error::PllSaiConfig);
+            }
+            pllsai2 = config.outputs(pllsai_input)?;
+        }
+
+        if clk48 == Some(Clk48Source::PllSai1Q) && pllsai1.q != Some(48.MHz()) {
+            return Err(Error::Clk48Frequency);
+        }
+
+        let range2 = pwr.power_range() == VosRange::LowPower;
+        if range2 {
+            let msi_too_fast =
+                matches!(self.msi, Some(MsiFreq::RANGE32M) | Some(MsiFreq::RANGE48M));
+            let hse_too_fast = matches!(&self.hse, Some(hse) if hse.speed > 26_000_000);
+            let pll_vco = pllconf.map_or(0, |pllconf| {
+                clock_speed / (pllconf.m as u32 + 1) * pllconf.n as u32
+            });
+            let pllsai_vco = self
+                .pllsai1
+                .iter()
+                .chain(self.pllsai2.iter())
+                .map(|config| pllsai_input * config.n as u32)
+                .max()
+                .unwrap_or(0);
+            if sysclk > 26_000_000
+                || msi_too_fast
+                || hse_too_fast
+                || pll_vco > 128_000_000
+                || pllsai_vco > 128_000_000
+            {
+                return Err(Error::VoltageRange);
+            }
+        }
+
+        // Everything is checked, the clocks are only changed from here on
+
+        // The flash wait states have to be raised before the clock frequency is, and may
+        // only be lowered after. Use the maximum while switching, they are adjusted to
+        // the final clocks below.
+        unsafe { acr.acr().modify(|_, w| w.latency().bits(0b100)) };
+        while acr.acr().read().latency().bits() != 0b100 {}
+
+        // Switch to MSI to prevent problems with PLL configuration.
+        if rcc.cr.read().msion().bit_is_clear() {
+            // Turn on MSI and configure it to 4MHz.
+            rcc.cr.modify(|_, w| {
+                w.msirgsel().set_bit(); // MSI Range is provided by MSIRANGE[3:0].
+                w.msirange().range4m();
+                w.msipllen().clear_bit();
+                w.msion().set_bit()
+            });
+
+            // Wait until MSI is running
+            while rcc.cr.read().msirdy().bit_is_clear() {}
+        }
+        if rcc.cfgr.read().sws().bits() != 0 {
+            // Set MSI as a clock source, reset prescalers.
+            rcc.cfgr.reset();
+            // Wait for clock switch status bits to change.
+            while rcc.cfgr.read().sws().bits() != 0 {}
+        }
+
+        //
+        // 1. Setup clocks
+        //
+
+        // Turn on the internal 32 kHz LSI oscillator
+        let lsi_used = match (self.lsi, &self.lse) {
+            (true, _)
+            | (
+                _,
+                &Some(LseConfig {
+                    css: ClockSecuritySystem::Enable,
+                    ..
+                }),
+            ) => {
+                rcc.csr.modify(|_, w| w.lsion().set_bit());
+
+                // Wait until LSI is running
+                while rcc.csr.read().lsirdy().bit_is_clear() {}
+
+                true
+            }
+            _ => false,
+        };
+
+        if let Some(lse_cfg) = &self.lse {
+            // 1. Unlock the backup domain
+            pwr.cr1.reg().modify(|_, w| w.dbp().set_bit());
+
+            // 2. Setup the LSE, bypass and drive have to be set before it is enabled
+            if rcc.bdcr.read().lseon().bit_is_clear() {
+                rcc.bdcr.modify(|_, w| {
+                    if lse_cfg.bypass == CrystalBypass::Enable {
+                        w.lsebyp().set_bit();
+                    } else {
+                        w.lsebyp().clear_bit();
+                    }
+
+                    unsafe { w.lsedrv().bits(lse_cfg.drive as u8) }
+                });
+            } else {
+                // The LSE survived a reset in the backup domain, only the drive
+                // capability may still be changed
+                rcc.bdcr
+                    .modify(|_, w| unsafe { w.lsedrv().bits(lse_cfg.drive as u8) });
+            }
+            rcc.bdcr.modify(|_, w| w.lseon().set_bit()); // Enable LSE
+
+            // Wait until LSE is running, polling once per millisecond at the current MSI
+            // frequency. The delay takes at least the given cycles, so this does not give up
+            // before the startup time has passed.
+            let ms_cycles = msi_range(rcc).unwrap_or(MsiFreq::RANGE48M).to_hertz().raw() / 1_000;
+            let mut elapsed_ms = 0;
+            while rcc.bdcr.read().lserdy().bit_is_clear() {
+                if elapsed_ms == LSE_STARTUP_TIME_MS {
+                    rcc.bdcr.modify(|_, w| w.lseon().clear_bit());
+                    return Err(Error::LseTimeout);
+                }
+                cortex_m::asm::delay(ms_cycles);
+                elapsed_ms += 1;
+            }
+
+            // Setup CSS
+            if lse_cfg.css == ClockSecuritySystem::Enable {
+                // Enable CSS and interrupt
+                rcc.bdcr.modify(|_, w| w.lsecsson().set_bit());
+                rcc.cier.modify(|_, w| w.lsecssie().set_bit());
+            }
+        }
+
+        // If HSE is available, set it up
+        if let Some(hse_cfg) = &self.hse {
+            // The bypass can only be changed while the HSE is off
+            if rcc.cr.read().hseon().bit_is_clear() {
+                rcc.cr
+                    .modify(|_, w| w.hsebyp().bit(hse_cfg.bypass == CrystalBypass::Enable));
+            }
+            rcc.cr.modify(|_, w| w.hseon().set_bit());
+
+            while rcc.cr.read().hserdy().bit_is_clear() {}
+
+            // Setup CSS
+            if hse_cfg.css == ClockSecuritySystem::Enable {
+                // Enable CSS
+                rcc.cr.modify(|_, w| w.csson().set_bit());
+            }
+        }
+
+        if let Some(msi) = self.msi {
+            // If LSE is enabled, enable calibration of MSI
+            let msi_pll = self.msi_pll.unwrap_or(self.lse.is_some());
+
+            // The range may only be changed while the MSI is off or ready
+            while rcc.cr.read().msirdy().bit_is_clear() {}
+
+            unsafe {
+                rcc.cr.modify(|_, w| {
+                    w.msirange()
+                        .bits(msi as u8)
+                        .msirgsel()
+                        .set_bit()
+                        .msion()
+                        .set_bit()
+                        .msipllen()
+                        .bit(msi_pll)
+                })
+            };
+
+            // Wait until MSI is running
+            while rcc.cr.read().msirdy().bit_is_clear() {}
+        }
+
+        // Turn on USB, RNG Clock using the HSI48 CLK source
+        if self.hsi48 {
+            // p. 180 in ref-manual
+            rcc.crrcr.modify(|_, w| w.hsi48on().set_bit());
+
+            // Wait until HSI48 is running
+            while rcc.crrcr.read().hsi48rdy().bit_is_clear() {}
+        }
+
+        //
+        // 2. Setup PLL
+        //
+
+        // Check if HSI should be started
+        if pll_source == PllSource::HSI16 || (self.msi.is_none() && self.hse.is_none()) {
+            rcc.cr.modify(|_, w| w.hsion().set_bit());
+            while rcc.cr.read().hsirdy().bit_is_clear() {}
+        }
+
+        let sysclk_src_bits;
+        let mut msi = self.msi;
+        if let Some(pllconf) = pllconf {
+            let pllq_used = clk48 == Some(Clk48Source::PllQ);
+
             // use PLL as source
             sysclk_src_bits = 0b11;
             rcc.cr.modify(|_, w| w.pllon().clear_bit());
@@ -756,7 +1152,13 @@ impl CFGR {
 
             while rcc.cr.read().pllrdy().bit_is_clear() {}
 
-            rcc.pllcfgr.modify(|_, w| w.pllren().set_bit());
+            rcc.pllcfgr.modify(|_, w| {
+                w.pllren().set_bit();
+                if pllq_used {
+                    unsafe { w.pllq().bits(0b00) }.pllqen().set_bit();
+                }
+                w
+            });
 
             // SW: PLL selected as system clock
             rcc.cfgr.modify(|_, w| unsafe {
@@ -791,8 +1193,116 @@ impl CFGR {
 
         while rcc.cfgr.read().sws().bits() != sysclk_src_bits {}
 
+        // adjust flash wait states, now that the final clocks are running
+        unsafe {
+            acr.acr().modify(|_, w| {
+                w.latency().bits(if range2 {
+                    // RM0394 Table 9, voltage range 2
+                    if hclk <= 6_000_000 {
+                        0b000
+                    } else if hclk <= 12_000_000 {
+                        0b001
+                    } else if hclk <= 18_000_000 {
+                        0b010
+                    } else {
+                        0b011
+                    }
+                } else if hclk <= 16_000_000 {
+                    0b000
+                } else if hclk <= 32_000_000 {
+                    0b001
+                } else if hclk <= 48_000_000 {
+                    0b010
+                } else if hclk <= 64_000_000 {
+                    0b011
+                } else {
+                    0b100
+                })
+            })
+        }
+
         //
-        // 3. Shutdown unused clocks that have auto-started
+        // 3. Setup PLLSAI
+        //
+
+        if self.pllsai1.is_some() || self.pllsai2.is_some() {
+            let m = pllsai_m;
+            if pllconf.is_none() {
+                rcc.cr.modify(|_, w| w.pllon().clear_bit());
+                while rcc.cr.read().pllrdy().bit_is_set() {}
+                rcc.pllcfgr.modify(|_, w| unsafe {
+                    w.pllsrc().bits(pll_source.to_pllsrc()).pllm().bits(m)
+                });
+            }
+
+            if let Some(config) = self.pllsai1 {
+                rcc.cr.modify(|_, w| w.pllsai1on().clear_bit());
+                while rcc.cr.read().pllsai1rdy().bit_is_set() {}
+
+                rcc.pllsai1cfgr.write(|w| unsafe {
+                    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+                    w.pllsai1m().bits(m);
+                    w.pllsai1n().bits(config.n);
+                    if let Some(p) = config.p {
+                        w.pllsai1p()
+                            .bit(p == PllSaiPDivider::Div17)
+                            .pllsai1pen()
+                            .set_bit();
+                    }
+                    if let Some(q) = config.q {
+                        w.pllsai1q().bits(q.to_bits()).pllsai1qen().set_bit();
+                    }
+                    if let Some(r) = config.r {
+                        w.pllsai1r().bits(r.to_bits()).pllsai1ren().set_bit();
+                    }
+                    w
+                });
+
+                rcc.cr.modify(|_, w| w.pllsai1on().set_bit());
+                while rcc.cr.read().pllsai1rdy().bit_is_clear() {}
+            }
+
+            #[cfg(any(
+                feature = "stm32l475",
+                feature = "stm32l476",
+                feature = "stm32l486",
+                feature = "stm32l496",
+                feature = "stm32l4a6",
+                feature = "stm32l4r9",
+                feature = "stm32l4s9",
+            ))]
+            if let Some(config) = self.pllsai2 {
+                rcc.cr.modify(|_, w| w.pllsai2on().clear_bit());
+                while rcc.cr.read().pllsai2rdy().bit_is_set() {}
+
+                rcc.pllsai2cfgr.write(|w| unsafe {
+                    #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+                    {
+                        w.pllsai2m().bits(m);
+                        if let Some(q) = config.q {
+                            w.pllsai2q().bits(q.to_bits()).pllsai2qen().set_bit();
+                        }
+                    }
+                    w.pllsai2n().bits(config.n);
+                    if let Some(p) = config.p {
+                        w.pllsai2p()
+                            .bit(p == PllSaiPDivider::Div17)
+                            .pllsai2pen()
+                            .set_bit();
+                    }
+                    if let Some(r) = config.r {
+                        w.pllsai2r().bits(r.to_bits()).pllsai2ren().set_bit();
+                    }
+                    w
+                });
+
+                rcc.cr.modify(|_, w| w.pllsai2on().set_bit());
+                while rcc.cr.read().pllsai2rdy().bit_is_clear() {}
+            }
+        }
+
+        //
+        // 4. Shutdown unused clocks that have auto-started
         //
 
         // MSI always starts on reset
@@ -801,11 +1311,45 @@ impl CFGR {
                 .modify(|_, w| w.msion().clear_bit().msipllen().clear_bit())
         }
 
+        // The other clocks can only be running from an earlier configuration
+        if pllconf.is_none() {
+            rcc.cr.modify(|_, w| w.pllon().clear_bit());
+        }
+        if self.pllsai1.is_none() {
+            rcc.cr.modify(|_, w| w.pllsai1on().clear_bit());
+        }
+        #[cfg(any(
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486",
+            feature = "stm32l496",
+            feature = "stm32l4a6",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        ))]
+        if self.pllsai2.is_none() {
+            rcc.cr.modify(|_, w| w.pllsai2on().clear_bit());
+        }
+        if self.hse.is_none() {
+            rcc.cr.modify(|_, w| w.hseon().clear_bit());
+        }
+        if !self.hsi48 {
+            rcc.crrcr.modify(|_, w| w.hsi48on().clear_bit());
+        }
+
+        if let Some(source) = clk48 {
+            unsafe { rcc.ccipr.modify(|_, w| w.clk48sel().bits(source as u8)) };
+        }
+
+        if let Some((source, prescaler)) = self.mco {
+            mco::configure(source, prescaler);
+        }
+
         //
-        // 4. Clock setup done!
+        // 5. Clock setup done!
         //
 
-        Clocks {
+        Ok(Clocks {
             hclk: hclk.Hz(),
             lsi: lsi_used,
             lse: self.lse.is_some(),
@@ -819,11 +1363,17 @@ impl CFGR {
             timclk1: timclk1.Hz(),
             timclk2: timclk2.Hz(),
             pll_source: pllconf.map(|_| pll_source),
-        }
+            clk48,
+            // Checked to be at 48 MHz above
+            pll48clk: clk48.map(|_| 48.MHz()),
+            pllsai1,
+            pllsai2,
+        })
     }
 }
 
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// PLL output divider options
 pub enum PllDivider {
     /// Divider PLL output by 2
@@ -879,7 +1429,115 @@ impl PllConfig {
     }
 }
 
+/// PLLSAI P output divider options
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum PllSaiPDivider {
+    /// Divide the VCO output by 7
+    Div7,
+    /// Divide the VCO output by 17
+    Div17,
+}
+
+impl PllSaiPDivider {
+    #[inline(always)]
+    fn to_division_factor(self) -> u32 {
+        match self {
+            Self::Div7 => 7,
+            Self::Div17 => 17,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+/// PLLSAI1 / PLLSAI2 configuration
+///
+/// Output = ((SourceClk / input_divider) * multiplier) / output_divider, where the
+/// source and input divider are the ones of the main PLL. Only the outputs that are
+/// given a divider are enabled:
+///
+/// ```ignore
+/// // 8 MHz HSE: 8 MHz * 24 / 4 = 48 MHz on R, e.g. for the ADC
+/// let pllsai1 = PllSaiConfig::new(24).r(PllDivider::Div4);
+/// ```
+pub struct PllSaiConfig {
+    // Multiplication factor
+    n: u8,
+    // Division factor for the P output (SAI)
+    p: Option<PllSaiPDivider>,
+    // Division factor for the Q output (48 MHz clock)
+    q: Option<PllDivider>,
+    // Division factor for the R output (ADC)
+    r: Option<PllDivider>,
+}
+
+impl PllSaiConfig {
+    /// Create a new PLLSAI config with all outputs disabled
+    pub fn new(multiplier: u8) -> Self {
+        PllSaiConfig {
+            n: multiplier,
+            p: None,
+            q: None,
+            r: None,
+        }
+    }
+
+    /// Enables the P output with the given divider
+    pub fn p(mut self, divider: PllSaiPDivider) -> Self {
+        self.p = Some(divider);
+        self
+    }
+
+    /// Enables the Q output with the given divider
+    pub fn q(mut self, divider: PllDivider) -> Self {
+        self.q = Some(divider);
+        self
+    }
+
+    /// Enables the R output with the given divider
+    pub fn r(mut self, divider: PllDivider) -> Self {
+        self.r = Some(divider);
+        self
+    }
+
+    /// Checks the configuration against the limits in the datasheet and returns the
+    /// output frequencies for the VCO `input` frequency
+    fn outputs(&self, input: u32) -> Result<PllSaiClocks, Error> {
+        let vco = input * self.n as u32;
+        if !(8..=86).contains(&self.n)
+            || !(4_000_000..=16_000_000).contains(&input)
+            || !(64_000_000..=344_000_000).contains(&vco)
+        {
+            return Err(Error::PllSaiConfig);
+        }
+
+        let output = |div: u32| {
+            let freq = vco / div;
+            if freq <= 80_000_000 {
+                Ok(freq.Hz())
+            } else {
+                Err(Error::PllSaiConfig)
+            }
+        };
+
+        Ok(PllSaiClocks {
+            p: self.p.map(|p| output(p.to_division_factor())).transpose()?,
+            q: self.q.map(|q| output(q.to_division_factor())).transpose()?,
+            r: self.r.map(|r| output(r.to_division_factor())).transpose()?,
+        })
+    }
+}
+
+/// Frequencies of the enabled PLLSAI outputs
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct PllSaiClocks {
+    p: Option<Hertz>,
+    q: Option<Hertz>,
+    r: Option<Hertz>,
+}
+
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// PLL Source
 pub enum PllSource {
     /// Multi-speed internal clock
@@ -900,10 +1558,18 @@ impl PllSource {
     }
 }
 
+/// Peripheral driver with clock dependent settings that can follow a clock change
+///
+/// See [`Clocks::release`].
+pub trait RateChange {
+    /// Re-derives the clock dependent settings after the clocks changed from `old` to `new`
+    fn clocks_changed(&mut self, old: &Clocks, new: &Clocks);
+}
+
 /// Frozen clock frequencies
 ///
 /// The existence of this value indicates that the clock configuration can no longer be changed
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Clocks {
     hclk: Hertz,
     hsi48: bool,
@@ -918,9 +1584,240 @@ pub struct Clocks {
     timclk1: Hertz,
     timclk2: Hertz,
     pll_source: Option<PllSource>,
+    clk48: Option<Clk48Source>,
+    pll48clk: Option<Hertz>,
+    pllsai1: PllSaiClocks,
+    pllsai2: PllSaiClocks,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Clocks {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "Clocks {{ sysclk: {} Hz, hclk: {} Hz, pclk1: {} Hz, pclk2: {} Hz, timclk1: {} Hz, timclk2: {} Hz, ppre1: {}, ppre2: {}, msi: {}, hsi48: {}, lsi: {}, lse: {}, pll_source: {}, clk48: {}, pll48clk: {} Hz, pllsai1_p: {} Hz, pllsai1_q: {} Hz, pllsai1_r: {} Hz, pllsai2_p: {} Hz, pllsai2_q: {} Hz, pllsai2_r: {} Hz }}",
+            self.sysclk.raw(),
+            self.hclk.raw(),
+            self.pclk1.raw(),
+            self.pclk2.raw(),
+            self.timclk1.raw(),
+            self.timclk2.raw(),
+            self.ppre1,
+            self.ppre2,
+            self.msi,
+            self.hsi48,
+            self.lsi,
+            self.lse,
+            self.pll_source,
+            self.clk48,
+            self.pll48clk.map(|f| f.raw()),
+            self.pllsai1.p.map(|f| f.raw()),
+            self.pllsai1.q.map(|f| f.raw()),
+            self.pllsai1.r.map(|f| f.raw()),
+            self.pllsai2.p.map(|f| f.raw()),
+            self.pllsai2.q.map(|f| f.raw()),
+            self.pllsai2.r.map(|f| f.raw()),
+        )
+    }
 }
 
 impl Clocks {
+    /// Releases the frozen clocks, returning an empty clock configuration to build the
+    /// next one from
+    ///
+    /// Freezing the returned configuration switches the clocks at runtime, e.g. to drop
+    /// to a low MSI range while idle and to go back to the PLL for a burst of work:
+    ///
+    /// ```ignore
+    /// let clocks = rcc.cfgr.sysclk(80.MHz()).freeze(&mut flash.acr, &mut pwr);
+    /// // ...
+    /// let idle = clocks
+    ///     .release()
+    ///     .msi(MsiFreq::RANGE2M)
+    ///     .freeze(&mut flash.acr, &mut pwr);
+    /// serial.clocks_changed(&clocks, &idle);
+    /// delay.clocks_changed(&clocks, &idle);
+    /// ```
+    ///
+    /// The flash wait states are raised before and lowered after the switch, and the
+    /// oscillators and PLLs that the new configuration does not use are turned off.
+    /// The LSE and LSI keep running.
+    ///
+    /// The voltage range 2 has to be selected after freezing clocks of at most 26 MHz, and
+    /// the range 1 before freezing faster clocks, see [`Pwr::set_power_range`].
+    ///
+    /// Peripheral drivers derive their dividers from the clocks passed when they were
+    /// created. Drivers that implement [`RateChange`] can be updated in place, all others
+    /// have to be created again. Peripherals should be idle while the clocks change.
+    pub fn release(self) -> CFGR {
+        CFGR::new()
+    }
+
+    /// Reconstructs the clock frequencies from the RCC registers
+    ///
+    /// This is meant for code that runs after a bootloader already configured the clocks.
+    /// The HSE frequency can not be read back, so it has to be passed if the HSE is used.
+    pub fn from_registers(rcc: &RCC, hse: Option<Hertz>) -> Clocks {
+        let cr = rcc.cr.read();
+        let cfgr = rcc.cfgr.read();
+        let pllcfgr = rcc.pllcfgr.read();
+
+        let msi = msi_range(rcc);
+        let hse = hse.map(|hse| hse.raw()).unwrap_or(0);
+
+        let (pll_source, pll_input) = match pllcfgr.pllsrc().bits() {
+            0b01 => (PllSource::MSI, msi.map_or(0, |msi| msi.to_hertz().raw())),
+            0b10 => (PllSource::HSI16, HSI),
+            _ => (PllSource::HSE, hse),
+        };
+        let pll_input = pll_input / (pllcfgr.pllm().bits() as u32 + 1);
+        let pll_vco = pll_input * pllcfgr.plln().bits() as u32;
+        let pll_div = |bits: u8| pll_vco / (2 * (bits as u32 + 1));
+
+        let sysclk = match cfgr.sws().bits() {
+            0b00 => msi.map_or(0, |msi| msi.to_hertz().raw()),
+            0b01 => HSI,
+            0b10 => hse,
+            _ => pll_div(pllcfgr.pllr().bits()),
+        };
+
+        let hpre = match cfgr.hpre().bits() {
+            0b1000 => 2,
+            0b1001 => 4,
+            0b1010 => 8,
+            0b1011 => 16,
+            0b1100 => 64,
+            0b1101 => 128,
+            0b1110 => 256,
+            0b1111 => 512,
+            _ => 1,
+        };
+        let ppre = |bits: u8| match bits {
+            0b100 => 2,
+            0b101 => 4,
+            0b110 => 8,
+            0b111 => 16,
+            _ => 1,
+        };
+        let ppre1 = ppre(cfgr.ppre1().bits());
+        let ppre2 = ppre(cfgr.ppre2().bits());
+
+        let hclk = sysclk / hpre;
+        let pclk1 = hclk / ppre1 as u32;
+        let pclk2 = hclk / ppre2 as u32;
+        let timclk1 = if ppre1 == 1 { pclk1 } else { 2 * pclk1 };
+        let timclk2 = if ppre2 == 1 { pclk2 } else { 2 * pclk2 };
+
+        #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+        let pllsai_input = |m: u8| pll_input * (pllcfgr.pllm().bits() as u32 + 1) / (m as u32 + 1);
+
+        let pllsai1 = if cr.pllsai1rdy().bit_is_set() {
+            let cfgr = rcc.pllsai1cfgr.read();
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+            let input = pllsai_input(cfgr.pllsai1m().bits());
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9")))]
+            let input = pll_input;
+            let vco = input * cfgr.pllsai1n().bits() as u32;
+            PllSaiClocks {
+                p: cfgr
+                    .pllsai1pen()
+                    .bit_is_set()
+                    .then(|| (vco / if cfgr.pllsai1p().bit() { 17 } else { 7 }).Hz()),
+                q: cfgr
+                    .pllsai1qen()
+                    .bit_is_set()
+                    .then(|| (vco / (2 * (cfgr.pllsai1q().bits() as u32 + 1))).Hz()),
+                r: cfgr
+                    .pllsai1ren()
+                    .bit_is_set()
+                    .then(|| (vco / (2 * (cfgr.pllsai1r().bits() as u32 + 1))).Hz()),
+            }
+        } else {
+            PllSaiClocks::default()
+        };
+
+        #[cfg(any(
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486",
+            feature = "stm32l496",
+            feature = "stm32l4a6",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        ))]
+        let pllsai2 = if cr.pllsai2rdy().bit_is_set() {
+            let cfgr = rcc.pllsai2cfgr.read();
+            #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+            let input = pllsai_input(cfgr.pllsai2m().bits());
+            #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9")))]
+            let input = pll_input;
+            let vco = input * cfgr.pllsai2n().bits() as u32;
+            PllSaiClocks {
+                p: cfgr
+                    .pllsai2pen()
+                    .bit_is_set()
+                    .then(|| (vco / if cfgr.pllsai2p().bit() { 17 } else { 7 }).Hz()),
+                #[cfg(any(feature = "stm32l4r9", feature = "stm32l4s9"))]
+                q: cfgr
+                    .pllsai2qen()
+                    .bit_is_set()
+                    .then(|| (vco / (2 * (cfgr.pllsai2q().bits() as u32 + 1))).Hz()),
+                #[cfg(not(any(feature = "stm32l4r9", feature = "stm32l4s9")))]
+                q: None,
+                r: cfgr
+                    .pllsai2ren()
+                    .bit_is_set()
+                    .then(|| (vco / (2 * (cfgr.pllsai2r().bits() as u32 + 1))).Hz()),
+            }
+        } else {
+            PllSaiClocks::default()
+        };
+        #[cfg(not(any(
+            feature = "stm32l475",
+            feature = "stm32l476",
+            feature = "stm32l486",
+            feature = "stm32l496",
+            feature = "stm32l4a6",
+            feature = "stm32l4r9",
+            feature = "stm32l4s9",
+        )))]
+        let pllsai2 = PllSaiClocks::default();
+
+        let hsi48 = rcc.crrcr.read().hsi48rdy().bit_is_set();
+        let pllq = (cr.pllrdy().bit_is_set() && pllcfgr.pllqen().bit_is_set())
+            .then(|| pll_div(pllcfgr.pllq().bits()).Hz());
+        let (clk48, pll48clk) = match rcc.ccipr.read().clk48sel().bits() {
+            0b00 => (Clk48Source::Hsi48, hsi48.then(|| 48.MHz())),
+            0b01 => (Clk48Source::PllSai1Q, pllsai1.q),
+            0b10 => (Clk48Source::PllQ, pllq),
+            _ => (Clk48Source::Msi, msi.map(|msi| msi.to_hertz())),
+        };
+
+        Clocks {
+            hclk: hclk.Hz(),
+            hsi48,
+            msi,
+            lsi: rcc.csr.read().lsirdy().bit_is_set(),
+            lse: rcc.bdcr.read().lserdy().bit_is_set(),
+            pclk1: pclk1.Hz(),
+            pclk2: pclk2.Hz(),
+            ppre1,
+            ppre2,
+            sysclk: sysclk.Hz(),
+            timclk1: timclk1.Hz(),
+            timclk2: timclk2.Hz(),
+            pll_source: if cfgr.sws().bits() == 0b11 {
+                Some(pll_source)
+            } else {
+                None
+            },
+            clk48: pll48clk.map(|_| clk48),
+            pll48clk,
+            pllsai1,
+            pllsai2,
+        }
+    }
+
     /// Returns the frequency of the AHB
     pub fn hclk(&self) -> Hertz {
         self.hclk
@@ -956,6 +1853,46 @@ impl Clocks {
         self.pclk2
     }
 
+    /// Returns the source of the 48 MHz clock, if one is selected
+    pub fn clk48(&self) -> Option<Clk48Source> {
+        self.clk48
+    }
+
+    /// Returns the frequency of the 48 MHz clock for USB, RNG and SDMMC, if one is selected
+    pub fn pll48clk(&self) -> Option<Hertz> {
+        self.pll48clk
+    }
+
+    /// Returns the frequency of the PLLSAI1 P output, if enabled
+    pub fn pllsai1_p(&self) -> Option<Hertz> {
+        self.pllsai1.p
+    }
+
+    /// Returns the frequency of the PLLSAI1 Q output, if enabled
+    pub fn pllsai1_q(&self) -> Option<Hertz> {
+        self.pllsai1.q
+    }
+
+    /// Returns the frequency of the PLLSAI1 R output, if enabled
+    pub fn pllsai1_r(&self) -> Option<Hertz> {
+        self.pllsai1.r
+    }
+
+    /// Returns the frequency of the PLLSAI2 P output, if enabled
+    pub fn pllsai2_p(&self) -> Option<Hertz> {
+        self.pllsai2.p
+    }
+
+    /// Returns the frequency of the PLLSAI2 Q output, if enabled
+    pub fn pllsai2_q(&self) -> Option<Hertz> {
+        self.pllsai2.q
+    }
+
+    /// Returns the frequency of the PLLSAI2 R output, if enabled
+    pub fn pllsai2_r(&self) -> Option<Hertz> {
+        self.pllsai2.r
+    }
+
     /// Get which source is being used for PLL
     pub fn pll_source(&self) -> Option<PllSource> {
         self.pll_source
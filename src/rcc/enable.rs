@@ -114,6 +114,7 @@ bus! {
     TIM2 => (APB1R1, tim2en, tim2smen, tim2rst), // 0
     TIM6 => (APB1R1, tim6en, tim6smen, tim6rst), // 4
     TIM7 => (APB1R1, tim7en, tim7smen, tim7rst), // 5
+    RTC => (APB1R1, rtcapben, rtcapbsmen,), // 10
     WWDG => (APB1R1, wwdgen, wwdgsmen,), // 11
     SPI2 => (APB1R1, spi2en, spi2smen, spi2rst), // 14
     SPI3 => (APB1R1, spi3en, sp3smen, spi3rst), // 15 // TODO: fix typo
@@ -172,6 +173,8 @@ bus! {
     feature = "stm32l4s9",
 ))]
 bus! {
+    DMAMUX1 => (AHB1, dmamux1en, dmamux1smen, dmamux1rst), // 2
+
     ADC => (AHB2, adcen, adcfssmen, adcrst), // 13
 
     FIREWALL => (APB2, fwen,,), // 7
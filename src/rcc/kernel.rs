@@ -0,0 +1,275 @@
+//! Peripheral kernel clock selection (RCC_CCIPR)
+//!
+//! Several peripherals have a kernel clock that is independent of their bus
+//! clock, which allows e.g. a USART on the HSI16 to receive in Stop mode:
+//!
+//! ```ignore
+//! rcc.ccipr
+//!     .set_kernel_clock::<USART2>(UsartClockSource::Hsi16, &clocks);
+//! let serial = Serial::usart2(dp.USART2, (tx, rx), 115_200.bps(), clocks, &mut rcc.apb1r1);
+//! assert_eq!(clocks.kernel_clock::<USART2>(), 16.MHz::<1, 1>());
+//! ```
+//!
+//! The peripheral drivers read the selection when they are created, so it has to be
+//! made before.
+
+use super::{Clocks, RccBus, CCIPR};
+use crate::lptimer::ClockSource as LptimClockSource;
+use crate::stm32::RCC;
+use crate::time::Hertz;
+use fugit::RateExtU32;
+
+/// Kernel clock source of the USARTs, UARTs and the LPUART
+///
+/// The discriminants are the USARTxSEL encodings of RCC_CCIPR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum UsartClockSource {
+    /// The clock of the APB the peripheral is on, the reset value
+    Pclk = 0b00,
+    /// System clock
+    Sysclk = 0b01,
+    /// HSI16, which keeps running in Stop mode when requested by the peripheral
+    Hsi16 = 0b10,
+    /// LSE
+    Lse = 0b11,
+}
+
+/// Kernel clock source of the I2Cs
+///
+/// The discriminants are the I2CxSEL encodings of RCC_CCIPR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum I2cClockSource {
+    /// APB1 clock, the reset value
+    Pclk = 0b00,
+    /// System clock
+    Sysclk = 0b01,
+    /// HSI16, which keeps running in Stop mode when requested by the peripheral
+    Hsi16 = 0b10,
+}
+
+const HSI16: u32 = 16_000_000;
+const LSE: u32 = 32_768;
+const LSI: u32 = 32_000;
+
+mod private {
+    use super::{Clocks, Hertz};
+
+    /// Encoding of a kernel clock source in RCC_CCIPR
+    pub trait Source: Copy {
+        fn bits(self) -> u32;
+        fn from_bits(bits: u32) -> Self;
+        /// Checks that the source is running, or turns it on
+        fn prepare(self, clocks: &Clocks);
+        fn frequency(self, clocks: &Clocks, pclk: Hertz) -> Hertz;
+    }
+
+    /// Position of the two bit selection field of a peripheral in RCC_CCIPR
+    pub trait Select {
+        const SHIFT: u8;
+    }
+}
+
+/// Peripheral with a kernel clock selected in RCC_CCIPR
+pub trait KernelClock: RccBus + private::Select {
+    /// Selectable kernel clock sources
+    type Source: private::Source;
+
+    /// Returns the currently selected kernel clock source
+    fn kernel_clock_source() -> Self::Source {
+        // NOTE(unsafe) atomic read with no side effects
+        let ccipr = unsafe { (*RCC::ptr()).ccipr.read().bits() };
+        private::Source::from_bits((ccipr >> Self::SHIFT) & 0b11)
+    }
+
+    /// Returns the frequency of the currently selected kernel clock
+    fn kernel_clock(clocks: &Clocks) -> Hertz;
+}
+
+/// Turns the HSI16 on, it is not running if another clock was picked as sysclk
+fn enable_hsi16() {
+    // NOTE(unsafe) atomic read-modify-write of a bit only the HAL sets
+    let rcc = unsafe { &*RCC::ptr() };
+    rcc.cr.modify(|_, w| w.hsion().set_bit());
+    while rcc.cr.read().hsirdy().bit_is_clear() {}
+}
+
+impl private::Source for UsartClockSource {
+    fn bits(self) -> u32 {
+        self as u32
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0b00 => Self::Pclk,
+            0b01 => Self::Sysclk,
+            0b10 => Self::Hsi16,
+            _ => Self::Lse,
+        }
+    }
+
+    fn prepare(self, clocks: &Clocks) {
+        match self {
+            Self::Hsi16 => enable_hsi16(),
+            Self::Lse => assert!(clocks.lse(), "LSE is not enabled"),
+            _ => {}
+        }
+    }
+
+    fn frequency(self, clocks: &Clocks, pclk: Hertz) -> Hertz {
+        match self {
+            Self::Pclk => pclk,
+            Self::Sysclk => clocks.sysclk(),
+            Self::Hsi16 => HSI16.Hz(),
+            Self::Lse => LSE.Hz(),
+        }
+    }
+}
+
+impl private::Source for I2cClockSource {
+    fn bits(self) -> u32 {
+        self as u32
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0b00 => Self::Pclk,
+            0b01 => Self::Sysclk,
+            _ => Self::Hsi16,
+        }
+    }
+
+    fn prepare(self, _clocks: &Clocks) {
+        if self == Self::Hsi16 {
+            enable_hsi16();
+        }
+    }
+
+    fn frequency(self, clocks: &Clocks, pclk: Hertz) -> Hertz {
+        match self {
+            Self::Pclk => pclk,
+            Self::Sysclk => clocks.sysclk(),
+            Self::Hsi16 => HSI16.Hz(),
+        }
+    }
+}
+
+impl private::Source for LptimClockSource {
+    fn bits(self) -> u32 {
+        self as u32
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        match bits {
+            0b00 => Self::PCLK,
+            0b01 => Self::LSI,
+            0b10 => Self::HSI16,
+            _ => Self::LSE,
+        }
+    }
+
+    fn prepare(self, clocks: &Clocks) {
+        match self {
+            Self::LSI => assert!(clocks.lsi(), "LSI is not enabled"),
+            Self::HSI16 => enable_hsi16(),
+            Self::LSE => assert!(clocks.lse(), "LSE is not enabled"),
+            _ => {}
+        }
+    }
+
+    fn frequency(self, _clocks: &Clocks, pclk: Hertz) -> Hertz {
+        match self {
+            Self::PCLK => pclk,
+            Self::LSI => LSI.Hz(),
+            Self::HSI16 => HSI16.Hz(),
+            Self::LSE => LSE.Hz(),
+        }
+    }
+}
+
+macro_rules! kernel_clock {
+    ($($(#[$meta:meta])* $PER:ident: ($Source:ident, $shift:literal, $pclk:ident),)+) => {
+        $(
+            $(#[$meta])*
+            impl private::Select for crate::pac::$PER {
+                const SHIFT: u8 = $shift;
+            }
+
+            $(#[$meta])*
+            impl KernelClock for crate::pac::$PER {
+                type Source = $Source;
+
+                fn kernel_clock(clocks: &Clocks) -> Hertz {
+                    private::Source::frequency(Self::kernel_clock_source(), clocks, clocks.$pclk())
+                }
+            }
+        )+
+    };
+}
+
+kernel_clock! {
+    USART1: (UsartClockSource, 0, pclk2),
+    USART2: (UsartClockSource, 2, pclk1),
+    #[cfg(not(any(feature = "stm32l432", feature = "stm32l442")))]
+    USART3: (UsartClockSource, 4, pclk1),
+    #[cfg(any(
+        feature = "stm32l452",
+        feature = "stm32l462",
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l485",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    UART4: (UsartClockSource, 6, pclk1),
+    #[cfg(any(
+        feature = "stm32l475",
+        feature = "stm32l476",
+        feature = "stm32l485",
+        feature = "stm32l486",
+        feature = "stm32l496",
+        feature = "stm32l4a6",
+        feature = "stm32l4r9",
+        feature = "stm32l4s9",
+    ))]
+    UART5: (UsartClockSource, 8, pclk1),
+    LPUART1: (UsartClockSource, 10, pclk1),
+    I2C1: (I2cClockSource, 12, pclk1),
+    I2C2: (I2cClockSource, 14, pclk1),
+    I2C3: (I2cClockSource, 16, pclk1),
+    LPTIM1: (LptimClockSource, 18, pclk1),
+    LPTIM2: (LptimClockSource, 20, pclk1),
+}
+
+impl CCIPR {
+    /// Selects the kernel clock of peripheral `P`
+    ///
+    /// This has to be done before the peripheral driver is created, as the drivers
+    /// derive their baud rate and timing settings from the selected clock.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the LSE or LSI is selected but was not enabled when freezing the
+    /// clocks. The HSI16 is turned on if required.
+    pub fn set_kernel_clock<P: KernelClock>(&mut self, source: P::Source, clocks: &Clocks) {
+        use private::Source;
+
+        source.prepare(clocks);
+        // NOTE(unsafe) the field only takes the valid encodings of `P::Source`
+        self.ccipr().modify(|r, w| unsafe {
+            w.bits((r.bits() & !(0b11 << P::SHIFT)) | (source.bits() << P::SHIFT))
+        });
+    }
+}
+
+impl Clocks {
+    /// Returns the kernel clock frequency of peripheral `P` as currently selected
+    /// in RCC_CCIPR
+    pub fn kernel_clock<P: KernelClock>(&self) -> Hertz {
+        P::kernel_clock(self)
+    }
+}
@@ -0,0 +1,126 @@
+//! Microcontroller clock output (MCO)
+
+use crate::gpio::{Alternate, PushPull, PA8};
+use crate::stm32::RCC;
+
+/// Clock source of the microcontroller clock output
+///
+/// The discriminants are the MCOSEL encodings of RCC_CFGR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum McoSource {
+    /// No clock output
+    Disabled = 0b0000,
+    /// System clock
+    Sysclk = 0b0001,
+    /// Multi-speed internal clock
+    Msi = 0b0010,
+    /// 16 MHz high-speed internal clock
+    Hsi16 = 0b0011,
+    /// High-speed external clock
+    Hse = 0b0100,
+    /// Main PLL clock (PLLCLK)
+    Pll = 0b0101,
+    /// Low-speed internal clock
+    Lsi = 0b0110,
+    /// Low-speed external clock
+    Lse = 0b0111,
+    /// 48 MHz internal clock, only available on devices with the HSI48
+    Hsi48 = 0b1000,
+}
+
+/// Prescaler of the microcontroller clock output
+///
+/// The discriminants are the MCOPRE encodings of RCC_CFGR.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum McoPrescaler {
+    /// MCO output is the source clock
+    Div1 = 0b000,
+    /// MCO output is the source clock divided by 2
+    Div2 = 0b001,
+    /// MCO output is the source clock divided by 4
+    Div4 = 0b010,
+    /// MCO output is the source clock divided by 8
+    Div8 = 0b011,
+    /// MCO output is the source clock divided by 16
+    Div16 = 0b100,
+}
+
+/// Writes MCOSEL and MCOPRE
+///
+/// The PAC only describes MCOSEL\[2:0\], so the raw register value is written to
+/// also reach MCOSEL\[3\], which selects the HSI48.
+pub(super) fn configure(source: McoSource, prescaler: McoPrescaler) {
+    // NOTE(unsafe) only the MCO bits are modified, which are not touched
+    // anywhere else once the clocks are frozen
+    unsafe {
+        (*RCC::ptr()).cfgr.modify(|r, w| {
+            w.bits(
+                (r.bits() & !(0x7f << 24)) | ((source as u32) << 24) | ((prescaler as u32) << 28),
+            )
+        })
+    };
+}
+
+/// Microcontroller clock output on PA8
+///
+/// The source and prescaler are usually selected with [`CFGR::mco`](super::CFGR::mco)
+/// before the clocks are frozen, taking the pin afterwards proves that the
+/// output is routed to it:
+///
+/// ```ignore
+/// let clocks = rcc
+///     .cfgr
+///     .sysclk(80.MHz())
+///     .mco(McoSource::Sysclk, McoPrescaler::Div8)
+///     .freeze(&mut flash.acr, &mut pwr);
+///
+/// let pa8 = gpioa
+///     .pa8
+///     .into_alternate(&mut gpioa.moder, &mut gpioa.otyper, &mut gpioa.afrh)
+///     .speed(Speed::VeryHigh);
+/// let mut mco = Mco::new(pa8);
+/// ```
+pub struct Mco {
+    pin: PA8<Alternate<PushPull, 0>>,
+}
+
+impl Mco {
+    /// Takes the MCO pin, keeping the source and prescaler set by the clock
+    /// configuration
+    pub fn new(pin: PA8<Alternate<PushPull, 0>>) -> Self {
+        Self { pin }
+    }
+
+    /// Changes the source and the prescaler of the clock output
+    ///
+    /// The selected source has to be running, otherwise the output stays low.
+    pub fn set_source(&mut self, source: McoSource, prescaler: McoPrescaler) {
+        configure(source, prescaler);
+    }
+
+    /// Returns the currently selected source, `None` for a reserved encoding
+    pub fn source(&self) -> Option<McoSource> {
+        // NOTE(unsafe) atomic read with no side effects
+        let bits = unsafe { ((*RCC::ptr()).cfgr.read().bits() >> 24) & 0b1111 };
+        Some(match bits {
+            0b0000 => McoSource::Disabled,
+            0b0001 => McoSource::Sysclk,
+            0b0010 => McoSource::Msi,
+            0b0011 => McoSource::Hsi16,
+            0b0100 => McoSource::Hse,
+            0b0101 => McoSource::Pll,
+            0b0110 => McoSource::Lsi,
+            0b0111 => McoSource::Lse,
+            0b1000 => McoSource::Hsi48,
+            _ => return None,
+        })
+    }
+
+    /// Disables the clock output and releases the pin
+    pub fn free(self) -> PA8<Alternate<PushPull, 0>> {
+        configure(McoSource::Disabled, McoPrescaler::Div1);
+        self.pin
+    }
+}
@@ -113,6 +113,7 @@ impl RngCore for Rng {
 impl CryptoRng for Rng {}
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {}
 
 #[cfg(feature = "unproven")]
@@ -34,17 +34,29 @@ pub mod rtc3;
 ))]
 pub use rtc3 as rtc_registers;
 
-use time::{Date, PrimitiveDateTime, Time};
+use time::{Date, PrimitiveDateTime, Time, Weekday};
 use void::Void;
 
 use crate::{
     hal::timer::{self, Cancel as _},
     pwr,
-    rcc::{APB1R1, BDCR},
+    rcc::{Enable, APB1R1, BDCR},
     stm32::{EXTI, RTC},
+    time::{Hertz, MilliSeconds},
+    timer::Timeout,
 };
 
+/// RTC error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The wakeup period is shorter than four RTCCLK cycles, or longer than 2^17 s
+    WakeupPeriodOutOfRange,
+}
+
 /// Interrupt event
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
     WakeupTimer,
     AlarmA,
@@ -52,6 +64,7 @@ pub enum Event {
     Timestamp,
 }
 
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Alarm {
     AlarmA,
     AlarmB,
@@ -66,13 +79,151 @@ impl From<Alarm> for Event {
     }
 }
 
+/// Day on which an alarm is triggered
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlarmDay {
+    /// Every day, the date and the weekday are not compared
+    EveryDay,
+    /// Day of the month, `1..=31`
+    Date(u8),
+    /// Day of the week
+    Weekday(Weekday),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for AlarmDay {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            AlarmDay::EveryDay => defmt::write!(f, "EveryDay"),
+            AlarmDay::Date(date) => defmt::write!(f, "Date({})", date),
+            // 1 for Monday to 7 for Sunday, as the RTC counts the weekdays
+            AlarmDay::Weekday(weekday) => {
+                defmt::write!(f, "Weekday({})", weekday.number_from_monday())
+            }
+        }
+    }
+}
+
+/// Calendar fields and subseconds an alarm is compared with
+///
+/// By default the alarm matches the hours, minutes, and seconds of the time every day. A
+/// masked field matches any value, e.g. an alarm with the hours and minutes masked is
+/// triggered every minute at the seconds of the time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AlarmConfig {
+    time: Time,
+    day: AlarmDay,
+    mask_hours: bool,
+    mask_minutes: bool,
+    mask_seconds: bool,
+    subseconds: u16,
+    subsecond_bits: u8,
+}
+
+impl AlarmConfig {
+    /// Triggers the alarm every day at `time`, its subseconds are not compared
+    pub fn new(time: Time) -> Self {
+        AlarmConfig {
+            time,
+            day: AlarmDay::EveryDay,
+            mask_hours: false,
+            mask_minutes: false,
+            mask_seconds: false,
+            subseconds: 0,
+            subsecond_bits: 0,
+        }
+    }
+
+    /// Sets the day on which the alarm is triggered
+    pub fn day(mut self, day: AlarmDay) -> Self {
+        self.day = day;
+        self
+    }
+
+    /// Matches any hour
+    pub fn mask_hours(mut self) -> Self {
+        self.mask_hours = true;
+        self
+    }
+
+    /// Matches any minute
+    pub fn mask_minutes(mut self) -> Self {
+        self.mask_minutes = true;
+        self
+    }
+
+    /// Matches any second
+    pub fn mask_seconds(mut self) -> Self {
+        self.mask_seconds = true;
+        self
+    }
+
+    /// Compares the lower `bits` bits of the subsecond counter with `subseconds`
+    ///
+    /// The subsecond counter counts down from the synchronous prescaler to 0 within each
+    /// second, so e.g. `subseconds(0, 7)` with all other fields masked triggers the alarm
+    /// every 128 ticks of `ck_apre`, i.e. twice a second with the default prescalers. With
+    /// `bits` 0, the default, the subseconds are not compared. The alarm is triggered
+    /// at the second boundary then.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is above 15, or `subseconds` is wider than 15 bits.
+    pub fn subseconds(mut self, subseconds: u16, bits: u8) -> Self {
+        assert!(bits <= 15 && subseconds <= 0x7fff);
+        self.subseconds = subseconds;
+        self.subsecond_bits = bits;
+        self
+    }
+
+    /// Values of the RTC_ALRMxR and RTC_ALRMxSSR registers in the 24 hour format
+    fn registers(&self) -> (u32, u32) {
+        let (wdsel, day, mask_day) = match self.day {
+            AlarmDay::EveryDay => (false, 1, true),
+            AlarmDay::Date(date) => {
+                assert!((1..=31).contains(&date));
+                (false, bcd(date), false)
+            }
+            // A weekday is only in DU, 1 for Monday to 7 for Sunday
+            AlarmDay::Weekday(weekday) => (true, u32::from(weekday.number_from_monday()), false),
+        };
+
+        // MSK4 (bit 31), WDSEL (bit 30), DT/DU (bits 24-29), MSK3 (bit 23), PM (bit 22),
+        // HT/HU (bits 16-21), MSK2 (bit 15), MNT/MNU (bits 8-14), MSK1 (bit 7), ST/SU (bits 0-6)
+        let alrmr = u32::from(mask_day) << 31
+            | u32::from(wdsel) << 30
+            | day << 24
+            | u32::from(self.mask_hours) << 23
+            | bcd(self.time.hour()) << 16
+            | u32::from(self.mask_minutes) << 15
+            | bcd(self.time.minute()) << 8
+            | u32::from(self.mask_seconds) << 7
+            | bcd(self.time.second());
+        // MASKSS (bits 24-27), SS (bits 0-14)
+        let alrmssr = u32::from(self.subsecond_bits) << 24 | u32::from(self.subseconds);
+
+        (alrmr, alrmssr)
+    }
+}
+
+impl From<PrimitiveDateTime> for AlarmConfig {
+    /// Triggers the alarm at the time on the day of the month of `datetime`
+    fn from(datetime: PrimitiveDateTime) -> Self {
+        AlarmConfig::new(datetime.time()).day(AlarmDay::Date(datetime.day()))
+    }
+}
+
 /// RTC Abstraction
+///
+/// The calendar runs in the 24 hour format. The 12 hour format is not supported, setting
+/// the time always clears the PM flag.
 pub struct Rtc {
     rtc: RTC,
     rtc_config: RtcConfig,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum RtcClockSource {
     /// 00: No clock
@@ -86,6 +237,7 @@ pub enum RtcClockSource {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum RtcWakeupClockSource {
     /// RTC/16 clock is selected
@@ -159,6 +311,7 @@ impl RtcConfig {
 }
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[repr(u8)]
 pub enum RtcCalibrationCyclePeriod {
     /// 8-second calibration period
@@ -185,7 +338,7 @@ impl Rtc {
     ) -> Self {
         // assert_eq!(clocks.lsi(), true); // make sure LSI is enabled
         // enable peripheral clock for communication
-        apb1r1.enr().modify(|_, w| w.rtcapben().set_bit());
+        RTC::enable(apb1r1);
         pwrcr1.reg().read(); // read to allow the pwr clock to enable
 
         let mut rtc_struct = Self { rtc, rtc_config };
@@ -195,6 +348,10 @@ impl Rtc {
     }
 
     /// Set date and time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the year is not in `2000..=2099`, the calendar range of the RTC.
     pub fn set_datetime(&mut self, datetime: &PrimitiveDateTime) {
         self.write(true, |rtc| {
             set_time_raw(rtc, datetime.time());
@@ -203,27 +360,43 @@ impl Rtc {
     }
 
     /// Get date and time.
+    ///
+    /// The subseconds are in steps of the synchronous prescaler, 1/256 s by default. After a
+    /// system reset or a wakeup from the Stop modes the calendar may not be synchronized yet,
+    /// see [`Rtc::wait_for_sync`].
     pub fn get_datetime(&self) -> PrimitiveDateTime {
-        let sync_p = self.rtc_config.sync_prescaler as u32;
         let ssr = self.rtc.ssr.read();
-        let micro = 1_000_000u32 / (sync_p + 1) * (sync_p - ssr.ss().bits() as u32);
-        let tr = self.rtc.tr.read();
-        let second = bcd2_to_byte((tr.st().bits(), tr.su().bits()));
-        let minute = bcd2_to_byte((tr.mnt().bits(), tr.mnu().bits()));
-        let hour = bcd2_to_byte((tr.ht().bits(), tr.hu().bits()));
+        let micro = subsecond_micros(self.rtc_config.sync_prescaler, ssr.ss().bits());
+        let tr = self.rtc.tr.read().bits();
         // Reading either RTC_SSR or RTC_TR locks the values in the higher-order
         // calendar shadow registers until RTC_DR is read.
-        let dr = self.rtc.dr.read();
+        let dr = self.rtc.dr.read().bits();
 
-        // let weekday = dr.wdu().bits();
-        let day = bcd2_to_byte((dr.dt().bits(), dr.du().bits()));
-        let month = bcd2_to_byte((dr.mt().bit() as u8, dr.mu().bits()));
-        let year = bcd2_to_byte((dr.yt().bits(), dr.yu().bits())) as u16 + 1970_u16;
+        date_from_register(dr).with_time(time_from_register(tr, micro))
+    }
 
-        let time = Time::from_hms_micro(hour, minute, second, micro).unwrap();
-        let date = Date::from_calendar_date(year.into(), month.try_into().unwrap(), day).unwrap();
+    /// Get the day of the week, which the RTC counts along with the date
+    pub fn get_weekday(&self) -> Weekday {
+        match self.rtc.dr.read().wdu().bits() {
+            1 => Weekday::Monday,
+            2 => Weekday::Tuesday,
+            3 => Weekday::Wednesday,
+            4 => Weekday::Thursday,
+            5 => Weekday::Friday,
+            6 => Weekday::Saturday,
+            // 0 is forbidden
+            _ => Weekday::Sunday,
+        }
+    }
 
-        date.with_time(time)
+    /// Waits until the calendar is copied to the shadow registers read by
+    /// [`Rtc::get_datetime`]
+    ///
+    /// The shadow registers are not updated in the Stop modes, and they may be outdated
+    /// after a system reset. Setting the date or the time already waits for them.
+    pub fn wait_for_sync(&mut self) {
+        self.write(false, rtc_registers::clear_synchronization_flag);
+        while !rtc_registers::is_synchronized(&self.rtc) {}
     }
 
     /// Check if daylight savings time is active.
@@ -247,6 +420,10 @@ impl Rtc {
 
     /// Set Date
     /// Note: If setting both time and date, use set_datetime(...) to avoid errors.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the year is not in `2000..=2099`, the calendar range of the RTC.
     pub fn set_date(&mut self, date: Date) {
         self.write(true, |rtc| {
             set_date_raw(rtc, date);
@@ -259,44 +436,28 @@ impl Rtc {
 
     /// Sets the time at which an alarm will be triggered
     /// This also clears the alarm flag if it is set
-    pub fn set_alarm(&mut self, alarm: Alarm, date: Date, time: Time) {
-        let (dt, du) = byte_to_bcd2(date.day() as u8);
-        let (ht, hu) = byte_to_bcd2(time.hour() as u8);
-        let (mnt, mnu) = byte_to_bcd2(time.minute() as u8);
-        let (st, su) = byte_to_bcd2(time.second() as u8);
+    ///
+    /// The alarm is disabled while its registers are written, so it can be changed while the
+    /// calendar is running. An `AlarmConfig` is also built from a `PrimitiveDateTime`, which
+    /// matches its day of the month and its time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the day of the month is not in `1..=31`.
+    pub fn set_alarm<C: Into<AlarmConfig>>(&mut self, alarm: Alarm, config: C) {
+        let config = config.into();
+        let (alrmr, alrmssr) = config.registers();
 
+        // This is safe, as the values are made of the valid register fields only
         self.write(false, |rtc| match alarm {
             Alarm::AlarmA => {
                 rtc.cr.modify(|_, w| w.alrae().clear_bit()); // Disable Alarm A
                 rtc_registers::clear_alarm_a_flag(rtc);
                 while !rtc_registers::is_alarm_a_accessible(rtc) {}
 
-                rtc.alrmar.modify(|_, w| unsafe {
-                    w.dt()
-                        .bits(dt)
-                        .du()
-                        .bits(du)
-                        .ht()
-                        .bits(ht)
-                        .hu()
-                        .bits(hu)
-                        .mnt()
-                        .bits(mnt)
-                        .mnu()
-                        .bits(mnu)
-                        .st()
-                        .bits(st)
-                        .su()
-                        .bits(su)
-                        .pm()
-                        .clear_bit()
-                        .wdsel()
-                        .clear_bit()
-                });
+                rtc.alrmar.write(|w| unsafe { w.bits(alrmr) });
+                rtc.alrmassr.write(|w| unsafe { w.bits(alrmssr) });
                 // binary mode alarm not implemented (RTC3 only)
-                // subsecond alarm not implemented
-                // would need a conversion method between `time.micros` and RTC ticks
-                // write the SS value and mask to `rtc.alrmassr`
 
                 // enable alarm and reenable interrupt if it was enabled
                 rtc.cr.modify(|_, w| w.alrae().set_bit());
@@ -307,32 +468,9 @@ impl Rtc {
                 rtc_registers::clear_alarm_b_flag(rtc);
                 while !rtc_registers::is_alarm_b_accessible(rtc) {}
 
-                rtc.alrmbr.modify(|_, w| unsafe {
-                    w.dt()
-                        .bits(dt)
-                        .du()
-                        .bits(du)
-                        .ht()
-                        .bits(ht)
-                        .hu()
-                        .bits(hu)
-                        .mnt()
-                        .bits(mnt)
-                        .mnu()
-                        .bits(mnu)
-                        .st()
-                        .bits(st)
-                        .su()
-                        .bits(su)
-                        .pm()
-                        .clear_bit()
-                        .wdsel()
-                        .clear_bit()
-                });
+                rtc.alrmbr.write(|w| unsafe { w.bits(alrmr) });
+                rtc.alrmbssr.write(|w| unsafe { w.bits(alrmssr) });
                 // binary mode alarm not implemented (RTC3 only)
-                // subsecond alarm not implemented
-                // would need a conversion method between `time.micros` and RTC ticks
-                // write the SS value and mask to `rtc.alrmbssr`
 
                 // enable alarm and reenable interrupt if it was enabled
                 rtc.cr.modify(|_, w| w.alrbe().set_bit());
@@ -340,7 +478,25 @@ impl Rtc {
         });
     }
 
+    /// Disables an alarm and clears its flag
+    pub fn disable_alarm(&mut self, alarm: Alarm) {
+        self.write(false, |rtc| match alarm {
+            Alarm::AlarmA => {
+                rtc.cr.modify(|_, w| w.alrae().clear_bit());
+                rtc_registers::clear_alarm_a_flag(rtc);
+            }
+            Alarm::AlarmB => {
+                rtc.cr.modify(|_, w| w.alrbe().clear_bit());
+                rtc_registers::clear_alarm_b_flag(rtc);
+            }
+        });
+    }
+
     /// Starts listening for an interrupt event
+    ///
+    /// This also enables the rising edge of the EXTI line of the event, line 18 for the
+    /// alarms, so the event wakes the MCU from the Stop modes. The RTC events wake it from
+    /// Standby and Shutdown with the RTC interrupt enabled alone.
     pub fn listen(&mut self, exti: &mut EXTI, event: Event) {
         self.write(false, |rtc| match event {
             Event::WakeupTimer => {
@@ -566,6 +722,78 @@ impl Rtc {
         WakeupTimer { rtc: self }
     }
 
+    /// Sets the period of the wakeup timer and stops it until
+    /// [`Rtc::enable_wakeup_timer`]
+    ///
+    /// The finest clock of the wakeup timer for `period` is chosen, RTCCLK divided by 2 to 16
+    /// up to 32 s at 32.768 kHz, and `ck_spre` in seconds up to 2^17 s, over 36 hours. The
+    /// frequency of RTCCLK is derived from the prescalers, which have to divide it to 1 Hz.
+    pub fn set_wakeup_timer(&mut self, period: MilliSeconds) -> Result<(), Error> {
+        let (wucksel, wut) = self.wakeup_prescaler(period.into())?;
+
+        self.disable_wakeup_timer();
+        self.write(false, |rtc| {
+            // This is safe, as the field accepts a full 16 bit value
+            rtc.wutr.write(|w| unsafe { w.wut().bits(wut) });
+            rtc.cr.modify(|_, w| unsafe { w.wucksel().bits(wucksel) });
+        });
+
+        Ok(())
+    }
+
+    /// Starts the wakeup timer with the period of [`Rtc::set_wakeup_timer`]
+    ///
+    /// Listen to [`Event::WakeupTimer`] to wake the MCU up from the low power modes.
+    pub fn enable_wakeup_timer(&mut self) {
+        self.write(false, |rtc| rtc.cr.modify(|_, w| w.wute().set_bit()));
+
+        // WUTWF clears once the timer runs, a write before that would be lost
+        while rtc_registers::is_wakeup_timer_write_flag_set(&self.rtc) {}
+    }
+
+    /// Stops the wakeup timer and clears its flag
+    pub fn disable_wakeup_timer(&mut self) {
+        // Can't panic, as the error type is `Void`.
+        self.wakeup_timer().cancel().unwrap();
+    }
+
+    /// Returns the WUCKSEL and WUT values for `period`
+    fn wakeup_prescaler(&self, period: Timeout) -> Result<(u8, u16), Error> {
+        let rtcclk = (u32::from(self.rtc_config.async_prescaler) + 1)
+            * (u32::from(self.rtc_config.sync_prescaler) + 1);
+
+        // WUT 0 is not allowed with RTCCLK/2
+        let ticks = period.ticks(Hertz::Hz(rtcclk / 2));
+        if ticks < 2 {
+            return Err(Error::WakeupPeriodOutOfRange);
+        }
+        for &(wucksel, div) in [
+            (RtcWakeupClockSource::RtcClkDiv2, 2),
+            (RtcWakeupClockSource::RtcClkDiv4, 4),
+            (RtcWakeupClockSource::RtcClkDiv8, 8),
+            (RtcWakeupClockSource::RtcClkDiv16, 16),
+        ]
+        .iter()
+        {
+            let ticks = period.ticks(Hertz::Hz(rtcclk / div));
+            if ticks <= 0x1_0000 {
+                return Ok((wucksel as u8, (ticks - 1) as u16));
+            }
+        }
+
+        // WUCKSEL 0b11x adds 2^16 to WUT
+        match period.ticks(Hertz::Hz(1)) {
+            seconds @ 1..=0x1_0000 => {
+                Ok((RtcWakeupClockSource::CkSpre as u8, (seconds - 1) as u16))
+            }
+            seconds @ 0x1_0001..=0x2_0000 => Ok((
+                RtcWakeupClockSource::CkSpre as u8 | 0b010,
+                (seconds - 0x1_0001) as u16,
+            )),
+            _ => Err(Error::WakeupPeriodOutOfRange),
+        }
+    }
+
     fn write<F, R>(&mut self, init_mode: bool, f: F) -> R
     where
         F: FnOnce(&RTC) -> R,
@@ -585,6 +813,9 @@ impl Rtc {
         let result = f(&self.rtc);
         if init_mode {
             rtc_registers::exit_init_mode(&self.rtc);
+            // The shadow registers are updated ~2 RTCCLK cycles after counting restarts,
+            // until then they hold the calendar before the change
+            while !rtc_registers::is_synchronized(&self.rtc) {}
         }
 
         // Re-enable write protection.
@@ -608,9 +839,25 @@ impl Rtc {
     ///
     /// The registers retain their values during wakes from standby mode or system resets. They also
     /// retain their value when Vdd is switched off as long as V_BAT is powered.
+    ///
+    /// Writes to registers beyond [`Rtc::BACKUP_REGISTER_COUNT`] are ignored. The backup domain
+    /// write protection is lifted when the `Rtc` is created.
     pub fn write_backup_register(&self, register: usize, value: u32) {
         rtc_registers::write_backup_register(&self.rtc, register, value)
     }
+
+    /// Returns `true` if a tamper event erased the backup registers
+    ///
+    /// The backup registers are also erased by a backup domain reset, which happens when
+    /// the RTC clock source is changed, see [`Rtc::set_config`]. That case is not flagged.
+    pub fn backup_registers_cleared(&self) -> bool {
+        rtc_registers::is_tamper_flag_set(&self.rtc)
+    }
+
+    /// Clears the tamper event flags reported by [`Rtc::backup_registers_cleared`]
+    pub fn clear_tamper_flags(&mut self) {
+        rtc_registers::clear_tamper_flags(&self.rtc)
+    }
 }
 
 /// The RTC wakeup timer
@@ -737,26 +984,7 @@ impl timer::Cancel for WakeupTimer<'_> {
 /// Raw set time
 /// Expects init mode enabled and write protection disabled
 fn set_time_raw(rtc: &RTC, time: Time) {
-    let (ht, hu) = byte_to_bcd2(time.hour() as u8);
-    let (mnt, mnu) = byte_to_bcd2(time.minute() as u8);
-    let (st, su) = byte_to_bcd2(time.second() as u8);
-
-    rtc.tr.write(|w| unsafe {
-        w.ht()
-            .bits(ht)
-            .hu()
-            .bits(hu)
-            .mnt()
-            .bits(mnt)
-            .mnu()
-            .bits(mnu)
-            .st()
-            .bits(st)
-            .su()
-            .bits(su)
-            .pm()
-            .clear_bit()
-    });
+    rtc.tr.write(|w| unsafe { w.bits(time_register(time)) });
 }
 
 fn set_daylight_savings_raw(rtc: &RTC, daylight_savings: bool) {
@@ -766,46 +994,192 @@ fn set_daylight_savings_raw(rtc: &RTC, daylight_savings: bool) {
 /// Raw set date
 /// Expects init mode enabled and write protection disabled
 fn set_date_raw(rtc: &RTC, date: Date) {
-    let (dt, du) = byte_to_bcd2(date.day() as u8);
-    let (mt, mu) = byte_to_bcd2(date.month() as u8);
-    let yr = date.year() as u16;
-    let yr_offset = (yr - 1970_u16) as u8;
-    let (yt, yu) = byte_to_bcd2(yr_offset);
-
-    rtc.dr.write(|w| unsafe {
-        w.dt()
-            .bits(dt)
-            .du()
-            .bits(du)
-            .mt()
-            .bit(mt > 0)
-            .mu()
-            .bits(mu)
-            .yt()
-            .bits(yt)
-            .yu()
-            .bits(yu)
-            .wdu()
-            .bits(date.weekday().number_from_monday() as u8)
-    });
+    rtc.dr.write(|w| unsafe { w.bits(date_register(date)) });
 }
 
-fn byte_to_bcd2(byte: u8) -> (u8, u8) {
-    let mut bcd_high: u8 = 0;
-    let mut value = byte;
+/// Value of the RTC_TR register in the 24 hour format
+fn time_register(time: Time) -> u32 {
+    // PM (bit 22) stays clear, HT/HU (bits 16-21), MNT/MNU (bits 8-14), ST/SU (bits 0-6)
+    bcd(time.hour()) << 16 | bcd(time.minute()) << 8 | bcd(time.second())
+}
 
-    while value >= 10 {
-        bcd_high += 1;
-        value -= 10;
-    }
+/// Time of day in the RTC_TR register `tr` in the 24 hour format, with `micro`
+/// microseconds
+fn time_from_register(tr: u32, micro: u32) -> Time {
+    let hour = from_bcd(tr, 16, 0b11);
+    let minute = from_bcd(tr, 8, 0b111);
+    let second = from_bcd(tr, 0, 0b111);
+    Time::from_hms_micro(hour, minute, second, micro).unwrap()
+}
+
+/// Value of the RTC_DR register
+///
+/// The RTC counts the years 00 to 99 and takes every fourth one as a leap year, which
+/// matches the years 2000 to 2099.
+fn date_register(date: Date) -> u32 {
+    let (yt, yu) = year_to_bcd2(date.year());
+    // YT/YU (bits 16-23), WDU (bits 13-15), MT/MU (bits 8-12), DT/DU (bits 0-5)
+    u32::from(yt) << 20
+        | u32::from(yu) << 16
+        | u32::from(date.weekday().number_from_monday()) << 13
+        | bcd(date.month() as u8) << 8
+        | bcd(date.day())
+}
 
-    (bcd_high, ((bcd_high << 4) | value) as u8)
+/// Date in the RTC_DR register `dr`
+fn date_from_register(dr: u32) -> Date {
+    let year = bcd2_to_year((((dr >> 20) & 0xf) as u8, ((dr >> 16) & 0xf) as u8));
+    let month = from_bcd(dr, 8, 0b1);
+    let day = from_bcd(dr, 0, 0b11);
+    Date::from_calendar_date(year.into(), month.try_into().unwrap(), day).unwrap()
 }
 
+/// Packs a value below 100 into the tens (bits 4-7) and the units (bits 0-3)
+fn bcd(value: u8) -> u32 {
+    let (tens, units) = byte_to_bcd2(value);
+    u32::from(tens) << 4 | u32::from(units)
+}
+
+/// Unpacks the value at `shift` in `register`, where `tens` masks the tens digit
+fn from_bcd(register: u32, shift: u32, tens: u32) -> u8 {
+    let register = register >> shift;
+    bcd2_to_byte((((register >> 4) & tens) as u8, (register & 0xf) as u8))
+}
+
+/// Splits a value below 100 into its tens and units digits
+fn byte_to_bcd2(byte: u8) -> (u8, u8) {
+    (byte / 10, byte % 10)
+}
+
+/// Joins the tens and units digits
 fn bcd2_to_byte(bcd: (u8, u8)) -> u8 {
-    let value = bcd.1 | bcd.0 << 4;
+    bcd.0 * 10 + bcd.1
+}
+
+/// Splits the year into the digits of the RTC, which counts from 2000
+///
+/// # Panics
+///
+/// Panics if the year is not in `2000..=2099`.
+fn year_to_bcd2(year: i32) -> (u8, u8) {
+    assert!(
+        (2000..=2099).contains(&year),
+        "year outside of the RTC calendar"
+    );
+    byte_to_bcd2((year - 2000) as u8)
+}
+
+/// Joins the digits of the RTC year, which counts from 2000
+fn bcd2_to_year(bcd: (u8, u8)) -> u16 {
+    u16::from(bcd2_to_byte(bcd)) + 2000
+}
+
+/// Microseconds of the subsecond register `ss`, which counts down from the synchronous
+/// prescaler `sync_p`
+///
+/// SS is above `sync_p` after a shift of the calendar backwards, until the next second.
+fn subsecond_micros(sync_p: u16, ss: u16) -> u32 {
+    let sync_p = u64::from(sync_p);
+    (sync_p.saturating_sub(u64::from(ss)) * 1_000_000 / (sync_p + 1)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bcd2_to_byte, bcd2_to_year, byte_to_bcd2, date_from_register, date_register,
+        subsecond_micros, time_from_register, time_register, year_to_bcd2,
+    };
+    use time::{Date, Month, Time};
+
+    const TR_PM: u32 = 1 << 22;
+
+    #[test]
+    fn bcd() {
+        assert_eq!(byte_to_bcd2(0), (0, 0));
+        assert_eq!(byte_to_bcd2(59), (5, 9));
+        for byte in 0..=99 {
+            let (tens, units) = byte_to_bcd2(byte);
+            assert!(tens < 10 && units < 10);
+            assert_eq!(bcd2_to_byte((tens, units)), byte);
+        }
+    }
+
+    #[test]
+    fn year_base() {
+        assert_eq!(year_to_bcd2(2000), (0, 0));
+        assert_eq!(year_to_bcd2(2024), (2, 4));
+        assert_eq!(year_to_bcd2(2099), (9, 9));
+        assert_eq!(bcd2_to_year((0, 0)), 2000);
+        assert_eq!(bcd2_to_year((7, 0)), 2070);
+        for year in 2000..=2099 {
+            assert_eq!(i32::from(bcd2_to_year(year_to_bcd2(year))), year);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn year_before_2000() {
+        year_to_bcd2(1999);
+    }
 
-    let tmp = ((value & 0xF0) >> 0x4) * 10;
+    #[test]
+    #[should_panic]
+    fn year_after_2099() {
+        year_to_bcd2(2100);
+    }
 
-    tmp + (value & 0x0F)
+    #[test]
+    fn time_round_trip() {
+        for &hour in [0, 1, 11, 12, 13, 23].iter() {
+            for &(minute, second) in [(0, 0), (30, 9), (59, 59)].iter() {
+                let time = Time::from_hms(hour, minute, second).unwrap();
+                let tr = time_register(time);
+                assert_eq!(tr & TR_PM, 0, "{}", time);
+                assert_eq!(time_from_register(tr, 0), time);
+            }
+        }
+        assert_eq!(time_register(Time::MIDNIGHT), 0);
+        assert_eq!(
+            time_register(Time::from_hms(12, 0, 0).unwrap()),
+            0x0012_0000
+        );
+        assert_eq!(
+            time_register(Time::from_hms(23, 59, 59).unwrap()),
+            0x0023_5959
+        );
+        assert_eq!(
+            time_from_register(0x0023_5959, 996_093),
+            Time::from_hms_micro(23, 59, 59, 996_093).unwrap()
+        );
+    }
+
+    #[test]
+    fn date_round_trip() {
+        let date = |year, month, day| Date::from_calendar_date(year, month, day).unwrap();
+        // Wednesday
+        assert_eq!(date_register(date(2024, Month::January, 31)), 0x0024_6131);
+        // Thursday
+        assert_eq!(date_register(date(2099, Month::December, 31)), 0x0099_9231);
+        // Tuesday of a leap year
+        assert_eq!(date_register(date(2000, Month::February, 29)), 0x0000_4229);
+
+        for &year in [2000, 2023, 2024, 2099].iter() {
+            let mut day = date(year, Month::January, 1);
+            while day.year() == year {
+                assert_eq!(date_from_register(date_register(day)), day);
+                day = day.next_day().unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn subseconds() {
+        assert_eq!(subsecond_micros(255, 255), 0);
+        assert_eq!(subsecond_micros(255, 127), 500_000);
+        assert_eq!(subsecond_micros(255, 0), 996_093);
+        assert_eq!(subsecond_micros(0, 0), 0);
+        assert_eq!(subsecond_micros(0x7fff, 0), 999_969);
+        // After a shift backwards
+        assert_eq!(subsecond_micros(255, 300), 0);
+    }
 }
@@ -20,6 +20,16 @@ pub fn exit_init_mode(rtc: &RTC) {
     rtc.isr.modify(|_, w| w.init().clear_bit()); // Exits init mode
 }
 
+/// true if rsf bit indicates the calendar shadow registers hold the current calendar
+pub fn is_synchronized(rtc: &RTC) -> bool {
+    rtc.isr.read().rsf().bit_is_set()
+}
+
+/// the rsf bit is set again at the next copy of the calendar to the shadow registers
+pub fn clear_synchronization_flag(rtc: &RTC) {
+    rtc.isr.modify(|_, w| w.rsf().clear_bit());
+}
+
 /// has wakeup timer expired?
 pub fn is_wakeup_timer_flag_set(rtc: &RTC) -> bool {
     rtc.isr.read().wutf().bit_is_set()
@@ -115,3 +125,21 @@ pub fn write_backup_register(rtc: &RTC, register: usize, value: u32) {
         unsafe { rtc.bkpr[register].write(|w| w.bits(value)) }
     }
 }
+
+/// has a tamper event occurred, which erases the backup registers
+pub fn is_tamper_flag_set(rtc: &RTC) -> bool {
+    let isr = rtc.isr.read();
+    isr.tamp1f().bit_is_set() || isr.tamp2f().bit_is_set() || isr.tamp3f().bit_is_set()
+}
+
+/// clear the tamper event flags
+pub fn clear_tamper_flags(rtc: &RTC) {
+    rtc.isr.modify(|_, w| {
+        w.tamp1f()
+            .clear_bit()
+            .tamp2f()
+            .clear_bit()
+            .tamp3f()
+            .clear_bit()
+    });
+}
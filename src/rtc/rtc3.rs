@@ -26,6 +26,16 @@ pub fn exit_init_mode(rtc: &RTC) {
     rtc.icsr.modify(|_, w| w.init().clear_bit()); // Exits init mode
 }
 
+/// true if rsf bit indicates the calendar shadow registers hold the current calendar
+pub fn is_synchronized(rtc: &RTC) -> bool {
+    rtc.icsr.read().rsf().bit_is_set()
+}
+
+/// the rsf bit is set again at the next copy of the calendar to the shadow registers
+pub fn clear_synchronization_flag(rtc: &RTC) {
+    rtc.icsr.modify(|_, w| w.rsf().clear_bit());
+}
+
 /// has wakeup timer expired?
 pub fn is_wakeup_timer_flag_set(rtc: &RTC) -> bool {
     rtc.sr.read().wutf().bit_is_set()
@@ -86,14 +96,21 @@ pub fn is_alarm_b_accessible(_rtc: &RTC) -> bool {
 // L41/2, L4P/Q and L4R/S have 32 backup registers
 pub const BACKUP_REGISTER_COUNT: usize = 32;
 
+// The backup registers and tamper flags of RTC type 3 are in the TAMP peripheral, which
+// is missing from the PAC
+const TAMP: usize = 0x4000_3400;
+const TAMP_SR: *const u32 = (TAMP + 0x30) as *const u32;
+const TAMP_SCR: *mut u32 = (TAMP + 0x3c) as *mut u32;
+const TAMP_BKP0R: *mut u32 = (TAMP + 0x100) as *mut u32;
+
 /// Read content of the backup register.
 ///
 /// The registers retain their values during wakes from standby mode or system resets. They also
 /// retain their value when Vdd is switched off as long as V_BAT is powered.
 pub fn read_backup_register(_rtc: &RTC, register: usize) -> Option<u32> {
     if register < BACKUP_REGISTER_COUNT {
-        //Some(rtc.bkpr[register].read().bits())
-        None // RTC3 backup registers come from the TAMP peripe=heral, not RTC. Not even in the L412 PAC
+        // NOTE(unsafe) the index is within the backup registers of the TAMP
+        Some(unsafe { core::ptr::read_volatile(TAMP_BKP0R.add(register)) })
     } else {
         None
     }
@@ -103,9 +120,22 @@ pub fn read_backup_register(_rtc: &RTC, register: usize) -> Option<u32> {
 ///
 /// The registers retain their values during wakes from standby mode or system resets. They also
 /// retain their value when Vdd is switched off as long as V_BAT is powered.
-pub fn write_backup_register(_rtc: &RTC, register: usize, _value: u32) {
+pub fn write_backup_register(_rtc: &RTC, register: usize, value: u32) {
     if register < BACKUP_REGISTER_COUNT {
-        // RTC3 backup registers come from the TAMP peripe=heral, not RTC. Not even in the L412 PAC
-        //unsafe { self.rtc.bkpr[register].write(|w| w.bits(value)) }
+        // NOTE(unsafe) the index is within the backup registers of the TAMP
+        unsafe { core::ptr::write_volatile(TAMP_BKP0R.add(register), value) }
     }
 }
+
+/// has a tamper event occurred, which erases the backup registers
+pub fn is_tamper_flag_set(_rtc: &RTC) -> bool {
+    // NOTE(unsafe) atomic read with no side effects
+    // TAMP1F to TAMP3F, ITAMP3F, ITAMP5F and ITAMP8F
+    unsafe { core::ptr::read_volatile(TAMP_SR) & 0x0094_0007 != 0 }
+}
+
+/// clear the tamper event flags
+pub fn clear_tamper_flags(_rtc: &RTC) {
+    // NOTE(unsafe) write-1-to-clear register
+    unsafe { core::ptr::write_volatile(TAMP_SCR, 0x0094_0007) }
+}
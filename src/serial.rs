@@ -7,44 +7,24 @@ use core::marker::PhantomData;
 use core::ops::DerefMut;
 use core::ptr;
 use core::sync::atomic::{self, Ordering};
-use embedded_dma::StaticWriteBuffer;
+use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
 use stable_deref_trait::StableDeref;
 
 use crate::hal::serial::{self, Write};
+use crate::hal::spi::{Mode, Phase, Polarity};
 
 use crate::dma::{
-    dma1, CircBuffer, DMAFrame, FrameReader, FrameSender, Receive, RxDma, TransferPayload,
-    Transmit, TxDma,
+    dma1, dma2, CircBuffer, DMAFrame, FrameReader, FrameSender, Receive, RxDma, Transfer,
+    TransferPayload, Transmit, TxDma, R,
 };
 use crate::dmamux::{DmaInput, DmaMux};
 use crate::gpio::{self, Alternate, OpenDrain, PushPull};
 use crate::pac;
-use crate::rcc::{Clocks, Enable, RccBus, Reset};
-use crate::time::{Bps, U32Ext};
-
-#[cfg(any(
-    //feature = "stm32l451", // missing PAC support
-    feature = "stm32l452",
-    feature = "stm32l462",
-    // feature = "stm32l471", // missing PAC support
-    feature = "stm32l475",
-    feature = "stm32l476",
-    feature = "stm32l485",
-    feature = "stm32l486",
-    feature = "stm32l496",
-    feature = "stm32l4a6",
-    // feature = "stm32l4p5",
-    // feature = "stm32l4q5",
-    // feature = "stm32l4r5",
-    // feature = "stm32l4s5",
-    // feature = "stm32l4r7",
-    // feature = "stm32l4s7",
-    feature = "stm32l4r9",
-    feature = "stm32l4s9",
-))]
-use crate::dma::dma2;
+use crate::rcc::{Clocks, Enable, KernelClock, RateChange, RccBus, Reset, UsartClockSource};
+use crate::time::{Bps, MicroSeconds, U32Ext};
 
 /// Interrupt event
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
     /// New data has been received
     Rxne,
@@ -54,25 +34,59 @@ pub enum Event {
     Idle,
     /// Character match
     CharacterMatch,
-    /// Receiver timeout
+    /// Receiver timeout, which the LPUART lacks
     ReceiverTimeout,
+    /// The CTS input has changed, see [`Tx::is_cts_changed`]
+    Cts,
+    /// Wakeup from Stop mode, see [`Serial::enable_stop_wakeup`]
+    WakeupFromStop,
+    /// Line break, detected with [`Config::break_detection`]
+    ///
+    /// Without it, and on the LPUART, a break is received as a 0 character with
+    /// [`Error::Framing`].
+    LineBreak,
 }
 
 /// Serial error
 #[non_exhaustive]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Framing error
     Framing,
     /// Noise error
     Noise,
     /// RX buffer overrun
+    ///
+    /// The characters after the one in the receive register are lost, the `read`
+    /// methods return that one first.
     Overrun,
     /// Parity check error
+    ///
+    /// The character is still received, the next read returns it.
     Parity,
+    /// The kernel clock does not run in Stop mode, see [`Serial::enable_stop_wakeup`]
+    WakeupClock,
+    /// The baud rate could not be detected, see [`Serial::request_autobaud`]
+    Autobaud,
+}
+
+/// USART data bits of a character, without the parity bit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WordLength {
+    /// 7 data bits
+    Bits7,
+    /// 8 data bits
+    Bits8,
+    /// 9 data bits, read and written with `read_u16` and `write_u16`
+    ///
+    /// Without parity only, a mark or space parity can be sent as the 9th bit.
+    Bits9,
 }
 
 /// USART parity settings
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Parity {
     /// No parity
     ParityNone,
@@ -83,6 +97,7 @@ pub enum Parity {
 }
 
 /// USART stopbits settings
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum StopBits {
     /// 1 stop bit
     STOP1,
@@ -95,6 +110,7 @@ pub enum StopBits {
 }
 
 /// USART oversampling settings
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Oversampling {
     /// Oversample 8 times (allows for faster data rates)
     Over8,
@@ -102,9 +118,136 @@ pub enum Oversampling {
     Over16,
 }
 
+/// Event which wakes the serial up from Stop mode, see [`Serial::enable_stop_wakeup`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WakeSource {
+    /// The start bit of a character
+    StartBit,
+    /// An address character, with the MSB set, matching the 7-bit address
+    ///
+    /// The address replaces the one of [`Config::character_match`].
+    AddressMatch(u8),
+    /// A received character, as with RXNE
+    RxNotEmpty,
+}
+
+/// Event which wakes the receiver up from mute mode, see [`Config::mute_mode`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MuteMode {
+    /// The line going idle
+    IdleLine,
+    /// An address character, with the MSB set, matching the address in the bits below
+    /// the MSB
+    ///
+    /// The address replaces the one of [`Config::character_match`].
+    AddressMark(u8),
+}
+
+/// Minimum length of a detected line break, see [`Config::break_detection`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BreakDetection {
+    /// 10 bit times low
+    Bits10,
+    /// 11 bit times low
+    Bits11,
+}
+
+/// Character from which the baud rate is detected, see [`Serial::request_autobaud`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum AutobaudMode {
+    /// The length of the start bit, of any character with the LSB set
+    StartBit,
+    /// The falling edges of the start bit and the bit 1, of a character starting with
+    /// 0b10, e.g. `b'a'` in LSB first order
+    FallingEdge,
+    /// A 0x7F character
+    Frame0x7F,
+    /// A 0x55 character, which is `b'U'`
+    Frame0x55,
+}
+
+/// Hardware flow control
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FlowControl {
+    /// No flow control
+    None,
+    /// RTS only, asserted while the receiver can take data
+    Rts,
+    /// CTS only, the transmitter only sends while CTS is asserted
+    Cts,
+    /// Both RTS and CTS
+    RtsCts,
+}
+
+/// Clock output configuration of the synchronous mode, see [`Serial::new_synchronous`]
+#[derive(Clone, Copy)]
+pub struct SynchronousConfig {
+    mode: Mode,
+    last_bit_clock: bool,
+    msb_first: bool,
+}
+
+impl SynchronousConfig {
+    /// Clock polarity and phase of the CK output, as for an SPI
+    ///
+    /// The last bit of a character is clocked out and the data is sent LSB first.
+    pub fn new(mode: Mode) -> Self {
+        SynchronousConfig {
+            mode,
+            last_bit_clock: true,
+            msb_first: false,
+        }
+    }
+
+    /// Whether CK pulses for the last data bit of a character, needed for 8 clocks per
+    /// byte as with an SPI
+    pub fn last_bit_clock(mut self, last_bit_clock: bool) -> Self {
+        self.last_bit_clock = last_bit_clock;
+        self
+    }
+
+    /// Send and receive the data MSB first, as most SPI devices do
+    ///
+    /// [`Config::msb_first`] does the same.
+    pub fn msb_first(mut self, msb_first: bool) -> Self {
+        self.msb_first = msb_first;
+        self
+    }
+}
+
+impl From<Mode> for SynchronousConfig {
+    fn from(mode: Mode) -> SynchronousConfig {
+        SynchronousConfig::new(mode)
+    }
+}
+
+/// Polarity of the RS-485 driver enable output
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DePolarity {
+    /// DE is high while the driver is enabled
+    ActiveHigh,
+    /// DE is low while the driver is enabled
+    ActiveLow,
+}
+
+/// RS-485 driver enable configuration, see [`Config::rs485_de`]
+#[derive(Clone, Copy, Debug)]
+struct DriverEnable {
+    polarity: DePolarity,
+    assertion_time: MicroSeconds,
+    deassertion_time: MicroSeconds,
+}
+
 /// USART Configuration structure
 pub struct Config {
     baudrate: Bps,
+    word_length: WordLength,
     parity: Parity,
     stopbits: StopBits,
     oversampling: Oversampling,
@@ -112,6 +255,15 @@ pub struct Config {
     receiver_timeout: Option<u32>,
     disable_overrun: bool,
     onebit_sampling: bool,
+    driver_enable: Option<DriverEnable>,
+    flow_control: Option<FlowControl>,
+    break_detection: Option<BreakDetection>,
+    mute_mode: Option<MuteMode>,
+    swap_pins: bool,
+    invert_tx: bool,
+    invert_rx: bool,
+    invert_data: bool,
+    msb_first: bool,
 }
 
 impl Config {
@@ -121,6 +273,15 @@ impl Config {
         self
     }
 
+    /// Set the number of data bits, the parity bit comes on top
+    ///
+    /// The frame may have up to 9 bits, 9 data bits with parity panic in the serial
+    /// constructor.
+    pub fn word_length(mut self, word_length: WordLength) -> Self {
+        self.word_length = word_length;
+        self
+    }
+
     /// Set parity to none
     pub fn parity_none(mut self) -> Self {
         self.parity = Parity::ParityNone;
@@ -145,13 +306,15 @@ impl Config {
         self
     }
 
-    /// Set the oversampling size
+    /// Set the oversampling size, which the LPUART ignores
     pub fn oversampling(mut self, oversampling: Oversampling) -> Self {
         self.oversampling = oversampling;
         self
     }
 
     /// Set the character match character
+    ///
+    /// See [`Serial::set_character_match`] to change it later.
     pub fn character_match(mut self, character_match: u8) -> Self {
         self.character_match = Some(character_match);
         self
@@ -159,7 +322,8 @@ impl Config {
 
     /// Set the receiver timeout, the value is the number of bit durations
     ///
-    /// Note that it only takes 24 bits, using more than this will cause a panic.
+    /// Note that it only takes 24 bits, using more than this will cause a panic. The
+    /// LPUART has no receiver timeout, its constructor panics with this.
     pub fn receiver_timeout(mut self, receiver_timeout: u32) -> Self {
         assert!(receiver_timeout < 1 << 24);
         self.receiver_timeout = Some(receiver_timeout);
@@ -172,11 +336,99 @@ impl Config {
         self
     }
 
-    /// Change to onebit sampling
+    /// Change to onebit sampling, which the LPUART lacks
     pub fn with_onebit_sampling(mut self) -> Self {
         self.onebit_sampling = true;
         self
     }
+
+    /// Swaps the TX and RX pin functions, e.g. for a board with crossed lines
+    ///
+    /// The swap is electrical only, the pins are still passed in their usual
+    /// positions: the pin of the TX position receives, and the one of the RX position
+    /// transmits.
+    pub fn swap_pins(mut self, swap: bool) -> Self {
+        self.swap_pins = swap;
+        self
+    }
+
+    /// Inverts the TX pin level, the line idles low
+    pub fn invert_tx(mut self, invert: bool) -> Self {
+        self.invert_tx = invert;
+        self
+    }
+
+    /// Inverts the RX pin level, the line idles low
+    pub fn invert_rx(mut self, invert: bool) -> Self {
+        self.invert_rx = invert;
+        self
+    }
+
+    /// Inverts the data bits, including the parity bit, but not the start and stop bits
+    pub fn invert_data(mut self, invert: bool) -> Self {
+        self.invert_data = invert;
+        self
+    }
+
+    /// Transmits and receives the MSB first, instead of the LSB
+    pub fn msb_first(mut self, msb_first: bool) -> Self {
+        self.msb_first = msb_first;
+        self
+    }
+
+    /// Configures the RS-485 driver enable output, used with a DE pin in the pins of
+    /// the serial
+    ///
+    /// DE is asserted `assertion_time` before the start bit of the first byte and
+    /// deasserted `deassertion_time` after the stop bit of the last byte. The times are
+    /// rounded up to sample times, 1/16 or 1/8 bit depending on the oversampling, and
+    /// may not exceed 31 sample times, or the serial constructor panics.
+    ///
+    /// Without this, DE is active high and asserted 15 sample times early and late.
+    pub fn rs485_de(
+        mut self,
+        polarity: DePolarity,
+        assertion_time: MicroSeconds,
+        deassertion_time: MicroSeconds,
+    ) -> Self {
+        self.driver_enable = Some(DriverEnable {
+            polarity,
+            assertion_time,
+            deassertion_time,
+        });
+        self
+    }
+
+    /// Set the hardware flow control
+    ///
+    /// Without this, the flow control follows the pins of the serial, RTS and CTS are
+    /// enabled when they are part of the pins. Enabling RTS or CTS without its pin
+    /// panics in the serial constructor.
+    pub fn flow_control(mut self, flow_control: FlowControl) -> Self {
+        self.flow_control = Some(flow_control);
+        self
+    }
+
+    /// Enables the mute mode for multi-drop buses, in which the receiver ignores the
+    /// characters from [`Rx::enter_mute_mode`] until `wake`
+    ///
+    /// With [`MuteMode::AddressMark`], the address character which wakes the receiver
+    /// up is received, and the next address character for another node mutes it again.
+    /// Together with [`Config::character_match`], the constructor panics.
+    pub fn mute_mode(mut self, wake: MuteMode) -> Self {
+        self.mute_mode = Some(wake);
+        self
+    }
+
+    /// Detects line breaks of at least `length`, raising [`Event::LineBreak`]
+    ///
+    /// This enables the LIN mode, which requires 1 stop bit, excludes the half duplex
+    /// and synchronous modes and makes [`Tx::send_break`] send 13 bits. The LPUART has
+    /// no LIN mode. Otherwise the serial constructor panics.
+    pub fn break_detection(mut self, length: BreakDetection) -> Self {
+        self.break_detection = Some(length);
+        self
+    }
 }
 
 impl Default for Config {
@@ -184,6 +436,7 @@ impl Default for Config {
         let baudrate = 115_200_u32.bps();
         Config {
             baudrate,
+            word_length: WordLength::Bits8,
             parity: Parity::ParityNone,
             stopbits: StopBits::STOP1,
             oversampling: Oversampling::Over16,
@@ -191,6 +444,15 @@ impl Default for Config {
             receiver_timeout: None,
             disable_overrun: false,
             onebit_sampling: false,
+            driver_enable: None,
+            flow_control: None,
+            break_detection: None,
+            mute_mode: None,
+            swap_pins: false,
+            invert_tx: false,
+            invert_rx: false,
+            invert_data: false,
+            msb_first: false,
         }
     }
 }
@@ -204,6 +466,11 @@ impl From<Bps> for Config {
     }
 }
 
+/// Placeholder for the unused RTS or CTS pin when only one of them is used
+///
+/// E.g. `(tx, rx, rts, NoPin)` for RTS only or `(tx, rx, NoPin, cts)` for CTS only.
+pub struct NoPin;
+
 /// Serial abstraction
 pub struct Serial<USART, PINS> {
     usart: USART,
@@ -220,12 +487,100 @@ pub struct Tx<USART> {
     _usart: PhantomData<USART>,
 }
 
+// Flags of ISR, the same on the USARTs, UARTs and LPUART. The clear flags of the errors
+// are at the same bits in ICR.
+const ISR_PE: u32 = 1 << 0;
+const ISR_FE: u32 = 1 << 1;
+const ISR_NF: u32 = 1 << 2;
+const ISR_ORE: u32 = 1 << 3;
+const ISR_RXNE: u32 = 1 << 5;
+const ISR_TC: u32 = 1 << 6;
+
+/// The first receive error flagged in `isr` and its flag, see [`Rx::check_errors`]
+fn receive_error(isr: u32) -> Option<(Error, u32)> {
+    [
+        (Error::Parity, ISR_PE),
+        (Error::Framing, ISR_FE),
+        (Error::Noise, ISR_NF),
+        (Error::Overrun, ISR_ORE),
+    ]
+    .iter()
+    .copied()
+    .find(|&(_, flag)| isr & flag != 0)
+}
+
+/// The receive error the `read` methods report for `isr` before the character, and its
+/// flag
+///
+/// The parity, framing and noise errors are the ones of the character in RDR, which the
+/// next read returns. On an overrun, RDR holds the last character before the lost ones,
+/// so it is read before ORE is reported and cleared.
+fn read_error(isr: u32) -> Option<(Error, u32)> {
+    receive_error(isr).filter(|&(error, _)| error != Error::Overrun || isr & ISR_RXNE == 0)
+}
+
+/// Accesses of a DMA transmission to the USART and its DMA channel
+trait DmaTransmission {
+    /// Clears TC of the USART
+    fn clear_transmission_complete(&mut self);
+    /// Enables the DMA channel
+    fn start_channel(&mut self);
+    /// The DMA wrote the last character to TDR
+    fn is_done(&self) -> bool;
+    /// ISR of the USART
+    fn isr(&self) -> u32;
+}
+
+/// Starts a DMA transmission, once the channel is set up
+///
+/// TC is still set from the previous transmission. Clearing it before the start is
+/// safe, the DMA only writes TDR once TXE is set. The first character goes out once the
+/// previous one left the shift register.
+fn start_transmission(tx: &mut impl DmaTransmission) {
+    tx.clear_transmission_complete();
+
+    // NOTE(compiler_fence) operations on the buffer should not be reordered after
+    // the next statement, which starts the DMA transfer
+    atomic::compiler_fence(Ordering::Release);
+
+    tx.start_channel();
+}
+
+/// Whether the last character of a DMA transmission is on the wire
+///
+/// TC of the USART is set once the shift register is empty, after the DMA is done. As
+/// [`start_transmission`] cleared it, a TC of the previous transmission does not count.
+fn transmission_complete(tx: &impl DmaTransmission) -> bool {
+    tx.is_done() && tx.isr() & ISR_TC != 0
+}
+
+/// The receiver enable of half duplex before transmitting if `transmit`, or receiving,
+/// `None` if it stays as it is
+///
+/// The receiver is off while transmitting, so that it does not receive the echo, and on
+/// again once TC is set, after the last character.
+fn half_duplex_receiver(transmit: bool, receiving: bool, isr: u32) -> Option<bool> {
+    if transmit && receiving {
+        Some(false)
+    } else if !transmit && !receiving && isr & ISR_TC != 0 {
+        Some(true)
+    } else {
+        None
+    }
+}
+
+/// Expands to the first block for the USARTs and UARTs, to the second for the LPUART
+macro_rules! by_kind {
+    (usart, { $($usart:tt)* }, { $($lpuart:tt)* }) => { $($usart)* };
+    (lpuart, { $($usart:tt)* }, { $($lpuart:tt)* }) => { $($lpuart)* };
+}
+
 macro_rules! hal {
     ($(
         $(#[$meta:meta])*
         $USARTX:ident: (
             $usartX:ident,
-            $pclkX:ident,
+            $kind:ident,
             tx: ($txdma:ident, $dmatxch:path, $dmatxsel:path),
             rx: ($rxdma:ident, $dmarxch:path, $dmarxsel:path)
         ),
@@ -239,10 +594,12 @@ macro_rules! hal {
                 ///
                 /// `Clocks` passes information about the current frequencies of
                 /// the clocks.  The existence of the struct ensures that the
-                /// clock settings are fixed.
+                /// clock settings are fixed. The baud rate is derived from the kernel
+                /// clock selected with [`CCIPR::set_kernel_clock`](crate::rcc::CCIPR::set_kernel_clock).
                 ///
                 /// The `serial` struct takes ownership over the `USARTX` device
-                /// registers and the specified `PINS`
+                /// registers and the specified `PINS`. `(tx, rx, rts, cts)` enables the
+                /// hardware flow control, [`NoPin`] stands in for an unused RTS or CTS.
                 ///
                 /// `MAPR` and `APBX` are register handles which are passed for
                 /// configuration. (`MAPR` is used to map the USART to the
@@ -268,50 +625,107 @@ macro_rules! hal {
                     usart.cr2.reset();
                     usart.cr3.reset();
 
-                    // Configure baud rate
-                    match config.oversampling {
-                        Oversampling::Over8 => {
-                            let uartdiv = 2 * clocks.$pclkX().raw() / config.baudrate.0;
-                            assert!(uartdiv >= 16, "impossible baud rate");
-
-                            let lower = (uartdiv & 0xf) >> 1;
-                            let brr = (uartdiv & !0xf) | lower;
-
-                            usart.cr1.modify(|_, w| w.over8().set_bit());
-                            usart.brr.write(|w| unsafe { w.bits(brr) });
+                    // Configure baud rate from the kernel clock selected in CCIPR
+                    let kernel_clock = clocks.kernel_clock::<pac::$USARTX>().raw();
+                    by_kind!($kind, {
+                        match config.oversampling {
+                            Oversampling::Over8 => {
+                                let uartdiv = 2 * kernel_clock / config.baudrate.0;
+                                assert!(uartdiv >= 16, "impossible baud rate");
+
+                                let lower = (uartdiv & 0xf) >> 1;
+                                let brr = (uartdiv & !0xf) | lower;
+
+                                usart.cr1.modify(|_, w| w.over8().set_bit());
+                                usart.brr.write(|w| unsafe { w.bits(brr) });
+                            }
+                            Oversampling::Over16 => {
+                                let brr = kernel_clock / config.baudrate.0;
+                                assert!(brr >= 16, "impossible baud rate");
+
+                                usart.brr.write(|w| unsafe { w.bits(brr) });
+                            }
                         }
-                        Oversampling::Over16 => {
-                            let brr = clocks.$pclkX().raw() / config.baudrate.0;
-                            assert!(brr >= 16, "impossible baud rate");
+                    }, {
+                        // No oversampling, BRR = 256 × f_ck / baud rate
+                        let baudrate = u64::from(config.baudrate.0);
+                        let brr = (256 * u64::from(kernel_clock) + baudrate / 2) / baudrate;
+                        assert!((0x300..1 << 20).contains(&brr), "impossible baud rate");
+
+                        usart.brr.write(|w| unsafe { w.bits(brr as u32) });
+
+                        assert!(
+                            config.receiver_timeout.is_none(),
+                            "LPUART without receiver timeout"
+                        );
+                        assert!(!config.onebit_sampling, "LPUART without one bit sampling");
+                    });
 
-                            usart.brr.write(|w| unsafe { w.bits(brr) });
+                    by_kind!($kind, {
+                        if let Some(val) = config.receiver_timeout {
+                            usart.rtor.modify(|_, w| w.rto().bits(val));
                         }
-                    }
-
-                    if let Some(val) = config.receiver_timeout {
-                        usart.rtor.modify(|_, w| w.rto().bits(val));
-                    }
+                    }, {});
 
                     // enable DMA transfers
                     usart.cr3.modify(|_, w| w.dmat().set_bit().dmar().set_bit());
 
                     // Configure hardware flow control (CTS/RTS or RS485 Driver Enable)
-                    if PINS::FLOWCTL {
-                        usart.cr3.modify(|_, w| w.rtse().set_bit().ctse().set_bit());
-                    } else if PINS::DEM {
-                        usart.cr3.modify(|_, w| w.dem().set_bit());
-
-                        // Pre/post driver enable set conservative to the max time
-                        usart.cr1.modify(|_, w| w.deat().bits(0b1111).dedt().bits(0b1111));
+                    if PINS::DEM {
+                        let (polarity, deat, dedt) = match config.driver_enable {
+                            Some(de) => {
+                                let oversampling = match config.oversampling {
+                                    Oversampling::Over8 => 8,
+                                    Oversampling::Over16 => 16,
+                                };
+                                let sample_rate = u64::from(config.baudrate.0) * oversampling;
+                                let sample_times = |time: MicroSeconds| {
+                                    let product = u64::from(time.ticks()) * sample_rate;
+                                    let samples = product / 1_000_000;
+                                    // Rounded up
+                                    let samples = if samples * 1_000_000 < product {
+                                        samples + 1
+                                    } else {
+                                        samples
+                                    };
+                                    assert!(samples < 32, "RS-485 DE time too long");
+                                    samples as u8
+                                };
+
+                                (
+                                    de.polarity,
+                                    sample_times(de.assertion_time),
+                                    sample_times(de.deassertion_time),
+                                )
+                            }
+                            // Pre/post driver enable set conservative
+                            None => (DePolarity::ActiveHigh, 0b1111, 0b1111),
+                        };
+
+                        usart.cr3.modify(|_, w| {
+                            w.dem().set_bit().dep().bit(polarity == DePolarity::ActiveLow)
+                        });
+                        usart.cr1.modify(|_, w| w.deat().bits(deat).dedt().bits(dedt));
                     } else {
-                        usart.cr3.modify(|_, w| w.rtse().clear_bit().ctse().clear_bit());
+                        let (rts, cts) = match config.flow_control {
+                            Some(FlowControl::None) => (false, false),
+                            Some(FlowControl::Rts) => (true, false),
+                            Some(FlowControl::Cts) => (false, true),
+                            Some(FlowControl::RtsCts) => (true, true),
+                            None => (PINS::RTS, PINS::CTS),
+                        };
+                        assert!(!rts || PINS::RTS, "RTS flow control without RTS pin");
+                        assert!(!cts || PINS::CTS, "CTS flow control without CTS pin");
+                        usart.cr3.modify(|_, w| w.rtse().bit(rts).ctse().bit(cts));
                     }
 
                     // Enable One bit sampling method
                     usart.cr3.modify(|_, w| {
-                        if config.onebit_sampling {
-                            w.onebit().set_bit();
-                        }
+                        by_kind!($kind, {
+                            if config.onebit_sampling {
+                                w.onebit().set_bit();
+                            }
+                        }, {});
 
                         if config.disable_overrun {
                             w.ovrdis().set_bit();
@@ -330,14 +744,22 @@ macro_rules! hal {
                     // the size of the data plus the parity bit. I.e. "word length"=8, parity=even
                     // results in 7 bits of data. Therefore, in order to get 8 bits and one parity
                     // bit, we need to set the "word" length to 9 when using parity bits.
-                    let (word_length, parity_control_enable, parity) = match config.parity {
-                        Parity::ParityNone => (false, false, false),
-                        Parity::ParityEven => (true, true, false),
-                        Parity::ParityOdd => (true, true, true),
+                    let (parity_control_enable, parity) = match config.parity {
+                        Parity::ParityNone => (false, false),
+                        Parity::ParityEven => (true, false),
+                        Parity::ParityOdd => (true, true),
+                    };
+                    // M1:M0 of 0b10 for 7 bits, 0b00 for 8 bits, 0b01 for 9 bits
+                    let (m1, m0) = match (config.word_length, parity_control_enable) {
+                        (WordLength::Bits7, false) => (true, false),
+                        (WordLength::Bits7, true) | (WordLength::Bits8, false) => (false, false),
+                        (WordLength::Bits8, true) | (WordLength::Bits9, false) => (false, true),
+                        (WordLength::Bits9, true) => panic!("serial with 9 data bits and parity"),
                     };
                     usart.cr1.modify(|_r, w| {
                         w
-                            .m0().bit(word_length)
+                            .m1().bit(m1)
+                            .m0().bit(m0)
                             .ps().bit(parity)
                             .pce().bit(parity_control_enable)
                     });
@@ -350,20 +772,56 @@ macro_rules! hal {
                         StopBits::STOP1P5 => 0b11,
                     };
                     usart.cr2.modify(|_r, w| {
-                        w.stop().bits(stop_bits);
+                        w.stop()
+                            .bits(stop_bits)
+                            .swap()
+                            .bit(config.swap_pins)
+                            .txinv()
+                            .bit(config.invert_tx)
+                            .rxinv()
+                            .bit(config.invert_rx)
+                            .datainv()
+                            .bit(config.invert_data)
+                            .msbfirst()
+                            .bit(config.msb_first);
 
                         // Setup character match (if requested)
                         if let Some(c) = config.character_match {
                             w.add().bits(c);
                         }
 
-                        if config.receiver_timeout.is_some() {
-                            w.rtoen().set_bit();
+                        if let Some(MuteMode::AddressMark(address)) = config.mute_mode {
+                            assert!(
+                                config.character_match.is_none(),
+                                "serial address mark with character match"
+                            );
+                            w.add().bits(address).addm7().set_bit();
                         }
 
+                        by_kind!($kind, {
+                            if config.receiver_timeout.is_some() {
+                                w.rtoen().set_bit();
+                            }
+
+                            if let Some(length) = config.break_detection {
+                                assert!(
+                                    matches!(config.stopbits, StopBits::STOP1) && !PINS::HALF_DUPLEX,
+                                    "LIN mode without 1 stop bit or in half duplex"
+                                );
+                                w.linen().set_bit().lbdl().bit(length == BreakDetection::Bits11);
+                            }
+                        }, {
+                            assert!(config.break_detection.is_none(), "LPUART without LIN mode");
+                        });
+
                         w
                     });
 
+                    if let Some(wake) = config.mute_mode {
+                        usart.cr1.modify(|_, w| {
+                            w.mme().set_bit().wake().bit(wake != MuteMode::IdleLine)
+                        });
+                    }
 
                     // UE: enable USART
                     // RE: enable receiver
@@ -390,9 +848,22 @@ macro_rules! hal {
                         Event::CharacterMatch => {
                             self.usart.cr1.modify(|_, w| w.cmie().set_bit())
                         },
-                        Event::ReceiverTimeout => {
+                        Event::ReceiverTimeout => by_kind!($kind, {
                             self.usart.cr1.modify(|_, w| w.rtoie().set_bit())
+                        }, {
+                            panic!("LPUART without receiver timeout")
+                        }),
+                        Event::Cts => {
+                            self.usart.cr3.modify(|_, w| w.ctsie().set_bit())
+                        },
+                        Event::WakeupFromStop => {
+                            self.usart.cr3.modify(|_, w| w.wufie().set_bit())
                         },
+                        Event::LineBreak => by_kind!($kind, {
+                            self.usart.cr2.modify(|_, w| w.lbdie().set_bit())
+                        }, {
+                            panic!("LPUART without LIN mode")
+                        }),
                     }
                 }
 
@@ -406,6 +877,226 @@ macro_rules! hal {
                     rx.check_for_error()
                 }
 
+                /// Checks for receive errors without clearing them
+                ///
+                /// See [`Rx::check_errors`].
+                pub fn check_errors(&self) -> Result<(), Error> {
+                    let rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.check_errors()
+                }
+
+                /// Clears all receive errors at once
+                ///
+                /// See [`Rx::clear_errors`].
+                pub fn clear_errors(&mut self) {
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.clear_errors()
+                }
+
+                /// Checks to see if the CTS input has changed, and clears the flag
+                ///
+                /// See [`Tx::is_cts_changed`].
+                pub fn is_cts_changed(&mut self, clear: bool) -> bool {
+                    let mut tx: Tx<pac::$USARTX> = Tx {
+                        _usart: PhantomData,
+                    };
+                    tx.is_cts_changed(clear)
+                }
+
+                /// Keeps the serial receiving in Stop mode, `source` raises
+                /// [`Event::WakeupFromStop`] to wake the core up
+                ///
+                /// The kernel clock has to be the HSI16, which the serial then requests in
+                /// Stop mode, or the LSE, otherwise this returns [`Error::WakeupClock`].
+                /// Only the LPUART keeps working in Stop 2, the USARTs and UARTs in Stop 0
+                /// and Stop 1. The wakeup EXTI lines are unmasked at reset.
+                ///
+                /// The HSI16 takes a few µs to start, so at high baud rates the character
+                /// which wakes the serial up may be received with an error. The serial is
+                /// disabled for the configuration after an ongoing transmission, a
+                /// character received meanwhile is lost.
+                pub fn enable_stop_wakeup(&mut self, source: WakeSource) -> Result<(), Error> {
+                    if !matches!(
+                        <pac::$USARTX as KernelClock>::kernel_clock_source(),
+                        UsartClockSource::Hsi16 | UsartClockSource::Lse
+                    ) {
+                        return Err(Error::WakeupClock);
+                    }
+
+                    let (wus, address) = match source {
+                        WakeSource::AddressMatch(address) => (0b00, Some(address)),
+                        WakeSource::StartBit => (0b10, None),
+                        WakeSource::RxNotEmpty => (0b11, None),
+                    };
+
+                    // WUS, ADD and ADDM7 can only be written while the USART is disabled
+                    while self.usart.isr.read().tc().bit_is_clear() {}
+                    let cr1 = self.usart.cr1.read().bits();
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    if let Some(address) = address {
+                        self.usart.cr2.modify(|_, w| w.add().bits(address).addm7().set_bit());
+                    }
+                    self.usart.cr3.modify(|_, w| unsafe { w.wus().bits(wus) });
+                    self.usart.cr1.write(|w| unsafe { w.bits(cr1) });
+                    self.usart.cr1.modify(|_, w| w.uesm().set_bit());
+
+                    Ok(())
+                }
+
+                /// Stops receiving in Stop mode
+                pub fn disable_stop_wakeup(&mut self) {
+                    self.usart.cr1.modify(|_, w| w.uesm().clear_bit());
+                }
+
+                /// Clears the flag of `event`
+                ///
+                /// RXNE and TXE are cleared by reading and writing the data, so does
+                /// nothing for [`Event::Rxne`] and [`Event::Txe`].
+                pub fn clear_event(&mut self, event: Event) {
+                    match event {
+                        Event::Rxne | Event::Txe => (),
+                        Event::Idle => self.usart.icr.write(|w| w.idlecf().set_bit()),
+                        Event::CharacterMatch => self.usart.icr.write(|w| w.cmcf().set_bit()),
+                        Event::ReceiverTimeout => by_kind!($kind, {
+                            self.usart.icr.write(|w| w.rtocf().set_bit())
+                        }, { () }),
+                        Event::Cts => self.usart.icr.write(|w| w.ctscf().set_bit()),
+                        Event::WakeupFromStop => self.usart.icr.write(|w| w.wucf().set_bit()),
+                        Event::LineBreak => by_kind!($kind, {
+                            self.usart.icr.write(|w| w.lbdcf().set_bit())
+                        }, { () }),
+                    }
+                }
+
+                /// Checks whether the flag of `event` is set, it stays set until
+                /// [`Serial::clear_event`]
+                ///
+                /// The LPUART never sets the flags of [`Event::ReceiverTimeout`] and
+                /// [`Event::LineBreak`].
+                pub fn is_event_triggered(&self, event: Event) -> bool {
+                    let isr = self.usart.isr.read();
+                    match event {
+                        Event::Rxne => isr.rxne().bit_is_set(),
+                        Event::Txe => isr.txe().bit_is_set(),
+                        Event::Idle => isr.idle().bit_is_set(),
+                        Event::CharacterMatch => isr.cmf().bit_is_set(),
+                        Event::ReceiverTimeout => by_kind!($kind, {
+                            isr.rtof().bit_is_set()
+                        }, { false }),
+                        Event::Cts => isr.ctsif().bit_is_set(),
+                        Event::WakeupFromStop => isr.wuf().bit_is_set(),
+                        Event::LineBreak => by_kind!($kind, {
+                            isr.lbdf().bit_is_set()
+                        }, { false }),
+                    }
+                }
+
+                by_kind!($kind, {
+                    /// Detects the baud rate from the next received character in `mode`,
+                    /// see [`Serial::check_autobaud`]
+                    ///
+                    /// The character is received at the detected baud rate, the frame
+                    /// modes distinguish the most baud rates. The LPUART has no baud rate
+                    /// detection. A failed detection is requested again with this.
+                    pub fn request_autobaud(&mut self, mode: AutobaudMode) {
+                        let abrmod = match mode {
+                            AutobaudMode::StartBit => 0b00,
+                            AutobaudMode::FallingEdge => 0b01,
+                            AutobaudMode::Frame0x7F => 0b10,
+                            AutobaudMode::Frame0x55 => 0b11,
+                        };
+
+                        let cr2 = self.usart.cr2.read();
+                        if cr2.abren().bit_is_set() && cr2.abrmod().bits() == abrmod {
+                            // Also clears ABRE and ABRF
+                            self.usart.rqr.write(|w| w.abrrq().set_bit());
+                        } else {
+                            // ABREN and ABRMOD can only be written while the USART is disabled
+                            while self.usart.isr.read().tc().bit_is_clear() {}
+                            let cr1 = self.usart.cr1.read().bits();
+                            self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                            self.usart.cr2.modify(|_, w| w.abren().set_bit().abrmod().bits(abrmod));
+                            self.usart.cr1.write(|w| unsafe { w.bits(cr1) });
+                        }
+                    }
+
+                    /// Checks whether the baud rate detection of [`Serial::request_autobaud`]
+                    /// is complete, and returns [`Error::Autobaud`] when it failed
+                    ///
+                    /// On success, the detected character can be read and
+                    /// [`Serial::baud_rate`] returns the detected baud rate.
+                    pub fn check_autobaud(&mut self) -> nb::Result<(), Error> {
+                        let isr = self.usart.isr.read();
+                        if isr.abre().bit_is_set() {
+                            Err(nb::Error::Other(Error::Autobaud))
+                        } else if isr.abrf().bit_is_set() {
+                            Ok(())
+                        } else {
+                            Err(nb::Error::WouldBlock)
+                        }
+                    }
+                }, {});
+
+                /// Returns the baud rate of the divider, e.g. the detected one of
+                /// [`Serial::request_autobaud`]
+                ///
+                /// `clocks` are the ones the serial was configured with.
+                pub fn baud_rate(&self, clocks: &Clocks) -> Bps {
+                    let kernel_clock = u64::from(clocks.kernel_clock::<pac::$USARTX>().raw());
+                    let brr = u64::from(self.usart.brr.read().bits());
+
+                    let baud_rate = by_kind!($kind, {
+                        if self.usart.cr1.read().over8().bit_is_set() {
+                            2 * kernel_clock / ((brr & !0xf) | ((brr & 0x7) << 1))
+                        } else {
+                            kernel_clock / brr
+                        }
+                    }, {
+                        256 * kernel_clock / brr
+                    });
+                    (baud_rate as u32).bps()
+                }
+
+                /// Changes the character match character
+                ///
+                /// See [`Rx::set_character_match`].
+                pub fn set_character_match(&mut self, character: u8) {
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.set_character_match(character)
+                }
+
+                /// Mutes the receiver until the wakeup of [`Config::mute_mode`]
+                ///
+                /// See [`Rx::enter_mute_mode`].
+                pub fn enter_mute_mode(&mut self) {
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.enter_mute_mode()
+                }
+
+                /// Checks whether the receiver is muted
+                pub fn is_muted(&self) -> bool {
+                    self.usart.isr.read().rwu().bit_is_set()
+                }
+
+                /// Checks to see if the serial has woken up from Stop mode, and clears the
+                /// flag
+                ///
+                /// See [`Rx::is_wakeup`].
+                pub fn is_wakeup(&mut self, clear: bool) -> bool {
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.is_wakeup(clear)
+                }
+
                 /// Stops listening for an interrupt event
                 pub fn unlisten(&mut self, event: Event) {
                     match event {
@@ -421,9 +1112,20 @@ macro_rules! hal {
                         Event::CharacterMatch => {
                             self.usart.cr1.modify(|_, w| w.cmie().clear_bit())
                         },
-                        Event::ReceiverTimeout => {
+                        Event::ReceiverTimeout => by_kind!($kind, {
                             self.usart.cr1.modify(|_, w| w.rtoie().clear_bit())
+                        }, { () }),
+                        Event::Cts => {
+                            self.usart.cr3.modify(|_, w| w.ctsie().clear_bit())
                         },
+                        Event::WakeupFromStop => {
+                            self.usart.cr3.modify(|_, w| w.wufie().clear_bit())
+                        },
+                        Event::LineBreak => by_kind!($kind, {
+                            self.usart.cr2.modify(|_, w| w.lbdie().clear_bit())
+                        }, {
+                            ()
+                        }),
                     }
                 }
 
@@ -445,10 +1147,71 @@ macro_rules! hal {
                 }
             }
 
+            impl private::SealedInstance for pac::$USARTX {}
+
+            impl Instance for pac::$USARTX {
+                fn configure<PINS>(
+                    self,
+                    pins: PINS,
+                    config: Config,
+                    clocks: Clocks,
+                    apb: &mut Self::Bus,
+                ) -> Serial<Self, PINS>
+                where
+                    PINS: Pins<Self>,
+                {
+                    Serial::$usartX(self, pins, config, clocks, apb)
+                }
+            }
+
+            impl<PINS> RateChange for Serial<pac::$USARTX, PINS> {
+                /// Rescales the baud rate divider to the new kernel clock
+                ///
+                /// Waits for an ongoing transmission to complete. Data received while the
+                /// divider is updated is lost.
+                fn clocks_changed(&mut self, old: &Clocks, new: &Clocks) {
+                    let old_clock = old.kernel_clock::<pac::$USARTX>().raw() as u64;
+                    let new_clock = new.kernel_clock::<pac::$USARTX>().raw() as u64;
+
+                    let brr = by_kind!($kind, {
+                        {
+                            let over8 = self.usart.cr1.read().over8().bit_is_set();
+                            let brr = self.usart.brr.read().bits();
+                            let usartdiv = if over8 {
+                                (brr & !0xf) | ((brr & 0x7) << 1)
+                            } else {
+                                brr
+                            };
+                            let usartdiv = (usartdiv as u64 * new_clock / old_clock) as u32;
+                            assert!(usartdiv >= 16, "impossible baud rate");
+                            if over8 {
+                                (usartdiv & !0xf) | ((usartdiv & 0xf) >> 1)
+                            } else {
+                                usartdiv
+                            }
+                        }
+                    }, {
+                        {
+                            let brr = u64::from(self.usart.brr.read().bits()) * new_clock / old_clock;
+                            assert!((0x300..1 << 20).contains(&brr), "impossible baud rate");
+                            brr as u32
+                        }
+                    });
+
+                    // BRR can only be written while the USART is disabled
+                    while self.usart.isr.read().tc().bit_is_clear() {}
+                    let cr1 = self.usart.cr1.read().bits();
+                    self.usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    self.usart.brr.write(|w| unsafe { w.bits(brr) });
+                    self.usart.cr1.write(|w| unsafe { w.bits(cr1) });
+                }
+            }
+
             impl<PINS> serial::Read<u8> for Serial<pac::$USARTX, PINS> {
                 type Error = Error;
 
                 fn read(&mut self) -> nb::Result<u8, Error> {
+                    self.turn_around(false);
                     let mut rx: Rx<pac::$USARTX> = Rx {
                         _usart: PhantomData,
                     };
@@ -460,19 +1223,46 @@ macro_rules! hal {
                 type Error = Error;
 
                 fn read(&mut self) -> nb::Result<u8, Error> {
-                    self.check_for_error()?;
+                    // Without the parity bit of 7 data bits
+                    self.receive().map(|word| word as u8)
+                }
+            }
 
-                    // NOTE(unsafe) atomic read with no side effects
-                    let isr = unsafe { (*pac::$USARTX::ptr()).isr.read() };
+            impl<PINS> Serial<pac::$USARTX, PINS> {
+                /// Reads a character of up to 9 data bits
+                ///
+                /// See [`Rx::read_u16`].
+                pub fn read_u16(&mut self) -> nb::Result<u16, Error> {
+                    self.turn_around(false);
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    rx.read_u16()
+                }
 
-                    if isr.rxne().bit_is_set() {
-                        // NOTE(read_volatile) see `write_volatile` below
-                        return Ok(unsafe {
-                            ptr::read_volatile(&(*pac::$USARTX::ptr()).rdr as *const _ as *const _)
-                        });
+                /// Writes a character of up to 9 data bits
+                ///
+                /// See [`Tx::write_u16`].
+                pub fn write_u16(&mut self, word: u16) -> nb::Result<(), Error> {
+                    self.turn_around(true);
+                    let mut tx: Tx<pac::$USARTX> = Tx {
+                        _usart: PhantomData,
+                    };
+                    tx.write_u16(word)
+                }
+
+                /// In half duplex, disables the receiver to transmit, and enables it again
+                /// to receive once the transmission is complete
+                fn turn_around(&mut self, transmit: bool) {
+                    if self.usart.cr3.read().hdsel().bit_is_clear() {
+                        return;
                     }
 
-                    Err(nb::Error::WouldBlock)
+                    let receiving = self.usart.cr1.read().re().bit_is_set();
+                    let isr = self.usart.isr.read().bits();
+                    if let Some(re) = half_duplex_receiver(transmit, receiving, isr) {
+                        self.usart.cr1.modify(|_, w| w.re().bit(re));
+                    }
                 }
             }
 
@@ -483,10 +1273,13 @@ macro_rules! hal {
                     let mut tx: Tx<pac::$USARTX> = Tx {
                         _usart: PhantomData,
                     };
-                    tx.flush()
+                    tx.flush()?;
+                    self.turn_around(false);
+                    Ok(())
                 }
 
                 fn write(&mut self, byte: u8) -> nb::Result<(), Error> {
+                    self.turn_around(true);
                     let mut tx: Tx<pac::$USARTX> = Tx {
                         _usart: PhantomData,
                     };
@@ -532,6 +1325,126 @@ macro_rules! hal {
             impl embedded_hal::blocking::serial::write::Default<u8>
                 for Tx<pac::$USARTX> {}
 
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::ErrorType for Rx<pac::$USARTX> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::Read for Rx<pac::$USARTX> {
+                /// Waits for a character, then reads the characters received until
+                /// `buf` is full
+                ///
+                /// An error after the first character is returned by the next read.
+                fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    buf[0] = nb::block!(serial::Read::read(self))?;
+                    let mut len = 1;
+                    while len < buf.len() && self.check_errors().is_ok() {
+                        match serial::Read::read(self) {
+                            Ok(byte) => buf[len] = byte,
+                            Err(_) => break,
+                        }
+                        len += 1;
+                    }
+                    Ok(len)
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::ReadReady for Rx<pac::$USARTX> {
+                /// Whether a character has been received (RXNE)
+                fn read_ready(&mut self) -> Result<bool, Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*pac::$USARTX::ptr()).isr.read().rxne().bit_is_set() })
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::ErrorType for Tx<pac::$USARTX> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::Write for Tx<pac::$USARTX> {
+                /// Waits until a character can be written, then writes the characters from
+                /// `buf` while the transmit register is empty
+                fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    if buf.is_empty() {
+                        return Ok(0);
+                    }
+
+                    nb::block!(serial::Write::write(self, buf[0]))?;
+                    let mut len = 1;
+                    while len < buf.len() && serial::Write::write(self, buf[len]).is_ok() {
+                        len += 1;
+                    }
+                    Ok(len)
+                }
+
+                fn flush(&mut self) -> Result<(), Error> {
+                    nb::block!(serial::Write::flush(self))
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl embedded_io::WriteReady for Tx<pac::$USARTX> {
+                /// Whether a character can be written (TXE)
+                fn write_ready(&mut self) -> Result<bool, Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    Ok(unsafe { (*pac::$USARTX::ptr()).isr.read().txe().bit_is_set() })
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<PINS> embedded_io::ErrorType for Serial<pac::$USARTX, PINS> {
+                type Error = Error;
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<PINS> embedded_io::Read for Serial<pac::$USARTX, PINS> {
+                fn read(&mut self, buf: &mut [u8]) -> Result<usize, Error> {
+                    self.turn_around(false);
+                    let mut rx: Rx<pac::$USARTX> = Rx {
+                        _usart: PhantomData,
+                    };
+                    embedded_io::Read::read(&mut rx, buf)
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<PINS> embedded_io::ReadReady for Serial<pac::$USARTX, PINS> {
+                fn read_ready(&mut self) -> Result<bool, Error> {
+                    self.turn_around(false);
+                    Ok(self.usart.isr.read().rxne().bit_is_set())
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<PINS> embedded_io::Write for Serial<pac::$USARTX, PINS> {
+                fn write(&mut self, buf: &[u8]) -> Result<usize, Error> {
+                    self.turn_around(true);
+                    let mut tx: Tx<pac::$USARTX> = Tx {
+                        _usart: PhantomData,
+                    };
+                    embedded_io::Write::write(&mut tx, buf)
+                }
+
+                fn flush(&mut self) -> Result<(), Error> {
+                    nb::block!(serial::Write::flush(self))
+                }
+            }
+
+            #[cfg(feature = "embedded-io")]
+            impl<PINS> embedded_io::WriteReady for Serial<pac::$USARTX, PINS> {
+                fn write_ready(&mut self) -> Result<bool, Error> {
+                    Ok(self.usart.isr.read().txe().bit_is_set())
+                }
+            }
+
             pub type $rxdma = RxDma<Rx<pac::$USARTX>, $dmarxch>;
             pub type $txdma = TxDma<Tx<pac::$USARTX>, $dmatxch>;
 
@@ -571,6 +1484,49 @@ macro_rules! hal {
                     }
                 }
 
+                /// Reads a character of up to 9 data bits, as with
+                /// [`WordLength::Bits9`]
+                ///
+                /// The parity bit is removed, as with the `u8` reads.
+                pub fn read_u16(&mut self) -> nb::Result<u16, Error> {
+                    self.receive()
+                }
+
+                /// Reads the data bits of the received character, after its errors
+                fn receive(&mut self) -> nb::Result<u16, Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+                    let isr = usart.isr.read();
+
+                    if let Some((error, flag)) = read_error(isr.bits()) {
+                        // NOTE(unsafe) the clear flag of the error only
+                        usart.icr.write(|w| unsafe { w.bits(flag) });
+                        return Err(nb::Error::Other(error));
+                    }
+
+                    // A pending ORE raises the RXNE interrupt again after the read
+                    if isr.rxne().bit_is_set() {
+                        return Ok(usart.rdr.read().bits() as u16 & Self::data_mask());
+                    }
+
+                    Err(nb::Error::WouldBlock)
+                }
+
+                /// Mask of the data bits of a received character, the parity bit is the MSB
+                fn data_mask() -> u16 {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let cr1 = unsafe { (*pac::$USARTX::ptr()).cr1.read() };
+                    let bits = if cr1.m1().bit_is_set() {
+                        7
+                    } else if cr1.m0().bit_is_set() {
+                        9
+                    } else {
+                        8
+                    };
+                    let bits = if cr1.pce().bit_is_set() { bits - 1 } else { bits };
+                    (1 << bits) - 1
+                }
+
                 /// Check for, and return, any errors
                 ///
                 /// The `read` methods can only return one error at a time, but
@@ -603,6 +1559,33 @@ macro_rules! hal {
                     Ok(())
                 }
 
+                /// Checks for receive errors without clearing them, e.g. before reading
+                ///
+                /// Returns the first error in the order of [`Rx::check_for_error`], the `read`
+                /// methods report and clear them one by one.
+                pub fn check_errors(&self) -> Result<(), Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let isr = unsafe { (*pac::$USARTX::ptr()).isr.read() };
+
+                    match receive_error(isr.bits()) {
+                        Some((error, _)) => Err(error),
+                        None => Ok(()),
+                    }
+                }
+
+                /// Clears all receive errors at once
+                ///
+                /// A character in the receive register stays there, and so does an error
+                /// received in the meantime.
+                pub fn clear_errors(&mut self) {
+                    // NOTE(unsafe) atomic write to stateless register
+                    unsafe {
+                        (*pac::$USARTX::ptr()).icr.write(|w| {
+                            w.pecf().clear().fecf().clear().ncf().clear().orecf().clear()
+                        })
+                    };
+                }
+
                 /// Checks to see if the USART peripheral has detected an idle line and clears
                 /// the flag
                 pub fn is_idle(&mut self, clear: bool) -> bool {
@@ -620,15 +1603,51 @@ macro_rules! hal {
                 }
 
 
-                /// Checks to see if the USART peripheral has detected an receiver timeout and
-                /// clears the flag
-                pub fn is_receiver_timeout(&mut self, clear: bool) -> bool {
+                by_kind!($kind, {
+                    /// Checks to see if the USART peripheral has detected an receiver timeout and
+                    /// clears the flag
+                    pub fn is_receiver_timeout(&mut self, clear: bool) -> bool {
+                        let isr = unsafe { &(*pac::$USARTX::ptr()).isr.read() };
+                        let icr = unsafe { &(*pac::$USARTX::ptr()).icr };
+
+                        if isr.rtof().bit_is_set() {
+                            if clear {
+                                icr.write(|w| w.rtocf().set_bit() );
+                            }
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                }, {});
+
+                by_kind!($kind, {
+                    /// Checks to see if the USART peripheral has detected a line break and
+                    /// clears the flag, see [`Config::break_detection`]
+                    pub fn is_line_break(&mut self, clear: bool) -> bool {
+                        let isr = unsafe { &(*pac::$USARTX::ptr()).isr.read() };
+                        let icr = unsafe { &(*pac::$USARTX::ptr()).icr };
+
+                        if isr.lbdf().bit_is_set() {
+                            if clear {
+                                icr.write(|w| w.lbdcf().set_bit());
+                            }
+                            true
+                        } else {
+                            false
+                        }
+                    }
+                }, {});
+
+                /// Checks to see if the serial has woken up from Stop mode, and clears the
+                /// flag
+                pub fn is_wakeup(&mut self, clear: bool) -> bool {
                     let isr = unsafe { &(*pac::$USARTX::ptr()).isr.read() };
                     let icr = unsafe { &(*pac::$USARTX::ptr()).icr };
 
-                    if isr.rtof().bit_is_set() {
+                    if isr.wuf().bit_is_set() {
                         if clear {
-                            icr.write(|w| w.rtocf().set_bit() );
+                            icr.write(|w| w.wucf().set_bit());
                         }
                         true
                     } else {
@@ -651,6 +1670,41 @@ macro_rules! hal {
                         false
                     }
                 }
+
+                /// Changes the character match character of [`Config::character_match`]
+                ///
+                /// The receiver is disabled for the change, a character received meanwhile
+                /// gets lost. Panics with [`MuteMode::AddressMark`], which uses the same
+                /// register.
+                pub fn set_character_match(&mut self, character: u8) {
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+
+                    let cr1 = usart.cr1.read();
+                    assert!(
+                        !(cr1.mme().bit_is_set() && cr1.wake().bit_is_set()),
+                        "serial character match with address mark"
+                    );
+
+                    // ADD can only be written while the receiver is disabled
+                    usart.cr1.modify(|_, w| w.re().clear_bit());
+                    usart.cr2.modify(|_, w| w.add().bits(character));
+                    usart.cr1.modify(|_, w| w.re().bit(cr1.re().bit_is_set()));
+                }
+
+                /// Mutes the receiver until the wakeup of [`Config::mute_mode`], e.g. after
+                /// receiving a frame for another node
+                ///
+                /// Does nothing without the mute mode.
+                pub fn enter_mute_mode(&mut self) {
+                    // NOTE(unsafe) atomic write to stateless register
+                    unsafe { (*pac::$USARTX::ptr()).rqr.write(|w| w.mmrq().set_bit()) };
+                }
+
+                /// Checks whether the receiver is muted
+                pub fn is_muted(&self) -> bool {
+                    // NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*pac::$USARTX::ptr()).isr.read().rwu().bit_is_set() }
+                }
             }
 
             impl crate::dma::CharacterMatch for Rx<pac::$USARTX> {
@@ -661,11 +1715,13 @@ macro_rules! hal {
                 }
             }
 
-            impl crate::dma::ReceiverTimeout for Rx<pac::$USARTX> {
-                fn check_receiver_timeout(&mut self, clear: bool) -> bool {
-                    self.is_receiver_timeout(clear)
+            by_kind!($kind, {
+                impl crate::dma::ReceiverTimeout for Rx<pac::$USARTX> {
+                    fn check_receiver_timeout(&mut self, clear: bool) -> bool {
+                        self.is_receiver_timeout(clear)
+                    }
                 }
-            }
+            }, {});
 
             impl crate::dma::OperationError<(), Error> for Rx<pac::$USARTX>{
                 fn check_operation_error(&mut self) -> Result<(), Error> {
@@ -674,6 +1730,129 @@ macro_rules! hal {
             }
 
             impl Tx<pac::$USARTX> {
+                /// Sends a break after the ongoing character, and with `wait` waits until
+                /// it is sent
+                ///
+                /// The line is low for the character length plus 2 bit times, 10 bit times
+                /// with 8 data bits and no parity, or 13 bit times in LIN mode.
+                pub fn send_break(&mut self, wait: bool) {
+                    // NOTE(unsafe) atomic write to stateless register
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+
+                    usart.rqr.write(|w| w.sbkrq().set_bit());
+                    if wait {
+                        // SBKF is cleared at the stop bit of the break
+                        while usart.isr.read().sbkf().bit_is_set() {}
+                    }
+                }
+
+                /// Sends a break of at least `duration`, which may be longer than the one
+                /// of [`Tx::send_break`], e.g. the 92 µs of DMX512
+                ///
+                /// Waits for the ongoing transmission, then sends a 0 character at a
+                /// lower baud rate, so the line is high for at least one bit time of it
+                /// afterwards. The baud rate is restored before this returns, characters
+                /// received meanwhile are garbled. `clocks` are the ones the serial was
+                /// configured with.
+                pub fn send_long_break(&mut self, duration: MicroSeconds, clocks: &Clocks) {
+                    // NOTE(unsafe) the serial is idle while the baud rate is changed
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+
+                    // The start bit and the data bits, a parity bit of 0 may be high
+                    let cr1 = usart.cr1.read();
+                    let bits = if cr1.m1().bit_is_set() {
+                        7
+                    } else if cr1.m0().bit_is_set() {
+                        9
+                    } else {
+                        8
+                    };
+                    let low_bits = if cr1.pce().bit_is_set() { bits } else { bits + 1 };
+
+                    // The kernel clock cycles per bit for `duration`, rounded up
+                    let kernel_clock = u64::from(clocks.kernel_clock::<pac::$USARTX>().raw());
+                    let product = u64::from(duration.ticks()) * kernel_clock;
+                    let divisor = low_bits * 1_000_000;
+                    let cycles = product / divisor;
+                    let cycles = if cycles * divisor < product { cycles + 1 } else { cycles };
+
+                    let brr = by_kind!($kind, {
+                        {
+                            assert!(cycles <= 0xffff, "serial break too long");
+                            let cycles = cycles as u32;
+                            if usart.cr1.read().over8().bit_is_set() {
+                                // USARTDIV is twice the cycles per bit
+                                let usartdiv = 2 * cycles;
+                                (usartdiv & !0xf) | ((usartdiv & 0xf) >> 1)
+                            } else {
+                                cycles
+                            }
+                        }
+                    }, {
+                        {
+                            let brr = 256 * cycles;
+                            assert!(brr < 1 << 20, "serial break too long");
+                            brr as u32
+                        }
+                    });
+
+                    // BRR can only be written while the USART is disabled
+                    while usart.isr.read().tc().bit_is_clear() {}
+                    let old_brr = usart.brr.read().bits();
+                    let cr1 = usart.cr1.read().bits();
+                    usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    usart.brr.write(|w| unsafe { w.bits(brr.max(old_brr)) });
+                    usart.cr1.write(|w| unsafe { w.bits(cr1) });
+
+                    // NOTE(write_volatile) 8-bit write that's not possible through the svd2rust API
+                    unsafe { ptr::write_volatile(&usart.tdr as *const _ as *mut u8, 0) };
+                    while usart.isr.read().tc().bit_is_clear() {}
+
+                    usart.cr1.modify(|_, w| w.ue().clear_bit());
+                    usart.brr.write(|w| unsafe { w.bits(old_brr) });
+                    usart.cr1.write(|w| unsafe { w.bits(cr1) });
+                }
+
+                /// Writes a character of up to 9 data bits, as with
+                /// [`WordLength::Bits9`]
+                pub fn write_u16(&mut self, word: u16) -> nb::Result<(), Error> {
+                    // NOTE(unsafe) atomic read with no side effects
+                    let usart = unsafe { &*pac::$USARTX::ptr() };
+
+                    if usart.isr.read().txe().bit_is_set() {
+                        // NOTE(unsafe) atomic write to stateless register
+                        usart.tdr.write(|w| unsafe { w.bits(u32::from(word)) });
+                        Ok(())
+                    } else {
+                        Err(nb::Error::WouldBlock)
+                    }
+                }
+
+                /// Checks to see if the CTS input has changed, and clears the flag
+                ///
+                /// With CTS flow control, the transmission stalls while CTS is deasserted,
+                /// see [`Tx::is_cts_asserted`].
+                pub fn is_cts_changed(&mut self, clear: bool) -> bool {
+                    let isr = unsafe { &(*pac::$USARTX::ptr()).isr.read() };
+                    let icr = unsafe { &(*pac::$USARTX::ptr()).icr };
+
+                    if isr.ctsif().bit_is_set() {
+                        if clear {
+                            icr.write(|w| w.ctscf().set_bit());
+                        }
+                        true
+                    } else {
+                        false
+                    }
+                }
+
+                /// Returns whether the CTS input is asserted (low), allowing to transmit
+                pub fn is_cts_asserted(&self) -> bool {
+                    // NOTE(unsafe): Only used for atomic access.
+                    let isr = unsafe { (*pac::$USARTX::ptr()).isr.read() };
+                    isr.cts().bit_is_set()
+                }
+
                 pub fn with_dma(self, channel: $dmatxch) -> $txdma {
                     TxDma {
                         payload: self,
@@ -756,6 +1935,33 @@ macro_rules! hal {
                 }
             }
 
+            impl<B> CircBuffer<B, $rxdma> {
+                /// Checks to see if the USART peripheral has detected an idle line and clears
+                /// the flag
+                ///
+                /// With [`Event::Idle`] listened for, the interrupt marks the end of a burst of
+                /// data, e.g. a message, after which the received bytes can be read.
+                pub fn is_idle(&mut self, clear: bool) -> bool {
+                    self.payload_mut().payload.is_idle(clear)
+                }
+
+                /// Checks to see if the USART peripheral has detected a character match and
+                /// clears the flag
+                ///
+                /// With [`Event::CharacterMatch`] listened for, the interrupt marks the end of
+                /// a message, e.g. a line, which has been written to the ring by then.
+                pub fn check_character_match(&mut self, clear: bool) -> bool {
+                    self.payload_mut().payload.check_character_match(clear)
+                }
+
+                /// Check for, and return, any errors of the USART
+                ///
+                /// See [`Rx::check_for_error`].
+                pub fn check_for_error(&mut self) -> Result<(), Error> {
+                    self.payload_mut().payload.check_for_error()
+                }
+            }
+
             impl $rxdma {
                 /// Create a frame reader that can either react on the Character match interrupt or
                 /// Transfer Complete from the DMA.
@@ -842,18 +2048,249 @@ macro_rules! hal {
                     FrameSender::new(self)
                 }
             }
+
+            impl<B> crate::dma::WriteDma<B, u8> for $txdma
+            where
+                B: StaticReadBuffer<Word = u8>,
+            {
+                /// Sends `buffer`, see [`Transfer::complete`] for the end of the transfer
+                fn write(mut self, buffer: B) -> Transfer<R, B, Self> {
+                    // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                    // until the end of the transfer.
+                    let (ptr, len) = unsafe { buffer.static_read_buffer() };
+                    let usart = unsafe { &(*pac::$USARTX::ptr()) };
+
+                    self.channel.set_peripheral_address(&usart.tdr as *const _ as u32, false);
+                    self.channel.set_memory_address(ptr as u32, true);
+                    self.channel.set_transfer_length(len as u16);
+
+                    // Tell DMA to request from serial
+                    self.channel.set_request_line($dmatxsel).unwrap();
+
+                    self.channel.ccr().modify(|_, w| {
+                        w
+                            // memory to memory mode disabled
+                            .mem2mem()
+                            .clear_bit()
+                            // medium channel priority level
+                            .pl()
+                            .medium()
+                            // 8-bit memory size
+                            .msize()
+                            .bits8()
+                            // 8-bit peripheral size
+                            .psize()
+                            .bits8()
+                            // circular mode disabled
+                            .circ()
+                            .clear_bit()
+                            // read from memory
+                            .dir()
+                            .set_bit()
+                    });
+
+                    start_transmission(&mut self);
+
+                    Transfer::r(buffer, self)
+                }
+            }
+
+            impl DmaTransmission for $txdma {
+                fn clear_transmission_complete(&mut self) {
+                    unsafe { (*pac::$USARTX::ptr()).icr.write(|w| w.tccf().set_bit()) };
+                }
+
+                fn start_channel(&mut self) {
+                    self.channel.start();
+                }
+
+                fn is_done(&self) -> bool {
+                    !self.channel.in_progress()
+                }
+
+                fn isr(&self) -> u32 {
+                    // NOTE(unsafe) atomic read with no side effects
+                    unsafe { (*pac::$USARTX::ptr()).isr.read().bits() }
+                }
+            }
+
+            impl<B> Transfer<R, B, $txdma> {
+                /// Returns `true` once the last byte is on the wire
+                ///
+                /// The DMA transfer completes as soon as the DMA wrote the last byte, while
+                /// the USART still sends it, which [`is_done`](Self::is_done) reports.
+                pub fn is_complete(&self) -> bool {
+                    transmission_complete(self.payload())
+                }
+
+                /// Blocks until the last byte is on the wire and returns the buffer and the
+                /// `TxDma`
+                ///
+                /// The line is idle then, e.g. to switch off an RS-485 driver. The next
+                /// transfer may start right away.
+                pub fn complete(self) -> (B, $txdma) {
+                    while !self.is_complete() {}
+
+                    self.wait()
+                }
+            }
+        )+
+    }
+}
+
+/// Serial instance, USART, UART or LPUART. This trait is sealed and cannot be
+/// implemented.
+pub trait Instance: private::SealedInstance + RccBus + Sized {
+    #[doc(hidden)]
+    /// Configures the serial as the constructor of the instance does
+    fn configure<PINS>(
+        self,
+        pins: PINS,
+        config: Config,
+        clocks: Clocks,
+        apb: &mut Self::Bus,
+    ) -> Serial<Self, PINS>
+    where
+        PINS: Pins<Self>;
+}
+
+impl<USART, TX> Serial<USART, (TX,)>
+where
+    USART: Instance,
+{
+    /// Configures the serial interface in single-wire half duplex mode, which transmits
+    /// and receives on the `tx` pin
+    ///
+    /// The pin is open drain and needs a pull-up, e.g. with
+    /// [`internal_pull_up`](crate::gpio::Pin::internal_pull_up), the RX pin stays free
+    /// for other uses. Otherwise the serial works as with the constructor of the
+    /// instance, e.g. [`Serial::usart1`] with `(tx,)` as the pins.
+    ///
+    /// The serial turns the line around: `write` disables the receiver, so it does not
+    /// receive the echo of the transmitted characters, and `flush` or `read` enable it
+    /// again once the transmission is complete. The [`Tx`] and [`Rx`] halves of
+    /// [`Serial::split`] and the DMA do not, the receiver gets the echo while both are
+    /// enabled.
+    pub fn new_half_duplex(
+        usart: USART,
+        tx: TX,
+        config: impl Into<Config>,
+        clocks: Clocks,
+        apb: &mut USART::Bus,
+    ) -> Self
+    where
+        (TX,): Pins<USART>,
+    {
+        usart.configure((tx,), config.into(), clocks, apb)
+    }
+}
+
+/// USART instance with the synchronous mode. This trait is sealed and cannot be
+/// implemented.
+pub trait SynchronousInstance: private::SealedSynchronous + Instance {
+    #[doc(hidden)]
+    /// Switches the configured serial to the synchronous mode
+    fn enable_clock(&self, clock: SynchronousConfig);
+}
+
+impl<USART, PINS, CK> Serial<USART, (PINS, CK)>
+where
+    USART: SynchronousInstance,
+{
+    /// Configures the serial interface in synchronous master mode, with the bit clock
+    /// output on the `ck` pin
+    ///
+    /// Otherwise the serial works as with the asynchronous constructor, e.g.
+    /// [`Serial::usart1`], it only receives while it transmits though. With 8 data bits,
+    /// no parity, [`SynchronousConfig::last_bit_clock`] and
+    /// [`SynchronousConfig::msb_first`], it is an SPI master without NSS.
+    ///
+    /// The half duplex pins are not allowed, as the synchronous mode is full duplex.
+    /// `release` returns the pins and the CK pin as `(pins, ck)`.
+    pub fn new_synchronous(
+        usart: USART,
+        pins: PINS,
+        ck: CK,
+        config: impl Into<Config>,
+        clock: impl Into<SynchronousConfig>,
+        clocks: Clocks,
+        apb: &mut USART::Bus,
+    ) -> Self
+    where
+        PINS: Pins<USART>,
+        CK: CkPin<USART>,
+    {
+        assert!(
+            !PINS::HALF_DUPLEX,
+            "synchronous serial with half duplex pins"
+        );
+        let config = config.into();
+        assert!(
+            config.break_detection.is_none(),
+            "synchronous serial in LIN mode"
+        );
+        let clock = clock.into();
+
+        let Serial { usart, pins } = usart.configure(pins, config, clocks, apb);
+
+        usart.enable_clock(clock);
+
+        Serial {
+            usart,
+            pins: (pins, ck),
+        }
+    }
+}
+
+macro_rules! synchronous {
+    ($(
+        $USARTX:ident,
+    )+) => {
+        $(
+            impl private::SealedSynchronous for pac::$USARTX {}
+
+            impl SynchronousInstance for pac::$USARTX {
+                fn enable_clock(&self, clock: SynchronousConfig) {
+                    // CLKEN, CPOL, CPHA, LBCL and MSBFIRST can only be written while disabled
+                    self.cr1.modify(|_, w| w.ue().clear_bit());
+                    self.cr2.modify(|r, w| {
+                        w.clken()
+                            .set_bit()
+                            .cpol()
+                            .bit(clock.mode.polarity == Polarity::IdleHigh)
+                            .cpha()
+                            .bit(clock.mode.phase == Phase::CaptureOnSecondTransition)
+                            .lbcl()
+                            .bit(clock.last_bit_clock)
+                            .msbfirst()
+                            .bit(clock.msb_first || r.msbfirst().bit_is_set())
+                    });
+                    self.cr1.modify(|_, w| w.ue().set_bit());
+                }
+            }
         )+
     }
 }
 
+synchronous! {
+    USART1,
+    USART2,
+}
+
+#[cfg(not(any(feature = "stm32l432", feature = "stm32l442")))]
+synchronous! {
+    USART3,
+}
+
 hal! {
-    USART1: (usart1, pclk2, tx: (TxDma1, dma1::C4, DmaInput::Usart1Tx), rx: (RxDma1, dma1::C5, DmaInput::Usart1Rx)),
-    USART2: (usart2, pclk1, tx: (TxDma2, dma1::C7, DmaInput::Usart2Tx), rx: (RxDma2, dma1::C6, DmaInput::Usart2Rx)),
+    USART1: (usart1, usart, tx: (TxDma1, dma1::C4, DmaInput::Usart1Tx), rx: (RxDma1, dma1::C5, DmaInput::Usart1Rx)),
+    USART2: (usart2, usart, tx: (TxDma2, dma1::C7, DmaInput::Usart2Tx), rx: (RxDma2, dma1::C6, DmaInput::Usart2Rx)),
+    LPUART1: (lpuart1, lpuart, tx: (LpTxDma1, dma2::C6, DmaInput::LpUart1Tx), rx: (LpRxDma1, dma2::C7, DmaInput::LpUart1Rx)),
 }
 
 #[cfg(not(any(feature = "stm32l432", feature = "stm32l442")))]
 hal! {
-    USART3: (usart3, pclk1, tx: (TxDma3, dma1::C2, DmaInput::Usart3Tx), rx: (RxDma3, dma1::C3, DmaInput::Usart3Rx)),
+    USART3: (usart3, usart, tx: (TxDma3, dma1::C2, DmaInput::Usart3Tx), rx: (RxDma3, dma1::C3, DmaInput::Usart3Rx)),
 }
 
 #[cfg(any(
@@ -877,7 +2314,7 @@ hal! {
     feature = "stm32l4s9",
 ))]
 hal! {
-    UART4: (uart4, pclk1, tx: (TxDma4, dma2::C3, DmaInput::Uart4Tx), rx: (RxDma4, dma2::C5, DmaInput::Uart4Rx)),
+    UART4: (uart4, usart, tx: (TxDma4, dma2::C3, DmaInput::Uart4Tx), rx: (RxDma4, dma2::C5, DmaInput::Uart4Rx)),
 }
 
 #[cfg(any(
@@ -898,7 +2335,7 @@ hal! {
     feature = "stm32l4s9",
 ))]
 hal! {
-    UART5: (uart5, pclk1, tx: (TxDma5, dma2::C1, DmaInput::Uart5Tx), rx: (RxDma5, dma2::C2, DmaInput::Uart5Rx)),
+    UART5: (uart5, usart, tx: (TxDma5, dma2::C1, DmaInput::Uart5Tx), rx: (RxDma5, dma2::C2, DmaInput::Uart5Rx)),
 }
 
 impl<USART, PINS> fmt::Write for Serial<USART, PINS>
@@ -929,6 +2366,16 @@ where
     }
 }
 
+#[cfg(feature = "embedded-io")]
+impl embedded_io::Error for Error {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        match self {
+            Error::Framing | Error::Noise | Error::Parity => embedded_io::ErrorKind::InvalidData,
+            _ => embedded_io::ErrorKind::Other,
+        }
+    }
+}
+
 /// Marks pins as being as being TX pins for the given USART instance
 pub trait TxPin<Instance>: private::SealedTx {}
 
@@ -944,6 +2391,9 @@ pub trait RtsDePin<Instance>: private::SealedRtsDe {}
 /// Marks pins as being as being CTS pins for the given USART instance
 pub trait CtsPin<Instance>: private::SealedCts {}
 
+/// Marks pins as being as being CK pins for the given USART instance
+pub trait CkPin<Instance>: private::SealedCk {}
+
 macro_rules! impl_pin_traits {
     (
         $(
@@ -954,6 +2404,7 @@ macro_rules! impl_pin_traits {
                         RX: $($rx:ident),*;
                         RTS_DE: $($rts_de:ident),*;
                         CTS: $($cts:ident),*;
+                        CK: $($ck:ident),*;
                     }
                 )*
             }
@@ -995,18 +2446,35 @@ macro_rules! impl_pin_traits {
                     impl CtsPin<pac::$instance> for
                         gpio::$cts<Alternate<PushPull, $af>> {}
                 )*
+
+                $(
+                    impl private::SealedCk for
+                        gpio::$ck<Alternate<PushPull, $af>> {}
+                    impl CkPin<pac::$instance> for
+                        gpio::$ck<Alternate<PushPull, $af>> {}
+                )*
             )*
         )*
     };
 }
 
 impl_pin_traits! {
+    LPUART1: {
+        8: {
+            TX: PA2, PB11, PC1;
+            RX: PA3, PB10, PC0;
+            RTS_DE: PB1, PB12;
+            CTS: PA6, PB13;
+            CK: ;
+        }
+    }
     USART1: {
         7: {
             TX: PA9, PB6;
             RX: PA10, PB7;
             RTS_DE: PA12, PB3;
             CTS: PA11, PB4;
+            CK: PA8, PB5;
         }
     }
     USART2: {
@@ -1015,12 +2483,14 @@ impl_pin_traits! {
             RX: PA3, PD6;
             RTS_DE: PA1, PD4;
             CTS: PA0, PD3;
+            CK: PA4, PD7;
         }
         3: {
             TX: ;
             RX: PA15;
             RTS_DE: ;
             CTS: ;
+            CK: ;
         }
     }
     USART3: {
@@ -1029,6 +2499,7 @@ impl_pin_traits! {
             RX: PB11, PC5, PC11, PD9;
             RTS_DE: PB1, PB14, PD2, PD12;
             CTS: PA6, PB13, PD11;
+            CK: PB0, PB12, PC12, PD10;
         }
     }
 }
@@ -1060,6 +2531,7 @@ impl_pin_traits! {
             RX: ;
             RTS_DE: PA15;
             CTS: ;
+            CK: ;
         }
     }
 }
@@ -1091,6 +2563,7 @@ impl_pin_traits! {
             RX: PA1, PC11;
             RTS_DE: PA15;
             CTS: PB7;
+            CK: ;
         }
     }
 }
@@ -1119,13 +2592,15 @@ impl_pin_traits! {
             RX: PD2;
             RTS_DE: PB4;
             CTS: PB5;
+            CK: ;
         }
     }
 }
 
 /// Pins trait for detecting hardware flow control or RS485 mode.
 pub trait Pins<USART> {
-    const FLOWCTL: bool;
+    const RTS: bool;
+    const CTS: bool;
     const DEM: bool;
     const HALF_DUPLEX: bool;
 }
@@ -1136,7 +2611,8 @@ where
     Tx: TxPin<Instance>,
     Rx: RxPin<Instance>,
 {
-    const FLOWCTL: bool = false;
+    const RTS: bool = false;
+    const CTS: bool = false;
     const DEM: bool = false;
     const HALF_DUPLEX: bool = false;
 }
@@ -1146,7 +2622,8 @@ impl<Instance, Tx> Pins<Instance> for (Tx,)
 where
     Tx: TxHalfDuplexPin<Instance>,
 {
-    const FLOWCTL: bool = false;
+    const RTS: bool = false;
+    const CTS: bool = false;
     const DEM: bool = false;
     const HALF_DUPLEX: bool = true;
 }
@@ -1159,7 +2636,34 @@ where
     Rts: RtsDePin<Instance>,
     Cts: CtsPin<Instance>,
 {
-    const FLOWCTL: bool = true;
+    const RTS: bool = true;
+    const CTS: bool = true;
+    const DEM: bool = false;
+    const HALF_DUPLEX: bool = false;
+}
+
+// Hardware flow control, Rx+Tx+Rts
+impl<Instance, Tx, Rx, Rts> Pins<Instance> for (Tx, Rx, Rts, NoPin)
+where
+    Tx: TxPin<Instance>,
+    Rx: RxPin<Instance>,
+    Rts: RtsDePin<Instance>,
+{
+    const RTS: bool = true;
+    const CTS: bool = false;
+    const DEM: bool = false;
+    const HALF_DUPLEX: bool = false;
+}
+
+// Hardware flow control, Rx+Tx+Cts
+impl<Instance, Tx, Rx, Cts> Pins<Instance> for (Tx, Rx, NoPin, Cts)
+where
+    Tx: TxPin<Instance>,
+    Rx: RxPin<Instance>,
+    Cts: CtsPin<Instance>,
+{
+    const RTS: bool = false;
+    const CTS: bool = true;
     const DEM: bool = false;
     const HALF_DUPLEX: bool = false;
 }
@@ -1171,7 +2675,8 @@ where
     Rx: RxPin<Instance>,
     De: RtsDePin<Instance>,
 {
-    const FLOWCTL: bool = false;
+    const RTS: bool = false;
+    const CTS: bool = false;
     const DEM: bool = true;
     const HALF_DUPLEX: bool = false;
 }
@@ -1183,4 +2688,166 @@ mod private {
     pub trait SealedRx {}
     pub trait SealedRtsDe {}
     pub trait SealedCts {}
+    pub trait SealedCk {}
+    pub trait SealedInstance {}
+    pub trait SealedSynchronous {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        half_duplex_receiver, read_error, receive_error, start_transmission, transmission_complete,
+        DmaTransmission, Error, ISR_FE, ISR_NF, ISR_ORE, ISR_PE, ISR_RXNE, ISR_TC,
+    };
+
+    // USART with its DMA channel, advanced one character time at a time
+    #[derive(Default)]
+    struct MockTransmission {
+        // Characters the DMA still writes to TDR
+        remaining: u32,
+        enabled: bool,
+        // The DMA channel is done as soon as it issued the last write, which reaches TDR
+        // one character time later
+        in_flight: bool,
+        // Characters in TDR and the shift register
+        queued: u32,
+        sent: u32,
+        tc: bool,
+    }
+
+    impl MockTransmission {
+        // Sets up the channel for `len` characters, as `write` does before the start
+        fn set_up(&mut self, len: u32) {
+            self.enabled = false;
+            self.remaining = len;
+        }
+
+        fn tick(&mut self) {
+            if self.queued != 0 {
+                self.queued -= 1;
+                self.sent += 1;
+                self.tc = self.queued == 0;
+            }
+            // Writing TDR clears TC as well
+            if self.in_flight {
+                self.in_flight = false;
+                self.queued += 1;
+                self.tc = false;
+            }
+            if self.enabled && self.remaining != 0 && self.queued < 2 {
+                self.remaining -= 1;
+                self.in_flight = true;
+            }
+        }
+    }
+
+    impl DmaTransmission for MockTransmission {
+        fn clear_transmission_complete(&mut self) {
+            self.tc = false;
+        }
+
+        fn start_channel(&mut self) {
+            self.enabled = true;
+        }
+
+        fn is_done(&self) -> bool {
+            self.enabled && self.remaining == 0
+        }
+
+        fn isr(&self) -> u32 {
+            if self.tc {
+                ISR_TC
+            } else {
+                0
+            }
+        }
+    }
+
+    #[test]
+    fn dma_transmission() {
+        let mut tx = MockTransmission::default();
+        let mut total = 0;
+        // The second transmission starts right after the first one with TC still set,
+        // and it takes a single character so that the DMA is done right away
+        for &len in [3, 1, 2].iter() {
+            tx.set_up(len);
+            start_transmission(&mut tx);
+            total += len;
+            while !transmission_complete(&tx) {
+                assert!(tx.sent < total, "{} of {} sent", tx.sent, total);
+                tx.tick();
+            }
+            // The last character of this transmission is on the wire
+            assert_eq!(tx.sent, total);
+            assert_eq!(tx.queued, 0);
+            assert!(!tx.in_flight);
+            assert_ne!(tx.isr() & ISR_TC, 0);
+        }
+    }
+
+    #[test]
+    fn stale_transmission_complete() {
+        // TC of the previous transmission
+        let mut tx = MockTransmission {
+            tc: true,
+            ..MockTransmission::default()
+        };
+        tx.set_up(1);
+        start_transmission(&mut tx);
+        assert!(!transmission_complete(&tx));
+        // The DMA is done while its write to TDR is still on the way
+        tx.tick();
+        assert!(tx.is_done());
+        assert!(!transmission_complete(&tx));
+        // The USART sends the character
+        tx.tick();
+        assert!(!transmission_complete(&tx));
+        tx.tick();
+        assert!(transmission_complete(&tx));
+        assert_eq!(tx.sent, 1);
+    }
+
+    #[test]
+    fn half_duplex_turnaround() {
+        // A write turns the receiver off, a read while transmitting leaves it off
+        assert_eq!(half_duplex_receiver(true, true, ISR_TC), Some(false));
+        assert_eq!(half_duplex_receiver(false, false, 0), None);
+        // The receiver is on again after the last character
+        assert_eq!(half_duplex_receiver(false, false, ISR_TC), Some(true));
+        assert_eq!(half_duplex_receiver(true, false, 0), None);
+        assert_eq!(half_duplex_receiver(false, true, 0), None);
+    }
+
+    #[test]
+    fn receive_errors() {
+        assert_eq!(receive_error(ISR_RXNE), None);
+        assert_eq!(
+            receive_error(ISR_PE | ISR_FE),
+            Some((Error::Parity, ISR_PE))
+        );
+        assert_eq!(
+            receive_error(ISR_FE | ISR_NF),
+            Some((Error::Framing, ISR_FE))
+        );
+        assert_eq!(
+            receive_error(ISR_NF | ISR_ORE),
+            Some((Error::Noise, ISR_NF))
+        );
+        assert_eq!(
+            receive_error(ISR_ORE | ISR_RXNE),
+            Some((Error::Overrun, ISR_ORE))
+        );
+    }
+
+    #[test]
+    fn overrun_recovery() {
+        // The character before the overrun is read first, ORE is reported after it
+        assert_eq!(read_error(ISR_ORE | ISR_RXNE), None);
+        assert_eq!(read_error(ISR_ORE), Some((Error::Overrun, ISR_ORE)));
+        // The errors of that character come before it
+        assert_eq!(
+            read_error(ISR_FE | ISR_ORE | ISR_RXNE),
+            Some((Error::Framing, ISR_FE))
+        );
+    }
 }
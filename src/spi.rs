@@ -5,6 +5,8 @@
 //! don't have it (L432xx and L442xx don't, L452xx does). Users of this MCU variant that
 //! don't have it shouldn't attempt to use it. Relevant info is on user-manual level.
 
+use core::marker::PhantomData;
+use core::ops::Deref;
 use core::ptr;
 use core::sync::atomic;
 use core::sync::atomic::Ordering;
@@ -14,7 +16,8 @@ use crate::dma::dma2;
 use crate::dma::{self, dma1, TransferPayload};
 use crate::dmamux::{DmaInput, DmaMux};
 use crate::gpio::{Alternate, PushPull};
-use crate::hal::spi::{FullDuplex, Mode, Phase, Polarity};
+use crate::hal::spi::{FullDuplex, Mode, Phase, Polarity, MODE_0};
+use crate::pac::spi1;
 use crate::rcc::{Clocks, Enable, RccBus, Reset};
 use crate::time::Hertz;
 
@@ -23,6 +26,7 @@ use embedded_dma::{StaticReadBuffer, StaticWriteBuffer};
 /// SPI error
 #[non_exhaustive]
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Overrun occurred
     Overrun,
@@ -32,20 +36,183 @@ pub enum Error {
     Crc,
 }
 
+/// SPI interrupt events
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Event {
+    /// New data has been received
+    Rxne,
+    /// The TX FIFO can take a new frame
+    Txe,
+    /// Overrun, mode fault, CRC error or TI frame format error
+    Error,
+}
+
 #[doc(hidden)]
 mod private {
     pub trait Sealed {}
 }
 
+/// Size of the data frames, `u8` or `u16`. This trait is sealed and cannot be implemented.
+pub trait FrameSize: Copy + private::Sealed {
+    /// DS: data size, in bits minus one
+    #[doc(hidden)]
+    const DS: u8;
+    /// FRXTH: RXNE event at a FIFO level of 8-bit instead of 16-bit
+    #[doc(hidden)]
+    const FRXTH: bool;
+    /// MSIZE and PSIZE of the DMA channel: 00: 8-bits, 01: 16-bits
+    #[doc(hidden)]
+    const DMA_SIZE: u8;
+}
+
+impl private::Sealed for u8 {}
+impl FrameSize for u8 {
+    const DS: u8 = 0b0111;
+    const FRXTH: bool = true;
+    const DMA_SIZE: u8 = 0b00;
+}
+
+impl private::Sealed for u16 {}
+impl FrameSize for u16 {
+    const DS: u8 = 0b1111;
+    const FRXTH: bool = false;
+    const DMA_SIZE: u8 = 0b01;
+}
+
 /// SCK pin. This trait is sealed and cannot be implemented.
 pub trait SckPin<SPI>: private::Sealed {}
 /// MISO pin. This trait is sealed and cannot be implemented.
 pub trait MisoPin<SPI>: private::Sealed {}
 /// MOSI pin. This trait is sealed and cannot be implemented.
 pub trait MosiPin<SPI>: private::Sealed {}
+/// NSS pin. This trait is sealed and cannot be implemented.
+pub trait NssPin<SPI>: private::Sealed {}
+
+/// Slave select input of a slave, an [`NssPin`] or [`SoftwareNss`]. This trait is sealed and
+/// cannot be implemented.
+pub trait SlaveSelect<SPI>: private::Sealed {
+    /// SSM: software slave management disabled
+    #[doc(hidden)]
+    const HARDWARE: bool;
+}
+
+/// In place of the NSS pin of a slave that is selected all the time, e.g. as the only slave on
+/// the bus
+pub struct SoftwareNss;
+
+impl private::Sealed for SoftwareNss {}
+impl<SPI> SlaveSelect<SPI> for SoftwareNss {
+    const HARDWARE: bool = false;
+}
+
+/// Pins of a master, `(sck, miso, mosi)` or `(sck, miso, mosi, nss)`. This trait is sealed and
+/// cannot be implemented.
+pub trait Pins<SPI>: private::Sealed {
+    #[doc(hidden)]
+    const NSS: bool;
+}
+
+impl<SCK, MISO, MOSI> private::Sealed for (SCK, MISO, MOSI) {}
+impl<SPI, SCK, MISO, MOSI> Pins<SPI> for (SCK, MISO, MOSI)
+where
+    SCK: SckPin<SPI>,
+    MISO: MisoPin<SPI>,
+    MOSI: MosiPin<SPI>,
+{
+    const NSS: bool = false;
+}
+
+impl<SCK, MISO, MOSI, NSS> private::Sealed for (SCK, MISO, MOSI, NSS) {}
+impl<SPI, SCK, MISO, MOSI, NSS> Pins<SPI> for (SCK, MISO, MOSI, NSS)
+where
+    SCK: SckPin<SPI>,
+    MISO: MisoPin<SPI>,
+    MOSI: MosiPin<SPI>,
+    NSS: NssPin<SPI>,
+{
+    const NSS: bool = true;
+}
+
+/// SPI peripheral instance. This trait is sealed and cannot be implemented.
+pub trait Instance: private::Sealed + Deref<Target = spi1::RegisterBlock> + Enable + Reset {
+    #[doc(hidden)]
+    fn clock(clocks: &Clocks) -> Hertz;
+    #[doc(hidden)]
+    fn ptr() -> *const spi1::RegisterBlock;
+}
+
+/// Frame format of a master
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FrameFormat {
+    /// Motorola frames, with the clock polarity and phase of the [`Mode`]
+    Motorola,
+    /// TI frames, where the NSS pin outputs a pulse before each frame and the [`Mode`] is
+    /// ignored
+    Ti,
+}
+
+/// Chip select of a master through the NSS pin
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct HardwareCs {
+    /// Drive NSS low while the peripheral is enabled. Otherwise NSS is an input, and a low
+    /// level from another master causes [`Error::ModeFault`].
+    pub output_enable: bool,
+    /// Pulse NSS high between two frames, for slaves that latch the frame on the rising edge
+    pub pulse_mode: bool,
+}
+
+/// Configuration of a master
+#[derive(Clone, Copy)]
+pub struct Config {
+    mode: Mode,
+    frequency: Hertz,
+    frame_format: FrameFormat,
+    hardware_cs: Option<HardwareCs>,
+    crc: Option<u16>,
+}
+
+impl Config {
+    /// Motorola frames in `mode` at `frequency` or the next lower baud rate, with the chip
+    /// select left to the application
+    ///
+    /// The baud rate is the kernel clock divided by 2 to 256, see [`Spi::set_frequency`].
+    pub fn new(mode: Mode, frequency: Hertz) -> Self {
+        Config {
+            mode,
+            frequency,
+            frame_format: FrameFormat::Motorola,
+            hardware_cs: None,
+            crc: None,
+        }
+    }
+
+    /// Selects the frame format
+    pub fn frame_format(mut self, frame_format: FrameFormat) -> Self {
+        self.frame_format = frame_format;
+        self
+    }
+
+    /// Lets the hardware drive the chip select through the NSS pin, only used with
+    /// [`FrameFormat::Motorola`]
+    pub fn hardware_cs(mut self, hardware_cs: HardwareCs) -> Self {
+        self.hardware_cs = Some(hardware_cs);
+        self
+    }
+
+    /// Enables the hardware CRC calculation with `polynomial`, see [`Spi::transfer_with_crc`]
+    ///
+    /// The CRC is as long as the frames, the polynomial is truncated for 8-bit frames.
+    pub fn crc(mut self, polynomial: u16) -> Self {
+        self.crc = Some(polynomial);
+        self
+    }
+}
 
 macro_rules! pins {
-    ($spi:ident, $af:literal, SCK: [$($sck:ident),*], MISO: [$($miso:ident),*], MOSI: [$($mosi:ident),*]) => {
+    ($spi:ident, $af:literal, SCK: [$($sck:ident),*], MISO: [$($miso:ident),*], MOSI: [$($mosi:ident),*], NSS: [$($nss:ident),*]) => {
         $(
             impl private::Sealed for $sck<Alternate<PushPull, $af>> {}
             impl SckPin<$spi> for $sck<Alternate<PushPull, $af>> {}
@@ -58,36 +225,41 @@ macro_rules! pins {
             impl private::Sealed for $mosi<Alternate<PushPull, $af>> {}
             impl MosiPin<$spi> for $mosi<Alternate<PushPull, $af>> {}
         )*
+        $(
+            impl private::Sealed for $nss<Alternate<PushPull, $af>> {}
+            impl NssPin<$spi> for $nss<Alternate<PushPull, $af>> {}
+            impl SlaveSelect<$spi> for $nss<Alternate<PushPull, $af>> {
+                const HARDWARE: bool = true;
+            }
+        )*
     }
 }
 
-/// SPI peripheral operating in full duplex master mode
-pub struct Spi<SPI, PINS> {
+/// SPI peripheral operating in full duplex master or slave mode
+///
+/// `WORD` is the size of the data frames, `u8` or `u16`, see [`Spi::frame_size`].
+pub struct Spi<SPI, PINS, WORD = u8> {
     spi: SPI,
     pins: PINS,
+    _word: PhantomData<WORD>,
 }
 
 macro_rules! hal {
     ($($SPIX:ident: ($spiX:ident, $spiX_slave:ident, $pclkX:ident),)+) => {
         $(
-            impl<PINS> Spi<$SPIX, PINS> {
-                /// Enable the SPI peripheral.
-                #[allow(unused)] // Only used for DMA.
-                #[inline]
-                fn enable(&mut self) {
-                    self.spi.cr1.modify(|_, w| w.spe().set_bit());
+            impl private::Sealed for $SPIX {}
+            impl Instance for $SPIX {
+                fn clock(clocks: &Clocks) -> Hertz {
+                    clocks.$pclkX()
                 }
 
-                /// Disable the SPI peripheral.
-                #[inline]
-                fn disable(&mut self) {
-                    self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+                fn ptr() -> *const spi1::RegisterBlock {
+                    $SPIX::ptr()
                 }
             }
 
             impl<SCK, MISO, MOSI> Spi<$SPIX, (SCK, MISO, MOSI)> {
                 /// Configures the SPI peripheral to operate in full duplex master mode
-                #[allow(unused_unsafe)]  // Necessary for stm32l4r9
                 pub fn $spiX(
                     spi: $SPIX,
                     pins: (SCK, MISO, MOSI),
@@ -101,57 +273,12 @@ macro_rules! hal {
                     MISO: MisoPin<$SPIX>,
                     MOSI: MosiPin<$SPIX>,
                 {
-                    // enable or reset $SPIX
-                    <$SPIX>::enable(apb2);
-                    <$SPIX>::reset(apb2);
-
-                    // FRXTH: RXNE event is generated if the FIFO level is greater than or equal to
-                    //        8-bit
-                    // DS: 8-bit data size
-                    // SSOE: Slave Select output disabled
-                    spi.cr2
-                        .write(|w| unsafe {
-                            w.frxth().set_bit().ds().bits(0b111).ssoe().clear_bit()
-                        });
-
-                    let br = Self::compute_baud_rate(clocks.$pclkX(), freq);
-
-                    // CPHA: phase
-                    // CPOL: polarity
-                    // MSTR: master mode
-                    // BR: 1 MHz
-                    // SPE: SPI disabled
-                    // LSBFIRST: MSB first
-                    // SSM: enable software slave management (NSS pin free for other uses)
-                    // SSI: set nss high = master mode
-                    // CRCEN: hardware CRC calculation disabled
-                    // BIDIMODE: 2 line unidirectional (full duplex)
-                    spi.cr1.write(|w| unsafe {
-                        w.cpha()
-                            .bit(mode.phase == Phase::CaptureOnSecondTransition)
-                            .cpol()
-                            .bit(mode.polarity == Polarity::IdleHigh)
-                            .mstr()
-                            .set_bit()
-                            .br()
-                            .bits(br)
-                            .spe()
-                            .set_bit()
-                            .lsbfirst()
-                            .clear_bit()
-                            .ssi()
-                            .set_bit()
-                            .ssm()
-                            .set_bit()
-                            .crcen()
-                            .clear_bit()
-                            .bidimode()
-                            .clear_bit()
-                    });
-
-                    Spi { spi, pins }
+                    Self::new(spi, pins, Config::new(mode, freq), clocks, apb2)
                 }
 
+                /// Configures the SPI peripheral to operate in full duplex slave mode, selected
+                /// by the hardware NSS input. See [`Spi::new_slave`], which also takes the NSS
+                /// pin.
                 pub fn $spiX_slave(spi: $SPIX, pins: (SCK, MISO, MOSI), mode: Mode, apb2: &mut <$SPIX as RccBus>::Bus) -> Self
                 where
                     SCK: SckPin<$SPIX>,
@@ -194,92 +321,892 @@ macro_rules! hal {
                         .write(|w| unsafe { w.ds().bits(0b111).frxth().set_bit() });
 
                     // SPE: SPI enabled
-                    spi.cr1.write(|w| w.spe().set_bit());
+                    spi.cr1.modify(|_, w| w.spe().set_bit());
 
-                    Spi { spi, pins }
+                    Spi {
+                        spi,
+                        pins,
+                        _word: PhantomData,
+                    }
                 }
+            }
 
-                pub fn clear_overrun(&mut self) {
-                    self.spi.dr.read().dr();
-                    self.spi.sr.read().ovr();
-                }
+        )+
+    }
+}
 
-                /// Change the baud rate of the SPI
-                #[allow(unused_unsafe)]  // Necessary for stm32l4r9
-                pub fn reclock(&mut self, freq: Hertz, clocks: Clocks) {
-                    self.disable();
-                    self.spi.cr1.modify(|_, w| unsafe {
-                        w.br().bits(Self::compute_baud_rate(clocks.$pclkX(), freq));
-                        w.spe().set_bit()
-                    });
-                }
+// CR1 and CR2 bits, some of them are missing from some PACs
+const CR1_MSTR: u32 = 1 << 2;
+const CR1_SPE: u32 = 1 << 6;
+const CR1_CRCL: u32 = 1 << 11;
+const CR2_DS: u32 = 0b1111 << 8;
+const CR2_FRXTH: u32 = 1 << 12;
+// SR bits
+const SR_BSY: u32 = 1 << 7;
+const SR_FRLVL: u32 = 0b11 << 9;
+const SR_FTLVL: u32 = 0b11 << 11;
+
+/// Register accesses of the procedures that disable the peripheral
+trait Registers {
+    fn cr1(&self) -> u32;
+    fn set_cr1(&mut self, bits: u32);
+    fn cr2(&self) -> u32;
+    fn set_cr2(&mut self, bits: u32);
+    /// Reading SR after DR clears an overrun
+    fn sr(&mut self) -> u32;
+    /// Pops a frame from the RX FIFO
+    fn read_dr(&mut self);
+}
 
-                fn compute_baud_rate(clocks: Hertz, freq: Hertz) -> u8 {
-                    match clocks / freq {
-                        0 => unreachable!(),
-                        1..=2 => 0b000,
-                        3..=5 => 0b001,
-                        6..=11 => 0b010,
-                        12..=23 => 0b011,
-                        24..=39 => 0b100,
-                        40..=95 => 0b101,
-                        96..=191 => 0b110,
-                        _ => 0b111,
-                    }
-                }
+impl Registers for &spi1::RegisterBlock {
+    fn cr1(&self) -> u32 {
+        self.cr1.read().bits()
+    }
 
-                /// Releases the SPI peripheral and associated pins
-                pub fn free(self) -> ($SPIX, (SCK, MISO, MOSI)) {
-                    (self.spi, self.pins)
-                }
+    fn set_cr1(&mut self, bits: u32) {
+        self.cr1.write(|w| unsafe { w.bits(bits) });
+    }
+
+    fn cr2(&self) -> u32 {
+        self.cr2.read().bits()
+    }
+
+    fn set_cr2(&mut self, bits: u32) {
+        self.cr2.write(|w| unsafe { w.bits(bits) });
+    }
+
+    fn sr(&mut self) -> u32 {
+        self.sr.read().bits()
+    }
+
+    fn read_dr(&mut self) {
+        self.dr.read();
+    }
+}
+
+/// Disables the peripheral once the last frame has been shifted out, following
+/// RM 40.4.9, subheading "Procedure for disabling the SPI".
+fn disable_when_idle(spi: &mut impl Registers) {
+    // A slave can only empty its TX FIFO while the master provides the clock
+    if spi.cr1() & CR1_MSTR != 0 {
+        // The DMA is done as soon as the last frame is in the TX FIFO, wait
+        // for it to be sent
+        while spi.sr() & SR_FTLVL != 0 {}
+        while spi.sr() & SR_BSY != 0 {}
+    }
+    let cr1 = spi.cr1();
+    spi.set_cr1(cr1 & !CR1_SPE);
+
+    // Drain the frames received meanwhile, e.g. during a TX only transfer, so
+    // that they do not show up as received data later
+    while spi.sr() & SR_FRLVL != 0 {
+        spi.read_dr();
+    }
+    // Reading SR after DR clears an overrun
+    spi.sr();
+}
+
+/// Selects `W` sized frames while the peripheral is disabled
+fn set_frame_size<W: FrameSize>(spi: &mut impl Registers) {
+    // DS: data size
+    // FRXTH: RXNE event at a FIFO level of 8-bit for 8-bit frames, 16-bit
+    //        otherwise, so that one read of DR returns exactly one frame
+    let cr2 = spi.cr2() & !(CR2_DS | CR2_FRXTH);
+    spi.set_cr2(cr2 | u32::from(W::DS) << 8 | if W::FRXTH { CR2_FRXTH } else { 0 });
+    // CRCL: 16-bit CRC for 16-bit frames
+    let cr1 = spi.cr1() & !CR1_CRCL;
+    spi.set_cr1(cr1 | if W::DS == u16::DS { CR1_CRCL } else { 0 });
+}
+
+/// Switches an enabled peripheral to `W` sized frames
+fn switch_frame_size<W: FrameSize>(spi: &mut impl Registers) {
+    disable_when_idle(spi);
+    set_frame_size::<W>(spi);
+    let cr1 = spi.cr1();
+    spi.set_cr1(cr1 | CR1_SPE);
+}
+
+impl<SPI: Instance, PINS, WORD> Spi<SPI, PINS, WORD> {
+    /// Enable the SPI peripheral.
+    #[inline]
+    fn enable(&mut self) {
+        self.spi.cr1.modify(|_, w| w.spe().set_bit());
+    }
+
+    /// Disable the SPI peripheral.
+    #[inline]
+    fn disable(&mut self) {
+        self.spi.cr1.modify(|_, w| w.spe().clear_bit());
+    }
+
+    /// Disables the peripheral once the last frame has been shifted out
+    fn disable_when_idle(&mut self) {
+        disable_when_idle(&mut &*self.spi);
+    }
+
+    /// Switches to `W` sized frames, e.g. `spi.frame_size::<u16>()`
+    ///
+    /// Waits for the ongoing frames to be shifted out and discards the received
+    /// frames that were not read yet, so that no stray bytes of the old frame size
+    /// remain in the RX FIFO. The chip select can stay asserted across the switch,
+    /// to mix frame sizes within one transaction.
+    pub fn frame_size<W: FrameSize>(self) -> Spi<SPI, PINS, W> {
+        switch_frame_size::<W>(&mut &*self.spi);
+        self.with_word()
+    }
+
+    /// Selects `W` sized frames while the peripheral is disabled
+    fn set_frame_size<W: FrameSize>(self) -> Spi<SPI, PINS, W> {
+        set_frame_size::<W>(&mut &*self.spi);
+        self.with_word()
+    }
+
+    fn with_word<W>(self) -> Spi<SPI, PINS, W> {
+        Spi {
+            spi: self.spi,
+            pins: self.pins,
+            _word: PhantomData,
+        }
+    }
+
+    /// Transfers `words` in place, followed by the CRC of the transmitted frames, and checks
+    /// the received CRC
+    ///
+    /// The CRC is calculated over this transfer only. The received CRC is not stored in
+    /// `words`, a mismatch is reported as [`Error::Crc`].
+    pub fn transfer_with_crc(&mut self, words: &mut [WORD]) -> Result<(), Error>
+    where
+        WORD: FrameSize,
+    {
+        // Reset the CRC calculation, CRCEN may only change while the peripheral is disabled
+        self.disable_when_idle();
+        self.spi.cr1.modify(|_, w| w.crcen().clear_bit());
+        self.spi.cr1.modify(|_, w| w.crcen().set_bit());
+        self.enable();
+
+        let last = match words.len().checked_sub(1) {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+        for (i, word) in words.iter_mut().enumerate() {
+            nb::block!(FullDuplex::send(self, *word))?;
+            if i == last {
+                // CRCNEXT: transmit the CRC after the frame that was just written
+                self.spi.cr1.modify(|_, w| w.crcnext().set_bit());
             }
+            *word = nb::block!(FullDuplex::read(self))?;
+        }
 
-            impl<PINS> FullDuplex<u8> for Spi<$SPIX, PINS> {
-                type Error = Error;
-
-                fn read(&mut self) -> nb::Result<u8, Error> {
-                    let sr = self.spi.sr.read();
-
-                    Err(if sr.ovr().bit_is_set() {
-                        nb::Error::Other(Error::Overrun)
-                    } else if sr.modf().bit_is_set() {
-                        nb::Error::Other(Error::ModeFault)
-                    } else if sr.crcerr().bit_is_set() {
-                        nb::Error::Other(Error::Crc)
-                    } else if sr.rxne().bit_is_set() {
-                        // NOTE(read_volatile) read only 1 byte (the svd2rust API only allows
-                        // reading a half-word)
-                        return Ok(unsafe {
-                            ptr::read_volatile(&self.spi.dr as *const _ as *const u8)
-                        });
-                    } else {
-                        nb::Error::WouldBlock
-                    })
-                }
+        // The received CRC follows the frames, and is checked by the hardware
+        while self.spi.sr.read().rxne().bit_is_clear() {}
+        // NOTE(read_volatile) see `FullDuplex::read`
+        let _: WORD = unsafe { ptr::read_volatile(&self.spi.dr as *const _ as *const WORD) };
+        while self.spi.sr.read().bsy().bit_is_set() {}
+
+        if self.spi.sr.read().crcerr().bit_is_set() {
+            // CRCERR is cleared by writing 0
+            self.spi.sr.write(|w| w.crcerr().clear_bit());
+            Err(Error::Crc)
+        } else {
+            Ok(())
+        }
+    }
 
-                fn send(&mut self, byte: u8) -> nb::Result<(), Error> {
-                    let sr = self.spi.sr.read();
-
-                    Err(if sr.ovr().bit_is_set() {
-                        nb::Error::Other(Error::Overrun)
-                    } else if sr.modf().bit_is_set() {
-                        nb::Error::Other(Error::ModeFault)
-                    } else if sr.crcerr().bit_is_set() {
-                        nb::Error::Other(Error::Crc)
-                    } else if sr.txe().bit_is_set() {
-                        // NOTE(write_volatile) see note above
-                        unsafe { ptr::write_volatile(&self.spi.dr as *const _ as *mut u8, byte) }
-                        return Ok(());
-                    } else {
-                        nb::Error::WouldBlock
-                    })
+    /// The CRC calculated over the transmitted frames, e.g. for debugging
+    pub fn tx_crc(&self) -> u16 {
+        self.spi.txcrcr.read().bits() as u16
+    }
+
+    /// The CRC calculated over the received frames, e.g. for debugging
+    pub fn rx_crc(&self) -> u16 {
+        self.spi.rxcrcr.read().bits() as u16
+    }
+
+    /// Receives `words` as a master whose clock runs while the peripheral is enabled, i.e.
+    /// in receive only or half duplex receive mode
+    ///
+    /// To generate no clocks after the last frame, the peripheral is disabled while the last
+    /// frame is received, following RM 40.4.9, subheading "Procedure for disabling the SPI".
+    /// The CPU has to read each frame before the next one is complete, otherwise more frames
+    /// are clocked and discarded.
+    fn receive_exact(&mut self, words: &mut [WORD]) -> Result<(), Error>
+    where
+        WORD: FrameSize,
+    {
+        let last = match words.len().checked_sub(1) {
+            Some(last) => last,
+            None => return Ok(()),
+        };
+
+        // The clock starts
+        self.enable();
+        let mut result = Ok(());
+        for (i, word) in words.iter_mut().enumerate() {
+            if i == last {
+                // The frame that is currently received is still completed
+                self.disable();
+            }
+            match nb::block!(FullDuplex::read(self)) {
+                Ok(frame) => *word = frame,
+                Err(error) => {
+                    result = Err(error);
+                    break;
                 }
             }
+        }
 
-            impl<PINS> crate::hal::blocking::spi::transfer::Default<u8> for Spi<$SPIX, PINS> {}
+        self.disable();
+        while self.spi.sr.read().bsy().bit_is_set() {}
+        // Drain the frames that were clocked in excess
+        while self.spi.sr.read().frlvl().bits() != 0 {
+            self.spi.dr.read();
+        }
+        // Reading SR after DR clears an overrun
+        self.spi.sr.read();
 
-            impl<PINS> crate::hal::blocking::spi::write::Default<u8> for Spi<$SPIX, PINS> {}
-        )+
+        result
+    }
+
+    pub fn clear_overrun(&mut self) {
+        self.spi.dr.read().dr();
+        self.spi.sr.read().ovr();
+    }
+
+    /// Clears a mode fault
+    ///
+    /// The hardware leaves master mode and disables the peripheral on a mode fault,
+    /// both are restored here.
+    pub fn clear_mode_fault(&mut self) {
+        // MODF is cleared by reading SR, followed by a write to CR1
+        self.spi.sr.read();
+        self.spi
+            .cr1
+            .modify(|_, w| w.mstr().set_bit().spe().set_bit());
+    }
+
+    /// Clears a CRC error
+    pub fn clear_crc_error(&mut self) {
+        // CRCERR is cleared by writing 0
+        self.spi.sr.write(|w| w.crcerr().clear_bit());
+    }
+
+    /// Starts listening for an interrupt event
+    pub fn listen(&mut self, event: Event) {
+        listen(&self.spi, event, true);
+    }
+
+    /// Stops listening for an interrupt event
+    pub fn unlisten(&mut self, event: Event) {
+        listen(&self.spi, event, false);
+    }
+
+    /// Returns `true` if `event` is pending, whether it is listened for or not
+    pub fn is_event_triggered(&self, event: Event) -> bool {
+        is_event_triggered(&self.spi, event)
+    }
+
+    /// Returns `true` while a frame is being shifted or the TX FIFO is not empty
+    pub fn is_busy(&self) -> bool {
+        self.spi.sr.read().bsy().bit_is_set()
+    }
+
+    /// Creates a transmitter and a receiver half, e.g. to move them to an interrupt
+    /// handler which feeds and drains the FIFOs while `self` keeps the configuration
+    ///
+    /// The halves only access the data register, the status register and the
+    /// interrupt enable bits, so that `self` can still change e.g. the baud rate
+    /// while the halves are idle.
+    ///
+    /// # Safety
+    ///
+    /// The halves must not be used after `self` changed the frame size or was freed.
+    /// Only one instance of each half may exist at a time.
+    pub unsafe fn split(&mut self) -> (Tx<SPI, WORD>, Rx<SPI, WORD>) {
+        (
+            Tx {
+                _spi: PhantomData,
+                _word: PhantomData,
+            },
+            Rx {
+                _spi: PhantomData,
+                _word: PhantomData,
+            },
+        )
+    }
+
+    /// Change the baud rate of the SPI
+    pub fn reclock(&mut self, freq: Hertz, clocks: Clocks) {
+        self.set_frequency(&clocks, freq);
+    }
+
+    /// Changes the baud rate of the SPI, e.g. between two devices on the same bus
+    ///
+    /// Waits for the ongoing frames to be shifted out, the frames received but not
+    /// read yet are discarded. The baud rate is the kernel clock divided by the smallest
+    /// power of two from 2 to 256 that gives at most `freq`, the achieved baud rate is
+    /// returned.
+    #[allow(unused_unsafe)] // Necessary for stm32l4r9
+    pub fn set_frequency(&mut self, clocks: &Clocks, freq: Hertz) -> Hertz {
+        let clock = SPI::clock(clocks);
+        let br = compute_baud_rate(clock, freq);
+
+        self.disable_when_idle();
+        self.spi.cr1.modify(|_, w| unsafe { w.br().bits(br) });
+        self.enable();
+
+        // BR selects a divider of 2 ^ (BR + 1)
+        Hertz::from_raw(clock.raw() >> (br + 1))
+    }
+
+    /// Changes the clock polarity and phase of the SPI, e.g. between two devices on
+    /// the same bus
+    ///
+    /// Waits for the ongoing frames to be shifted out, the frames received but not
+    /// read yet are discarded. Has no effect with the TI frame format.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.disable_when_idle();
+        self.spi.cr1.modify(|_, w| {
+            w.cpol()
+                .bit(mode.polarity == Polarity::IdleHigh)
+                .cpha()
+                .bit(mode.phase == Phase::CaptureOnSecondTransition)
+        });
+        self.enable();
+    }
+
+    /// Resynchronizes a slave to the frames of the master, e.g. after a glitch on
+    /// SCK or after the master aborted a frame
+    ///
+    /// The peripheral is reset, which discards a partially received frame and the
+    /// frames in both FIFOs, and configured as before. Call it while the master
+    /// does not select the slave, e.g. after NSS went high.
+    pub fn reset_frame(&mut self, apb: &mut <SPI as RccBus>::Bus) {
+        let cr1 = self.spi.cr1.read().bits();
+        let cr2 = self.spi.cr2.read().bits();
+        let crcpr = self.spi.crcpr.read().bits();
+
+        SPI::reset(apb);
+
+        self.spi.crcpr.write(|w| unsafe { w.bits(crcpr) });
+        self.spi.cr2.write(|w| unsafe { w.bits(cr2) });
+        // SPE (bit 6) is set last, once the peripheral is configured
+        self.spi.cr1.write(|w| unsafe { w.bits(cr1 & !(1 << 6)) });
+        self.spi.cr1.write(|w| unsafe { w.bits(cr1) });
+    }
+
+    /// Releases the SPI peripheral and associated pins
+    pub fn free(self) -> (SPI, PINS) {
+        (self.spi, self.pins)
+    }
+}
+
+impl<SPI: Instance, PINS, WORD: FrameSize> FullDuplex<WORD> for Spi<SPI, PINS, WORD> {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<WORD, Error> {
+        read(&self.spi)
+    }
+
+    fn send(&mut self, word: WORD) -> nb::Result<(), Error> {
+        send(&self.spi, word)
+    }
+}
+
+impl<SPI: Instance, PINS, WORD: FrameSize> crate::hal::blocking::spi::transfer::Default<WORD>
+    for Spi<SPI, PINS, WORD>
+{
+}
+
+impl<SPI: Instance, PINS, WORD: FrameSize> crate::hal::blocking::spi::write::Default<WORD>
+    for Spi<SPI, PINS, WORD>
+{
+}
+
+fn read<WORD: FrameSize>(spi: &spi1::RegisterBlock) -> nb::Result<WORD, Error> {
+    let sr = spi.sr.read();
+
+    Err(if sr.ovr().bit_is_set() {
+        nb::Error::Other(Error::Overrun)
+    } else if sr.modf().bit_is_set() {
+        nb::Error::Other(Error::ModeFault)
+    } else if sr.crcerr().bit_is_set() {
+        nb::Error::Other(Error::Crc)
+    } else if sr.rxne().bit_is_set() {
+        // NOTE(read_volatile) read only one frame, i.e. 1 byte for 8-bit frames
+        // (the svd2rust API only allows reading a half-word, which would pop two
+        // 8-bit frames from the RX FIFO)
+        return Ok(unsafe { ptr::read_volatile(&spi.dr as *const _ as *const WORD) });
+    } else {
+        nb::Error::WouldBlock
+    })
+}
+
+fn send<WORD: FrameSize>(spi: &spi1::RegisterBlock, word: WORD) -> nb::Result<(), Error> {
+    let sr = spi.sr.read();
+
+    Err(if sr.ovr().bit_is_set() {
+        nb::Error::Other(Error::Overrun)
+    } else if sr.modf().bit_is_set() {
+        nb::Error::Other(Error::ModeFault)
+    } else if sr.crcerr().bit_is_set() {
+        nb::Error::Other(Error::Crc)
+    } else if sr.txe().bit_is_set() {
+        // NOTE(write_volatile) see note above
+        unsafe { ptr::write_volatile(&spi.dr as *const _ as *mut WORD, word) }
+        return Ok(());
+    } else {
+        nb::Error::WouldBlock
+    })
+}
+
+fn listen(spi: &spi1::RegisterBlock, event: Event, enable: bool) {
+    // The halves may run in an interrupt handler, which must not interrupt the
+    // read-modify-write of CR2
+    cortex_m::interrupt::free(|_| match event {
+        Event::Rxne => spi.cr2.modify(|_, w| w.rxneie().bit(enable)),
+        Event::Txe => spi.cr2.modify(|_, w| w.txeie().bit(enable)),
+        Event::Error => spi.cr2.modify(|_, w| w.errie().bit(enable)),
+    })
+}
+
+fn is_event_triggered(spi: &spi1::RegisterBlock, event: Event) -> bool {
+    let sr = spi.sr.read();
+    match event {
+        Event::Rxne => sr.rxne().bit_is_set(),
+        Event::Txe => sr.txe().bit_is_set(),
+        Event::Error => {
+            sr.ovr().bit_is_set()
+                || sr.modf().bit_is_set()
+                || sr.crcerr().bit_is_set()
+                // FRE (bit 8) is named differently on stm32l4r9
+                || sr.bits() & (1 << 8) != 0
+        }
+    }
+}
+
+/// SPI transmitter half, see [`Spi::split`]
+pub struct Tx<SPI, WORD = u8> {
+    _spi: PhantomData<SPI>,
+    _word: PhantomData<WORD>,
+}
+
+impl<SPI: Instance, WORD: FrameSize> Tx<SPI, WORD> {
+    fn spi(&self) -> &spi1::RegisterBlock {
+        // NOTE(unsafe) only DR, SR and the interrupt enable bits of CR2 are accessed
+        unsafe { &*SPI::ptr() }
+    }
+
+    /// Writes a frame to the TX FIFO
+    pub fn send(&mut self, word: WORD) -> nb::Result<(), Error> {
+        send(self.spi(), word)
+    }
+
+    /// Starts listening for [`Event::Txe`]
+    pub fn listen(&mut self) {
+        listen(self.spi(), Event::Txe, true);
+    }
+
+    /// Stops listening for [`Event::Txe`], e.g. once the last frame has been written
+    pub fn unlisten(&mut self) {
+        listen(self.spi(), Event::Txe, false);
+    }
+
+    /// Returns `true` if the TX FIFO can take a new frame
+    pub fn is_txe(&self) -> bool {
+        is_event_triggered(self.spi(), Event::Txe)
+    }
+
+    /// Returns `true` while a frame is being shifted or the TX FIFO is not empty
+    pub fn is_busy(&self) -> bool {
+        self.spi().sr.read().bsy().bit_is_set()
+    }
+}
+
+/// SPI receiver half, see [`Spi::split`]
+pub struct Rx<SPI, WORD = u8> {
+    _spi: PhantomData<SPI>,
+    _word: PhantomData<WORD>,
+}
+
+impl<SPI: Instance, WORD: FrameSize> Rx<SPI, WORD> {
+    fn spi(&self) -> &spi1::RegisterBlock {
+        // NOTE(unsafe) only DR, SR and the interrupt enable bits of CR2 are accessed
+        unsafe { &*SPI::ptr() }
+    }
+
+    /// Reads a frame from the RX FIFO
+    pub fn read(&mut self) -> nb::Result<WORD, Error> {
+        read(self.spi())
+    }
+
+    /// Starts listening for [`Event::Rxne`]
+    pub fn listen(&mut self) {
+        listen(self.spi(), Event::Rxne, true);
+    }
+
+    /// Stops listening for [`Event::Rxne`]
+    pub fn unlisten(&mut self) {
+        listen(self.spi(), Event::Rxne, false);
+    }
+
+    /// Returns `true` if a received frame is waiting in the RX FIFO
+    pub fn is_rxne(&self) -> bool {
+        is_event_triggered(self.spi(), Event::Rxne)
+    }
+}
+
+/// BR for the smallest divider 2 ^ (BR + 1) that gives at most `freq`
+///
+/// Frequencies above half the kernel clock give the divider 2, frequencies below 1/256 of
+/// the kernel clock the divider 256.
+fn compute_baud_rate(clocks: Hertz, freq: Hertz) -> u8 {
+    let ratio = clocks.raw().saturating_sub(1) / freq.raw().max(1) + 1;
+    let divider = ratio.clamp(2, 256).next_power_of_two();
+    (divider.trailing_zeros() - 1) as u8
+}
+
+impl<SPI: Instance, PINS: Pins<SPI>> Spi<SPI, PINS> {
+    /// Configures the SPI peripheral to operate in full duplex master mode
+    ///
+    /// The pins are `(sck, miso, mosi)`, or `(sck, miso, mosi, nss)` for
+    /// [`Config::hardware_cs`] and [`FrameFormat::Ti`], and are returned by [`Spi::free`].
+    ///
+    /// # Panics
+    ///
+    /// If the configuration needs the NSS pin but it is not passed, or if NSS pulses are
+    /// requested with [`Phase::CaptureOnSecondTransition`].
+    pub fn new(
+        spi: SPI,
+        pins: PINS,
+        config: Config,
+        clocks: Clocks,
+        apb: &mut <SPI as RccBus>::Bus,
+    ) -> Self {
+        Self::configure(spi, pins, config, PINS::NSS, clocks, apb)
+    }
+}
+
+impl<SPI: Instance, PINS> Spi<SPI, PINS> {
+    /// Configures a full duplex master with the `pins`, which include the NSS pin if `nss`
+    #[allow(unused_unsafe)] // Necessary for stm32l4r9
+    fn configure(
+        spi: SPI,
+        pins: PINS,
+        config: Config,
+        nss: bool,
+        clocks: Clocks,
+        apb: &mut <SPI as RccBus>::Bus,
+    ) -> Self {
+        let ti = config.frame_format == FrameFormat::Ti;
+        assert!(
+            nss || !(ti || config.hardware_cs.is_some()),
+            "the NSS pin is required"
+        );
+        // The hardware ignores CPOL and CPHA in TI mode
+        let mode = if ti { MODE_0 } else { config.mode };
+        let (hardware_cs, output_enable, pulse_mode) = match config.hardware_cs {
+            Some(cs) if !ti => (true, cs.output_enable, cs.pulse_mode),
+            _ => (false, false, false),
+        };
+        assert!(
+            !pulse_mode || mode.phase == Phase::CaptureOnFirstTransition,
+            "NSS pulses require capturing on the first transition"
+        );
+
+        // enable or reset SPI
+        SPI::enable(apb);
+        SPI::reset(apb);
+
+        // FRXTH: RXNE event is generated if the FIFO level is greater than or equal to
+        //        8-bit
+        // DS: 8-bit data size
+        // SSOE: Slave Select output
+        // NSSP: NSS pulse between two frames
+        // FRF: Motorola or TI frame format
+        spi.cr2.write(|w| unsafe {
+            w.frxth()
+                .set_bit()
+                .ds()
+                .bits(0b111)
+                .ssoe()
+                .bit(output_enable)
+                .nssp()
+                .bit(pulse_mode)
+                .frf()
+                .bit(ti)
+        });
+
+        let br = compute_baud_rate(SPI::clock(&clocks), config.frequency);
+
+        // CPHA: phase
+        // CPOL: polarity
+        // MSTR: master mode
+        // BR: 1 MHz
+        // SPE: SPI disabled
+        // LSBFIRST: MSB first
+        // SSM: enable software slave management (NSS pin free for other uses), unless the
+        //      chip select is done by the hardware
+        // SSI: set nss high = master mode
+        // CRCEN: hardware CRC calculation, if configured
+        // CRCL: 8-bit CRC, like the frames
+        // BIDIMODE: 2 line unidirectional (full duplex)
+        spi.cr1.write(|w| unsafe {
+            w.cpha()
+                .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                .cpol()
+                .bit(mode.polarity == Polarity::IdleHigh)
+                .mstr()
+                .set_bit()
+                .br()
+                .bits(br)
+                .spe()
+                .clear_bit()
+                .lsbfirst()
+                .clear_bit()
+                .ssi()
+                .set_bit()
+                .ssm()
+                .bit(!hardware_cs)
+                .crcen()
+                .bit(config.crc.is_some())
+                .bidimode()
+                .clear_bit()
+        });
+        if let Some(polynomial) = config.crc {
+            spi.crcpr.write(|w| unsafe { w.bits(polynomial as u32) });
+        }
+
+        // SPE: SPI enabled, once configured
+        spi.cr1.modify(|_, w| w.spe().set_bit());
+
+        Spi {
+            spi,
+            pins,
+            _word: PhantomData,
+        }
+    }
+}
+
+impl<SPI: Instance, SCK, MISO, MOSI, NSS> Spi<SPI, (SCK, MISO, MOSI, NSS)> {
+    /// Configures the SPI peripheral to operate in full duplex slave mode
+    ///
+    /// With an [`NssPin`], the slave only takes part in the transfers while the master pulls
+    /// NSS low. With [`SoftwareNss`], it is selected all the time.
+    ///
+    /// The master provides the clock, so [`FullDuplex::send`] only queues a frame that is
+    /// shifted out during the next frame of the master, and [`FullDuplex::read`] returns the
+    /// frames as they were received. If they are not read in time, `read` fails with
+    /// [`Error::Overrun`] until [`Spi::clear_overrun`] is called. To keep up with the master,
+    /// receive into a circular buffer with [`dma::CircReadDma`].
+    #[allow(unused_unsafe)] // Necessary for stm32l4r9
+    pub fn new_slave(
+        spi: SPI,
+        pins: (SCK, MISO, MOSI, NSS),
+        mode: Mode,
+        apb: &mut <SPI as RccBus>::Bus,
+    ) -> Self
+    where
+        SCK: SckPin<SPI>,
+        MISO: MisoPin<SPI>,
+        MOSI: MosiPin<SPI>,
+        NSS: SlaveSelect<SPI>,
+    {
+        SPI::enable(apb);
+        SPI::reset(apb);
+
+        // DS: 8-bit data size
+        // FRXTH: RXNE event is generated if the FIFO level is greater than or equal to
+        //        8-bit
+        spi.cr2
+            .write(|w| unsafe { w.ds().bits(0b111).frxth().set_bit() });
+
+        // CPOL: polarity
+        // CPHA: phase
+        // BIDIMODE: 2 line unidirectional (full duplex)
+        // LSBFIRST: MSB first
+        // CRCEN: hardware CRC calculation disabled
+        // MSTR: slave mode
+        // SSM: software slave management for a slave without NSS pin
+        // SSI: nss low = slave selected, only used with SSM
+        spi.cr1.write(|w| {
+            w.cpol()
+                .bit(mode.polarity == Polarity::IdleHigh)
+                .cpha()
+                .bit(mode.phase == Phase::CaptureOnSecondTransition)
+                .bidimode()
+                .clear_bit()
+                .lsbfirst()
+                .clear_bit()
+                .crcen()
+                .clear_bit()
+                .mstr()
+                .clear_bit()
+                .ssm()
+                .bit(!NSS::HARDWARE)
+                .ssi()
+                .clear_bit()
+        });
+
+        // SPE: SPI enabled
+        spi.cr1.modify(|_, w| w.spe().set_bit());
+
+        Spi {
+            spi,
+            pins,
+            _word: PhantomData,
+        }
+    }
+}
+
+impl<SPI: Instance, SCK, SD> Spi<SPI, (SCK, SD)> {
+    /// Configures the SPI peripheral to operate in half duplex (3-wire) master mode, where the
+    /// MOSI pin `sd` is used to transmit and to receive
+    ///
+    /// The peripheral starts in transmit direction.
+    ///
+    /// # Panics
+    ///
+    /// If the configuration needs the NSS pin.
+    pub fn new_half_duplex(
+        spi: SPI,
+        pins: (SCK, SD),
+        config: Config,
+        clocks: Clocks,
+        apb: &mut <SPI as RccBus>::Bus,
+    ) -> HalfDuplex<SPI, (SCK, SD)>
+    where
+        SCK: SckPin<SPI>,
+        SD: MosiPin<SPI>,
+    {
+        let mut spi = Self::configure(spi, pins, config, false, clocks, apb);
+
+        // BIDIMODE: 1 line bidirectional
+        // BIDIOE: output enabled, i.e. transmit direction
+        spi.disable();
+        spi.spi
+            .cr1
+            .modify(|_, w| w.bidimode().set_bit().bidioe().set_bit());
+        spi.enable();
+
+        HalfDuplex { spi }
+    }
+
+    /// Configures the SPI peripheral to operate in receive only master mode, on the MISO pin
+    ///
+    /// # Panics
+    ///
+    /// If the configuration needs the NSS pin.
+    pub fn new_receive_only(
+        spi: SPI,
+        pins: (SCK, SD),
+        config: Config,
+        clocks: Clocks,
+        apb: &mut <SPI as RccBus>::Bus,
+    ) -> ReceiveOnly<SPI, (SCK, SD)>
+    where
+        SCK: SckPin<SPI>,
+        SD: MisoPin<SPI>,
+    {
+        let mut spi = Self::configure(spi, pins, config, false, clocks, apb);
+
+        // RXONLY: output disabled, the clock runs as long as the peripheral is enabled, so
+        //         it is only enabled while reading
+        spi.disable();
+        spi.spi.cr1.modify(|_, w| w.rxonly().set_bit());
+
+        ReceiveOnly { spi }
+    }
+}
+
+/// SPI peripheral operating in half duplex (3-wire) master mode
+///
+/// Frames are transmitted and received on the MOSI pin, one direction at a time.
+pub struct HalfDuplex<SPI, PINS, WORD = u8> {
+    spi: Spi<SPI, PINS, WORD>,
+}
+
+impl<SPI: Instance, PINS, WORD: FrameSize> HalfDuplex<SPI, PINS, WORD> {
+    /// Turns the data line around to transmit, if it is not already
+    pub fn set_transmit(&mut self) {
+        if self.spi.spi.cr1.read().bidioe().bit_is_clear() {
+            // The peripheral is disabled in receive direction when not reading
+            self.spi.spi.cr1.modify(|_, w| w.bidioe().set_bit());
+            self.spi.enable();
+        }
+    }
+
+    /// Turns the data line around to receive, if it is not already
+    ///
+    /// Waits for the last frame to be transmitted. The clock only runs while reading.
+    pub fn set_receive(&mut self) {
+        if self.spi.spi.cr1.read().bidioe().bit_is_set() {
+            // The direction may only change while the peripheral is disabled, and enabling
+            // it in receive direction starts the clock
+            self.spi.disable_when_idle();
+            self.spi.spi.cr1.modify(|_, w| w.bidioe().clear_bit());
+        }
+    }
+
+    /// Transmits `words`, switching to transmit direction if needed, and waits for the last
+    /// frame to be transmitted
+    pub fn write(&mut self, words: &[WORD]) -> Result<(), Error> {
+        self.set_transmit();
+        for word in words {
+            nb::block!(FullDuplex::send(&mut self.spi, *word))?;
+        }
+        while self.spi.spi.sr.read().ftlvl().bits() != 0 {}
+        while self.spi.spi.sr.read().bsy().bit_is_set() {}
+        Ok(())
+    }
+
+    /// Receives `words`, switching to receive direction if needed
+    ///
+    /// No clocks are generated after the last frame, if the CPU can keep up with the frames.
+    pub fn read(&mut self, words: &mut [WORD]) -> Result<(), Error> {
+        self.set_receive();
+        self.spi.receive_exact(words)
+    }
+
+    /// Switches to `W` sized frames, transmitting the last frames first
+    pub fn frame_size<W: FrameSize>(mut self) -> HalfDuplex<SPI, PINS, W> {
+        self.set_transmit();
+        HalfDuplex {
+            spi: self.spi.frame_size(),
+        }
+    }
+
+    /// Releases the SPI peripheral and associated pins
+    pub fn free(self) -> (SPI, PINS) {
+        self.spi.free()
+    }
+}
+
+/// SPI peripheral operating in receive only master mode
+///
+/// The clock only runs while reading.
+pub struct ReceiveOnly<SPI, PINS, WORD = u8> {
+    spi: Spi<SPI, PINS, WORD>,
+}
+
+impl<SPI: Instance, PINS, WORD: FrameSize> ReceiveOnly<SPI, PINS, WORD> {
+    /// Receives `words`
+    ///
+    /// No clocks are generated after the last frame, if the CPU can keep up with the frames.
+    pub fn read(&mut self, words: &mut [WORD]) -> Result<(), Error> {
+        self.spi.receive_exact(words)
+    }
+
+    /// Switches to `W` sized frames
+    pub fn frame_size<W: FrameSize>(self) -> ReceiveOnly<SPI, PINS, W> {
+        // The peripheral is disabled and the RX FIFO drained after each read
+        ReceiveOnly {
+            spi: self.spi.set_frame_size(),
+        }
+    }
+
+    /// Releases the SPI peripheral and associated pins
+    pub fn free(self) -> (SPI, PINS) {
+        self.spi.free()
     }
 }
 
@@ -312,7 +1239,8 @@ hal! {
 pins!(SPI1, 5,
     SCK: [PA5, PB3, PE13],
     MISO: [PA6, PB4, PE14],
-    MOSI: [PA7, PB5, PE15]);
+    MOSI: [PA7, PB5, PE15],
+    NSS: [PA4, PA15, PB0, PE12]);
 
 #[cfg(any(
     // feature = "stm32l471", // missing PAC support for Port G
@@ -331,7 +1259,7 @@ pins!(SPI1, 5,
     feature = "stm32l4r9",
     feature = "stm32l4s9",
 ))]
-pins!(SPI1, 5, SCK: [PG2], MISO: [PG3], MOSI: [PG4]);
+pins!(SPI1, 5, SCK: [PG2], MISO: [PG3], MOSI: [PG4], NSS: [PG5]);
 
 #[cfg(not(any(feature = "stm32l433", feature = "stm32l443",)))]
 use crate::stm32::SPI3;
@@ -345,7 +1273,8 @@ hal! {
 pins!(SPI3, 6,
     SCK: [PB3, PC10],
     MISO: [PB4, PC11],
-    MOSI: [PB5, PC12]);
+    MOSI: [PB5, PC12],
+    NSS: [PA4, PA15]);
 
 #[cfg(any(
     // feature = "stm32l471", // missing PAC support for Port G
@@ -364,7 +1293,7 @@ pins!(SPI3, 6,
     feature = "stm32l4r9",
     feature = "stm32l4s9",
 ))]
-pins!(SPI3, 6, SCK: [PG9], MISO: [PG10], MOSI: [PG11]);
+pins!(SPI3, 6, SCK: [PG9], MISO: [PG10], MOSI: [PG11], NSS: [PG12]);
 
 use crate::stm32::SPI2;
 
@@ -375,38 +1304,43 @@ hal! {
 pins!(SPI2, 5,
     SCK: [PB13, PB10, PD1],
     MISO: [PB14, PC2, PD3],
-    MOSI: [PB15, PC3, PD4]);
+    MOSI: [PB15, PC3, PD4],
+    NSS: [PB9, PB12, PD0]);
 
-pub struct SpiPayload<SPI, PINS> {
-    spi: Spi<SPI, PINS>,
+pub struct SpiPayload<SPI, PINS, WORD = u8> {
+    spi: Spi<SPI, PINS, WORD>,
 }
 
-pub type SpiRxDma<SPI, PINS, CHANNEL> = dma::RxDma<SpiPayload<SPI, PINS>, CHANNEL>;
+pub type SpiRxDma<SPI, PINS, CHANNEL, WORD = u8> = dma::RxDma<SpiPayload<SPI, PINS, WORD>, CHANNEL>;
 
-pub type SpiTxDma<SPI, PINS, CHANNEL> = dma::TxDma<SpiPayload<SPI, PINS>, CHANNEL>;
+pub type SpiTxDma<SPI, PINS, CHANNEL, WORD = u8> = dma::TxDma<SpiPayload<SPI, PINS, WORD>, CHANNEL>;
 
-pub type SpiRxTxDma<SPI, PINS, RXCH, TXCH> = dma::RxTxDma<SpiPayload<SPI, PINS>, RXCH, TXCH>;
+pub type SpiRxTxDma<SPI, PINS, RXCH, TXCH, WORD = u8> =
+    dma::RxTxDma<SpiPayload<SPI, PINS, WORD>, RXCH, TXCH>;
 
 macro_rules! spi_dma {
     ($SPIX:ident, $RX_CH:path, $RX_CHSEL:path, $TX_CH:path, $TX_CHSEL:path) => {
-        impl<PINS> dma::Receive for SpiRxDma<$SPIX, PINS, $RX_CH> {
+        impl<PINS, WORD> dma::Receive for SpiRxDma<$SPIX, PINS, $RX_CH, WORD> {
             type RxChannel = $RX_CH;
-            type TransmittedWord = u8;
+            type TransmittedWord = WORD;
         }
 
-        impl<PINS> dma::Transmit for SpiTxDma<$SPIX, PINS, $TX_CH> {
+        impl<PINS, WORD> dma::Transmit for SpiTxDma<$SPIX, PINS, $TX_CH, WORD> {
             type TxChannel = $TX_CH;
-            type ReceivedWord = u8;
+            type ReceivedWord = WORD;
         }
 
-        impl<PINS> dma::ReceiveTransmit for SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH> {
+        impl<PINS, WORD> dma::ReceiveTransmit for SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH, WORD> {
             type RxChannel = $RX_CH;
             type TxChannel = $TX_CH;
-            type TransferedWord = u8;
+            type TransferedWord = WORD;
         }
 
-        impl<PINS> Spi<$SPIX, PINS> {
-            pub fn with_rx_dma(self, mut channel: $RX_CH) -> SpiRxDma<$SPIX, PINS, $RX_CH> {
+        impl<PINS, WORD: FrameSize> Spi<$SPIX, PINS, WORD> {
+            /// Receives with DMA, see [`dma::ReadDma`]
+            ///
+            /// The words of the buffer are `WORD` sized frames.
+            pub fn with_rx_dma(self, mut channel: $RX_CH) -> SpiRxDma<$SPIX, PINS, $RX_CH, WORD> {
                 let payload = SpiPayload { spi: self };
 
                 // Perform one-time setup actions to keep the work minimal when using the driver.
@@ -416,7 +1350,7 @@ macro_rules! spi_dma {
                     false,
                 );
                 channel.set_request_line($RX_CHSEL).unwrap();
-                channel.ccr().modify(|_, w| {
+                channel.ccr().modify(|_, w| unsafe {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
@@ -424,12 +1358,11 @@ macro_rules! spi_dma {
                         // medium channel priority level
                         .pl()
                         .medium()
-                        // 8-bit memory size
+                        // memory and peripheral size of one frame
                         .msize()
-                        .bits8()
-                        // 8-bit peripheral size
+                        .bits(WORD::DMA_SIZE)
                         .psize()
-                        .bits8()
+                        .bits(WORD::DMA_SIZE)
                         // circular mode disabled
                         .circ()
                         .clear_bit()
@@ -441,7 +1374,12 @@ macro_rules! spi_dma {
                 SpiRxDma { payload, channel }
             }
 
-            pub fn with_tx_dma(self, mut channel: $TX_CH) -> SpiTxDma<$SPIX, PINS, $TX_CH> {
+            /// Transmits with DMA, see [`dma::WriteDma`]
+            ///
+            /// The words of the buffer are `WORD` sized frames. The transfer is done
+            /// once the last frame has been shifted out, the frames received meanwhile are
+            /// discarded.
+            pub fn with_tx_dma(self, mut channel: $TX_CH) -> SpiTxDma<$SPIX, PINS, $TX_CH, WORD> {
                 let payload = SpiPayload { spi: self };
 
                 // Perform one-time setup actions to keep the work minimal when using the driver.
@@ -451,7 +1389,7 @@ macro_rules! spi_dma {
                     false,
                 );
                 channel.set_request_line($TX_CHSEL).unwrap();
-                channel.ccr().modify(|_, w| {
+                channel.ccr().modify(|_, w| unsafe {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
@@ -459,12 +1397,11 @@ macro_rules! spi_dma {
                         // medium channel priority level
                         .pl()
                         .medium()
-                        // 8-bit memory size
+                        // memory and peripheral size of one frame
                         .msize()
-                        .bits8()
-                        // 8-bit peripheral size
+                        .bits(WORD::DMA_SIZE)
                         .psize()
-                        .bits8()
+                        .bits(WORD::DMA_SIZE)
                         // circular mode disabled
                         .circ()
                         .clear_bit()
@@ -476,11 +1413,14 @@ macro_rules! spi_dma {
                 SpiTxDma { payload, channel }
             }
 
+            /// Transmits and receives with DMA using the same buffer, see [`dma::TransferDma`]
+            ///
+            /// The words of the buffer are `WORD` sized frames.
             pub fn with_rxtx_dma(
                 self,
                 mut rx_channel: $RX_CH,
                 mut tx_channel: $TX_CH,
-            ) -> SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH> {
+            ) -> SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH, WORD> {
                 let payload = SpiPayload { spi: self };
 
                 // Perform one-time setup actions to keep the work minimal when using the driver.
@@ -494,7 +1434,7 @@ macro_rules! spi_dma {
                 );
                 rx_channel.set_request_line($RX_CHSEL).unwrap();
 
-                rx_channel.ccr().modify(|_, w| {
+                rx_channel.ccr().modify(|_, w| unsafe {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
@@ -502,12 +1442,11 @@ macro_rules! spi_dma {
                         // medium channel priority level
                         .pl()
                         .medium()
-                        // 8-bit memory size
+                        // memory and peripheral size of one frame
                         .msize()
-                        .bits8()
-                        // 8-bit peripheral size
+                        .bits(WORD::DMA_SIZE)
                         .psize()
-                        .bits8()
+                        .bits(WORD::DMA_SIZE)
                         // circular mode disabled
                         .circ()
                         .clear_bit()
@@ -525,7 +1464,7 @@ macro_rules! spi_dma {
                 );
                 tx_channel.set_request_line($TX_CHSEL).unwrap();
 
-                tx_channel.ccr().modify(|_, w| {
+                tx_channel.ccr().modify(|_, w| unsafe {
                     w
                         // memory to memory mode disabled
                         .mem2mem()
@@ -533,12 +1472,11 @@ macro_rules! spi_dma {
                         // medium channel priority level
                         .pl()
                         .medium()
-                        // 8-bit memory size
+                        // memory and peripheral size of one frame
                         .msize()
-                        .bits8()
-                        // 8-bit peripheral size
+                        .bits(WORD::DMA_SIZE)
                         .psize()
-                        .bits8()
+                        .bits(WORD::DMA_SIZE)
                         // circular mode disabled
                         .circ()
                         .clear_bit()
@@ -555,8 +1493,8 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<PINS> SpiRxDma<$SPIX, PINS, $RX_CH> {
-            pub fn split(mut self) -> (Spi<$SPIX, PINS>, $RX_CH) {
+        impl<PINS, WORD> SpiRxDma<$SPIX, PINS, $RX_CH, WORD> {
+            pub fn split(mut self) -> (Spi<$SPIX, PINS, WORD>, $RX_CH) {
                 self.stop();
                 let mut spi = self.payload.spi;
                 // Keep the peripheral itself enabled after stopping DMA.
@@ -565,8 +1503,8 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<PINS> SpiTxDma<$SPIX, PINS, $TX_CH> {
-            pub fn split(mut self) -> (Spi<$SPIX, PINS>, $TX_CH) {
+        impl<PINS, WORD> SpiTxDma<$SPIX, PINS, $TX_CH, WORD> {
+            pub fn split(mut self) -> (Spi<$SPIX, PINS, WORD>, $TX_CH) {
                 self.stop();
                 let mut spi = self.payload.spi;
                 // Keep the peripheral itself enabled after stopping DMA.
@@ -575,8 +1513,8 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<PINS> SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH> {
-            pub fn split(mut self) -> (Spi<$SPIX, PINS>, $RX_CH, $TX_CH) {
+        impl<PINS, WORD> SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH, WORD> {
+            pub fn split(mut self) -> (Spi<$SPIX, PINS, WORD>, $RX_CH, $TX_CH) {
                 self.stop();
                 let mut spi = self.payload.spi;
                 // Keep the peripheral itself enabled after stopping DMA.
@@ -585,7 +1523,7 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<PINS> dma::TransferPayload for SpiRxDma<$SPIX, PINS, $RX_CH> {
+        impl<PINS, WORD> dma::TransferPayload for SpiRxDma<$SPIX, PINS, $RX_CH, WORD> {
             fn start(&mut self) {
                 // Setup DMA channels in accordance with RM 40.4.9, subheading "Communication using
                 // DMA (direct memory addressing)".
@@ -616,7 +1554,7 @@ macro_rules! spi_dma {
                 // 3. Disable DMA Tx and Rx buffers by clearing the TXDMAEN and RXDMAEN bits in the
                 //    SPI_CR2 register, if DMA Tx and/or DMA Rx are used.
                 self.channel.stop(); // 1.
-                self.payload.spi.disable(); // 2.
+                self.payload.spi.disable_when_idle(); // 2.
                 self.payload
                     .spi
                     .spi
@@ -625,7 +1563,7 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<PINS> dma::TransferPayload for SpiTxDma<$SPIX, PINS, $TX_CH> {
+        impl<PINS, WORD> dma::TransferPayload for SpiTxDma<$SPIX, PINS, $TX_CH, WORD> {
             fn start(&mut self) {
                 // Setup DMA channels in accordance with RM 40.4.9, subheading "Communication using
                 // DMA (direct memory addressing)".
@@ -656,7 +1594,7 @@ macro_rules! spi_dma {
                 // 3. Disable DMA Tx and Rx buffers by clearing the TXDMAEN and RXDMAEN bits in the
                 //    SPI_CR2 register, if DMA Tx and/or DMA Rx are used.
                 self.channel.stop(); // 1.
-                self.payload.spi.disable(); // 2.
+                self.payload.spi.disable_when_idle(); // 2.
                 self.payload
                     .spi
                     .spi
@@ -665,7 +1603,7 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<PINS> dma::TransferPayload for SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH> {
+        impl<PINS, WORD> dma::TransferPayload for SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH, WORD> {
             fn start(&mut self) {
                 // Setup DMA channels in accordance with RM 40.4.9, subheading "Communication using
                 // DMA (direct memory addressing)".
@@ -703,7 +1641,7 @@ macro_rules! spi_dma {
                 //    SPI_CR2 register, if DMA Tx and/or DMA Rx are used.
                 self.tx_channel.stop(); // 1.
                 self.rx_channel.stop(); // 1.
-                self.payload.spi.disable(); // 2.
+                self.payload.spi.disable_when_idle(); // 2.
                 self.payload
                     .spi
                     .spi
@@ -712,9 +1650,10 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<B, PINS> dma::ReadDma<B, u8> for SpiRxDma<$SPIX, PINS, $RX_CH>
+        impl<B, PINS, WORD> dma::ReadDma<B, WORD> for SpiRxDma<$SPIX, PINS, $RX_CH, WORD>
         where
-            B: StaticWriteBuffer<Word = u8>,
+            B: StaticWriteBuffer<Word = WORD>,
+            WORD: FrameSize,
         {
             fn read(mut self, mut buffer: B) -> dma::Transfer<dma::W, B, Self> {
                 // Setup DMA channels in accordance with RM 40.4.9, subheading "Communication using
@@ -727,6 +1666,7 @@ macro_rules! spi_dma {
                 // Setup RX channel addresses and length
                 self.channel.set_memory_address(ptr as u32, true);
                 self.channel.set_transfer_length(len as u16);
+                self.channel.ccr().modify(|_, w| w.circ().clear_bit());
 
                 // Fences and start
                 atomic::compiler_fence(Ordering::Release);
@@ -736,9 +1676,35 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<B, PINS> dma::WriteDma<B, u8> for SpiTxDma<$SPIX, PINS, $TX_CH>
+        impl<B, PINS, WORD> dma::CircReadDma<B, WORD> for SpiRxDma<$SPIX, PINS, $RX_CH, WORD>
+        where
+            &'static mut B: StaticWriteBuffer<Word = WORD>,
+            B: 'static,
+            WORD: FrameSize,
+        {
+            fn circ_read(mut self, mut buffer: &'static mut B) -> dma::CircBuffer<B, Self> {
+                // NOTE(unsafe) We own the buffer now and we won't call other `&mut` on it
+                // until the end of the transfer.
+                let (ptr, len) = unsafe { buffer.static_write_buffer() };
+
+                // Setup RX channel addresses and length, the channel wraps around at the end
+                // of the buffer
+                self.channel.set_memory_address(ptr as u32, true);
+                self.channel.set_transfer_length(len as u16);
+                self.channel.ccr().modify(|_, w| w.circ().set_bit());
+
+                // Fences and start
+                atomic::compiler_fence(Ordering::Release);
+                self.start();
+
+                dma::CircBuffer::new(buffer, self)
+            }
+        }
+
+        impl<B, PINS, WORD> dma::WriteDma<B, WORD> for SpiTxDma<$SPIX, PINS, $TX_CH, WORD>
         where
-            B: StaticReadBuffer<Word = u8>,
+            B: StaticReadBuffer<Word = WORD>,
+            WORD: FrameSize,
         {
             fn write(mut self, buffer: B) -> dma::Transfer<dma::R, B, Self> {
                 // Setup DMA channels in accordance with RM 40.4.9, subheading "Communication using
@@ -760,9 +1726,11 @@ macro_rules! spi_dma {
             }
         }
 
-        impl<B, PINS> dma::TransferDma<B, u8> for SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH>
+        impl<B, PINS, WORD> dma::TransferDma<B, WORD>
+            for SpiRxTxDma<$SPIX, PINS, $RX_CH, $TX_CH, WORD>
         where
-            B: StaticWriteBuffer<Word = u8>,
+            B: StaticWriteBuffer<Word = WORD>,
+            WORD: FrameSize,
         {
             fn transfer(mut self, mut buffer: B) -> dma::Transfer<dma::RW, B, Self> {
                 // Setup DMA channels in accordance with RM 40.4.9, subheading "Communication using
@@ -805,3 +1773,191 @@ spi_dma!(SPI2, dma1::C4, DmaInput::Spi2Rx, dma1::C5, DmaInput::Spi2Tx);
 // spi_dma!(SPI1, dma2::C3, c3s, map4, dma2::C4, c4s, map4);
 #[cfg(not(any(feature = "stm32l433", feature = "stm32l443",)))]
 spi_dma!(SPI3, dma2::C1, DmaInput::Spi3Rx, dma2::C2, DmaInput::Spi3Tx);
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compute_baud_rate, disable_when_idle, switch_frame_size, FrameSize, Registers, CR1_CRCL,
+        CR1_MSTR, CR1_SPE, CR2_DS, CR2_FRXTH, SR_BSY,
+    };
+    use core::mem::size_of;
+    use fugit::RateExtU32;
+
+    const SR_OVR: u32 = 1 << 6;
+
+    // Peripheral that shifts out one frame of the TX FIFO per SR read while it is an
+    // enabled master, receiving a frame for each
+    #[derive(Default)]
+    struct MockSpi {
+        cr1: u32,
+        cr2: u32,
+        tx: u32,
+        // SR reads with BSY set after the TX FIFO has run empty
+        busy: u32,
+        rx: u32,
+        overrun: bool,
+        dr_read: bool,
+        // TX FIFO level and BSY when SPE got cleared
+        disabled: Option<(u32, bool)>,
+    }
+
+    impl Registers for MockSpi {
+        fn cr1(&self) -> u32 {
+            self.cr1
+        }
+
+        fn set_cr1(&mut self, bits: u32) {
+            if self.cr1 & CR1_SPE != 0 && bits & CR1_SPE == 0 {
+                self.disabled = Some((self.tx, self.tx != 0 || self.busy != 0));
+            }
+            self.cr1 = bits;
+        }
+
+        fn cr2(&self) -> u32 {
+            self.cr2
+        }
+
+        fn set_cr2(&mut self, bits: u32) {
+            assert_eq!(self.cr1 & CR1_SPE, 0, "CR2 written while enabled");
+            self.cr2 = bits;
+        }
+
+        fn sr(&mut self) -> u32 {
+            if self.dr_read {
+                self.overrun = false;
+                self.dr_read = false;
+            }
+            let sr = self.tx.min(3) << 11
+                | self.rx.min(3) << 9
+                | if self.tx != 0 || self.busy != 0 {
+                    SR_BSY
+                } else {
+                    0
+                }
+                | if self.overrun { SR_OVR } else { 0 };
+            if self.cr1 & (CR1_MSTR | CR1_SPE) == CR1_MSTR | CR1_SPE {
+                if self.tx != 0 {
+                    self.tx -= 1;
+                    if self.rx == 4 {
+                        self.overrun = true;
+                    } else {
+                        self.rx += 1;
+                    }
+                } else {
+                    self.busy = self.busy.saturating_sub(1);
+                }
+            }
+            sr
+        }
+
+        fn read_dr(&mut self) {
+            self.rx = self.rx.saturating_sub(1);
+            self.dr_read = true;
+        }
+    }
+
+    fn check_frame_size<W: FrameSize>() {
+        // DS is the frame size in bits minus one
+        assert_eq!(usize::from(W::DS) + 1, 8 * size_of::<W>());
+        // One read of DR has to return exactly one frame, so 8-bit frames need the RXNE
+        // event at a quarter full FIFO
+        assert_eq!(W::FRXTH, size_of::<W>() == 1);
+        // The DMA moves one frame per request
+        assert_eq!(1 << W::DMA_SIZE, size_of::<W>());
+    }
+
+    #[test]
+    fn frame_sizes() {
+        check_frame_size::<u8>();
+        check_frame_size::<u16>();
+    }
+
+    #[test]
+    fn master_disables_after_last_frame() {
+        let mut spi = MockSpi {
+            cr1: CR1_MSTR | CR1_SPE,
+            tx: 3,
+            busy: 2,
+            rx: 2,
+            ..MockSpi::default()
+        };
+        disable_when_idle(&mut spi);
+        assert_eq!(spi.disabled, Some((0, false)));
+        assert_eq!(spi.cr1, CR1_MSTR);
+        // The frames received meanwhile, including those of the last transfer, are discarded
+        assert_eq!(spi.rx, 0);
+        assert!(!spi.overrun);
+    }
+
+    #[test]
+    fn slave_disables_immediately() {
+        let mut spi = MockSpi {
+            cr1: CR1_SPE,
+            tx: 2,
+            rx: 1,
+            ..MockSpi::default()
+        };
+        disable_when_idle(&mut spi);
+        assert_eq!(spi.disabled, Some((2, true)));
+        assert_eq!(spi.rx, 0);
+    }
+
+    #[test]
+    fn overrun_is_cleared() {
+        let mut spi = MockSpi {
+            cr1: CR1_MSTR | CR1_SPE,
+            tx: 2,
+            rx: 3,
+            ..MockSpi::default()
+        };
+        disable_when_idle(&mut spi);
+        assert_eq!(spi.rx, 0);
+        assert!(!spi.overrun);
+    }
+
+    #[test]
+    fn frame_size_switch() {
+        const CR2_TXDMAEN: u32 = 1 << 1;
+        let mut spi = MockSpi {
+            cr1: CR1_MSTR | CR1_SPE,
+            cr2: 0b0111 << 8 | CR2_FRXTH | CR2_TXDMAEN,
+            tx: 1,
+            rx: 3,
+            ..MockSpi::default()
+        };
+        switch_frame_size::<u16>(&mut spi);
+        assert_eq!(spi.disabled, Some((0, false)));
+        assert_eq!(spi.cr1, CR1_MSTR | CR1_SPE | CR1_CRCL);
+        assert_eq!(spi.cr2, CR2_DS | CR2_TXDMAEN);
+        assert_eq!(spi.rx, 0);
+
+        // Stale 16-bit frames must not be read as pairs of 8-bit frames
+        spi.disabled = None;
+        spi.rx = 2;
+        switch_frame_size::<u8>(&mut spi);
+        assert_eq!(spi.disabled, Some((0, false)));
+        assert_eq!(spi.cr1, CR1_MSTR | CR1_SPE);
+        assert_eq!(spi.cr2, 0b0111 << 8 | CR2_FRXTH | CR2_TXDMAEN);
+        assert_eq!(spi.rx, 0);
+    }
+
+    #[test]
+    fn baud_rate() {
+        let clock = 80.MHz();
+        // Exact powers of two
+        assert_eq!(compute_baud_rate(clock, 40.MHz()), 0b000);
+        assert_eq!(compute_baud_rate(clock, 10.MHz()), 0b010);
+        assert_eq!(compute_baud_rate(clock, 312_500.Hz()), 0b111);
+        assert_eq!(compute_baud_rate(clock, 2_500_000.Hz()), 0b100);
+        // In between, the next slower baud rate
+        assert_eq!(compute_baud_rate(clock, 20_000_001.Hz()), 0b001);
+        assert_eq!(compute_baud_rate(clock, 8.MHz()), 0b011);
+        assert_eq!(compute_baud_rate(clock, 2_499_999.Hz()), 0b101);
+        assert_eq!(compute_baud_rate(clock, 400.kHz()), 0b111);
+        // Clamped to the fastest and slowest baud rate
+        assert_eq!(compute_baud_rate(clock, 80.MHz()), 0b000);
+        assert_eq!(compute_baud_rate(clock, 100.MHz()), 0b000);
+        assert_eq!(compute_baud_rate(clock, 1.kHz()), 0b111);
+        assert_eq!(compute_baud_rate(clock, 0.Hz()), 0b111);
+    }
+}
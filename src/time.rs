@@ -3,6 +3,7 @@
 pub use fugit::{
     HertzU32 as Hertz, KilohertzU32 as KiloHertz, MegahertzU32 as MegaHertz,
     MicrosDurationU32 as MicroSeconds, MillisDurationU32 as MilliSeconds,
+    NanosDurationU32 as NanoSeconds,
 };
 
 use crate::rcc::Clocks;
@@ -24,6 +25,16 @@ impl U32Ext for u32 {
     }
 }
 
+/// Divides rounding up, e.g. for the clock ticks that cover a duration
+pub(crate) fn div_ceil(dividend: u64, divisor: u64) -> u64 {
+    let quotient = dividend / divisor;
+    if quotient * divisor < dividend {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
 /// A monotonic nondecreasing timer
 #[derive(Clone, Copy, Debug)]
 pub struct MonoTimer {
@@ -68,3 +79,20 @@ impl Instant {
         DWT::cycle_count().wrapping_sub(self.now)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::div_ceil;
+
+    #[test]
+    fn rounding_up() {
+        assert_eq!(div_ceil(0, 3), 0);
+        assert_eq!(div_ceil(1, 3), 1);
+        assert_eq!(div_ceil(3, 3), 1);
+        assert_eq!(div_ceil(4, 3), 2);
+        assert_eq!(div_ceil(7, 1), 7);
+        assert_eq!(div_ceil(u64::MAX, 1), u64::MAX);
+        assert_eq!(div_ceil(u64::MAX, 2), 1 << 63);
+        assert_eq!(div_ceil(u64::MAX - 1, u64::MAX), 1);
+    }
+}
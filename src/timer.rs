@@ -1,6 +1,7 @@
 //! Timers
 
 use core::convert::Infallible;
+use core::ptr;
 
 use crate::hal::timer::{Cancel, CountDown, Periodic};
 
@@ -26,6 +27,15 @@ use crate::hal::timer::{Cancel, CountDown, Periodic};
 ))]
 use crate::stm32::TIM3;
 
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+use crate::stm32::TIM17;
 #[cfg(not(any(
     feature = "stm32l412",
     feature = "stm32l422",
@@ -34,7 +44,7 @@ use crate::stm32::TIM3;
     feature = "stm32l462",
 )))]
 use crate::stm32::TIM7;
-use crate::stm32::{TIM15, TIM16, TIM2, TIM6};
+use crate::stm32::{TIM1, TIM15, TIM16, TIM2, TIM6};
 #[cfg(any(
     // feature = "stm32l471", // missing PAC support
     feature = "stm32l475",
@@ -52,71 +62,143 @@ use crate::stm32::{TIM15, TIM16, TIM2, TIM6};
     feature = "stm32l4r9",
     feature = "stm32l4s9",
 ))]
-use crate::stm32::{TIM17, TIM4, TIM5};
+use crate::stm32::{TIM4, TIM5, TIM8};
 
-// TIM1/TIM8 ("Advcanced Control Timers") -> no impl
+// TIM1/TIM8 ("Advcanced Control Timers")
 // TIM2/TIM3/TIM4/TIM5 ("General Purpose Timers")
 // TIM15/TIM16/TIM17 ("General Purpose Timers")
 // TIM6/TIM7 ("Basic Timers")
 // LPTIM ("Low power Timer") -> no impl
 
-use cast::{u16, u32};
 use void::Void;
 
 use crate::rcc::{Clocks, Enable, Reset, APB1R1, APB2};
-use crate::time::Hertz;
-use fugit::RateExtU32;
+use crate::time::{Hertz, MicroSeconds};
 
 /// Hardware timers
 pub struct Timer<TIM> {
     clock: Hertz,
     tim: TIM,
-    timeout: Hertz,
 }
 
 /// Interrupt events
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
-    /// Timer timed out / count down ended
+    /// Update event, the count down ended or the counter overflowed
+    Update,
+    /// Same as [`Event::Update`]
     TimeOut,
 }
 
+/// Count down mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Mode {
+    /// The count down restarts when it ends
+    Periodic,
+    /// The timer stops when the count down ends, until the next `start`
+    OneShot,
+}
+
+/// Timer error
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum Error {
+    /// The period is shorter than two ticks of the timer clock
+    PeriodTooShort,
+    /// The period is longer than the prescaler and the auto-reload register allow
+    PeriodTooLong,
+}
+
+/// Period of a count down, as a frequency or as a duration
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Timeout {
+    /// Number of count downs per second
+    Frequency(Hertz),
+    /// Duration of a count down, in ns
+    Nanos(u64),
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Timeout {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Timeout::Frequency(frequency) => defmt::write!(f, "Frequency({} Hz)", frequency.raw()),
+            Timeout::Nanos(nanos) => defmt::write!(f, "Nanos({})", nanos),
+        }
+    }
+}
+
+impl From<Hertz> for Timeout {
+    fn from(frequency: Hertz) -> Self {
+        Timeout::Frequency(frequency)
+    }
+}
+
+impl<const NOM: u32, const DENOM: u32> From<fugit::Duration<u32, NOM, DENOM>> for Timeout {
+    fn from(duration: fugit::Duration<u32, NOM, DENOM>) -> Self {
+        let nanos =
+            u128::from(duration.ticks()) * u128::from(NOM) * 1_000_000_000 / u128::from(DENOM);
+        Timeout::Nanos(if nanos > u128::from(u64::MAX) {
+            u64::MAX
+        } else {
+            nanos as u64
+        })
+    }
+}
+
+impl Timeout {
+    /// Returns the ticks of the timer clock `clock` in the period, rounded down
+    pub(crate) fn ticks(self, clock: Hertz) -> u64 {
+        match self {
+            Timeout::Frequency(frequency) if frequency.raw() == 0 => u64::MAX,
+            Timeout::Frequency(frequency) => u64::from(clock.raw() / frequency.raw()),
+            Timeout::Nanos(nanos) => {
+                (u128::from(nanos) * u128::from(clock.raw()) / 1_000_000_000) as u64
+            }
+        }
+    }
+}
+
+/// Returns the prescaler and auto-reload values for a period of `ticks`, with an
+/// auto-reload value of at most `max_arr`
+///
+/// The prescaler is as small as possible, for the finest resolution of the counter.
+pub(crate) fn prescale(ticks: u64, max_arr: u64) -> Result<(u16, u32), Error> {
+    if ticks < 2 {
+        return Err(Error::PeriodTooShort);
+    }
+    let psc = (ticks - 1) / (max_arr + 1);
+    if psc > u64::from(u16::MAX) {
+        return Err(Error::PeriodTooLong);
+    }
+    let arr = ticks / (psc + 1) - 1;
+
+    Ok((psc as u16, arr as u32))
+}
+
 macro_rules! hal {
     ($($TIM:ident: ($tim:ident, $frname:ident, $apb:ident, $width:ident, $timclk:ident),)+) => {
         $(
             impl Periodic for Timer<$TIM> {}
 
             impl CountDown for Timer<$TIM> {
-                type Time = Hertz;
+                type Time = Timeout;
 
-                // NOTE(allow) `w.psc().bits()` is safe for TIM{6,7} but not for TIM{2,3,4} due to
-                // some SVD omission.
-                #[allow(unused_unsafe)]
+                /// Starts a count down of `timeout`, a frequency or a duration
+                ///
+                /// # Panics
+                ///
+                /// Panics if the period is out of the range of the timer, see
+                /// [`Timer::try_start`].
                 fn start<T>(&mut self, timeout: T)
                 where
-                    T: Into<Hertz>,
+                    T: Into<Timeout>,
                 {
-                    self.pause();
-
-                    self.timeout = timeout.into();
-                    let ticks = self.clock / self.timeout; // TODO check pclk that timer is on
-                    let psc = u16((ticks - 1) / (1 << 16)).unwrap();
-
-                    self.tim.psc.write(|w| unsafe { w.psc().bits(psc) });
-
-                    let arr = u16(ticks / u32(psc + 1)).unwrap();
-
-                    self.tim.arr.write(|w| unsafe { w.bits(u32(arr)) });
-
-                    // Trigger an update event to load the prescaler value to the clock.
-                    self.tim.egr.write(|w| w.ug().set_bit());
-
-                    // The above line raises an update event which will indicate
-                    // that the timer is already finished. Since this is not the case,
-                    // it should be cleared.
-                    self.clear_update_interrupt_flag();
-
-                    // Start counter.
-                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+                    if let Err(error) = self.try_start(timeout) {
+                        panic!("timer period out of range: {:?}", error);
+                    }
                 }
 
                 fn wait(&mut self) -> nb::Result<(), Void> {
@@ -145,18 +227,17 @@ macro_rules! hal {
                 // even if the `$TIM` are non overlapping (compare to the `free` function below
                 // which just works)
                 /// Configures a TIM peripheral as a periodic count down timer
-                pub fn $tim(tim: $TIM, timeout: Hertz, clocks: Clocks, apb: &mut $apb) -> Self {
+                pub fn $tim<T>(tim: $TIM, timeout: T, clocks: Clocks, apb: &mut $apb) -> Self
+                where
+                    T: Into<Timeout>,
+                {
                     // enable and reset peripheral to a clean slate state
                     <$TIM>::enable(apb);
                     <$TIM>::reset(apb);
 
                     let clock = clocks.$timclk();
 
-                    let mut timer = Timer {
-                        clock,
-                        tim,
-                        timeout: 0.Hz(),
-                    };
+                    let mut timer = Timer { clock, tim };
                     timer.start(timeout);
 
                     timer
@@ -181,10 +262,10 @@ macro_rules! hal {
 
                     debug_assert!(clock >= frequency);
                     debug_assert!(frequency.raw() > 0);
-                    debug_assert!(psc <= core::u16::MAX.into());
+                    debug_assert!(psc <= u16::MAX.into());
 
                     tim.psc.write(|w| w.psc().bits((psc as u16).into()) );
-                    let max = core::$width::MAX;
+                    let max = <$width>::MAX;
                     tim.arr.write(|w| unsafe { w.bits(max.into()) });
 
                     // Trigger an update event to load the prescaler value to the clock.
@@ -209,19 +290,58 @@ macro_rules! hal {
                         w
                     });
 
-                    Timer {
-                        clock,
-                        tim,
-                        timeout: frequency,
-                    }
+                    Timer { clock, tim }
+                }
+
+                /// Starts a count down of `timeout`, a frequency or a duration
+                ///
+                /// Periods from two ticks of the timer clock up to 2^16 times the range of
+                /// the counter are accepted, the timer is left untouched on an error.
+                // NOTE(allow) `w.psc().bits()` is safe for TIM{6,7} but not for TIM{2,3,4} due to
+                // some SVD omission.
+                #[allow(unused_unsafe)]
+                pub fn try_start<T>(&mut self, timeout: T) -> Result<(), Error>
+                where
+                    T: Into<Timeout>,
+                {
+                    let ticks = timeout.into().ticks(self.clock);
+                    let (psc, arr) = prescale(ticks, u64::from(<$width>::MAX))?;
+
+                    self.pause();
+
+                    self.tim.psc.write(|w| unsafe { w.psc().bits(psc) });
+                    self.tim.arr.write(|w| unsafe { w.bits(arr) });
+
+                    // Trigger an update event to load the prescaler value to the clock.
+                    self.tim.egr.write(|w| w.ug().set_bit());
+
+                    // The above line raises an update event which will indicate
+                    // that the timer is already finished. Since this is not the case,
+                    // it should be cleared.
+                    self.clear_update_interrupt_flag();
+
+                    // Start counter.
+                    self.tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    Ok(())
+                }
+
+                /// Selects whether the count down restarts when it ends
+                ///
+                /// Takes effect with the next `start`. In [`Mode::OneShot`], `wait` returns
+                /// once and the timer stays stopped afterwards.
+                pub fn set_mode(&mut self, mode: Mode) {
+                    self.tim
+                        .cr1
+                        .modify(|_, w| w.opm().bit(mode == Mode::OneShot));
                 }
 
                 /// Starts listening for an `event`
                 pub fn listen(&mut self, event: Event) {
                     match event {
-                        Event::TimeOut => {
+                        Event::Update | Event::TimeOut => {
                             // Enable update event interrupt.
-                            self.tim.dier.write(|w| w.uie().set_bit());
+                            self.tim.dier.modify(|_, w| w.uie().set_bit());
                         }
                     }
                 }
@@ -232,7 +352,7 @@ macro_rules! hal {
                 /// retrigger after the ISR has finished.
                 pub fn clear_interrupt(&mut self, event: Event) {
                     match event {
-                        Event::TimeOut => {
+                        Event::Update | Event::TimeOut => {
                             // Clear interrupt flag
                             self.tim.sr.write(|w| w.uif().clear_bit());
                         }
@@ -242,9 +362,9 @@ macro_rules! hal {
                 /// Stops listening for an `event`.
                 pub fn unlisten(&mut self, event: Event) {
                     match event {
-                        Event::TimeOut => {
+                        Event::Update | Event::TimeOut => {
                             // Enable update event interrupt
-                            self.tim.dier.write(|w| w.uie().clear_bit());
+                            self.tim.dier.modify(|_, w| w.uie().clear_bit());
                         }
                     }
                 }
@@ -260,6 +380,11 @@ macro_rules! hal {
                     cnt.cnt().bits()
                 }
 
+                /// Returns the current value of the counter
+                pub fn counter(&self) -> $width {
+                    self.tim.cnt.read().cnt().bits()
+                }
+
                 /// Pause the counter.
                 pub fn pause(&mut self) {
                     self.tim.cr1.modify(|_, w| w.cen().clear_bit());
@@ -275,12 +400,22 @@ macro_rules! hal {
                     self.pause();
                     self.tim
                 }
+
+                /// Stops the timer and its interrupt, and releases the TIM peripheral
+                ///
+                /// The peripheral can then be used e.g. for PWM, which configures it again.
+                pub fn release(mut self) -> $TIM {
+                    self.unlisten(Event::Update);
+                    self.set_mode(Mode::Periodic);
+                    self.free()
+                }
             }
         )+
     }
 }
 
 hal! {
+    TIM1:  (tim1, free_running_tim1, APB2, u16, timclk2),
     TIM2:  (tim2, free_running_tim2, APB1R1, u32, timclk1),
     TIM6:  (tim6, free_running_tim6, APB1R1, u16, timclk1),
     //TIM7:  (tim7, free_running_tim7, APB1R1, u16, timclk1),
@@ -342,5 +477,703 @@ hal! {
 hal! {
     TIM4:  (tim4, free_running_tim4, APB1R1, u16, timclk1),
     TIM5:  (tim5, free_running_tim5, APB1R1, u32, timclk1),
+    TIM8:  (tim8, free_running_tim8, APB2, u16, timclk2),
     TIM17: (tim17, free_running_tim17, APB2, u16, timclk2),
 }
+
+macro_rules! repetition {
+    ($($TIM:ident,)+) => {
+        $(
+            impl Timer<$TIM> {
+                /// Ends the count down only every `repetitions + 1` periods, e.g. for an
+                /// update interrupt every N periods
+                ///
+                /// Takes effect with the next `start`, or at the next update event.
+                pub fn set_repetition(&mut self, repetitions: u8) {
+                    self.tim.rcr.write(|w| unsafe { w.rep().bits(repetitions.into()) });
+                }
+            }
+        )+
+    }
+}
+
+repetition! {
+    TIM1,
+    TIM15,
+    TIM16,
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    // feature = "stm32l4r9",
+    // feature = "stm32l4s9",
+))]
+repetition! {
+    TIM8,
+    TIM17,
+}
+
+/// Output of the master mode controller on TRGO, e.g. to trigger the ADC, the DAC or
+/// another timer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MasterMode {
+    /// The UG bit and the reset of the slave mode controller
+    Reset = 0,
+    /// The counter enable, CEN or the trigger input in gated mode
+    Enable = 1,
+    /// The update events
+    ///
+    /// In the center-aligned PWM modes, there are two update events per period, see
+    /// [`PwmTimer::set_alignment`], unless the repetition counter skips one of them.
+    ///
+    /// [`PwmTimer::set_alignment`]: crate::pwm::PwmTimer::set_alignment
+    Update = 2,
+    /// A pulse on every capture or compare match of channel 1
+    ComparePulse = 3,
+    /// The reference signal of channel 1
+    Oc1Ref = 4,
+    /// The reference signal of channel 2
+    Oc2Ref = 5,
+    /// The reference signal of channel 3
+    Oc3Ref = 6,
+    /// The reference signal of channel 4
+    Oc4Ref = 7,
+}
+
+/// Output of the second master mode controller on TRGO2 of TIM1 and TIM8, which
+/// triggers the ADC only
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MasterMode2 {
+    /// The UG bit and the reset of the slave mode controller
+    Reset = 0,
+    /// The counter enable
+    Enable = 1,
+    /// The update events, two per period in the center-aligned PWM modes
+    Update = 2,
+    /// A pulse on every capture or compare match of channel 1
+    ComparePulse = 3,
+    /// The reference signal of channel 1
+    Oc1Ref = 4,
+    /// The reference signal of channel 2
+    Oc2Ref = 5,
+    /// The reference signal of channel 3
+    Oc3Ref = 6,
+    /// The reference signal of channel 4
+    Oc4Ref = 7,
+    /// The reference signal of channel 5
+    Oc5Ref = 8,
+    /// The reference signal of channel 6
+    Oc6Ref = 9,
+}
+
+/// What the trigger input of the slave mode controller does to the counter
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlaveMode {
+    /// The slave mode controller is off, the counter runs on the internal clock
+    Disabled = 0,
+    /// A rising edge of the trigger resets the counter
+    Reset = 4,
+    /// The counter only runs while the trigger is high
+    Gated = 5,
+    /// A rising edge of the trigger starts the counter, which does not stop
+    Trigger = 6,
+    /// The rising edges of the trigger clock the counter
+    ExternalClock = 7,
+}
+
+/// Trigger input of the slave mode controller
+///
+/// The timers connected to the internal triggers `Itr0` to `Itr3` differ between the
+/// timers, [`InternalTrigger`] has the table.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TriggerSource {
+    /// Internal trigger 0
+    Itr0 = 0,
+    /// Internal trigger 1
+    Itr1 = 1,
+    /// Internal trigger 2
+    Itr2 = 2,
+    /// Internal trigger 3
+    Itr3 = 3,
+    /// Both edges of the channel 1 input
+    Ti1EdgeDetector = 4,
+    /// The filtered channel 1 input, with the polarity of channel 1
+    Ti1 = 5,
+    /// The filtered channel 2 input, with the polarity of channel 2
+    Ti2 = 6,
+    /// The external trigger input, not on TIM15
+    Etr = 7,
+}
+
+/// Timers with a master mode controller driving TRGO
+pub trait MasterTimer {
+    #[doc(hidden)]
+    const CHANNELS: u8;
+    #[doc(hidden)]
+    fn base() -> *const u8;
+}
+
+/// Timers with a second master mode controller driving TRGO2
+pub trait Trgo2Timer: MasterTimer {}
+
+/// Timers with a slave mode controller
+pub trait SlaveTimer {
+    #[doc(hidden)]
+    const ETR: bool;
+    #[doc(hidden)]
+    fn base() -> *const u8;
+}
+
+/// Connection of the TRGO of `MASTER` to one of the internal triggers of this timer
+///
+/// | Slave | `Itr0` | `Itr1` | `Itr2` | `Itr3` |
+/// |-------|--------|--------|--------|--------|
+/// | TIM1  | TIM15  | TIM2   | TIM3   | TIM4   |
+/// | TIM2  | TIM1   | TIM8   | TIM3   | TIM4   |
+/// | TIM3  | TIM1   | TIM2   | TIM15  | TIM4   |
+/// | TIM4  | TIM1   | TIM2   | TIM3   | TIM8   |
+/// | TIM5  | TIM2   | TIM3   | TIM4   | TIM8   |
+/// | TIM8  | TIM1   | TIM2   | TIM4   | TIM5   |
+/// | TIM15 | TIM1   | TIM3   | -      | -      |
+pub trait InternalTrigger<MASTER>: SlaveTimer {
+    /// The internal trigger which `MASTER` drives
+    const SOURCE: TriggerSource;
+}
+
+/// Returns the internal trigger of `SLAVE` which the TRGO of `MASTER` drives
+pub fn internal_trigger<SLAVE, MASTER>() -> TriggerSource
+where
+    SLAVE: InternalTrigger<MASTER>,
+{
+    SLAVE::SOURCE
+}
+
+/// Modifies the register at `offset` of the timer at `base`, which is missing from the
+/// PAC for some timers
+fn modify_register(base: *const u8, offset: usize, mask: u32, bits: u32) {
+    let register = base.wrapping_add(offset) as *mut u32;
+    unsafe { ptr::write_volatile(register, ptr::read_volatile(register) & !mask | bits) }
+}
+
+pub(crate) fn set_master_mode<TIM: MasterTimer>(mode: MasterMode) {
+    let valid = match mode {
+        MasterMode::Reset | MasterMode::Enable | MasterMode::Update => true,
+        MasterMode::ComparePulse => TIM::CHANNELS > 0,
+        _ => mode as u8 - 3 <= TIM::CHANNELS,
+    };
+    assert!(valid, "master mode of a missing channel");
+    // MMS (CR2 bits 4-6)
+    modify_register(TIM::base(), 0x04, 0b111 << 4, (mode as u32) << 4);
+}
+
+pub(crate) fn set_master_mode2<TIM: Trgo2Timer>(mode: MasterMode2) {
+    // MMS2 (CR2 bits 20-23)
+    modify_register(TIM::base(), 0x04, 0b1111 << 20, (mode as u32) << 20);
+}
+
+pub(crate) fn set_slave_mode<TIM: SlaveTimer>(mode: SlaveMode, source: TriggerSource) {
+    assert!(
+        TIM::ETR || source != TriggerSource::Etr,
+        "trigger source missing on this timer"
+    );
+    // TS (SMCR bits 4-6) and SMS (SMCR bits 0-2 and 16), the source is changed with the
+    // slave mode controller off
+    modify_register(TIM::base(), 0x08, 0b111 | 1 << 16, 0);
+    modify_register(TIM::base(), 0x08, 0b111 << 4, (source as u32) << 4);
+    modify_register(TIM::base(), 0x08, 0b111, mode as u32);
+}
+
+pub(crate) fn set_master_slave_mode<TIM: SlaveTimer>(enabled: bool) {
+    // MSM (SMCR bit 7)
+    modify_register(TIM::base(), 0x08, 1 << 7, (enabled as u32) << 7);
+}
+
+impl<TIM: MasterTimer> Timer<TIM> {
+    /// Selects the output of the timer on TRGO
+    ///
+    /// # Panics
+    ///
+    /// Panics for the compare modes of channels which the timer does not have.
+    pub fn set_master_mode(&mut self, mode: MasterMode) {
+        set_master_mode::<TIM>(mode);
+    }
+}
+
+impl<TIM: Trgo2Timer> Timer<TIM> {
+    /// Selects the output of the timer on TRGO2
+    pub fn set_master_mode2(&mut self, mode: MasterMode2) {
+        set_master_mode2::<TIM>(mode);
+    }
+}
+
+impl<TIM: SlaveTimer> Timer<TIM> {
+    /// Controls the counter with the trigger `source`, see [`internal_trigger`] for the
+    /// other timers
+    ///
+    /// # Panics
+    ///
+    /// Panics for a trigger source which the timer does not have.
+    pub fn set_slave_mode(&mut self, mode: SlaveMode, source: TriggerSource) {
+        set_slave_mode::<TIM>(mode, source);
+    }
+
+    /// Delays the trigger input until TRGO, so this timer and its slaves start at once
+    pub fn set_master_slave_mode(&mut self, enabled: bool) {
+        set_master_slave_mode::<TIM>(enabled);
+    }
+}
+
+macro_rules! master {
+    ($($TIM:ident: $channels:literal,)+) => {
+        $(
+            impl MasterTimer for $TIM {
+                const CHANNELS: u8 = $channels;
+
+                fn base() -> *const u8 {
+                    $TIM::ptr() as *const u8
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! slave {
+    ($($TIM:ident: $etr:literal,)+) => {
+        $(
+            impl SlaveTimer for $TIM {
+                const ETR: bool = $etr;
+
+                fn base() -> *const u8 {
+                    $TIM::ptr() as *const u8
+                }
+            }
+        )+
+    }
+}
+
+macro_rules! internal_triggers {
+    ($($SLAVE:ident: $(($MASTER:ident, $source:ident)),+;)+) => {
+        $(
+            $(
+                impl InternalTrigger<$MASTER> for $SLAVE {
+                    const SOURCE: TriggerSource = TriggerSource::$source;
+                }
+            )+
+        )+
+    }
+}
+
+master! {
+    TIM1: 4,
+    TIM2: 4,
+    TIM6: 0,
+    TIM15: 2,
+}
+
+slave! {
+    TIM1: true,
+    TIM2: true,
+    TIM15: false,
+}
+
+impl Trgo2Timer for TIM1 {}
+
+internal_triggers! {
+    TIM1: (TIM15, Itr0), (TIM2, Itr1);
+    TIM2: (TIM1, Itr0);
+    TIM15: (TIM1, Itr0);
+}
+
+#[cfg(any(
+    // feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    // feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    // feature = "stm32l4r9",
+    // feature = "stm32l4s9",
+))]
+master! {
+    TIM3: 4,
+}
+
+#[cfg(any(
+    // feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    // feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    // feature = "stm32l4r9",
+    // feature = "stm32l4s9",
+))]
+slave! {
+    TIM3: true,
+}
+
+#[cfg(any(
+    // feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+    // feature = "stm32l471",
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    // feature = "stm32l4r9",
+    // feature = "stm32l4s9",
+))]
+internal_triggers! {
+    TIM1: (TIM3, Itr2);
+    TIM2: (TIM3, Itr2);
+    TIM3: (TIM1, Itr0), (TIM2, Itr1), (TIM15, Itr2);
+    TIM15: (TIM3, Itr1);
+}
+
+#[cfg(not(any(
+    feature = "stm32l412",
+    feature = "stm32l422",
+    feature = "stm32l451",
+    feature = "stm32l452",
+    feature = "stm32l462",
+)))]
+master! {
+    TIM7: 0,
+}
+
+#[cfg(any(
+    // feature = "stm32l471", // missing PAC support
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+master! {
+    TIM4: 4,
+    TIM5: 4,
+    TIM8: 4,
+}
+
+#[cfg(any(
+    // feature = "stm32l471", // missing PAC support
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+slave! {
+    TIM4: true,
+    TIM5: true,
+    TIM8: true,
+}
+
+#[cfg(any(
+    // feature = "stm32l471", // missing PAC support
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+impl Trgo2Timer for TIM8 {}
+
+#[cfg(any(
+    // feature = "stm32l471", // missing PAC support
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+internal_triggers! {
+    TIM1: (TIM4, Itr3);
+    TIM2: (TIM8, Itr1), (TIM4, Itr3);
+    TIM4: (TIM1, Itr0), (TIM2, Itr1), (TIM8, Itr3);
+    TIM5: (TIM2, Itr0), (TIM4, Itr2), (TIM8, Itr3);
+    TIM8: (TIM1, Itr0), (TIM2, Itr1), (TIM4, Itr2), (TIM5, Itr3);
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+))]
+internal_triggers! {
+    TIM3: (TIM4, Itr3);
+    TIM4: (TIM3, Itr2);
+    TIM5: (TIM3, Itr1);
+}
+
+/// Instant of a [`FreeRunningTimer`], in µs
+pub type Instant = fugit::TimerInstantU32<1_000_000>;
+
+/// Source of [`Instant`]s, e.g. for a [`CountdownHandle`]
+pub trait Clock {
+    /// Returns the current instant
+    fn now(&self) -> Instant;
+}
+
+/// Free running 32-bit timer counting µs
+///
+/// The counter wraps around after about 71 minutes, durations are computed with wrapping
+/// arithmetic and are correct as long as they are shorter.
+pub struct FreeRunningTimer<TIM> {
+    tim: TIM,
+}
+
+/// Timeout measured on a [`Clock`], see [`FreeRunningTimer::countdown`]
+///
+/// Drivers can borrow it to bound their busy waits, without owning a timer.
+pub struct CountdownHandle<'a> {
+    clock: &'a dyn Clock,
+    start: Instant,
+    timeout: MicroSeconds,
+}
+
+impl<'a> CountdownHandle<'a> {
+    /// Starts a countdown of `timeout` on `clock`
+    pub fn new(clock: &'a dyn Clock, timeout: MicroSeconds) -> Self {
+        CountdownHandle {
+            clock,
+            start: clock.now(),
+            timeout,
+        }
+    }
+
+    /// Returns the time elapsed since the start of the countdown
+    pub fn elapsed(&self) -> MicroSeconds {
+        MicroSeconds::from_ticks(self.clock.now().ticks().wrapping_sub(self.start.ticks()))
+    }
+
+    /// Checks whether the timeout has elapsed
+    pub fn is_expired(&self) -> bool {
+        self.elapsed() >= self.timeout
+    }
+
+    /// Returns the time left until the timeout, zero once it has elapsed
+    pub fn remaining(&self) -> MicroSeconds {
+        let elapsed = self.elapsed();
+        if elapsed >= self.timeout {
+            MicroSeconds::from_ticks(0)
+        } else {
+            self.timeout - elapsed
+        }
+    }
+
+    /// Starts the countdown again from now
+    pub fn restart(&mut self) {
+        self.start = self.clock.now();
+    }
+}
+
+macro_rules! free_running {
+    ($($TIM:ident: ($tim:ident, $apb:ident, $timclk:ident),)+) => {
+        $(
+            impl FreeRunningTimer<$TIM> {
+                /// Starts a free running timer counting µs
+                ///
+                /// The timer clock is divided down to 1 MHz, it is rounded if it is not a
+                /// multiple of 1 MHz.
+                pub fn $tim(tim: $TIM, clocks: Clocks, apb: &mut $apb) -> Self {
+                    <$TIM>::enable(apb);
+                    <$TIM>::reset(apb);
+
+                    let clock = clocks.$timclk().raw();
+                    assert!(clock >= 1_000_000, "timer clock slower than 1 MHz");
+                    let psc = (clock + 500_000) / 1_000_000 - 1;
+                    tim.psc.write(|w| unsafe { w.bits(psc) });
+                    tim.arr.write(|w| unsafe { w.bits(u32::MAX) });
+
+                    // Loads the prescaler, the update flag is not used
+                    tim.egr.write(|w| w.ug().set_bit());
+                    tim.sr.write(|w| w.uif().clear_bit());
+
+                    tim.cr1.modify(|_, w| w.cen().set_bit());
+
+                    FreeRunningTimer { tim }
+                }
+
+                /// Returns the frequency of the counter, 1 MHz
+                pub fn frequency(&self) -> Hertz {
+                    Hertz::from_raw(1_000_000)
+                }
+
+                /// Returns the current instant
+                pub fn now(&self) -> Instant {
+                    // CNT is a single 32-bit register, read in one access
+                    Instant::from_ticks(self.tim.cnt.read().bits())
+                }
+
+                /// Returns the time elapsed since `since`
+                pub fn elapsed(&self, since: Instant) -> MicroSeconds {
+                    MicroSeconds::from_ticks(self.now().ticks().wrapping_sub(since.ticks()))
+                }
+
+                /// Starts a countdown of `timeout`, which borrows the timer
+                pub fn countdown(&self, timeout: MicroSeconds) -> CountdownHandle<'_> {
+                    CountdownHandle::new(self, timeout)
+                }
+
+                /// Stops the timer and releases the TIM peripheral
+                pub fn release(self) -> $TIM {
+                    self.tim.cr1.modify(|_, w| w.cen().clear_bit());
+                    self.tim
+                }
+            }
+
+            impl Clock for FreeRunningTimer<$TIM> {
+                fn now(&self) -> Instant {
+                    FreeRunningTimer::<$TIM>::now(self)
+                }
+            }
+        )+
+    }
+}
+
+free_running! {
+    TIM2: (tim2, APB1R1, timclk1),
+}
+
+#[cfg(any(
+    feature = "stm32l475",
+    feature = "stm32l476",
+    feature = "stm32l485",
+    feature = "stm32l486",
+    feature = "stm32l496",
+    feature = "stm32l4a6",
+    // feature = "stm32l4p5",
+    // feature = "stm32l4q5",
+    // feature = "stm32l4r5",
+    // feature = "stm32l4s5",
+    // feature = "stm32l4r7",
+    // feature = "stm32l4s7",
+    feature = "stm32l4r9",
+    feature = "stm32l4s9",
+))]
+free_running! {
+    TIM5: (tim5, APB1R1, timclk1),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{prescale, Error};
+
+    #[test]
+    fn prescaler() {
+        let max = u64::from(u16::MAX);
+        assert_eq!(prescale(0, max), Err(Error::PeriodTooShort));
+        assert_eq!(prescale(1, max), Err(Error::PeriodTooShort));
+        assert_eq!(prescale(2, max), Ok((0, 1)));
+        // The full range of the counter without a prescaler
+        assert_eq!(prescale(0x1_0000, max), Ok((0, 0xffff)));
+        // One more tick needs the prescaler, the period is rounded down
+        assert_eq!(prescale(0x1_0001, max), Ok((1, 0x7fff)));
+        assert_eq!(prescale(0x1_0001, u64::from(u32::MAX)), Ok((0, 0x1_0000)));
+        // The longest period
+        assert_eq!(prescale(0x1_0000 * 0x1_0000, max), Ok((0xffff, 0xffff)));
+        assert_eq!(
+            prescale(0x1_0000 * 0x1_0000 + 1, max),
+            Err(Error::PeriodTooLong)
+        );
+    }
+
+    #[test]
+    fn prescaled_period() {
+        let max = 999;
+        for ticks in (2..200_000).step_by(7) {
+            let (psc, arr) = prescale(ticks, max).unwrap();
+            let (psc, arr) = (u64::from(psc), u64::from(arr));
+            assert!(arr <= max, "{} ticks", ticks);
+            // The smallest prescaler, with the period rounded down by less than a
+            // prescaled tick
+            assert!(psc == 0 || ticks > psc * (max + 1), "{} ticks", ticks);
+            let period = (psc + 1) * (arr + 1);
+            assert!(period <= ticks && ticks - period <= psc, "{} ticks", ticks);
+        }
+    }
+}
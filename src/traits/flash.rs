@@ -5,6 +5,7 @@ pub struct FlashPage(pub usize);
 
 /// Flash operation error
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Flash controller is not done yet
     Busy,
@@ -13,6 +13,7 @@ use crate::rcc::{Enable, Reset, AHB1};
 use crate::stm32::TSC;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Event {
     /// Max count error
     MaxCountError,
@@ -21,6 +22,7 @@ pub enum Event {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
     /// Max count error
     MaxCountError,
@@ -86,6 +88,7 @@ pub struct Config {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum ClockPrescaler {
     Hclk = 0b000,
     HclkDiv2 = 0b001,
@@ -98,6 +101,7 @@ pub enum ClockPrescaler {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum MaxCountError {
     /// 000: 255
     U255 = 0b000,
@@ -116,6 +120,7 @@ pub enum MaxCountError {
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 /// How many tsc cycles are spent charging / discharging
 pub enum ChargeDischargeTime {
     C1 = 0b0000,